@@ -5,12 +5,32 @@
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use mpca_core::{AgentRuntime, MpcaConfig};
+use mpca_core::agent::ScriptedAgentBackend;
+use mpca_core::config::{ConfigFile, ConfigLoader, ConfigOverrides, LoadedConfig, canonical_key};
+use mpca_core::steps::load_plan_steps;
+use mpca_core::tools::fs_impl::StdFsAdapter;
+use mpca_core::{
+    AgentRuntime, CancellationToken, FeatureState, MPCAError, MpcaConfig, Phase, StepStatus, Usage,
+    workflows,
+};
+use mpca_core::{migrate, prompts};
+use mpca_pm::TemplateSource;
+use std::io::{IsTerminal, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tracing::{error, info};
 
 mod tui;
 
+/// Environment variable naming an agent transcript to replay instead of Claude
+const TRANSCRIPT_ENV: &str = "MPCA_AGENT_TRANSCRIPT";
+
+/// Exit code when a run stops because a `[budget]` cap was reached
+const EXIT_BUDGET_EXCEEDED: i32 = 3;
+
+/// Exit code when a run is interrupted by SIGINT or SIGTERM (128 + SIGINT)
+const EXIT_INTERRUPTED: i32 = 130;
+
 /// MPCA - Mine Personal Coding Agent
 ///
 /// Automated feature development workflows using Claude Agent SDK.
@@ -25,6 +45,18 @@ struct Cli {
     /// Enable verbose logging
     #[arg(short, long, global = true)]
     verbose: bool,
+
+    /// Model for every workflow, overriding the configuration
+    #[arg(long, global = true)]
+    model: Option<String>,
+
+    /// Claude API base URL, overriding the configuration
+    #[arg(long, global = true)]
+    base_url: Option<String>,
+
+    /// Configuration profile to apply ([profiles.<name>]), overriding MPCA_PROFILE
+    #[arg(long, global = true)]
+    profile: Option<String>,
 }
 
 /// Available MPCA commands
@@ -80,6 +112,162 @@ enum Commands {
         /// Feature slug to resume
         feature_name: String,
     },
+
+    /// Show or change a feature's phase
+    ///
+    /// Without --set, prints the current phase and transition history. With
+    /// --set, moves the feature to another phase (e.g. back from verify to
+    /// run after a failed review); illegal moves are rejected.
+    Phase {
+        /// Feature slug
+        feature_name: String,
+
+        /// Target phase (plan, run, verify)
+        #[arg(long)]
+        set: Option<Phase>,
+
+        /// Reason recorded in the transition history
+        #[arg(long, requires = "set")]
+        reason: Option<String>,
+    },
+
+    /// Show a feature's progress and effective configuration
+    ///
+    /// The configuration includes the feature's own overrides from
+    /// .mpca/specs/<slug>/config.toml and the profile it was last run with.
+    Status {
+        /// Feature slug
+        feature_name: String,
+
+        /// Print each configuration value with the layer it came from
+        #[arg(long)]
+        origin: bool,
+    },
+
+    /// Show agent usage and cost
+    ///
+    /// With a feature slug, breaks the usage of that feature down by phase
+    /// and plan step. Without one, totals every feature in .mpca/specs.
+    Cost {
+        /// Feature slug (all features if omitted)
+        feature_name: Option<String>,
+    },
+
+    /// Remove a leftover feature lock
+    ///
+    /// Removes the lock file of a feature whose MPCA process is gone. Locks
+    /// held by a live process on this host are only removed with --force.
+    Unlock {
+        /// Feature slug to unlock
+        feature_name: String,
+
+        /// Remove the lock even if its holder may still be running
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Inspect and edit the configuration
+    ///
+    /// The configuration is resolved from built-in defaults, then
+    /// ~/.config/mpca/config.toml, then .mpca/config.toml, then MPCA_*
+    /// environment variables, then command-line flags.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Inspect prompt templates
+    ///
+    /// Templates are resolved from the prompt_dirs in the configuration,
+    /// in order, then from .mpca/prompts/, then from the templates bundled
+    /// into mpca; each template comes from the first layer that has it.
+    /// Partials shared by the templates, such as partials/rules.j2, are
+    /// layered the same way.
+    Prompts {
+        #[command(subcommand)]
+        command: PromptsCommand,
+    },
+
+    /// Upgrade .mpca/ to the current layout
+    ///
+    /// Renames old configuration keys, moves feature files into the
+    /// documented layout and rewrites old state files, after copying .mpca/
+    /// to .mpca/backups/. Other commands apply pending migrations
+    /// automatically.
+    Migrate {
+        /// Print the pending changes without applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Configuration subcommands
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the effective configuration
+    Show {
+        /// Print each value with the layer it came from
+        #[arg(long)]
+        origin: bool,
+    },
+
+    /// Print the effective value of a key (e.g., git.auto_commit)
+    Get {
+        /// Dotted key path
+        key: String,
+    },
+
+    /// Set a key in .mpca/config.toml, keeping comments and formatting
+    Set {
+        /// Dotted key path
+        key: String,
+
+        /// New value, as a TOML value (strings may be left unquoted)
+        value: String,
+    },
+
+    /// Remove a key from .mpca/config.toml
+    Unset {
+        /// Dotted key path
+        key: String,
+    },
+
+    /// Check the effective configuration for invalid values
+    Validate,
+
+    /// Open .mpca/config.toml in $VISUAL or $EDITOR, then validate it
+    Edit,
+}
+
+/// Prompt template subcommands
+#[derive(Subcommand)]
+enum PromptsCommand {
+    /// Print the file a template is loaded from and the files it shadows
+    Which {
+        /// Template name without extension (e.g., plan)
+        name: String,
+    },
+
+    /// Copy bundled templates into .mpca/prompts/ for customization
+    ///
+    /// Templates that were already copied are left alone unless --force is
+    /// given.
+    Eject {
+        /// Template name without extension (e.g., plan or partials/rules); all
+        /// templates and partials if omitted
+        name: Option<String>,
+
+        /// Overwrite templates already in .mpca/prompts/
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Check every template for syntax errors and unknown variables
+    ///
+    /// Parses the templates of every layer, shadowed ones included, and
+    /// compares the variables they use with the variables mpca renders them
+    /// with. Exits non-zero if any template has a problem.
+    Check,
 }
 
 #[tokio::main]
@@ -90,18 +278,65 @@ async fn main() -> Result<()> {
     // Initialize tracing subscriber
     init_tracing(cli.verbose);
 
+    // Features a budget stop or interruption can be resumed for
+    let resumable = match &cli.command {
+        Commands::Run { feature_name } | Commands::Resume { feature_name } => {
+            Some(feature_name.clone())
+        }
+        _ => None,
+    };
+
+    let overrides = ConfigOverrides {
+        model: cli.model,
+        base_url: cli.base_url,
+        profile: cli.profile,
+    };
+
     // Execute command
-    if let Err(e) = run_command(cli.command).await {
+    if let Err(e) = run_command(cli.command, &overrides).await {
         // Log with tracing
         error!("Command failed: {:#}", e);
         // Also print to stderr for CLI users
         eprintln!("Error: {:#}", e);
-        std::process::exit(1);
+
+        match core_error(&e) {
+            Some(MPCAError::BudgetExceeded { .. }) => {
+                if let Some(feature_name) = resumable {
+                    eprintln!(
+                        "Progress was saved. Raise the [budget] limits in .mpca/config.toml, then run `mpca resume {}`.",
+                        feature_name
+                    );
+                }
+                std::process::exit(EXIT_BUDGET_EXCEEDED);
+            }
+            Some(MPCAError::Cancelled) => {
+                if let Some(feature_name) = resumable {
+                    eprintln!(
+                        "Progress was saved. Run `mpca resume {}` to continue.",
+                        feature_name
+                    );
+                }
+                std::process::exit(EXIT_INTERRUPTED);
+            }
+            Some(MPCAError::AuthenticationFailed(_)) => {
+                eprintln!(
+                    "Check api.api_key_env, api.api_key_cmd or api.api_key_file and api.headers in .mpca/config.toml."
+                );
+                std::process::exit(1);
+            }
+            _ => std::process::exit(1),
+        }
     }
 
     Ok(())
 }
 
+/// Finds the MPCA error a command failed with, if any
+fn core_error(e: &anyhow::Error) -> Option<&MPCAError> {
+    e.chain()
+        .find_map(|cause| cause.downcast_ref::<MPCAError>())
+}
+
 /// Initialize tracing subscriber for structured logging
 fn init_tracing(verbose: bool) {
     use tracing_subscriber::{EnvFilter, fmt};
@@ -120,7 +355,7 @@ fn init_tracing(verbose: bool) {
 }
 
 /// Execute the specified command
-async fn run_command(command: Commands) -> Result<()> {
+async fn run_command(command: Commands, overrides: &ConfigOverrides) -> Result<()> {
     match command {
         Commands::Init => {
             info!("Initializing repository for MPCA...");
@@ -131,24 +366,57 @@ async fn run_command(command: Commands) -> Result<()> {
             interactive,
         } => {
             info!("Planning feature: {}", feature_name);
-            run_plan(&feature_name, interactive).await
+            run_plan(&feature_name, interactive, overrides).await
         }
         Commands::Run { feature_name } => {
             info!("Executing feature: {}", feature_name);
-            run_execute(&feature_name).await
+            run_execute(&feature_name, overrides).await
         }
         Commands::Review { feature_name } => {
             info!("Reviewing feature: {}", feature_name);
-            run_review(&feature_name).await
+            run_review(&feature_name, overrides).await
         }
         Commands::Chat => {
             info!("Entering chat mode...");
-            run_chat().await
+            run_chat(overrides).await
         }
         Commands::Resume { feature_name } => {
             info!("Resuming feature: {}", feature_name);
-            run_resume(&feature_name).await
+            run_resume(&feature_name, overrides).await
+        }
+        Commands::Phase {
+            feature_name,
+            set,
+            reason,
+        } => run_phase(&feature_name, set, reason.as_deref(), overrides).await,
+        Commands::Status {
+            feature_name,
+            origin,
+        } => run_status(&feature_name, origin, overrides).await,
+        Commands::Cost { feature_name } => run_cost(feature_name.as_deref(), overrides).await,
+        Commands::Unlock {
+            feature_name,
+            force,
+        } => {
+            info!("Unlocking feature: {}", feature_name);
+            run_unlock(&feature_name, force, overrides).await
         }
+        Commands::Config { command } => match command {
+            ConfigCommand::Show { origin } => run_config_show(origin, overrides).await,
+            ConfigCommand::Get { key } => run_config_get(&key, overrides).await,
+            ConfigCommand::Set { key, value } => run_config_set(&key, &value).await,
+            ConfigCommand::Unset { key } => run_config_unset(&key).await,
+            ConfigCommand::Validate => run_config_validate(overrides).await,
+            ConfigCommand::Edit => run_config_edit(overrides).await,
+        },
+        Commands::Prompts { command } => match command {
+            PromptsCommand::Which { name } => run_prompts_which(&name, overrides).await,
+            PromptsCommand::Eject { name, force } => {
+                run_prompts_eject(name.as_deref(), force, overrides).await
+            }
+            PromptsCommand::Check => run_prompts_check(overrides).await,
+        },
+        Commands::Migrate { dry_run } => run_migrate(dry_run).await,
     }
 }
 
@@ -164,11 +432,12 @@ async fn run_init() -> Result<()> {
     let config = MpcaConfig::new(repo_root.clone());
 
     // Create runtime
-    let runtime = AgentRuntime::new(config).context("Failed to create agent runtime")?;
+    let runtime = create_runtime(config)?;
 
     // Execute init workflow
     runtime
         .init_project()
+        .await
         .context("Failed to initialize repository")?;
 
     println!("✔ Detected repository root");
@@ -186,16 +455,20 @@ async fn run_init() -> Result<()> {
 }
 
 /// Run the plan command
-async fn run_plan(feature_name: &str, interactive: bool) -> Result<()> {
+async fn run_plan(
+    feature_name: &str,
+    interactive: bool,
+    overrides: &ConfigOverrides,
+) -> Result<()> {
     // Find repository root
     let repo_root = find_repo_root()
         .context("Failed to find repository root - are you in a git repository?")?;
 
     // Load configuration
-    let config = load_config(&repo_root).context("Failed to load MPCA configuration")?;
+    let config = load_config(&repo_root, overrides).context("Failed to load MPCA configuration")?;
 
     // Create runtime
-    let runtime = AgentRuntime::new(config).context("Failed to create agent runtime")?;
+    let runtime = create_runtime(config)?;
 
     if interactive {
         // Run interactive TUI mode
@@ -208,6 +481,7 @@ async fn run_plan(feature_name: &str, interactive: bool) -> Result<()> {
         info!("Planning feature: {}", feature_name);
         runtime
             .plan_feature(feature_name)
+            .await
             .context("Feature planning failed")?;
         println!("✔ Feature planned: {}", feature_name);
         println!("\nNext steps:");
@@ -218,20 +492,21 @@ async fn run_plan(feature_name: &str, interactive: bool) -> Result<()> {
 }
 
 /// Run the execute command
-async fn run_execute(feature_name: &str) -> Result<()> {
+async fn run_execute(feature_name: &str, overrides: &ConfigOverrides) -> Result<()> {
     // Find repository root
     let repo_root = find_repo_root()
         .context("Failed to find repository root - are you in a git repository?")?;
 
     // Load configuration
-    let config = load_config(&repo_root).context("Failed to load MPCA configuration")?;
+    let config = load_config(&repo_root, overrides).context("Failed to load MPCA configuration")?;
 
-    // Create runtime
-    let runtime = AgentRuntime::new(config).context("Failed to create agent runtime")?;
+    // Create runtime; SIGINT/SIGTERM stop it at the next checkpoint
+    let runtime = cancel_on_signal(create_runtime(config)?);
 
-    // Execute feature (stub for now)
+    // Execute feature
     runtime
         .run_feature(feature_name)
+        .await
         .context("Feature execution failed")?;
 
     println!("✔ Feature executed: {}", feature_name);
@@ -242,13 +517,14 @@ async fn run_execute(feature_name: &str) -> Result<()> {
 }
 
 /// Run the review command
-async fn run_review(feature_name: &str) -> Result<()> {
+async fn run_review(feature_name: &str, overrides: &ConfigOverrides) -> Result<()> {
     // Find repository root
     let repo_root = find_repo_root()
         .context("Failed to find repository root - are you in a git repository?")?;
 
     // Load configuration
-    let _config = load_config(&repo_root).context("Failed to load MPCA configuration")?;
+    let _config =
+        load_config(&repo_root, overrides).context("Failed to load MPCA configuration")?;
 
     // Review feature (stub for now)
     println!("✔ Reviewing feature: {}", feature_name);
@@ -261,33 +537,289 @@ async fn run_review(feature_name: &str) -> Result<()> {
 }
 
 /// Run the chat command
-async fn run_chat() -> Result<()> {
+///
+/// Reads one message per line from stdin and prints the agent's reply,
+/// until end of input or `exit`.
+async fn run_chat(overrides: &ConfigOverrides) -> Result<()> {
     // Find repository root
     let repo_root = find_repo_root()
         .context("Failed to find repository root - are you in a git repository?")?;
 
     // Load configuration
-    let _config = load_config(&repo_root).context("Failed to load MPCA configuration")?;
+    let config = load_config(&repo_root, overrides).context("Failed to load MPCA configuration")?;
+    let runtime = create_runtime(config)?;
+
+    let interactive = std::io::stdin().is_terminal();
+    if interactive {
+        println!("Chatting with Claude in {}", repo_root.display());
+        println!("Type `exit` or press Ctrl-D to quit.\n");
+    }
+
+    loop {
+        if interactive {
+            print!("> ");
+            std::io::stdout().flush()?;
+        }
 
-    // Chat mode (stub for now)
-    println!("Chat mode is not yet implemented.");
-    println!("This will enable interactive conversation with Claude.");
+        let line = tokio::task::spawn_blocking(|| {
+            let mut line = String::new();
+            std::io::stdin()
+                .read_line(&mut line)
+                .map(|n| (n > 0).then_some(line))
+        })
+        .await
+        .context("Failed to read from stdin")??;
+        let Some(line) = line else {
+            break;
+        };
+
+        let message = line.trim();
+        if message.is_empty() {
+            continue;
+        }
+        if matches!(message, "exit" | "quit") {
+            break;
+        }
+
+        let reply = runtime.chat(message).await.context("Chat failed")?;
+        println!("{}\n", reply.trim_end());
+    }
 
     Ok(())
 }
 
 /// Run the resume command
-async fn run_resume(feature_name: &str) -> Result<()> {
+async fn run_resume(feature_name: &str, overrides: &ConfigOverrides) -> Result<()> {
+    // Find repository root
+    let repo_root = find_repo_root()
+        .context("Failed to find repository root - are you in a git repository?")?;
+
+    // Load configuration
+    let mut config =
+        load_config(&repo_root, overrides).context("Failed to load MPCA configuration")?;
+
+    let fs = StdFsAdapter::new();
+    let state_file = FeatureState::path(&config, feature_name);
+    let mut state = FeatureState::load(&fs, &state_file).context("Failed to load feature state")?;
+
+    // Reuse the profile the feature was run with unless another one is selected
+    if config.profile.is_none()
+        && let Some(profile) = &state.profile
+    {
+        let overrides = ConfigOverrides {
+            profile: Some(profile.clone()),
+            ..overrides.clone()
+        };
+        config = load_config(&repo_root, &overrides).with_context(|| {
+            format!("Failed to load MPCA configuration with profile {}", profile)
+        })?;
+        println!("Using profile {} recorded for {}", profile, feature_name);
+    }
+
+    if state.phase == Phase::Verify {
+        println!("Feature {} has already been verified.", feature_name);
+        println!("\nTo continue implementation, send it back first:");
+        println!("  mpca phase {} --set run", feature_name);
+        return Ok(());
+    }
+
+    // Match recorded progress against the current plan to find where to continue
+    let plan_file = state_file.with_file_name("plan.md");
+    let plan_steps = load_plan_steps(&fs, &plan_file, feature_name)?;
+    state.sync_steps(&plan_steps);
+
+    match state.next_step() {
+        Some(step) => println!(
+            "✔ Resuming feature: {} from step {}: {}",
+            feature_name, step.number, step.title
+        ),
+        None => match state.stages.iter().find(|s| s.status != StepStatus::Done) {
+            Some(stage) => println!(
+                "✔ Resuming feature: {} at stage {}",
+                feature_name, stage.name
+            ),
+            None => println!("✔ Resuming feature: {}", feature_name),
+        },
+    }
+
+    // Continue execution from the first unfinished step
+    let runtime = cancel_on_signal(create_runtime(config)?);
+    runtime
+        .run_feature(feature_name)
+        .await
+        .context("Feature execution failed")?;
+
+    let state = FeatureState::load(&fs, &state_file).context("Failed to load feature state")?;
+    println!("\nSteps:");
+    for step in &state.steps {
+        match &step.commit {
+            Some(commit) => println!(
+                "  {}. [{}] {} ({})",
+                step.number,
+                step.status,
+                step.title,
+                &commit[..commit.len().min(7)]
+            ),
+            None => println!("  {}. [{}] {}", step.number, step.status, step.title),
+        }
+    }
+    if state.stages.len() > 1 {
+        println!("\nStages:");
+        for stage in &state.stages {
+            println!("  [{}] {}", stage.status, stage.name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the phase command
+async fn run_phase(
+    feature_name: &str,
+    set: Option<Phase>,
+    reason: Option<&str>,
+    overrides: &ConfigOverrides,
+) -> Result<()> {
+    // Find repository root
+    let repo_root = find_repo_root()
+        .context("Failed to find repository root - are you in a git repository?")?;
+
+    // Load configuration
+    let config = load_config(&repo_root, overrides).context("Failed to load MPCA configuration")?;
+
+    let fs = StdFsAdapter::new();
+    let state = match set {
+        Some(phase) => {
+            info!("Setting phase of {} to {}", feature_name, phase);
+            let state = workflows::set_phase(&config, feature_name, phase, reason, &fs)
+                .await
+                .context("Failed to change feature phase")?;
+            println!("✔ Feature {} is now in phase {}", feature_name, state.phase);
+            state
+        }
+        None => {
+            let state = FeatureState::load(&fs, &FeatureState::path(&config, feature_name))
+                .context("Failed to load feature state")?;
+            println!("Feature {} is in phase {}", feature_name, state.phase);
+            state
+        }
+    };
+
+    if !state.transitions.is_empty() {
+        println!("\nHistory:");
+        for t in &state.transitions {
+            match &t.reason {
+                Some(reason) => println!(
+                    "  {}  {} -> {}  ({})",
+                    t.at.to_rfc3339(),
+                    t.from,
+                    t.to,
+                    reason
+                ),
+                None => println!("  {}  {} -> {}", t.at.to_rfc3339(), t.from, t.to),
+            }
+        }
+    }
+
+    let next: Vec<&str> = state
+        .phase
+        .allowed_transitions()
+        .iter()
+        .map(|p| p.as_str())
+        .collect();
+    println!("\nAllowed next phases: {}", next.join(", "));
+
+    Ok(())
+}
+
+/// Run the cost command
+async fn run_cost(feature_name: Option<&str>, overrides: &ConfigOverrides) -> Result<()> {
     // Find repository root
     let repo_root = find_repo_root()
         .context("Failed to find repository root - are you in a git repository?")?;
 
     // Load configuration
-    let _config = load_config(&repo_root).context("Failed to load MPCA configuration")?;
+    let config = load_config(&repo_root, overrides).context("Failed to load MPCA configuration")?;
+
+    let fs = StdFsAdapter::new();
+    let Some(feature_name) = feature_name else {
+        let states =
+            FeatureState::load_all(&config, &fs).context("Failed to load feature states")?;
+        if states.is_empty() {
+            println!("No features planned yet.");
+            return Ok(());
+        }
+
+        let width = states
+            .iter()
+            .map(|s| s.feature_slug.len())
+            .max()
+            .unwrap_or(0)
+            .max("total".len());
+        let totals: Vec<Usage> = states.iter().map(FeatureState::total_usage).collect();
 
-    // Resume workflow (stub for now)
-    println!("✔ Resuming feature: {}", feature_name);
-    println!("\nResume functionality is not yet implemented.");
+        println!("Usage of all features:");
+        for (state, usage) in states.iter().zip(&totals) {
+            println!("  {:<width$}  {}", state.feature_slug, usage);
+        }
+        println!("  {:<width$}  {}", "total", totals.iter().sum::<Usage>());
+        return Ok(());
+    };
+
+    let state = FeatureState::load(&fs, &FeatureState::path(&config, feature_name))
+        .context("Failed to load feature state")?;
+
+    println!("Usage of feature {}:", feature_name);
+    for (phase, usage) in &state.usage {
+        println!("  {:<6}  {}", phase.as_str(), usage);
+    }
+    println!("  {:<6}  {}", "total", state.total_usage());
+
+    let steps: Vec<_> = state.steps.iter().filter(|s| !s.usage.is_empty()).collect();
+    if !steps.is_empty() {
+        println!("\nSteps:");
+        for step in steps {
+            println!("  {}. {}: {}", step.number, step.title, step.usage);
+        }
+    }
+
+    let stages: Vec<_> = state
+        .stages
+        .iter()
+        .filter(|s| !s.usage.is_empty())
+        .collect();
+    if !stages.is_empty() {
+        println!("\nStages:");
+        for stage in stages {
+            println!("  {}: {}", stage.name, stage.usage);
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the unlock command
+async fn run_unlock(feature_name: &str, force: bool, overrides: &ConfigOverrides) -> Result<()> {
+    // Find repository root
+    let repo_root = find_repo_root()
+        .context("Failed to find repository root - are you in a git repository?")?;
+
+    // Load configuration
+    let config = load_config(&repo_root, overrides).context("Failed to load MPCA configuration")?;
+
+    let fs = StdFsAdapter::new();
+    match mpca_core::lock::unlock_feature(&config, feature_name, &fs, force)
+        .context("Failed to unlock feature")?
+    {
+        Some(holder) => println!(
+            "✔ Removed lock on {} held by pid {} on {} since {}",
+            feature_name,
+            holder.pid,
+            holder.host,
+            holder.acquired_at.to_rfc3339()
+        ),
+        None => println!("Feature {} is not locked.", feature_name),
+    }
 
     Ok(())
 }
@@ -312,8 +844,235 @@ fn find_repo_root() -> Result<PathBuf> {
     }
 }
 
-/// Load MPCA configuration from .mpca/config.toml
-fn load_config(repo_root: &Path) -> Result<MpcaConfig> {
+/// Run the config show command
+async fn run_config_show(origin: bool, overrides: &ConfigOverrides) -> Result<()> {
+    // Find repository root
+    let repo_root = find_repo_root()
+        .context("Failed to find repository root - are you in a git repository?")?;
+
+    let loaded =
+        load_config_layers(&repo_root, overrides).context("Failed to load MPCA configuration")?;
+    print_config(&loaded, origin)
+}
+
+/// Print a resolved configuration as TOML, or one value per line with its origin
+fn print_config(loaded: &LoadedConfig, origin: bool) -> Result<()> {
+    if !origin {
+        let content =
+            toml::to_string(&loaded.config).context("Failed to serialize configuration")?;
+        print!("{}", content);
+        return Ok(());
+    }
+
+    let entries: Vec<_> = loaded
+        .entries()?
+        .into_iter()
+        .map(|(key, value, origin)| (format!("{} = {}", key, value), origin))
+        .collect();
+    let width = entries
+        .iter()
+        .map(|(line, _)| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (line, origin) in entries {
+        println!("{:<width$}  # {}", line, origin, width = width);
+    }
+
+    Ok(())
+}
+
+/// Run the status command
+async fn run_status(feature_name: &str, origin: bool, overrides: &ConfigOverrides) -> Result<()> {
+    // Find repository root
+    let repo_root = find_repo_root()
+        .context("Failed to find repository root - are you in a git repository?")?;
+
+    // Load configuration
+    let config = load_config(&repo_root, overrides).context("Failed to load MPCA configuration")?;
+
+    let fs = StdFsAdapter::new();
+    let state = FeatureState::load(&fs, &FeatureState::path(&config, feature_name))
+        .context("Failed to load feature state")?;
+
+    // Resolve the configuration `mpca resume` would use for the feature
+    let mut overrides = overrides.clone();
+    if config.profile.is_none() {
+        overrides.profile = state.profile.clone();
+    }
+    let loaded = ConfigLoader::new(repo_root)
+        .with_overrides(overrides)
+        .with_feature(feature_name)
+        .load()
+        .context("Failed to load feature configuration")?;
+
+    println!("Feature: {}", feature_name);
+    println!("Phase:   {}", state.phase);
+    if !state.steps.is_empty() {
+        println!(
+            "Steps:   {}/{} done",
+            state.completed_steps().count(),
+            state.steps.len()
+        );
+    }
+    if !state.stages.is_empty() {
+        let stages: Vec<String> = state
+            .stages
+            .iter()
+            .map(|s| format!("{} [{}]", s.name, s.status))
+            .collect();
+        println!("Stages:  {}", stages.join(", "));
+    }
+    if let Some(profile) = &loaded.config.profile {
+        println!("Profile: {}", profile);
+    }
+    let feature_config = config.feature_config_file(feature_name);
+    if feature_config.exists() {
+        println!("Config:  {}", feature_config.display());
+    }
+
+    println!("\nEffective configuration:\n");
+    print_config(&loaded, origin)
+}
+
+/// Run the config get command
+async fn run_config_get(key: &str, overrides: &ConfigOverrides) -> Result<()> {
+    // Find repository root
+    let repo_root = find_repo_root()
+        .context("Failed to find repository root - are you in a git repository?")?;
+
+    let loaded =
+        load_config_layers(&repo_root, overrides).context("Failed to load MPCA configuration")?;
+
+    let key = canonical_key(key);
+    let nested = format!("{}.", key);
+    let entries: Vec<_> = loaded
+        .entries()?
+        .into_iter()
+        .filter(|(entry, _, _)| *entry == key || entry.starts_with(&nested))
+        .collect();
+
+    match entries.as_slice() {
+        [] => anyhow::bail!("Unknown or unset config key: {}", key),
+        [(entry, value, _)] if *entry == key => println!("{}", value),
+        _ => {
+            for (entry, value, _) in entries {
+                println!("{} = {}", entry, value);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the config set command
+async fn run_config_set(key: &str, value: &str) -> Result<()> {
+    let repo_root = open_config_repo()?;
+
+    let mut file = ConfigFile::open(&repo_root).context("Failed to open MPCA configuration")?;
+    file.set(key, value)
+        .with_context(|| format!("Failed to set {}", key))?;
+    file.save().context("Failed to write MPCA configuration")?;
+
+    println!("✔ Set {} in {}", key, file.path().display());
+    Ok(())
+}
+
+/// Run the config unset command
+async fn run_config_unset(key: &str) -> Result<()> {
+    let repo_root = open_config_repo()?;
+
+    let mut file = ConfigFile::open(&repo_root).context("Failed to open MPCA configuration")?;
+    if file
+        .unset(key)
+        .with_context(|| format!("Failed to unset {}", key))?
+    {
+        file.save().context("Failed to write MPCA configuration")?;
+        println!("✔ Removed {} from {}", key, file.path().display());
+    } else {
+        println!("{} is not set in {}", key, file.path().display());
+    }
+
+    Ok(())
+}
+
+/// Run the config validate command
+async fn run_config_validate(overrides: &ConfigOverrides) -> Result<()> {
+    // Find repository root
+    let repo_root = find_repo_root()
+        .context("Failed to find repository root - are you in a git repository?")?;
+
+    let config = load_config(&repo_root, overrides).context("Failed to load MPCA configuration")?;
+    report_problems(&config)
+}
+
+/// Run the config edit command
+async fn run_config_edit(overrides: &ConfigOverrides) -> Result<()> {
+    let repo_root = open_config_repo()?;
+    let config_file = repo_root.join(".mpca").join("config.toml");
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut args = editor.split_whitespace();
+    let program = args.next().context("$VISUAL or $EDITOR is empty")?;
+
+    let status = std::process::Command::new(program)
+        .args(args)
+        .arg(&config_file)
+        .status()
+        .with_context(|| format!("Failed to start editor: {}", editor))?;
+    if !status.success() {
+        anyhow::bail!("Editor exited with {}", status);
+    }
+
+    let config = load_config(&repo_root, overrides).context("Failed to load MPCA configuration")?;
+    report_problems(&config)
+}
+
+/// Print every problem in a configuration, failing if there are any
+fn report_problems(config: &MpcaConfig) -> Result<()> {
+    let problems = config.problems();
+    if problems.is_empty() {
+        println!("✔ Configuration is valid");
+        return Ok(());
+    }
+
+    for problem in &problems {
+        println!("✘ {}", problem);
+    }
+    Err(MPCAError::InvalidConfig(format!(
+        "{} problem(s) found in the configuration",
+        problems.len()
+    ))
+    .into())
+}
+
+/// Find the repository root of an initialized repository
+fn open_config_repo() -> Result<PathBuf> {
+    let repo_root = find_repo_root()
+        .context("Failed to find repository root - are you in a git repository?")?;
+    ensure_initialized(&repo_root)?;
+    upgrade_layout(&repo_root)?;
+    Ok(repo_root)
+}
+
+/// Load the effective MPCA configuration from all configuration layers
+fn load_config(repo_root: &Path, overrides: &ConfigOverrides) -> Result<MpcaConfig> {
+    Ok(load_config_layers(repo_root, overrides)?.config)
+}
+
+/// Resolve the MPCA configuration, keeping the origin of each value
+fn load_config_layers(repo_root: &Path, overrides: &ConfigOverrides) -> Result<LoadedConfig> {
+    ensure_initialized(repo_root)?;
+    upgrade_layout(repo_root)?;
+
+    Ok(ConfigLoader::new(repo_root.to_path_buf())
+        .with_overrides(overrides.clone())
+        .load()?)
+}
+
+/// Fail unless `mpca init` has been run in the repository
+fn ensure_initialized(repo_root: &Path) -> Result<()> {
     // Check if .mpca directory exists
     let mpca_dir = repo_root.join(".mpca");
     if !mpca_dir.exists() {
@@ -324,7 +1083,228 @@ fn load_config(repo_root: &Path) -> Result<MpcaConfig> {
         );
     }
 
-    // For now, just return default config
-    // TODO: Load from config.toml file in Stage 5
-    Ok(MpcaConfig::new(repo_root.to_path_buf()))
+    Ok(())
+}
+
+/// Run the prompts which command
+async fn run_prompts_which(name: &str, overrides: &ConfigOverrides) -> Result<()> {
+    // Find repository root
+    let repo_root = find_repo_root()
+        .context("Failed to find repository root - are you in a git repository?")?;
+
+    let config = load_config(&repo_root, overrides).context("Failed to load MPCA configuration")?;
+    let dirs = prompts::template_dirs(&config);
+    let pm = prompts::prompt_manager(&config).context("Failed to load prompt templates")?;
+
+    let candidates = pm.candidates(name);
+    let Some((winner, shadowed)) = candidates.split_first() else {
+        let mut searched: Vec<String> = dirs.iter().map(|dir| dir.display().to_string()).collect();
+        searched.push("bundled templates".to_string());
+        return Err(MPCAError::TemplateNotFound(format!(
+            "{} (searched: {})",
+            name,
+            searched.join(", ")
+        ))
+        .into());
+    };
+
+    println!("{}", winner);
+    for source in shadowed {
+        println!("  shadows {}", source);
+    }
+    Ok(())
+}
+
+/// Run the prompts eject command
+async fn run_prompts_eject(
+    name: Option<&str>,
+    force: bool,
+    overrides: &ConfigOverrides,
+) -> Result<()> {
+    // Find repository root
+    let repo_root = find_repo_root()
+        .context("Failed to find repository root - are you in a git repository?")?;
+
+    let config = load_config(&repo_root, overrides).context("Failed to load MPCA configuration")?;
+    let ejected = prompts::eject(&config, &StdFsAdapter::new(), name, force)
+        .context("Failed to copy prompt templates")?;
+
+    for template in &ejected {
+        let path = template
+            .path
+            .strip_prefix(&repo_root)
+            .unwrap_or(&template.path);
+        if template.written {
+            println!("✔ Wrote {}", path.display());
+        } else {
+            println!(
+                "- Kept {} (already exists; use --force to overwrite)",
+                path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Run the prompts check command
+async fn run_prompts_check(overrides: &ConfigOverrides) -> Result<()> {
+    // Find repository root
+    let repo_root = find_repo_root()
+        .context("Failed to find repository root - are you in a git repository?")?;
+
+    let config = load_config(&repo_root, overrides).context("Failed to load MPCA configuration")?;
+    let pm = prompts::prompt_manager(&config).context("Failed to load prompt templates")?;
+    let checks = pm.check().context("Failed to read prompt templates")?;
+
+    let mut problems = 0;
+    for check in &checks {
+        let source = match &check.source {
+            TemplateSource::File(path) => {
+                TemplateSource::File(path.strip_prefix(&repo_root).unwrap_or(path).to_path_buf())
+            }
+            bundled => bundled.clone(),
+        };
+        if check.is_ok() {
+            println!("✔ {}", source);
+            continue;
+        }
+
+        println!("✘ {}", source);
+        for problem in &check.problems {
+            println!("    {}", problem);
+        }
+        problems += check.problems.len();
+    }
+
+    if problems > 0 {
+        return Err(MPCAError::InvalidTemplates(format!(
+            "{} problem(s) found in prompt templates",
+            problems
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Run the migrate command
+async fn run_migrate(dry_run: bool) -> Result<()> {
+    // Find repository root
+    let repo_root = find_repo_root()
+        .context("Failed to find repository root - are you in a git repository?")?;
+    ensure_initialized(&repo_root)?;
+
+    let config = MpcaConfig::new(repo_root.clone());
+    let fs = StdFsAdapter::new();
+    let report = if dry_run {
+        migrate::pending_migrations(&config, &fs)
+    } else {
+        migrate::migrate(&config, &fs)
+    }
+    .context("Failed to upgrade .mpca")?;
+
+    if report.is_up_to_date() {
+        println!("✔ .mpca is up to date (layout version {})", report.to);
+        return Ok(());
+    }
+
+    println!("Layout version {} -> {}", report.from, report.to);
+    for migration in &report.migrations {
+        println!("  {}: {}", migration.version, migration.description);
+        for action in &migration.actions {
+            println!("       {}", action.describe(&repo_root));
+        }
+    }
+
+    if dry_run {
+        println!("\nDry run: nothing was changed. Run `mpca migrate` to apply.");
+    } else {
+        println!("\n✔ Upgraded .mpca to layout version {}", report.to);
+        if let Some(backup) = &report.backup {
+            println!("  Previous files are in {}", backup.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply pending `.mpca/` layout migrations, reporting them on stderr
+fn upgrade_layout(repo_root: &Path) -> Result<()> {
+    let config = MpcaConfig::new(repo_root.to_path_buf());
+    let report = migrate::migrate(&config, &StdFsAdapter::new())
+        .context("Failed to upgrade .mpca (see `mpca migrate --dry-run`)")?;
+
+    if !report.is_up_to_date() {
+        eprintln!(
+            "✔ Upgraded .mpca from layout version {} to {}",
+            report.from, report.to
+        );
+        if let Some(backup) = &report.backup {
+            eprintln!("  Previous files are in {}", backup.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Create the agent runtime
+///
+/// When `MPCA_AGENT_TRANSCRIPT` is set, the agent is replaced by the recorded
+/// transcript at that path so workflows can run offline.
+fn create_runtime(config: MpcaConfig) -> Result<AgentRuntime> {
+    let runtime = AgentRuntime::new(config).context("Failed to create agent runtime")?;
+
+    match std::env::var_os(TRANSCRIPT_ENV) {
+        Some(path) => {
+            let backend = ScriptedAgentBackend::from_file(Path::new(&path)).with_context(|| {
+                format!("Failed to load agent transcript from {}", TRANSCRIPT_ENV)
+            })?;
+            info!("Replaying agent transcript: {}", Path::new(&path).display());
+            Ok(runtime.with_agent_backend(Arc::new(backend)))
+        }
+        None => Ok(runtime),
+    }
+}
+
+/// Cancel the runtime's workflows on SIGINT or SIGTERM
+///
+/// The first signal abandons the running agent turn; the workflow then
+/// commits the worktree as work in progress (unless `git.wip_commit` is off)
+/// and checkpoints its state instead of the process being killed mid-step.
+/// A second signal exits immediately.
+fn cancel_on_signal(runtime: AgentRuntime) -> AgentRuntime {
+    let cancel = CancellationToken::new();
+    let token = cancel.clone();
+    tokio::spawn(async move {
+        let Ok(signal) = shutdown_signal().await else {
+            return;
+        };
+        eprintln!(
+            "\nReceived {}, saving progress... (press Ctrl-C again to quit immediately)",
+            signal
+        );
+        token.cancel();
+
+        if shutdown_signal().await.is_ok() {
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+    });
+    runtime.with_cancellation(cancel)
+}
+
+/// Wait for SIGINT or SIGTERM, returning the signal's name
+#[cfg(unix)]
+async fn shutdown_signal() -> std::io::Result<&'static str> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result.map(|_| "SIGINT"),
+        _ = terminate.recv() => Ok("SIGTERM"),
+    }
+}
+
+/// Wait for Ctrl-C, returning the signal's name
+#[cfg(not(unix))]
+async fn shutdown_signal() -> std::io::Result<&'static str> {
+    tokio::signal::ctrl_c().await.map(|_| "Ctrl-C")
 }
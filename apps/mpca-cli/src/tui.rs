@@ -4,14 +4,12 @@
 //! Users can review generated specs, edit content, and approve or regenerate plans.
 
 use anyhow::{Context, Result};
-use claude_agent_sdk_rs::{ClaudeAgentOptions, ClaudeClient, ContentBlock, Message};
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use futures::stream::StreamExt;
-use mpca_core::AgentRuntime;
+use mpca_core::{AgentRuntime, AgentSession, WorkflowKind, prompts};
 use ratatui::{
     Frame, Terminal,
     backend::CrosstermBackend,
@@ -163,7 +161,15 @@ impl PlanningApp {
 }
 
 /// Run the interactive planning TUI
-pub async fn run_planning_tui(feature_name: &str, _runtime: &AgentRuntime) -> Result<()> {
+pub async fn run_planning_tui(feature_name: &str, runtime: &AgentRuntime) -> Result<()> {
+    // Configure the agent for the planning workflow before touching the terminal
+    let context = prompts::plan_context(&runtime.config, feature_name, runtime.tools.fs.as_ref())
+        .context("Failed to read the feature's specs")?;
+    let mut session = runtime
+        .agent_runner()
+        .session(WorkflowKind::Plan, &context)
+        .context("Failed to configure planning agent")?;
+
     // Setup terminal
     enable_raw_mode().context("Failed to enable raw mode")?;
     let mut stdout = io::stdout();
@@ -176,7 +182,6 @@ pub async fn run_planning_tui(feature_name: &str, _runtime: &AgentRuntime) -> Re
     // Create app state
     let mut app = PlanningApp::new(feature_name.to_string());
 
-    // Initialize Claude client with planning-appropriate settings
     let initial_prompt = format!(
         "I'm planning a new feature called '{}'. \
          Help me create comprehensive specifications including:\n\
@@ -195,33 +200,8 @@ pub async fn run_planning_tui(feature_name: &str, _runtime: &AgentRuntime) -> Re
 
     // Spawn agent task
     let agent_task = tokio::spawn(async move {
-        // Configure Claude for planning workflow
-        let options = ClaudeAgentOptions {
-            model: Some("claude-3-5-sonnet-20241022".to_string()),
-            max_turns: Some(20),
-            ..Default::default()
-        };
-
-        let mut client = ClaudeClient::new(options);
-
-        // Connect to Claude
-        if let Err(e) = client.connect().await {
-            tracing::error!("Failed to connect to Claude: {}", e);
-            let _ = agent_tx.send(format!("Error: {}", e)).await;
-            return;
-        }
-
         // Send initial planning prompt
-        if let Err(e) = client.query(&initial_prompt).await {
-            tracing::error!("Failed to send initial prompt: {}", e);
-            let _ = agent_tx.send(format!("Error: {}", e)).await;
-            return;
-        }
-
-        // Process initial response
-        if let Err(e) = process_agent_response(&mut client, &agent_tx).await {
-            tracing::error!("Failed to process initial response: {}", e);
-        }
+        send_to_agent(&mut session, &initial_prompt, &agent_tx).await;
 
         // Wait for user messages
         while let Some(message) = user_rx.recv().await {
@@ -229,19 +209,11 @@ pub async fn run_planning_tui(feature_name: &str, _runtime: &AgentRuntime) -> Re
                 break;
             }
 
-            if let Err(e) = client.query(&message).await {
-                tracing::error!("Failed to send message: {}", e);
-                let _ = agent_tx.send(format!("Error: {}", e)).await;
-                continue;
-            }
-
-            if let Err(e) = process_agent_response(&mut client, &agent_tx).await {
-                tracing::error!("Failed to process response: {}", e);
-            }
+            send_to_agent(&mut session, &message, &agent_tx).await;
         }
 
         // Disconnect
-        if let Err(e) = client.disconnect().await {
+        if let Err(e) = session.disconnect().await {
             tracing::error!("Failed to disconnect from Claude: {}", e);
         }
     });
@@ -268,36 +240,22 @@ pub async fn run_planning_tui(feature_name: &str, _runtime: &AgentRuntime) -> Re
     result
 }
 
-/// Process Claude's response and send to UI
-async fn process_agent_response(
-    client: &mut ClaudeClient,
-    tx: &mpsc::Sender<String>,
-) -> Result<()> {
-    let mut response_text = String::new();
-    let mut stream = client.receive_messages();
-
-    while let Some(result) = stream.next().await {
-        match result? {
-            Message::Assistant(msg) => {
-                for block in msg.message.content {
-                    if let ContentBlock::Text(text) = block {
-                        response_text.push_str(&text.text);
-                    }
-                }
-            }
-            Message::Result(_) => {
-                // End of response
-                break;
-            }
-            _ => continue,
+/// Send a message to Claude and forward the response to the UI
+async fn send_to_agent(session: &mut AgentSession, message: &str, tx: &mpsc::Sender<String>) {
+    let reply = match session.send(message, &mut |_| {}).await {
+        Ok(response) => {
+            tracing::debug!(usage = %response.usage, "planning reply received");
+            response.text
         }
-    }
+        Err(e) => {
+            tracing::error!("Failed to get response from Claude: {}", e);
+            format!("Error: {}", e)
+        }
+    };
 
-    if !response_text.is_empty() {
-        tx.send(response_text).await?;
+    if !reply.is_empty() {
+        let _ = tx.send(reply).await;
     }
-
-    Ok(())
 }
 
 /// Run the main application loop
@@ -4,7 +4,8 @@
 //! proper behavior and error handling.
 
 use anyhow::Result;
-use std::process::Command;
+use std::io::Write;
+use std::process::{Command, Stdio};
 use tempfile::TempDir;
 
 /// Helper to create a temporary git repository
@@ -54,6 +55,12 @@ fn mpca_bin() -> String {
     format!("{}/../../target/debug/mpca", manifest_dir)
 }
 
+/// Path to the recorded agent transcript replayed instead of Claude
+fn offline_transcript() -> String {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    format!("{}/../../fixtures/transcripts/offline.json", manifest_dir)
+}
+
 #[test]
 fn test_cli_version() -> Result<()> {
     let output = Command::new(mpca_bin()).arg("--version").output()?;
@@ -122,6 +129,7 @@ fn test_plan_command_requires_init() -> Result<()> {
 
     let output = Command::new(mpca_bin())
         .args(["plan", "test-feature"])
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
         .current_dir(temp_repo.path())
         .output()?;
 
@@ -145,6 +153,7 @@ fn test_plan_command_after_init() -> Result<()> {
     // Now plan
     let output = Command::new(mpca_bin())
         .args(["plan", "test-feature"])
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
         .current_dir(temp_repo.path())
         .output()?;
 
@@ -161,6 +170,7 @@ fn test_run_command_requires_init() -> Result<()> {
 
     let output = Command::new(mpca_bin())
         .args(["run", "test-feature"])
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
         .current_dir(temp_repo.path())
         .output()?;
 
@@ -181,9 +191,17 @@ fn test_run_command_after_init() -> Result<()> {
         .current_dir(temp_repo.path())
         .output()?;
 
+    // Plan the feature so there is something to run
+    Command::new(mpca_bin())
+        .args(["plan", "test-feature"])
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
+        .current_dir(temp_repo.path())
+        .output()?;
+
     // Now run
     let output = Command::new(mpca_bin())
         .args(["run", "test-feature"])
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
         .current_dir(temp_repo.path())
         .output()?;
 
@@ -194,6 +212,51 @@ fn test_run_command_after_init() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_run_command_exits_with_budget_code() -> Result<()> {
+    let temp_repo = create_test_repo()?;
+
+    Command::new(mpca_bin())
+        .arg("init")
+        .current_dir(temp_repo.path())
+        .output()?;
+    Command::new(mpca_bin())
+        .args(["plan", "test-feature"])
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
+        .current_dir(temp_repo.path())
+        .output()?;
+
+    // The scripted step takes 3 turns, more than the cap allows
+    let config_path = temp_repo.path().join(".mpca/config.toml");
+    let mut config = std::fs::read_to_string(&config_path)?;
+    config.push_str("\n[budget.execute]\nmax_turns = 1\n");
+    std::fs::write(&config_path, config)?;
+
+    let output = Command::new(mpca_bin())
+        .args(["run", "test-feature"])
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
+        .current_dir(temp_repo.path())
+        .output()?;
+
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("max_turns"));
+    assert!(stderr.contains("mpca resume test-feature"));
+
+    // The interrupted step stays resumable
+    let state = std::fs::read_to_string(
+        temp_repo
+            .path()
+            .join(".mpca/specs/test-feature/specs/state.toml"),
+    )?;
+    let state: toml::Value = toml::from_str(&state)?;
+    let steps = state["steps"].as_array().expect("steps array");
+    assert_eq!(steps[0]["status"].as_str(), Some("in_progress"));
+    assert_eq!(steps[1]["status"].as_str(), Some("pending"));
+
+    Ok(())
+}
+
 #[test]
 fn test_review_command_requires_init() -> Result<()> {
     let temp_repo = create_test_repo()?;
@@ -259,15 +322,29 @@ fn test_chat_command_after_init() -> Result<()> {
         .current_dir(temp_repo.path())
         .output()?;
 
-    // Now chat
-    let output = Command::new(mpca_bin())
+    // Now chat, feeding one message on stdin
+    let mut child = Command::new(mpca_bin())
         .arg("chat")
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
         .current_dir(temp_repo.path())
-        .output()?;
-
-    assert!(output.status.success());
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(b"hello\nexit\n")?;
+    let output = child.wait_with_output()?;
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
     let stdout = String::from_utf8(output.stdout)?;
-    assert!(stdout.contains("not yet implemented") || stdout.contains("Chat mode"));
+    assert!(stdout.contains("Hello from the offline transcript."));
 
     Ok(())
 }
@@ -278,6 +355,7 @@ fn test_resume_command_requires_init() -> Result<()> {
 
     let output = Command::new(mpca_bin())
         .args(["resume", "test-feature"])
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
         .current_dir(temp_repo.path())
         .output()?;
 
@@ -298,15 +376,139 @@ fn test_resume_command_after_init() -> Result<()> {
         .current_dir(temp_repo.path())
         .output()?;
 
+    // Plan the feature so there is something to resume
+    Command::new(mpca_bin())
+        .args(["plan", "test-feature"])
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
+        .current_dir(temp_repo.path())
+        .output()?;
+
     // Now resume
     let output = Command::new(mpca_bin())
         .args(["resume", "test-feature"])
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
         .current_dir(temp_repo.path())
         .output()?;
 
     assert!(output.status.success());
     let stdout = String::from_utf8(output.stdout)?;
     assert!(stdout.contains("test-feature"));
+    assert!(stdout.contains("from step 1"));
+    assert!(stdout.contains("1. [done] Add core types"));
+
+    Ok(())
+}
+
+#[test]
+fn test_phase_command_rejects_illegal_move() -> Result<()> {
+    let temp_repo = create_test_repo()?;
+
+    Command::new(mpca_bin())
+        .arg("init")
+        .current_dir(temp_repo.path())
+        .output()?;
+    Command::new(mpca_bin())
+        .args(["plan", "test-feature"])
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
+        .current_dir(temp_repo.path())
+        .output()?;
+
+    // Plan -> Verify skips execution
+    let output = Command::new(mpca_bin())
+        .args(["phase", "test-feature", "--set", "verify"])
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("invalid state transition"));
+
+    // Plan -> Run is allowed
+    let output = Command::new(mpca_bin())
+        .args(["phase", "test-feature", "--set", "run", "--reason", "ready"])
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("phase run"));
+    assert!(stdout.contains("plan -> run"));
+
+    Ok(())
+}
+
+#[test]
+fn test_cost_command_reports_usage() -> Result<()> {
+    let temp_repo = create_test_repo()?;
+
+    Command::new(mpca_bin())
+        .arg("init")
+        .current_dir(temp_repo.path())
+        .output()?;
+    for feature in ["feature-one", "feature-two"] {
+        Command::new(mpca_bin())
+            .args(["plan", feature])
+            .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
+            .current_dir(temp_repo.path())
+            .output()?;
+    }
+    Command::new(mpca_bin())
+        .args(["resume", "feature-one"])
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
+        .current_dir(temp_repo.path())
+        .output()?;
+
+    // One feature, by phase and step
+    let output = Command::new(mpca_bin())
+        .args(["cost", "feature-one"])
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("plan    2 turns, 1500 in / 200 out tokens"));
+    assert!(stdout.contains("run     9 turns, 2700 in / 450 out tokens"));
+    assert!(stdout.contains("total   11 turns"));
+    assert!(stdout.contains("1. Add core types: 3 turns"));
+
+    // Every feature
+    let output = Command::new(mpca_bin())
+        .arg("cost")
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("feature-one  11 turns"));
+    assert!(stdout.contains("feature-two  2 turns"));
+    assert!(stdout.contains("total        13 turns"));
+
+    Ok(())
+}
+
+#[test]
+fn test_unlock_command_removes_stale_lock() -> Result<()> {
+    let temp_repo = create_test_repo()?;
+
+    Command::new(mpca_bin())
+        .arg("init")
+        .current_dir(temp_repo.path())
+        .output()?;
+
+    // Lock left behind by a process that no longer exists
+    let lock_file = temp_repo.path().join(".mpca/specs/test-feature/mpca.lock");
+    std::fs::create_dir_all(lock_file.parent().unwrap())?;
+    let lock_content = format!(
+        "pid = 999999999\nhost = \"{}\"\nacquired_at = \"2024-01-01T00:00:00Z\"\n",
+        String::from_utf8(Command::new("hostname").output()?.stdout)?.trim()
+    );
+    std::fs::write(&lock_file, lock_content)?;
+
+    let output = Command::new(mpca_bin())
+        .args(["unlock", "test-feature"])
+        .current_dir(temp_repo.path())
+        .output()?;
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("999999999"));
+    assert!(!lock_file.exists());
 
     Ok(())
 }
@@ -324,3 +526,432 @@ fn test_verbose_flag() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_config_show_origin() -> Result<()> {
+    let temp_repo = create_test_repo()?;
+    let home = tempfile::tempdir()?;
+
+    Command::new(mpca_bin())
+        .arg("init")
+        .current_dir(temp_repo.path())
+        .output()?;
+
+    let user_config = home.path().join(".config/mpca/config.toml");
+    std::fs::create_dir_all(user_config.parent().unwrap())?;
+    std::fs::write(&user_config, "[budget.feature]\nmax_cost_usd = 5.0\n")?;
+
+    let output = Command::new(mpca_bin())
+        .args(["config", "show", "--origin", "--model", "cli-model"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env("MPCA_BASE_URL", "http://localhost:8080")
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let line = |key: &str| {
+        stdout
+            .lines()
+            .find(|line| line.starts_with(&format!("{} = ", key)))
+            .unwrap_or_else(|| panic!("{} missing from:\n{}", key, stdout))
+            .to_string()
+    };
+    assert!(line("git.auto_commit").ends_with(".mpca/config.toml)"));
+    assert!(line("budget.feature.max_cost_usd").starts_with("budget.feature.max_cost_usd = 5.0 "));
+    assert!(line("budget.feature.max_cost_usd").contains("# user ("));
+    assert!(line("api.base_url").ends_with("# env (MPCA_BASE_URL)"));
+    assert!(line("agent_modes.plan.model").contains("\"cli-model\""));
+    assert!(line("agent_modes.plan.model").ends_with("# cli (--model)"));
+
+    // Without --origin the effective configuration is printed as TOML
+    let output = Command::new(mpca_bin())
+        .args(["config", "show"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("[git]"));
+    assert!(stdout.contains("max_cost_usd = 5.0"));
+
+    Ok(())
+}
+
+#[test]
+fn test_config_set_get_unset_validate() -> Result<()> {
+    let temp_repo = create_test_repo()?;
+    let home = tempfile::tempdir()?;
+    let mpca = |args: &[&str]| {
+        Command::new(mpca_bin())
+            .args(args)
+            .env("HOME", home.path())
+            .env_remove("XDG_CONFIG_HOME")
+            .current_dir(temp_repo.path())
+            .output()
+    };
+
+    mpca(&["init"])?;
+    let config_file = temp_repo.path().join(".mpca/config.toml");
+
+    let output = mpca(&[
+        "config",
+        "set",
+        "agent_modes.plan.model",
+        "claude-opus-4-20250514",
+    ])?;
+    assert!(output.status.success());
+    let output = mpca(&["config", "set", "git.auto_commit", "false"])?;
+    assert!(output.status.success());
+
+    // Comments written by init survive the edits
+    let content = std::fs::read_to_string(&config_file)?;
+    assert!(content.contains("# MPCA Configuration"));
+    assert!(content.contains("auto_commit = false"));
+
+    let output = mpca(&["config", "get", "agent_modes.plan.model"])?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?.trim(),
+        "\"claude-opus-4-20250514\""
+    );
+
+    let output = mpca(&["config", "get", "git"])?;
+    assert!(String::from_utf8(output.stdout)?.contains("git.auto_commit = false"));
+
+    // Invalid values are rejected and leave the file untouched
+    let output = mpca(&["config", "set", "agent_modes.plan.temperature", "2"])?;
+    assert!(!output.status.success());
+    let output = mpca(&["config", "set", "git.autocommit", "true"])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("unknown config key"));
+    assert_eq!(std::fs::read_to_string(&config_file)?, content);
+
+    let output = mpca(&["config", "unset", "git.auto_commit"])?;
+    assert!(output.status.success());
+    let output = mpca(&["config", "get", "git.auto_commit"])?;
+    assert_eq!(String::from_utf8(output.stdout)?.trim(), "true");
+
+    let output = mpca(&["config", "validate"])?;
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)?.contains("Configuration is valid"));
+
+    // Hand edits are checked by validate
+    let content = std::fs::read_to_string(&config_file)?;
+    std::fs::write(
+        &config_file,
+        content.replace("feature/{feature_slug}", "feature"),
+    )?;
+    let output = mpca(&["config", "validate"])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stdout)?.contains("{feature_slug}"));
+
+    Ok(())
+}
+
+#[test]
+fn test_profile_is_recorded_and_reused_on_resume() -> Result<()> {
+    let temp_repo = create_test_repo()?;
+
+    Command::new(mpca_bin())
+        .arg("init")
+        .current_dir(temp_repo.path())
+        .output()?;
+    let config_file = temp_repo.path().join(".mpca/config.toml");
+    let mut content = std::fs::read_to_string(&config_file)?;
+    content.push_str("\n[profiles.draft.agent_modes]\nrun = { model = \"draft-model\" }\n");
+    std::fs::write(&config_file, content)?;
+
+    let output = Command::new(mpca_bin())
+        .args(["config", "show", "--origin", "--profile", "draft"])
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.lines().any(|line| {
+        line.starts_with("agent_modes.execute.model = \"draft-model\"")
+            && line.ends_with("# profile (draft)")
+    }));
+
+    // Unknown profiles are rejected
+    let output = Command::new(mpca_bin())
+        .args(["config", "show"])
+        .env("MPCA_PROFILE", "nope")
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("expected one of draft"));
+
+    let output = Command::new(mpca_bin())
+        .args(["plan", "test-feature", "--profile", "draft"])
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert!(output.status.success());
+    let state = std::fs::read_to_string(
+        temp_repo
+            .path()
+            .join(".mpca/specs/test-feature/specs/state.toml"),
+    )?;
+    assert!(state.contains("profile = \"draft\""));
+
+    let output = Command::new(mpca_bin())
+        .args(["resume", "test-feature"])
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("Using profile draft recorded for test-feature"));
+
+    Ok(())
+}
+
+#[test]
+fn test_status_shows_feature_config() -> Result<()> {
+    let temp_repo = create_test_repo()?;
+
+    Command::new(mpca_bin())
+        .arg("init")
+        .current_dir(temp_repo.path())
+        .output()?;
+
+    // Overrides may be written before the feature is planned
+    let feature_dir = temp_repo.path().join(".mpca/specs/test-feature");
+    std::fs::create_dir_all(&feature_dir)?;
+    std::fs::write(
+        feature_dir.join("config.toml"),
+        "[git]\nbranch_naming = \"spike/{feature_slug}\"\n",
+    )?;
+
+    let output = Command::new(mpca_bin())
+        .args(["plan", "test-feature"])
+        .env("MPCA_AGENT_TRANSCRIPT", offline_transcript())
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert!(output.status.success());
+
+    let output = Command::new(mpca_bin())
+        .args(["status", "test-feature", "--origin"])
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("Phase:   plan"));
+    assert!(stdout.contains("Config:  "));
+    assert!(stdout.lines().any(|line| {
+        line.starts_with("git.branch_naming = \"spike/{feature_slug}\"")
+            && line.contains("# feature (")
+    }));
+
+    // The repository configuration is unchanged
+    let output = Command::new(mpca_bin())
+        .args(["config", "get", "git.branch_naming"])
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert_eq!(
+        String::from_utf8(output.stdout)?.trim(),
+        "\"feature/{feature_slug}\""
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_migrate_upgrades_old_layout() -> Result<()> {
+    let temp_repo = create_test_repo()?;
+
+    Command::new(mpca_bin())
+        .arg("init")
+        .current_dir(temp_repo.path())
+        .output()?;
+
+    // Lay the repository out the way older versions of mpca did
+    let mpca_dir = temp_repo.path().join(".mpca");
+    std::fs::remove_file(mpca_dir.join("version"))?;
+    std::fs::write(
+        mpca_dir.join("config.toml"),
+        "[tool_sets]\nrun = \"full\"\n",
+    )?;
+    let specs_dir = mpca_dir.join("specs/test-feature/specs");
+    std::fs::create_dir_all(&specs_dir)?;
+    std::fs::write(
+        specs_dir.join("state.toml"),
+        "feature_slug = \"test-feature\"\nphase = \"Plan\"\n",
+    )?;
+    std::fs::write(specs_dir.join("README.md"), "# Feature: test-feature\n")?;
+
+    let output = Command::new(mpca_bin())
+        .args(["migrate", "--dry-run"])
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert!(output.status.success(), "Dry run failed: {:?}", output);
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("Layout version 1 -> 3"));
+    assert!(stdout.contains("write .mpca/config.toml"));
+    assert!(stdout.contains("remove .mpca/specs/test-feature/specs/README.md"));
+    assert!(stdout.contains("nothing was changed"));
+    assert!(specs_dir.join("README.md").exists());
+    assert!(!mpca_dir.join("version").exists());
+
+    // Any command that reads the configuration upgrades first
+    let output = Command::new(mpca_bin())
+        .args(["config", "get", "tool_sets.execute"])
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?.trim(), "\"full\"");
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("Upgraded .mpca from layout version 1 to 3"));
+    assert!(stderr.contains(".mpca/backups/"));
+
+    assert_eq!(std::fs::read_to_string(mpca_dir.join("version"))?, "3\n");
+    assert_eq!(
+        std::fs::read_to_string(mpca_dir.join("config.toml"))?,
+        "[tool_sets]\nexecute = \"full\"\n"
+    );
+    assert!(!specs_dir.join("README.md").exists());
+    // The feature was never planned, so no plan is made up for it
+    assert!(!specs_dir.join("plan.md").exists());
+
+    let output = Command::new(mpca_bin())
+        .arg("migrate")
+        .current_dir(temp_repo.path())
+        .output()?;
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)?.contains("up to date (layout version 3)"));
+
+    Ok(())
+}
+
+#[test]
+fn test_prompts_which_shows_winning_layer() -> Result<()> {
+    let temp_repo = create_test_repo()?;
+    let home = tempfile::tempdir()?;
+    let mpca = |args: &[&str]| {
+        Command::new(mpca_bin())
+            .args(args)
+            .env("HOME", home.path())
+            .env_remove("XDG_CONFIG_HOME")
+            .current_dir(temp_repo.path())
+            .output()
+    };
+
+    mpca(&["init"])?;
+    let prompts = temp_repo.path().join(".mpca/prompts");
+    std::fs::create_dir_all(&prompts)?;
+    std::fs::write(prompts.join("plan.j2"), "Custom plan prompt")?;
+    let output = mpca(&["config", "set", "prompt_dirs", r#"[".mpca/prompts"]"#])?;
+    assert!(output.status.success());
+
+    // The override wins and shadows the bundled template
+    let output = mpca(&["prompts", "which", "plan"])?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines[0].ends_with(".mpca/prompts/plan.j2"));
+    assert!(lines[1].starts_with("  shadows "));
+    assert_eq!(lines[1], "  shadows <bundled>/plan.j2");
+    assert_eq!(lines.len(), 2);
+
+    // Templates without an override come from the bundled layer
+    let output = mpca(&["prompts", "which", "execute"])?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert_eq!(stdout.trim(), "<bundled>/execute.j2");
+
+    let output = mpca(&["prompts", "which", "nonexistent"])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("template not found: nonexistent"));
+
+    Ok(())
+}
+
+#[test]
+fn test_prompts_eject_copies_bundled_templates() -> Result<()> {
+    let temp_repo = create_test_repo()?;
+    let home = tempfile::tempdir()?;
+    let mpca = |args: &[&str]| {
+        Command::new(mpca_bin())
+            .args(args)
+            .env("HOME", home.path())
+            .env_remove("XDG_CONFIG_HOME")
+            .current_dir(temp_repo.path())
+            .output()
+    };
+
+    mpca(&["init"])?;
+    let output = mpca(&["prompts", "eject", "plan"])?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?.trim(),
+        "✔ Wrote .mpca/prompts/plan.j2"
+    );
+
+    // The copy is picked up without any configuration
+    let plan = temp_repo.path().join(".mpca/prompts/plan.j2");
+    std::fs::write(&plan, "Edited plan prompt")?;
+    let output = mpca(&["prompts", "which", "plan"])?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(
+        stdout
+            .lines()
+            .next()
+            .unwrap()
+            .ends_with(".mpca/prompts/plan.j2")
+    );
+
+    // Ejecting everything keeps the edited template
+    let output = mpca(&["prompts", "eject"])?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("Kept .mpca/prompts/plan.j2"));
+    assert!(stdout.contains("✔ Wrote .mpca/prompts/execute.j2"));
+    assert_eq!(std::fs::read_to_string(&plan)?, "Edited plan prompt");
+
+    let output = mpca(&["prompts", "eject", "plan", "--force"])?;
+    assert!(output.status.success());
+    assert_ne!(std::fs::read_to_string(&plan)?, "Edited plan prompt");
+
+    let output = mpca(&["prompts", "eject", "nonexistent"])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("template not found: nonexistent"));
+
+    Ok(())
+}
+
+#[test]
+fn test_prompts_check_fails_on_broken_templates() -> Result<()> {
+    let temp_repo = create_test_repo()?;
+    let home = tempfile::tempdir()?;
+    let mpca = |args: &[&str]| {
+        Command::new(mpca_bin())
+            .args(args)
+            .env("HOME", home.path())
+            .env_remove("XDG_CONFIG_HOME")
+            .current_dir(temp_repo.path())
+            .output()
+    };
+
+    mpca(&["init"])?;
+    let output = mpca(&["prompts", "check"])?;
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)?.contains("✔ <bundled>/plan.j2"));
+
+    let prompts = temp_repo.path().join(".mpca/prompts");
+    std::fs::create_dir_all(&prompts)?;
+    std::fs::write(prompts.join("plan.j2"), "Plan\n{{ feature_slug }\n")?;
+    std::fs::write(prompts.join("docs.j2"), "Document {{ feature }}")?;
+
+    let output = mpca(&["prompts", "check"])?;
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("✘ .mpca/prompts/docs.j2\n    undeclared variable `feature`"));
+    assert!(stdout.contains("✘ .mpca/prompts/plan.j2\n    2:17: syntax error: "));
+    assert!(stdout.contains("✔ <bundled>/plan.j2"));
+    assert!(String::from_utf8(output.stderr)?.contains("2 problem(s) found in prompt templates"));
+
+    Ok(())
+}
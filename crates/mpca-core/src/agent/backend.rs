@@ -0,0 +1,120 @@
+//! Agent backend trait and message types.
+//!
+//! This module defines the `AgentBackend` trait that [`AgentSession`]
+//! talks to, allowing the Claude Agent SDK to be replaced by recorded
+//! transcripts in tests.
+//!
+//! [`AgentSession`]: crate::agent::AgentSession
+
+use crate::error::Result;
+use crate::usage::Usage;
+use async_trait::async_trait;
+use claude_agent_sdk_rs::ClaudeAgentOptions;
+use serde::{Deserialize, Serialize};
+
+/// A message streamed from the agent while it works on a query.
+///
+/// Serialized with a `type` tag so transcripts can be stored as JSON:
+///
+/// ```json
+/// {"type": "text", "text": "Done."}
+/// {"type": "tool_use", "name": "Edit", "input": {"file_path": "src/lib.rs"}}
+/// {"type": "result", "turns": 3, "cost_usd": 0.02, "input_tokens": 1200, "output_tokens": 300}
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentEvent {
+    /// Assistant text.
+    Text {
+        /// Text content.
+        text: String,
+    },
+
+    /// The agent called a tool.
+    ToolUse {
+        /// Tool name (e.g., "Edit").
+        name: String,
+
+        /// Tool input as sent by the agent.
+        #[serde(default)]
+        input: serde_json::Value,
+    },
+
+    /// Resources consumed by one assistant message of an in-progress query.
+    ///
+    /// Lets a query that is abandoned mid-stream still be accounted for;
+    /// the final [`AgentEvent::Result`] reports the authoritative totals.
+    Usage(Usage),
+
+    /// The query finished. Always the last event of a query.
+    Result(AgentResult),
+}
+
+/// Final summary of a query, as reported by the agent.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AgentResult {
+    /// Turns, tokens, cost and duration of the query.
+    #[serde(flatten)]
+    pub usage: Usage,
+
+    /// Claude session identifier, if reported.
+    pub session_id: Option<String>,
+
+    /// Whether the query ended in an error.
+    pub is_error: bool,
+
+    /// Error description when `is_error` is set.
+    pub error: Option<String>,
+}
+
+/// Agent backend trait.
+///
+/// Creates connections to an agent. Implementations can be real (using the
+/// Claude Agent SDK) or scripted for testing.
+#[async_trait]
+pub trait AgentBackend: Send + Sync + std::fmt::Debug {
+    /// Opens a conversation with the agent.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options for the conversation (model, tools, prompt).
+    ///
+    /// # Returns
+    ///
+    /// A connection ready to accept queries.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::AgentError` if the agent cannot be started.
+    async fn connect(&self, options: &ClaudeAgentOptions) -> Result<Box<dyn AgentConnection>>;
+}
+
+/// An open conversation with the agent.
+#[async_trait]
+pub trait AgentConnection: Send {
+    /// Sends a user prompt.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::AgentError` if the prompt cannot be sent.
+    async fn query(&mut self, prompt: &str) -> Result<()>;
+
+    /// Receives the next event of the current query.
+    ///
+    /// # Returns
+    ///
+    /// The next event, or `None` if the agent closed the stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::AgentError` if receiving fails.
+    async fn next_event(&mut self) -> Result<Option<AgentEvent>>;
+
+    /// Closes the conversation.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::AgentError` if the agent fails to shut down.
+    async fn disconnect(&mut self) -> Result<()>;
+}
@@ -0,0 +1,128 @@
+//! Claude Agent SDK backend.
+//!
+//! This module provides the production implementation of the `AgentBackend`
+//! trait, which runs the Claude CLI through `claude-agent-sdk-rs`.
+
+use crate::agent::backend::{AgentBackend, AgentConnection, AgentEvent, AgentResult};
+use crate::error::Result;
+use crate::usage::Usage;
+use async_trait::async_trait;
+use claude_agent_sdk_rs::{ClaudeAgentOptions, ClaudeClient, ContentBlock, Message};
+use futures::StreamExt;
+use std::collections::VecDeque;
+
+/// Agent backend using the Claude Agent SDK.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SdkAgentBackend;
+
+impl SdkAgentBackend {
+    /// Creates a new SDK backend.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl AgentBackend for SdkAgentBackend {
+    async fn connect(&self, options: &ClaudeAgentOptions) -> Result<Box<dyn AgentConnection>> {
+        let mut client = ClaudeClient::try_new(options.clone())?;
+        client.connect().await?;
+
+        Ok(Box::new(SdkConnection {
+            client,
+            pending: VecDeque::new(),
+            last_message_id: None,
+        }))
+    }
+}
+
+/// Connection backed by a connected `ClaudeClient`.
+struct SdkConnection {
+    client: ClaudeClient,
+
+    /// Events from an assistant message not yet handed out.
+    pending: VecDeque<AgentEvent>,
+
+    /// ID of the last assistant message whose usage was reported.
+    last_message_id: Option<String>,
+}
+
+#[async_trait]
+impl AgentConnection for SdkConnection {
+    async fn query(&mut self, prompt: &str) -> Result<()> {
+        self.pending.clear();
+        self.last_message_id = None;
+        self.client.query(prompt).await?;
+        Ok(())
+    }
+
+    async fn next_event(&mut self) -> Result<Option<AgentEvent>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(Some(event));
+            }
+
+            let Some(message) = self.client.receive_messages().next().await else {
+                return Ok(None);
+            };
+
+            match message? {
+                Message::Assistant(msg) => {
+                    // Each content block arrives as its own message carrying
+                    // the usage of the whole API response, so report it once
+                    if let Some(usage) = &msg.message.usage
+                        && (msg.message.id.is_none() || msg.message.id != self.last_message_id)
+                    {
+                        self.last_message_id = msg.message.id.clone();
+                        self.pending.push_back(AgentEvent::Usage(Usage {
+                            turns: 1,
+                            ..Usage::from_token_json(usage)
+                        }));
+                    }
+
+                    for block in msg.message.content {
+                        match block {
+                            ContentBlock::Text(text) => {
+                                self.pending.push_back(AgentEvent::Text { text: text.text })
+                            }
+                            ContentBlock::ToolUse(tool) => {
+                                self.pending.push_back(AgentEvent::ToolUse {
+                                    name: tool.name,
+                                    input: tool.input,
+                                })
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Message::Result(result) => {
+                    let tokens = result
+                        .usage
+                        .as_ref()
+                        .map(Usage::from_token_json)
+                        .unwrap_or_default();
+
+                    return Ok(Some(AgentEvent::Result(AgentResult {
+                        usage: Usage {
+                            turns: result.num_turns,
+                            cost_usd: result.total_cost_usd.unwrap_or(0.0),
+                            duration_ms: result.duration_ms,
+                            ..tokens
+                        },
+                        session_id: Some(result.session_id),
+                        is_error: result.is_error,
+                        error: result.is_error.then(|| {
+                            format!("{}: {}", result.subtype, result.result.unwrap_or_default())
+                        }),
+                    })));
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        self.client.disconnect().await?;
+        Ok(())
+    }
+}
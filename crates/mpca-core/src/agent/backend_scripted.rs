@@ -0,0 +1,296 @@
+//! Scripted agent backend for testing.
+//!
+//! This module provides an implementation of the `AgentBackend` trait that
+//! replays recorded transcripts instead of running Claude, so workflows can
+//! be tested end-to-end offline.
+//!
+//! A transcript is a JSON file with a list of turns. Each query is answered
+//! by the first remaining turn whose `prompt_contains` matches the prompt
+//! (turns without it match any prompt). A turn is used once unless `repeat`
+//! is set:
+//!
+//! ```json
+//! {
+//!   "turns": [
+//!     {
+//!       "prompt_contains": "Implement step",
+//!       "repeat": true,
+//!       "events": [
+//!         {"type": "tool_use", "name": "Edit", "input": {"file_path": "src/lib.rs"}},
+//!         {"type": "text", "text": "Step done."},
+//!         {"type": "result", "turns": 2, "cost_usd": 0.01}
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Scripted tool calls are passed to the session's permission callback, so
+//! tool policies can be tested offline; the script carries on whatever the
+//! callback decides.
+//!
+//! Like the real agent, the backend honours the `max_turns` and
+//! `max_budget_usd` options: a scripted result exceeding them is turned into
+//! an error result capped at the limit, so budget stops can be tested
+//! offline too.
+
+use crate::agent::backend::{AgentBackend, AgentConnection, AgentEvent, AgentResult};
+use crate::error::{MPCAError, Result};
+use async_trait::async_trait;
+use claude_agent_sdk_rs::{
+    CanUseToolCallback, ClaudeAgentOptions, SystemPrompt, ToolPermissionContext,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// A recorded agent transcript.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Transcript {
+    /// Turns in the order they are matched.
+    pub turns: Vec<ScriptedTurn>,
+}
+
+/// The scripted answer to one query.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScriptedTurn {
+    /// Only answer prompts containing this text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_contains: Option<String>,
+
+    /// Keep the turn after use so it answers every matching query.
+    #[serde(default)]
+    pub repeat: bool,
+
+    /// Events streamed back, normally ending with a result event.
+    pub events: Vec<AgentEvent>,
+}
+
+/// A query received by the scripted backend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptedQuery {
+    /// The user prompt.
+    pub prompt: String,
+
+    /// Model requested in the session options.
+    pub model: Option<String>,
+
+    /// System prompt text (the appended text for presets).
+    pub system_prompt: Option<String>,
+
+    /// Working directory requested in the session options.
+    pub cwd: Option<PathBuf>,
+}
+
+/// Scripted agent backend for testing.
+///
+/// Replays a [`Transcript`] and records every query it receives. Clones
+/// share the same transcript and history, so a test can keep a handle
+/// while the runtime owns another.
+///
+/// # Examples
+///
+/// ```
+/// use mpca_core::agent::{AgentEvent, AgentResult, AgentSession, ScriptedAgentBackend, ScriptedTurn};
+/// use mpca_core::agent::build_options;
+/// use mpca_core::{MpcaConfig, WorkflowKind};
+/// use std::path::PathBuf;
+/// use std::sync::Arc;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> mpca_core::Result<()> {
+/// let backend = ScriptedAgentBackend::new(vec![ScriptedTurn {
+///     events: vec![
+///         AgentEvent::Text { text: "Hi!".to_string() },
+///         AgentEvent::Result(AgentResult::default()),
+///     ],
+///     ..Default::default()
+/// }]);
+///
+/// let config = MpcaConfig::new(PathBuf::from("/repo"));
+/// let session = AgentSession::new(build_options(&config, WorkflowKind::Plan, None)?)
+///     .with_backend(Arc::new(backend.clone()));
+///
+/// let response = session.run("Hello", &mut |_| {}).await?;
+/// assert_eq!(response.text, "Hi!");
+/// assert_eq!(backend.queries()[0].prompt, "Hello");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedAgentBackend {
+    /// Turns not yet used
+    turns: Arc<Mutex<Vec<ScriptedTurn>>>,
+    /// History of received queries
+    queries: Arc<Mutex<Vec<ScriptedQuery>>>,
+}
+
+impl ScriptedAgentBackend {
+    /// Creates a backend replaying the given turns.
+    ///
+    /// # Arguments
+    ///
+    /// * `turns` - Scripted turns, matched in order.
+    pub fn new(turns: Vec<ScriptedTurn>) -> Self {
+        Self {
+            turns: Arc::new(Mutex::new(turns)),
+            queries: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Creates a backend from a transcript.
+    ///
+    /// # Arguments
+    ///
+    /// * `transcript` - The transcript to replay.
+    pub fn from_transcript(transcript: Transcript) -> Self {
+        Self::new(transcript.turns)
+    }
+
+    /// Loads a transcript from a JSON file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the transcript file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::FileReadError` if the file cannot be read, or
+    /// `MPCAError::AgentError` if it is not a valid transcript.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| MPCAError::FileReadError(format!("{}: {}", path.display(), e)))?;
+        let transcript: Transcript = serde_json::from_str(&content).map_err(|e| {
+            MPCAError::AgentError(format!("invalid transcript {}: {}", path.display(), e))
+        })?;
+
+        Ok(Self::from_transcript(transcript))
+    }
+
+    /// Returns the queries received so far.
+    pub fn queries(&self) -> Vec<ScriptedQuery> {
+        self.queries.lock().unwrap().clone()
+    }
+
+    /// Returns the number of turns not yet used (repeating turns included).
+    pub fn remaining(&self) -> usize {
+        self.turns.lock().unwrap().len()
+    }
+
+    /// Picks the turn answering `prompt`.
+    fn take_turn(&self, prompt: &str) -> Result<ScriptedTurn> {
+        let mut turns = self.turns.lock().unwrap();
+        let index = turns
+            .iter()
+            .position(|turn| {
+                turn.prompt_contains
+                    .as_deref()
+                    .is_none_or(|needle| prompt.contains(needle))
+            })
+            .ok_or_else(|| {
+                MPCAError::AgentError(format!("no scripted turn matches prompt: {}", prompt))
+            })?;
+
+        if turns[index].repeat {
+            Ok(turns[index].clone())
+        } else {
+            Ok(turns.remove(index))
+        }
+    }
+}
+
+#[async_trait]
+impl AgentBackend for ScriptedAgentBackend {
+    async fn connect(&self, options: &ClaudeAgentOptions) -> Result<Box<dyn AgentConnection>> {
+        let system_prompt = options
+            .system_prompt
+            .as_ref()
+            .and_then(|prompt| match prompt {
+                SystemPrompt::Text(text) => Some(text.clone()),
+                SystemPrompt::Preset(preset) => preset.append.clone(),
+            });
+
+        Ok(Box::new(ScriptedConnection {
+            backend: self.clone(),
+            model: options.model.clone(),
+            system_prompt,
+            cwd: options.cwd.clone(),
+            can_use_tool: options.can_use_tool.clone(),
+            max_turns: options.max_turns,
+            max_budget_usd: options.max_budget_usd,
+            events: VecDeque::new(),
+        }))
+    }
+}
+
+/// Connection replaying scripted turns.
+struct ScriptedConnection {
+    backend: ScriptedAgentBackend,
+    model: Option<String>,
+    system_prompt: Option<String>,
+    cwd: Option<PathBuf>,
+    can_use_tool: Option<CanUseToolCallback>,
+    max_turns: Option<u32>,
+    max_budget_usd: Option<f64>,
+    events: VecDeque<AgentEvent>,
+}
+
+/// Stops a scripted result at the session's turn and cost limits.
+fn apply_limits(result: &mut AgentResult, max_turns: Option<u32>, max_budget_usd: Option<f64>) {
+    if result.is_error {
+        return;
+    }
+
+    if let Some(max) = max_turns
+        && result.usage.turns > max
+    {
+        result.usage.turns = max;
+        result.is_error = true;
+        result.error = Some("error_max_turns: reached the maximum number of turns".to_string());
+    } else if let Some(max) = max_budget_usd
+        && result.usage.cost_usd > max
+    {
+        result.is_error = true;
+        result.error = Some("error_max_budget_usd: reached the maximum budget".to_string());
+    }
+}
+
+#[async_trait]
+impl AgentConnection for ScriptedConnection {
+    async fn query(&mut self, prompt: &str) -> Result<()> {
+        self.backend.queries.lock().unwrap().push(ScriptedQuery {
+            prompt: prompt.to_string(),
+            model: self.model.clone(),
+            system_prompt: self.system_prompt.clone(),
+            cwd: self.cwd.clone(),
+        });
+
+        self.events = self.backend.take_turn(prompt)?.events.into();
+        for event in &mut self.events {
+            if let AgentEvent::Result(result) = event {
+                apply_limits(result, self.max_turns, self.max_budget_usd);
+            }
+        }
+        Ok(())
+    }
+
+    async fn next_event(&mut self) -> Result<Option<AgentEvent>> {
+        let event = self.events.pop_front();
+        if let Some(AgentEvent::ToolUse { name, input }) = &event
+            && let Some(can_use_tool) = &self.can_use_tool
+        {
+            can_use_tool(
+                name.clone(),
+                input.clone(),
+                ToolPermissionContext::default(),
+            )
+            .await;
+        }
+        Ok(event)
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,1302 @@
+//! Claude agent sessions.
+//!
+//! [`AgentSession`] is the single code path MPCA uses to talk to Claude.
+//! It builds `ClaudeAgentOptions` from the workflow's [`AgentMode`],
+//! [`ToolSet`] and [`ApiConfig`], renders the workflow's system prompt
+//! through `mpca-pm`, and streams the agent's messages, returning the
+//! final text together with the turn count and cost reported by the agent.
+//!
+//! Sessions talk to an [`AgentBackend`]: [`SdkAgentBackend`] runs Claude,
+//! while [`ScriptedAgentBackend`] replays recorded transcripts so workflows
+//! can be tested offline. Workflow sessions check every tool call against
+//! the configured tool policy with a [`ToolGuard`].
+//!
+//! [`AgentMode`]: crate::config::AgentMode
+//! [`ToolSet`]: crate::config::ToolSet
+//! [`ApiConfig`]: crate::config::ApiConfig
+
+pub mod backend;
+pub mod backend_impl;
+pub mod permissions;
+
+// Scripted backend for testing
+pub mod backend_scripted;
+
+pub use backend::{AgentBackend, AgentConnection, AgentEvent, AgentResult};
+pub use backend_impl::SdkAgentBackend;
+pub use backend_scripted::{ScriptedAgentBackend, ScriptedQuery, ScriptedTurn, Transcript};
+pub use permissions::ToolGuard;
+
+use crate::budget::Budget;
+use crate::cancel::CancellationToken;
+use crate::config::{
+    AgentMode, ApiCredentials, MpcaConfig, PermissionMode, Stage, ToolSet, WorkflowKind,
+};
+use crate::error::{MPCAError, Result};
+use crate::state::StepState;
+use crate::usage::Usage;
+use crate::workflows::StepRunner;
+use claude_agent_sdk_rs::{ClaudeAgentOptions, SystemPrompt, SystemPromptPreset};
+use mpca_pm::{ExecuteContext, PromptEngine, PromptManager, TemplateContext};
+use std::sync::Arc;
+
+/// Name of the Claude Code system prompt preset.
+pub const CODE_PRESET: &str = "claude_code";
+
+/// Environment variable used to point the Claude CLI at a custom API endpoint.
+const BASE_URL_ENV: &str = "ANTHROPIC_BASE_URL";
+
+/// Environment variable limiting the length of a single model response.
+const MAX_OUTPUT_TOKENS_ENV: &str = "CLAUDE_CODE_MAX_OUTPUT_TOKENS";
+
+/// Environment variable passing the API key to the Claude CLI.
+const API_KEY_ENV: &str = "ANTHROPIC_API_KEY";
+
+/// Environment variable passing extra request headers (`Name: value` lines)
+/// to the Claude CLI.
+const CUSTOM_HEADERS_ENV: &str = "ANTHROPIC_CUSTOM_HEADERS";
+
+/// The outcome of a single agent query.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AgentResponse {
+    /// Assistant text, concatenated across all turns.
+    pub text: String,
+
+    /// Turns, tokens, cost and duration of the query.
+    pub usage: Usage,
+
+    /// Claude session identifier, if reported.
+    pub session_id: Option<String>,
+}
+
+/// Returns the prompt template used as system prompt for a workflow.
+pub fn template_name(workflow: WorkflowKind) -> &'static str {
+    match workflow {
+        WorkflowKind::Init => "init",
+        WorkflowKind::Plan => "plan",
+        WorkflowKind::Execute => "execute",
+        WorkflowKind::Review => "review",
+        WorkflowKind::Verify => "verification",
+    }
+}
+
+impl From<PermissionMode> for claude_agent_sdk_rs::PermissionMode {
+    fn from(mode: PermissionMode) -> Self {
+        match mode {
+            PermissionMode::Default => Self::Default,
+            PermissionMode::AcceptEdits => Self::AcceptEdits,
+            PermissionMode::Plan => Self::Plan,
+            PermissionMode::BypassPermissions => Self::BypassPermissions,
+        }
+    }
+}
+
+/// Builds Claude agent options for a workflow.
+///
+/// The model, fallback model, output token limit, turn limit, permission
+/// mode and extra directories come from the workflow's agent mode, and the
+/// API endpoint, key and headers from the API configuration. The allowed
+/// tools are the workflow's tool set plus the mode's `allowed_tools`, minus
+/// its `disallowed_tools` and whatever the tool policy doesn't permit.
+///
+/// `system_prompt` is followed by the mode's `append_system_prompt`. When
+/// the mode uses the code preset, the result is appended to the Claude Code
+/// preset; otherwise it replaces it.
+///
+/// The Claude CLI has no temperature setting, so `AgentMode::temperature`
+/// is not applied.
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration
+/// * `workflow` - Workflow the agent runs for
+/// * `system_prompt` - Rendered system prompt, if any
+///
+/// # Returns
+///
+/// Options ready to pass to the Claude SDK.
+///
+/// # Errors
+///
+/// Returns `MPCAError::AuthenticationFailed` if the configured API key or
+/// a header's environment variable cannot be read.
+///
+/// # Examples
+///
+/// ```
+/// use mpca_core::agent::build_options;
+/// use mpca_core::{MpcaConfig, WorkflowKind};
+/// use std::path::PathBuf;
+///
+/// let config = MpcaConfig::new(PathBuf::from("/repo"));
+/// let options = build_options(&config, WorkflowKind::Plan, None)?;
+/// assert_eq!(options.model.as_deref(), Some(config.agent_modes.plan.model.as_str()));
+/// # Ok::<(), mpca_core::MPCAError>(())
+/// ```
+pub fn build_options(
+    config: &MpcaConfig,
+    workflow: WorkflowKind,
+    system_prompt: Option<String>,
+) -> Result<ClaudeAgentOptions> {
+    build_mode_options(
+        config,
+        config.agent_modes.get(workflow),
+        config.tool_sets.get(workflow),
+        system_prompt,
+    )
+}
+
+/// Builds Claude agent options for an explicit agent mode and tool set.
+///
+/// Used for pipeline stages, which bind their own mode and tools; see
+/// [`build_options`] for how the options are derived. Tools the tool
+/// policy doesn't permit are left out of the allowed tools.
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration with the API settings
+/// * `mode` - Agent mode to run with
+/// * `tool_set` - Tools the agent may use
+/// * `system_prompt` - Rendered system prompt, if any
+///
+/// # Errors
+///
+/// Returns `MPCAError::AuthenticationFailed` if the configured API key or
+/// a header's environment variable cannot be read.
+///
+/// # Examples
+///
+/// ```
+/// use mpca_core::agent::build_mode_options;
+/// use mpca_core::config::{AgentMode, ToolSet};
+/// use mpca_core::MpcaConfig;
+/// use std::path::PathBuf;
+///
+/// let config = MpcaConfig::new(PathBuf::from("/repo"));
+/// let mode = AgentMode::preset("standard").unwrap();
+/// let options = build_mode_options(&config, &mode, ToolSet::Minimal, None)?;
+/// assert_eq!(options.allowed_tools, ToolSet::Minimal.allowed_tools());
+/// # Ok::<(), mpca_core::MPCAError>(())
+/// ```
+pub fn build_mode_options(
+    config: &MpcaConfig,
+    mode: &AgentMode,
+    tool_set: ToolSet,
+    system_prompt: Option<String>,
+) -> Result<ClaudeAgentOptions> {
+    let system_prompt = match (system_prompt, &mode.append_system_prompt) {
+        (Some(prompt), Some(append)) => Some(format!("{}\n\n{}", prompt, append)),
+        (prompt, append) => prompt.or_else(|| append.clone()),
+    };
+    let system_prompt = match (mode.use_code_preset, system_prompt) {
+        (true, Some(prompt)) => Some(SystemPrompt::Preset(SystemPromptPreset::with_append(
+            CODE_PRESET,
+            prompt,
+        ))),
+        (true, None) => Some(SystemPrompt::Preset(SystemPromptPreset::new(CODE_PRESET))),
+        (false, prompt) => prompt.map(SystemPrompt::Text),
+    };
+
+    let mut allowed_tools = tool_set.allowed_tools();
+    for tool in &mode.allowed_tools {
+        if !allowed_tools.contains(tool) {
+            allowed_tools.push(tool.clone());
+        }
+    }
+    allowed_tools.retain(|tool| {
+        !mode.disallowed_tools.contains(tool) && config.tool_policy.permits_tool(tool)
+    });
+
+    let mut disallowed_tools = mode.disallowed_tools.clone();
+    for tool in &config.tool_policy.denied_tools {
+        if !disallowed_tools.contains(tool) {
+            disallowed_tools.push(tool.clone());
+        }
+    }
+
+    let mut options = ClaudeAgentOptions {
+        model: Some(mode.model.clone()),
+        fallback_model: mode.fallback_model.clone(),
+        max_turns: Some(mode.max_turns),
+        permission_mode: Some(mode.permission_mode.into()),
+        system_prompt,
+        allowed_tools,
+        disallowed_tools,
+        cwd: Some(config.repo_root.clone()),
+        add_dirs: mode
+            .add_dirs
+            .iter()
+            .map(|dir| config.repo_root.join(dir))
+            .collect(),
+        ..Default::default()
+    };
+
+    options.env.insert(
+        MAX_OUTPUT_TOKENS_ENV.to_string(),
+        mode.max_tokens.to_string(),
+    );
+    if let Some(base_url) = &config.api.base_url {
+        options
+            .env
+            .insert(BASE_URL_ENV.to_string(), base_url.clone());
+    }
+
+    let credentials = ApiCredentials::resolve(&config.api, &config.repo_root)?;
+    if let Some(api_key) = credentials.api_key {
+        options
+            .env
+            .insert(API_KEY_ENV.to_string(), api_key.expose().to_string());
+    }
+    if !credentials.headers.is_empty() {
+        let headers: Vec<String> = credentials
+            .headers
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, value.expose()))
+            .collect();
+        options
+            .env
+            .insert(CUSTOM_HEADERS_ENV.to_string(), headers.join("\n"));
+    }
+
+    Ok(options)
+}
+
+/// A conversation with the Claude agent.
+///
+/// The session connects lazily on the first query and keeps the
+/// conversation open until [`AgentSession::disconnect`] is called, so it
+/// can be used both for one-shot workflow queries and for interactive
+/// chats. Sessions use [`SdkAgentBackend`] unless another backend is set
+/// with [`AgentSession::with_backend`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use mpca_core::{AgentSession, MpcaConfig, WorkflowKind, prompts};
+/// use mpca_core::tools::fs_impl::StdFsAdapter;
+/// use std::path::PathBuf;
+///
+/// # async fn example() -> mpca_core::Result<()> {
+/// let config = MpcaConfig::new(PathBuf::from("/repo"));
+/// let context = prompts::plan_context(&config, "add-caching", &StdFsAdapter::new())?;
+/// let session = AgentSession::for_workflow(&config, WorkflowKind::Plan, None, &context)?;
+///
+/// let response = session.run("Draft a plan", &mut |_| {}).await?;
+/// println!("{} ({} turns)", response.text, response.usage.turns);
+/// # Ok(())
+/// # }
+/// ```
+pub struct AgentSession {
+    /// Options the connection is created with.
+    options: ClaudeAgentOptions,
+
+    /// Backend used to connect to the agent.
+    backend: Arc<dyn AgentBackend>,
+
+    /// Open connection, once the first query has been sent.
+    connection: Option<Box<dyn AgentConnection>>,
+
+    /// Caps enforced while the agent works, if any.
+    budget: Option<Budget>,
+
+    /// Abandons the query in flight when cancelled.
+    cancel: CancellationToken,
+
+    /// Checks the agent's tool calls, if set.
+    guard: Option<ToolGuard>,
+}
+
+impl AgentSession {
+    /// Creates a session with explicit options.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Claude agent options.
+    pub fn new(options: ClaudeAgentOptions) -> Self {
+        Self {
+            options,
+            backend: Arc::new(SdkAgentBackend::new()),
+            connection: None,
+            budget: None,
+            cancel: CancellationToken::new(),
+            guard: None,
+        }
+    }
+
+    /// Sets the backend used to connect to the agent.
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - Agent backend (e.g., a [`ScriptedAgentBackend`] in tests).
+    pub fn with_backend(mut self, backend: Arc<dyn AgentBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Enforces a budget on the session's queries.
+    ///
+    /// The remaining turns and cost are passed to the agent as its own
+    /// limits, and a query still running when the wall-clock cap is reached
+    /// is abandoned.
+    ///
+    /// # Arguments
+    ///
+    /// * `budget` - Budget of the workflow run; the session tracks its own copy.
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Stops the session's queries when `cancel` is triggered.
+    ///
+    /// # Arguments
+    ///
+    /// * `cancel` - Cancellation token of the workflow run.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Checks every tool call of the agent with `guard`.
+    ///
+    /// The guard is installed when the session connects, so it sees the
+    /// final allowed tools and working directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `guard` - Tool guard enforcing the tool policy.
+    pub fn with_tool_guard(mut self, guard: ToolGuard) -> Self {
+        self.guard = Some(guard);
+        self
+    }
+
+    /// Creates a session for a workflow.
+    ///
+    /// Renders the workflow's system prompt template with `context` when a
+    /// prompt manager is available, then builds the options with
+    /// [`build_options`]. Tool calls are checked against the configured
+    /// tool policy, with denials recorded for the context's feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - MPCA configuration
+    /// * `workflow` - Workflow the agent runs for
+    /// * `pm` - Prompt manager, if templates are available
+    /// * `context` - Context for the system prompt template
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::TemplateNotFound` or
+    /// `MPCAError::TemplateRenderError` if the system prompt cannot be
+    /// rendered, `MPCAError::InvalidTemplateContext` if the template uses a
+    /// variable `context` does not provide, or
+    /// `MPCAError::AuthenticationFailed` if the API key cannot be read.
+    pub fn for_workflow<C: TemplateContext>(
+        config: &MpcaConfig,
+        workflow: WorkflowKind,
+        pm: Option<&PromptManager>,
+        context: &C,
+    ) -> Result<Self> {
+        let system_prompt = match pm {
+            Some(pm) => Some(pm.render(template_name(workflow), context)?),
+            None => {
+                tracing::warn!(
+                    workflow = %workflow,
+                    "no prompt manager - running agent without a workflow system prompt"
+                );
+                None
+            }
+        };
+
+        Ok(Self::new(build_options(config, workflow, system_prompt)?)
+            .with_tool_guard(ToolGuard::for_feature(config, context.feature_slug())))
+    }
+
+    /// Creates a session for a pipeline stage.
+    ///
+    /// Renders the stage's template with `context` when a prompt manager is
+    /// available, and uses the stage's agent mode and tool set. Tool calls
+    /// are checked like in [`AgentSession::for_workflow`].
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - MPCA configuration
+    /// * `stage` - Pipeline stage the agent runs for
+    /// * `pm` - Prompt manager, if templates are available
+    /// * `context` - Context for the stage template
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`AgentSession::for_workflow`].
+    pub fn for_stage(
+        config: &MpcaConfig,
+        stage: &Stage,
+        pm: Option<&PromptManager>,
+        context: &ExecuteContext,
+    ) -> Result<Self> {
+        let system_prompt = match pm {
+            Some(pm) => Some(pm.render(&stage.template, context)?),
+            None => {
+                tracing::warn!(
+                    stage = %stage.name,
+                    "no prompt manager - running agent without a stage system prompt"
+                );
+                None
+            }
+        };
+
+        Ok(Self::new(build_mode_options(
+            config,
+            &stage.agent_mode,
+            stage.tool_set,
+            system_prompt,
+        )?)
+        .with_tool_guard(ToolGuard::for_feature(config, Some(&context.feature_slug))))
+    }
+
+    /// Returns the options the session uses.
+    pub fn options(&self) -> &ClaudeAgentOptions {
+        &self.options
+    }
+
+    /// Returns the options for modification before the first query.
+    pub fn options_mut(&mut self) -> &mut ClaudeAgentOptions {
+        &mut self.options
+    }
+
+    /// Sends a query and streams the agent's reply.
+    ///
+    /// Connects on the first call. `on_event` is called for every event as
+    /// it arrives, including the final result.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The user prompt.
+    /// * `on_event` - Callback for streamed events.
+    ///
+    /// # Returns
+    ///
+    /// The collected response with turn count and cost.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::BudgetExceeded` if the session's budget is
+    /// exhausted before or during the query, `MPCAError::Cancelled` if the
+    /// session is cancelled, `MPCAError::AuthenticationFailed` if the API
+    /// rejects the credentials, or `MPCAError::AgentError` if the backend
+    /// fails, the agent reports an error result, or the stream ends before a
+    /// result.
+    pub async fn send(
+        &mut self,
+        prompt: &str,
+        on_event: &mut (dyn FnMut(&AgentEvent) + Send),
+    ) -> Result<AgentResponse> {
+        self.cancel.check()?;
+        if let Some(budget) = &self.budget {
+            budget.check()?;
+        }
+        let cancel = self.cancel.clone();
+        let deadline = self
+            .budget
+            .as_ref()
+            .and_then(Budget::remaining_wall_clock)
+            .map(|remaining| tokio::time::Instant::now() + remaining);
+
+        let connection = match &mut self.connection {
+            Some(connection) => connection,
+            None => {
+                let mut options = budgeted_options(&self.options, self.budget.as_ref());
+                if let Some(guard) = &self.guard {
+                    guard.install(&mut options);
+                }
+                let connection = self.backend.connect(&options).await?;
+                self.connection.insert(connection)
+            }
+        };
+
+        connection.query(prompt).await?;
+
+        let started = tokio::time::Instant::now();
+        // Usage streamed so far, reported if the query is abandoned
+        let mut streamed = Usage::default();
+        let mut response = AgentResponse::default();
+        loop {
+            let next = tokio::select! {
+                next = connection.next_event() => next,
+                _ = cancel.cancelled() => Err(MPCAError::Cancelled),
+                _ = sleep_until(deadline) => {
+                    let usage = Usage {
+                        duration_ms: u64::try_from(started.elapsed().as_millis())
+                            .unwrap_or(u64::MAX),
+                        ..streamed
+                    };
+                    if let Some(budget) = &mut self.budget {
+                        budget.record(&usage);
+                    }
+                    Err(MPCAError::BudgetExceeded {
+                        reason: self
+                            .budget
+                            .as_ref()
+                            .and_then(Budget::exhausted)
+                            .unwrap_or_else(|| "max_wall_clock reached".to_string()),
+                        usage,
+                    })
+                }
+            };
+
+            let next = match next {
+                Err(e @ (MPCAError::Cancelled | MPCAError::BudgetExceeded { .. })) => {
+                    // The agent is mid-query; drop the connection rather than wait
+                    if let Some(mut connection) = self.connection.take()
+                        && let Err(e) = connection.disconnect().await
+                    {
+                        tracing::warn!(error = %e, "failed to disconnect from agent");
+                    }
+                    return Err(e);
+                }
+                next => next?,
+            };
+
+            let event = next.ok_or_else(|| {
+                MPCAError::AgentError("agent stream ended without a result".to_string())
+            })?;
+            on_event(&event);
+
+            match event {
+                AgentEvent::Text { text } => response.text.push_str(&text),
+                AgentEvent::ToolUse { .. } => {}
+                AgentEvent::Usage(usage) => streamed += &usage,
+                AgentEvent::Result(result) => {
+                    if let Some(budget) = &mut self.budget {
+                        budget.record(&result.usage);
+                    }
+
+                    if result.is_error {
+                        // The agent stops itself at the turn and cost limits we passed it
+                        if let Some(reason) = self.budget.as_ref().and_then(Budget::exhausted) {
+                            return Err(MPCAError::BudgetExceeded {
+                                reason,
+                                usage: result.usage,
+                            });
+                        }
+
+                        return Err(MPCAError::agent_failure(
+                            result
+                                .error
+                                .unwrap_or_else(|| "agent reported an error".to_string()),
+                        ));
+                    }
+
+                    response.usage = result.usage;
+                    response.session_id = result.session_id;
+                    break;
+                }
+            }
+        }
+
+        tracing::debug!(
+            turns = response.usage.turns,
+            input_tokens = response.usage.input_tokens,
+            output_tokens = response.usage.output_tokens,
+            cost_usd = response.usage.cost_usd,
+            "agent query finished"
+        );
+
+        Ok(response)
+    }
+
+    /// Ends the conversation.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::AgentError` if the backend fails to disconnect.
+    pub async fn disconnect(&mut self) -> Result<()> {
+        if let Some(mut connection) = self.connection.take() {
+            connection.disconnect().await?;
+        }
+        Ok(())
+    }
+
+    /// Sends a single query and ends the conversation.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The user prompt.
+    /// * `on_event` - Callback for streamed events.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`AgentSession::send`].
+    pub async fn run(
+        mut self,
+        prompt: &str,
+        on_event: &mut (dyn FnMut(&AgentEvent) + Send),
+    ) -> Result<AgentResponse> {
+        let response = self.send(prompt, on_event).await;
+        if let Err(e) = self.disconnect().await {
+            tracing::warn!(error = %e, "failed to disconnect from agent");
+        }
+        response
+    }
+}
+
+impl std::fmt::Debug for AgentSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentSession")
+            .field("model", &self.options.model)
+            .field("max_turns", &self.options.max_turns)
+            .field("allowed_tools", &self.options.allowed_tools)
+            .field("backend", &self.backend)
+            .field("connected", &self.connection.is_some())
+            .field("budget", &self.budget)
+            .field("guard", &self.guard)
+            .finish()
+    }
+}
+
+/// Runs workflow queries against an agent backend.
+///
+/// Bundles the configuration, backend and prompt manager a workflow needs
+/// to start agent sessions. As a [`StepRunner`] it runs each plan step and
+/// each other pipeline stage in a fresh session inside the feature's
+/// worktree, with the stage's agent mode and tool set.
+#[derive(Debug, Clone)]
+pub struct AgentRunner<'a> {
+    config: &'a MpcaConfig,
+    backend: Arc<dyn AgentBackend>,
+    pm: Option<&'a PromptManager>,
+    cancel: CancellationToken,
+}
+
+impl<'a> AgentRunner<'a> {
+    /// Creates an agent runner.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - MPCA configuration
+    /// * `backend` - Agent backend
+    /// * `pm` - Prompt manager, if templates are available
+    pub fn new(
+        config: &'a MpcaConfig,
+        backend: Arc<dyn AgentBackend>,
+        pm: Option<&'a PromptManager>,
+    ) -> Self {
+        Self {
+            config,
+            backend,
+            pm,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    /// Cancels the runner's sessions when `cancel` is triggered.
+    ///
+    /// # Arguments
+    ///
+    /// * `cancel` - Cancellation token of the workflow run.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Creates a session for a workflow on this runner's backend.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`AgentSession::for_workflow`].
+    pub fn session<C: TemplateContext>(
+        &self,
+        workflow: WorkflowKind,
+        context: &C,
+    ) -> Result<AgentSession> {
+        Ok(
+            AgentSession::for_workflow(self.config, workflow, self.pm, context)?
+                .with_backend(Arc::clone(&self.backend))
+                .with_cancellation(self.cancel.clone()),
+        )
+    }
+
+    /// Sends a single query for a workflow and ends the session.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow` - Workflow the agent runs for
+    /// * `context` - Context for the system prompt template
+    /// * `prompt` - The user prompt
+    /// * `budget` - What the workflow run may still spend
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`AgentSession::for_workflow`] and
+    /// [`AgentSession::send`].
+    pub async fn query<C: TemplateContext>(
+        &self,
+        workflow: WorkflowKind,
+        context: &C,
+        prompt: &str,
+        budget: &Budget,
+    ) -> Result<AgentResponse> {
+        let session = self.session(workflow, context)?.with_budget(budget.clone());
+        session
+            .run(prompt, &mut |event| log_event(workflow, event))
+            .await
+    }
+}
+
+impl AgentRunner<'_> {
+    /// Creates a session for a pipeline stage, working in the feature's worktree.
+    fn stage_session(
+        &self,
+        stage: &Stage,
+        context: &ExecuteContext,
+        budget: &Budget,
+    ) -> Result<AgentSession> {
+        let mut session = AgentSession::for_stage(self.config, stage, self.pm, context)?
+            .with_backend(Arc::clone(&self.backend))
+            .with_cancellation(self.cancel.clone())
+            .with_budget(budget.clone());
+        session.options_mut().cwd = Some(context.worktree_dir.clone());
+        Ok(session)
+    }
+}
+
+#[async_trait::async_trait]
+impl StepRunner for AgentRunner<'_> {
+    async fn run_step(
+        &mut self,
+        stage: &Stage,
+        step: &StepState,
+        context: &ExecuteContext,
+        budget: &Budget,
+    ) -> Result<AgentResponse> {
+        let session = self.stage_session(stage, context, budget)?;
+        let prompt = format!(
+            "Implement step {}: {}. Stop when this step is complete.",
+            step.number, step.title
+        );
+        session
+            .run(&prompt, &mut |event| {
+                log_event(WorkflowKind::Execute, event)
+            })
+            .await
+    }
+
+    async fn run_stage(
+        &mut self,
+        stage: &Stage,
+        context: &ExecuteContext,
+        budget: &Budget,
+    ) -> Result<AgentResponse> {
+        let session = self.stage_session(stage, context, budget)?;
+        let prompt = stage.prompt.clone().unwrap_or_else(|| {
+            format!(
+                "Run the {} stage for this feature. Stop when the stage is complete.",
+                stage.name
+            )
+        });
+        session
+            .run(&prompt, &mut |event| {
+                log_event(WorkflowKind::Execute, event)
+            })
+            .await
+    }
+}
+
+/// Logs tool calls made by the agent.
+fn log_event(workflow: WorkflowKind, event: &AgentEvent) {
+    if let AgentEvent::ToolUse { name, .. } = event {
+        tracing::info!(workflow = %workflow, tool = %name, "agent tool call");
+    }
+}
+
+/// Applies the remaining turns and cost of a budget to agent options.
+fn budgeted_options(options: &ClaudeAgentOptions, budget: Option<&Budget>) -> ClaudeAgentOptions {
+    let mut options = options.clone();
+    if let Some(budget) = budget {
+        if let Some(turns) = budget.remaining_turns() {
+            options.max_turns = Some(options.max_turns.map_or(turns, |max| max.min(turns)));
+        }
+        if let Some(cost) = budget.remaining_cost_usd() {
+            options.max_budget_usd = Some(options.max_budget_usd.map_or(cost, |max| max.min(cost)));
+        }
+    }
+    options
+}
+
+/// Sleeps until `deadline`, or forever without one.
+async fn sleep_until(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ToolSet;
+    use mpca_pm::{PlanContext, ReviewContext};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_build_options_from_workflow_config() {
+        let mut config = MpcaConfig::new(PathBuf::from("/repo"));
+        config.agent_modes.execute.model = "claude-sonnet-4-5".to_string();
+        config.api.base_url = Some("https://proxy.example.com".to_string());
+
+        let options =
+            build_options(&config, WorkflowKind::Execute, Some("prompt".to_string())).unwrap();
+
+        assert_eq!(options.model.as_deref(), Some("claude-sonnet-4-5"));
+        assert_eq!(options.max_turns, Some(AgentMode::DEFAULT_MAX_TURNS));
+        assert_eq!(options.cwd, Some(PathBuf::from("/repo")));
+        assert_eq!(options.allowed_tools, ToolSet::Full.allowed_tools());
+        assert_eq!(
+            options.env.get(BASE_URL_ENV).map(String::as_str),
+            Some("https://proxy.example.com")
+        );
+        assert_eq!(
+            options.env.get(MAX_OUTPUT_TOKENS_ENV).map(String::as_str),
+            Some("8192")
+        );
+
+        match options.system_prompt {
+            Some(SystemPrompt::Preset(preset)) => {
+                assert_eq!(preset.preset, CODE_PRESET);
+                assert_eq!(preset.append.as_deref(), Some("prompt"));
+            }
+            other => panic!("expected code preset, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_build_options_passes_credentials() {
+        let repo = tempfile::TempDir::new().unwrap();
+        std::fs::write(repo.path().join("api-key"), "sk-test\n").unwrap();
+        let mut config = MpcaConfig::new(repo.path().to_path_buf());
+        config.api.api_key_file = Some(PathBuf::from("api-key"));
+        config
+            .api
+            .headers
+            .insert("X-Team".to_string(), "platform".to_string());
+        config
+            .api
+            .headers
+            .insert("X-Workflow".to_string(), "mpca".to_string());
+
+        let options = build_options(&config, WorkflowKind::Plan, None).unwrap();
+        assert_eq!(
+            options.env.get(API_KEY_ENV).map(String::as_str),
+            Some("sk-test")
+        );
+        assert_eq!(
+            options.env.get(CUSTOM_HEADERS_ENV).map(String::as_str),
+            Some("X-Team: platform\nX-Workflow: mpca")
+        );
+
+        config.api.api_key_file = Some(PathBuf::from("missing"));
+        assert!(matches!(
+            build_options(&config, WorkflowKind::Plan, None),
+            Err(MPCAError::AuthenticationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_build_options_without_code_preset() {
+        let config = MpcaConfig::new(PathBuf::from("/repo"));
+
+        let options = build_options(&config, WorkflowKind::Init, Some("init".to_string())).unwrap();
+        assert!(matches!(
+            options.system_prompt,
+            Some(SystemPrompt::Text(ref text)) if text == "init"
+        ));
+        assert_eq!(options.allowed_tools, ToolSet::Minimal.allowed_tools());
+        assert!(!options.env.contains_key(BASE_URL_ENV));
+
+        let options = build_options(&config, WorkflowKind::Init, None).unwrap();
+        assert!(options.system_prompt.is_none());
+    }
+
+    #[test]
+    fn test_build_options_applies_agent_mode_settings() {
+        let mut config = MpcaConfig::from_toml(
+            r#"
+[agent_modes.plan]
+permission_mode = "acceptEdits"
+append_system_prompt = "Answer in British English."
+allowed_tools = ["mcp__docs__search", "Read"]
+disallowed_tools = ["Bash"]
+max_turns = 5
+fallback_model = "claude-3-5-haiku-20241022"
+add_dirs = ["../shared"]
+
+[tool_policy]
+denied_tools = ["Write"]
+"#,
+        )
+        .unwrap();
+        config.repo_root = PathBuf::from("/repo");
+
+        let options = build_options(&config, WorkflowKind::Plan, Some("plan".to_string())).unwrap();
+
+        assert_eq!(
+            options.permission_mode,
+            Some(claude_agent_sdk_rs::PermissionMode::AcceptEdits)
+        );
+        assert_eq!(options.max_turns, Some(5));
+        assert_eq!(
+            options.fallback_model.as_deref(),
+            Some("claude-3-5-haiku-20241022")
+        );
+        assert_eq!(options.add_dirs, [PathBuf::from("/repo/../shared")]);
+        assert_eq!(
+            options.allowed_tools,
+            [
+                "Read",
+                "Glob",
+                "Grep",
+                "LS",
+                "Edit",
+                "MultiEdit",
+                "mcp__docs__search"
+            ]
+        );
+        assert_eq!(options.disallowed_tools, ["Bash", "Write"]);
+
+        match options.system_prompt {
+            Some(SystemPrompt::Preset(preset)) => assert_eq!(
+                preset.append.as_deref(),
+                Some("plan\n\nAnswer in British English.")
+            ),
+            other => panic!("expected code preset, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tool_sets_are_nested() {
+        let minimal = ToolSet::Minimal.allowed_tools();
+        let standard = ToolSet::Standard.allowed_tools();
+        let full = ToolSet::Full.allowed_tools();
+
+        assert!(!minimal.contains(&"Write".to_string()));
+        assert!(minimal.iter().all(|t| standard.contains(t)));
+        assert!(standard.iter().all(|t| full.contains(t)));
+        assert!(full.len() > standard.len());
+    }
+
+    #[test]
+    fn test_for_workflow_renders_system_prompt() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp.path().join("plan.j2"), "Planning {{ feature_slug }}").unwrap();
+        let pm = PromptManager::new(temp.path().to_path_buf()).unwrap();
+        let config = MpcaConfig::new(PathBuf::from("/repo"));
+        let context = PlanContext {
+            feature_slug: "add-caching".to_string(),
+            ..Default::default()
+        };
+
+        let session =
+            AgentSession::for_workflow(&config, WorkflowKind::Plan, Some(&pm), &context).unwrap();
+
+        match &session.options().system_prompt {
+            Some(SystemPrompt::Preset(preset)) => {
+                assert_eq!(preset.append.as_deref(), Some("Planning add-caching"));
+            }
+            other => panic!("expected code preset, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_for_workflow_missing_template() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let pm = PromptManager::new(temp.path().to_path_buf()).unwrap();
+        let config = MpcaConfig::new(PathBuf::from("/repo"));
+        let context = ReviewContext::default();
+
+        let result = AgentSession::for_workflow(&config, WorkflowKind::Review, Some(&pm), &context);
+        assert!(matches!(result, Err(MPCAError::TemplateNotFound(_))));
+    }
+
+    #[test]
+    fn test_for_workflow_rejects_undefined_variables() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp.path().join("plan.j2"), "Planning {{ feature }}").unwrap();
+        let pm = PromptManager::new(temp.path().to_path_buf()).unwrap();
+        let config = MpcaConfig::new(PathBuf::from("/repo"));
+
+        let result = AgentSession::for_workflow(
+            &config,
+            WorkflowKind::Plan,
+            Some(&pm),
+            &PlanContext::default(),
+        );
+        assert!(matches!(
+            result,
+            Err(MPCAError::InvalidTemplateContext(msg)) if msg.contains("plan")
+        ));
+    }
+
+    fn turn(prompt_contains: Option<&str>, text: &str, turns: u32) -> ScriptedTurn {
+        ScriptedTurn {
+            prompt_contains: prompt_contains.map(str::to_string),
+            repeat: false,
+            events: vec![
+                AgentEvent::ToolUse {
+                    name: "Read".to_string(),
+                    input: serde_json::json!({ "file_path": "README.md" }),
+                },
+                AgentEvent::Text {
+                    text: text.to_string(),
+                },
+                AgentEvent::Result(AgentResult {
+                    usage: Usage {
+                        turns,
+                        input_tokens: 100,
+                        output_tokens: 20,
+                        cost_usd: 0.5,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }),
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_streams_events_and_keeps_conversation() {
+        let backend = ScriptedAgentBackend::new(vec![
+            turn(Some("first"), "one", 1),
+            turn(Some("second"), "two", 2),
+        ]);
+        let config = MpcaConfig::new(PathBuf::from("/repo"));
+        let mut session =
+            AgentSession::new(build_options(&config, WorkflowKind::Plan, None).unwrap())
+                .with_backend(Arc::new(backend.clone()));
+
+        let mut events = Vec::new();
+        let response = session
+            .send("first question", &mut |e| events.push(e.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.text, "one");
+        assert_eq!(response.usage.turns, 1);
+        assert_eq!(response.usage.input_tokens, 100);
+        assert_eq!(response.usage.cost_usd, 0.5);
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], AgentEvent::ToolUse { ref name, .. } if name == "Read"));
+
+        let response = session.send("second question", &mut |_| {}).await.unwrap();
+        assert_eq!(response.text, "two");
+        session.disconnect().await.unwrap();
+
+        assert_eq!(backend.remaining(), 0);
+        assert_eq!(backend.queries().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_session_requires_result() {
+        let backend = ScriptedAgentBackend::new(vec![ScriptedTurn {
+            events: vec![AgentEvent::Text {
+                text: "partial".to_string(),
+            }],
+            ..Default::default()
+        }]);
+        let session =
+            AgentSession::new(ClaudeAgentOptions::default()).with_backend(Arc::new(backend));
+
+        let result = session.run("hello", &mut |_| {}).await;
+        assert!(matches!(result, Err(MPCAError::AgentError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_scripted_backend_without_matching_turn() {
+        let backend = ScriptedAgentBackend::new(vec![turn(Some("plan"), "plan", 1)]);
+        let session =
+            AgentSession::new(ClaudeAgentOptions::default()).with_backend(Arc::new(backend));
+
+        let result = session.run("chat", &mut |_| {}).await;
+        assert!(
+            matches!(result, Err(MPCAError::AgentError(ref e)) if e.contains("no scripted turn"))
+        );
+    }
+
+    fn execute_budget(max_turns: Option<u32>, max_wall_clock: Option<u64>) -> Budget {
+        let mut config = crate::config::BudgetConfig::default();
+        config.execute.max_turns = max_turns;
+        config.execute.max_cost_usd = Some(2.0);
+        config.execute.max_wall_clock = max_wall_clock;
+        Budget::new(&config, WorkflowKind::Execute, Usage::default())
+    }
+
+    #[test]
+    fn test_budgeted_options_lower_agent_limits() {
+        let config = MpcaConfig::new(PathBuf::from("/repo"));
+        let options = build_options(&config, WorkflowKind::Execute, None).unwrap();
+
+        let budgeted = budgeted_options(&options, Some(&execute_budget(Some(5), None)));
+        assert_eq!(budgeted.max_turns, Some(5));
+        assert_eq!(budgeted.max_budget_usd, Some(2.0));
+
+        let budgeted = budgeted_options(&options, Some(&execute_budget(Some(500), None)));
+        assert_eq!(budgeted.max_turns, Some(AgentMode::DEFAULT_MAX_TURNS));
+
+        let unbudgeted = budgeted_options(&options, None);
+        assert_eq!(unbudgeted.max_budget_usd, None);
+    }
+
+    #[tokio::test]
+    async fn test_session_reports_exhausted_budget() {
+        let backend = ScriptedAgentBackend::new(vec![ScriptedTurn {
+            repeat: true,
+            events: vec![AgentEvent::Result(AgentResult {
+                usage: Usage {
+                    turns: 2,
+                    cost_usd: 0.1,
+                    ..Default::default()
+                },
+                is_error: true,
+                error: Some("error_max_turns".to_string()),
+                ..Default::default()
+            })],
+            ..Default::default()
+        }]);
+        let mut session = AgentSession::new(ClaudeAgentOptions::default())
+            .with_backend(Arc::new(backend.clone()))
+            .with_budget(execute_budget(Some(2), None));
+
+        let result = session.send("work", &mut |_| {}).await;
+        match result {
+            Err(MPCAError::BudgetExceeded { reason, usage }) => {
+                assert!(reason.contains("max_turns = 2"));
+                assert_eq!(usage.turns, 2);
+            }
+            other => panic!("expected BudgetExceeded, got {:?}", other),
+        }
+
+        // Further queries are refused without reaching the agent
+        let result = session.send("more work", &mut |_| {}).await;
+        assert!(matches!(result, Err(MPCAError::BudgetExceeded { .. })));
+        assert_eq!(backend.queries().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_session_reports_rejected_credentials() {
+        let backend = ScriptedAgentBackend::new(vec![ScriptedTurn {
+            events: vec![AgentEvent::Result(AgentResult {
+                is_error: true,
+                error: Some(
+                    "error_during_execution: 401 {\"type\":\"authentication_error\"}".to_string(),
+                ),
+                ..Default::default()
+            })],
+            ..Default::default()
+        }]);
+        let mut session =
+            AgentSession::new(ClaudeAgentOptions::default()).with_backend(Arc::new(backend));
+
+        let result = session.send("work", &mut |_| {}).await;
+        assert!(matches!(result, Err(MPCAError::AuthenticationFailed(_))));
+    }
+
+    /// Backend that streams the given events and then never finishes.
+    #[derive(Debug, Default)]
+    struct StalledBackend(Vec<AgentEvent>);
+
+    struct StalledConnection(std::collections::VecDeque<AgentEvent>);
+
+    #[async_trait::async_trait]
+    impl AgentBackend for StalledBackend {
+        async fn connect(&self, _: &ClaudeAgentOptions) -> Result<Box<dyn AgentConnection>> {
+            Ok(Box::new(StalledConnection(self.0.clone().into())))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AgentConnection for StalledConnection {
+        async fn query(&mut self, _: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn next_event(&mut self) -> Result<Option<AgentEvent>> {
+            match self.0.pop_front() {
+                Some(event) => Ok(Some(event)),
+                None => std::future::pending().await,
+            }
+        }
+
+        async fn disconnect(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_abandons_query_at_wall_clock_cap() {
+        let session = AgentSession::new(ClaudeAgentOptions::default())
+            .with_backend(Arc::new(StalledBackend::default()))
+            .with_budget(execute_budget(None, Some(1)));
+
+        let result = session.run("work", &mut |_| {}).await;
+        assert!(
+            matches!(result, Err(MPCAError::BudgetExceeded { ref reason, .. }) if reason.contains("max_wall_clock"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wall_clock_cap_reports_streamed_usage() {
+        let turn = Usage {
+            turns: 1,
+            input_tokens: 100,
+            output_tokens: 20,
+            ..Default::default()
+        };
+        let backend = StalledBackend(vec![
+            AgentEvent::Usage(turn),
+            AgentEvent::Text {
+                text: "working".to_string(),
+            },
+            AgentEvent::Usage(turn),
+        ]);
+        let session = AgentSession::new(ClaudeAgentOptions::default())
+            .with_backend(Arc::new(backend))
+            .with_budget(execute_budget(None, Some(1)));
+
+        match session.run("work", &mut |_| {}).await {
+            Err(MPCAError::BudgetExceeded { usage, .. }) => {
+                assert_eq!(usage.turns, 2);
+                assert_eq!(usage.input_tokens, 200);
+                assert_eq!(usage.output_tokens, 40);
+                assert!(usage.duration_ms >= 1000);
+            }
+            other => panic!("expected BudgetExceeded, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_abandons_query_when_cancelled() {
+        let cancel = CancellationToken::new();
+        let session = AgentSession::new(ClaudeAgentOptions::default())
+            .with_backend(Arc::new(StalledBackend::default()))
+            .with_cancellation(cancel.clone());
+
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            cancel.cancel();
+        });
+
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            session.run("work", &mut |_| {}),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(result, Err(MPCAError::Cancelled)));
+    }
+
+    #[test]
+    fn test_offline_transcript_fixture_parses() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../fixtures/transcripts/offline.json");
+        let backend = ScriptedAgentBackend::from_file(&path).unwrap();
+        assert_eq!(backend.remaining(), 3);
+    }
+}
@@ -0,0 +1,371 @@
+//! Tool permission enforcement.
+//!
+//! [`ToolGuard`] checks every tool call of the agent against the session's
+//! allowed tools and the configured [`ToolPolicy`]. Denied calls are
+//! logged, and for sessions working on a feature also recorded in the
+//! feature's `docs/impl_details.md` together with the reason.
+
+use crate::config::{MpcaConfig, ToolPolicy};
+use claude_agent_sdk_rs::{
+    ClaudeAgentOptions, HookCallback, HookContext, HookEvent, HookInput, HookJsonOutput,
+    HookMatcher, HookSpecificOutput, PermissionResult, PermissionResultAllow, PermissionResultDeny,
+    PreToolUseHookSpecificOutput, SyncHookJsonOutput, ToolPermissionContext,
+};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Heading of the denial log section in `impl_details.md`.
+const DENIALS_HEADING: &str = "## Tool Permission Denials";
+
+/// Enforces a [`ToolPolicy`] on the agent's tool calls.
+///
+/// # Examples
+///
+/// ```
+/// use mpca_core::agent::ToolGuard;
+/// use mpca_core::config::ToolPolicy;
+/// use serde_json::json;
+/// use std::path::Path;
+///
+/// let guard = ToolGuard::new(ToolPolicy::default());
+/// let tools = vec!["Read".to_string(), "Edit".to_string()];
+/// let cwd = Path::new("/repo");
+///
+/// assert!(guard.check("Edit", &json!({"file_path": "src/lib.rs"}), &tools, cwd).is_ok());
+/// assert!(guard.check("Bash", &json!({"command": "ls"}), &tools, cwd).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolGuard {
+    policy: ToolPolicy,
+    denial_log: Option<PathBuf>,
+}
+
+impl ToolGuard {
+    /// Creates a guard enforcing `policy`.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Tool policy to enforce.
+    pub fn new(policy: ToolPolicy) -> Self {
+        Self {
+            policy,
+            denial_log: None,
+        }
+    }
+
+    /// Creates the guard for a session, recording denials in the feature's
+    /// `docs/impl_details.md` when the session works on a feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - MPCA configuration with the tool policy
+    /// * `feature_slug` - Feature the session works on, if any
+    pub fn for_feature(config: &MpcaConfig, feature_slug: Option<&str>) -> Self {
+        let guard = Self::new(config.tool_policy.clone());
+        match feature_slug {
+            Some(slug) => guard.with_denial_log(
+                config
+                    .specs_dir
+                    .join(slug)
+                    .join("docs")
+                    .join("impl_details.md"),
+            ),
+            None => guard,
+        }
+    }
+
+    /// Records denied tool calls in a Markdown file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File the denials are appended to.
+    pub fn with_denial_log(mut self, path: PathBuf) -> Self {
+        self.denial_log = Some(path);
+        self
+    }
+
+    /// Decides whether the agent may make a tool call.
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - SDK tool name
+    /// * `input` - Tool input as sent by the agent
+    /// * `allowed_tools` - Tools of the session's tool set
+    /// * `cwd` - Working directory of the agent
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the call is allowed, or the reason it is denied.
+    pub fn check(
+        &self,
+        tool: &str,
+        input: &serde_json::Value,
+        allowed_tools: &[String],
+        cwd: &Path,
+    ) -> std::result::Result<(), String> {
+        self.policy.check(tool, input, cwd)?;
+        if !allowed_tools.iter().any(|allowed| allowed == tool) {
+            return Err(format!("`{}` is not in the session's tool set", tool));
+        }
+        Ok(())
+    }
+
+    /// Installs the guard on agent options.
+    ///
+    /// The check is bound to the options' allowed tools and working
+    /// directory, so install the guard once the options are final. Tools
+    /// denied outright are also passed to the agent as disallowed tools.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options the session connects with.
+    pub fn install(&self, options: &mut ClaudeAgentOptions) {
+        let check = Arc::new(BoundCheck {
+            guard: self.clone(),
+            allowed_tools: options.allowed_tools.clone(),
+            cwd: options.cwd.clone().unwrap_or_default(),
+        });
+
+        for tool in &self.policy.denied_tools {
+            if !options.disallowed_tools.contains(tool) {
+                options.disallowed_tools.push(tool.clone());
+            }
+        }
+
+        let permission = Arc::clone(&check);
+        options.can_use_tool = Some(Arc::new(
+            move |tool: String, input: serde_json::Value, _: ToolPermissionContext| {
+                let decision = permission.decide(&tool, &input);
+                Box::pin(async move {
+                    match decision {
+                        Ok(()) => PermissionResult::Allow(PermissionResultAllow::default()),
+                        Err(message) => PermissionResult::Deny(PermissionResultDeny {
+                            message,
+                            interrupt: false,
+                        }),
+                    }
+                })
+            },
+        ));
+
+        // The SDK doesn't route permission requests to `can_use_tool` yet,
+        // but it does run hooks, so the same check runs before each tool use
+        let hook: HookCallback =
+            Arc::new(move |input: HookInput, _: Option<String>, _: HookContext| {
+                let decision = match &input {
+                    HookInput::PreToolUse(call) => check.decide(&call.tool_name, &call.tool_input),
+                    _ => Ok(()),
+                };
+                Box::pin(async move {
+                    let mut output = SyncHookJsonOutput::builder().build();
+                    if let Err(reason) = decision {
+                        output.hook_specific_output = Some(HookSpecificOutput::PreToolUse(
+                            PreToolUseHookSpecificOutput::builder()
+                                .permission_decision("deny")
+                                .permission_decision_reason(reason)
+                                .build(),
+                        ));
+                    }
+                    HookJsonOutput::Sync(output)
+                })
+            });
+        options
+            .hooks
+            .get_or_insert_with(Default::default)
+            .entry(HookEvent::PreToolUse)
+            .or_default()
+            .push(HookMatcher::builder().hooks(vec![hook]).build());
+    }
+
+    /// Logs a denied tool call and records it in the denial log.
+    fn record_denial(&self, tool: &str, reason: &str) {
+        tracing::warn!(tool = %tool, reason = %reason, "tool call denied");
+
+        let Some(path) = &self.denial_log else {
+            return;
+        };
+        if let Err(e) = append_denial(path, tool, reason) {
+            tracing::warn!(
+                path = %path.display(),
+                error = %e,
+                "failed to record tool denial"
+            );
+        }
+    }
+}
+
+/// A guard bound to the allowed tools and working directory of a session.
+struct BoundCheck {
+    guard: ToolGuard,
+    allowed_tools: Vec<String>,
+    cwd: PathBuf,
+}
+
+impl BoundCheck {
+    /// Checks a tool call, recording it if denied.
+    fn decide(&self, tool: &str, input: &serde_json::Value) -> std::result::Result<(), String> {
+        let decision = self
+            .guard
+            .check(tool, input, &self.allowed_tools, &self.cwd);
+        if let Err(reason) = &decision {
+            self.guard.record_denial(tool, reason);
+        }
+        decision
+    }
+}
+
+/// Appends a denial entry to `path`, adding the section heading if missing.
+fn append_denial(path: &Path, tool: &str, reason: &str) -> std::io::Result<()> {
+    let existing = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    let mut entry = String::new();
+    if !existing.contains(DENIALS_HEADING) {
+        if !existing.is_empty() {
+            entry.push_str(if existing.ends_with('\n') {
+                "\n"
+            } else {
+                "\n\n"
+            });
+        }
+        entry.push_str(DENIALS_HEADING);
+        entry.push_str("\n\n");
+    }
+    entry.push_str(&format!(
+        "- {} `{}`: {}\n",
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        tool,
+        reason
+    ));
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(entry.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tools() -> Vec<String> {
+        crate::config::ToolSet::Standard.allowed_tools()
+    }
+
+    #[test]
+    fn test_check_enforces_tool_set_and_policy() {
+        let guard = ToolGuard::new(ToolPolicy {
+            shell_deny: vec!["git push*".to_string()],
+            ..Default::default()
+        });
+        let cwd = Path::new("/repo/.trees/feature");
+
+        assert!(
+            guard
+                .check("Read", &json!({"file_path": "/etc/hosts"}), &tools(), cwd)
+                .is_ok()
+        );
+        assert!(
+            guard
+                .check("WebFetch", &json!({}), &tools(), cwd)
+                .unwrap_err()
+                .contains("tool set")
+        );
+        assert!(
+            guard
+                .check(
+                    "Bash",
+                    &json!({"command": "git push origin"}),
+                    &tools(),
+                    cwd
+                )
+                .unwrap_err()
+                .contains("shell_deny")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_install_denies_and_records_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("docs").join("impl_details.md");
+        let policy = ToolPolicy {
+            denied_tools: vec!["WebSearch".to_string()],
+            ..Default::default()
+        };
+        let mut options = ClaudeAgentOptions {
+            allowed_tools: tools(),
+            cwd: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        ToolGuard::new(policy)
+            .with_denial_log(log.clone())
+            .install(&mut options);
+
+        assert_eq!(options.disallowed_tools, ["WebSearch"]);
+        assert_eq!(
+            options.hooks.as_ref().unwrap()[&HookEvent::PreToolUse].len(),
+            1
+        );
+
+        let can_use_tool = options.can_use_tool.unwrap();
+        let allowed = can_use_tool(
+            "Edit".to_string(),
+            json!({"file_path": "src/lib.rs"}),
+            ToolPermissionContext::default(),
+        )
+        .await;
+        assert!(matches!(allowed, PermissionResult::Allow(_)));
+        assert!(!log.exists());
+
+        let denied = can_use_tool(
+            "Write".to_string(),
+            json!({"file_path": ".mpca/config.toml"}),
+            ToolPermissionContext::default(),
+        )
+        .await;
+        let PermissionResult::Deny(deny) = denied else {
+            panic!("expected the write to be denied");
+        };
+        assert!(deny.message.contains("write_deny"));
+
+        can_use_tool(
+            "WebSearch".to_string(),
+            json!({}),
+            ToolPermissionContext::default(),
+        )
+        .await;
+
+        let content = std::fs::read_to_string(&log).unwrap();
+        assert!(content.starts_with(DENIALS_HEADING));
+        assert_eq!(content.matches(DENIALS_HEADING).count(), 1);
+        assert!(content.contains("`Write`: writing .mpca/config.toml is denied"));
+        assert!(content.contains("`WebSearch`: `WebSearch` is denied by tool_policy.denied_tools"));
+    }
+
+    #[test]
+    fn test_denials_are_appended_after_existing_notes() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("impl_details.md");
+        std::fs::write(&log, "# Implementation Details\n\nUses a LRU cache.").unwrap();
+
+        append_denial(
+            &log,
+            "Bash",
+            "`curl x` does not match tool_policy.shell_allow",
+        )
+        .unwrap();
+
+        let content = std::fs::read_to_string(&log).unwrap();
+        assert!(content.starts_with("# Implementation Details\n\nUses a LRU cache.\n\n"));
+        assert!(content.contains(&format!("{}\n\n- ", DENIALS_HEADING)));
+    }
+}
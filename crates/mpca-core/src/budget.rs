@@ -0,0 +1,266 @@
+//! Budget enforcement for agent workflows.
+//!
+//! A [`Budget`] tracks what a workflow run has spent against the caps in
+//! [`BudgetConfig`]: the per-run limits of the workflow and the limits of
+//! the whole feature. Workflows check it between agent queries, and
+//! [`AgentSession`](crate::agent::AgentSession) enforces it while the agent
+//! streams its reply.
+
+use crate::config::{BudgetConfig, BudgetLimits, WorkflowKind};
+use crate::error::{MPCAError, Result};
+use crate::usage::Usage;
+use std::time::{Duration, Instant};
+
+/// Spending of one workflow run, checked against its caps.
+///
+/// # Examples
+///
+/// ```
+/// use mpca_core::budget::Budget;
+/// use mpca_core::config::{BudgetConfig, WorkflowKind};
+/// use mpca_core::usage::Usage;
+///
+/// let mut config = BudgetConfig::default();
+/// config.execute.max_turns = Some(10);
+///
+/// let mut budget = Budget::new(&config, WorkflowKind::Execute, Usage::default());
+/// assert_eq!(budget.remaining_turns(), Some(10));
+///
+/// budget.record(&Usage { turns: 10, ..Default::default() });
+/// assert!(budget.check().is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Budget {
+    workflow: WorkflowKind,
+
+    /// Caps for this run of the workflow.
+    run: BudgetLimits,
+
+    /// Caps for the whole feature.
+    feature: BudgetLimits,
+
+    /// Usage recorded for the feature before this run.
+    before: Usage,
+
+    /// Usage of this run so far.
+    spent: Usage,
+
+    /// When this run started.
+    started: Instant,
+}
+
+impl Budget {
+    /// Creates the budget of a workflow run.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Budget configuration.
+    /// * `workflow` - Workflow being run.
+    /// * `feature_spent` - Usage already recorded for the feature.
+    pub fn new(config: &BudgetConfig, workflow: WorkflowKind, feature_spent: Usage) -> Self {
+        Self {
+            workflow,
+            run: *config.get(workflow),
+            feature: config.feature,
+            before: feature_spent,
+            spent: Usage::default(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Creates a budget without caps.
+    pub fn unlimited(workflow: WorkflowKind) -> Self {
+        Self::new(&BudgetConfig::default(), workflow, Usage::default())
+    }
+
+    /// Returns `true` if no cap is set.
+    pub fn is_unlimited(&self) -> bool {
+        self.run.is_unlimited() && self.feature.is_unlimited()
+    }
+
+    /// Adds the usage of a finished agent query.
+    pub fn record(&mut self, usage: &Usage) {
+        self.spent += usage;
+    }
+
+    /// Returns the usage of this run so far.
+    pub fn spent(&self) -> &Usage {
+        &self.spent
+    }
+
+    /// Checks whether any cap has been reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::BudgetExceeded` naming the first exhausted cap.
+    pub fn check(&self) -> Result<()> {
+        match self.exhausted() {
+            Some(reason) => Err(MPCAError::BudgetExceeded {
+                reason,
+                usage: Usage::default(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Describes the first cap that has been reached, if any.
+    pub fn exhausted(&self) -> Option<String> {
+        self.scopes().into_iter().find_map(|(scope, limits, used)| {
+            let elapsed = used.duration_ms / 1000;
+            if let Some(max) = limits.max_cost_usd
+                && used.cost_usd >= max
+            {
+                Some(format!(
+                    "{} reached max_cost_usd = {:.2} (spent ${:.4})",
+                    scope, max, used.cost_usd
+                ))
+            } else if let Some(max) = limits.max_turns
+                && used.turns >= max
+            {
+                Some(format!(
+                    "{} reached max_turns = {} (used {})",
+                    scope, max, used.turns
+                ))
+            } else if let Some(max) = limits.max_wall_clock
+                && elapsed >= max
+            {
+                Some(format!(
+                    "{} reached max_wall_clock = {}s (ran {}s)",
+                    scope, max, elapsed
+                ))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the turns left before a cap is reached.
+    pub fn remaining_turns(&self) -> Option<u32> {
+        self.scopes()
+            .into_iter()
+            .filter_map(|(_, limits, used)| {
+                limits.max_turns.map(|max| max.saturating_sub(used.turns))
+            })
+            .min()
+    }
+
+    /// Returns the cost in USD left before a cap is reached.
+    pub fn remaining_cost_usd(&self) -> Option<f64> {
+        self.scopes()
+            .into_iter()
+            .filter_map(|(_, limits, used)| {
+                limits
+                    .max_cost_usd
+                    .map(|max| (max - used.cost_usd).max(0.0))
+            })
+            .min_by(f64::total_cmp)
+    }
+
+    /// Returns the wall-clock time left before a cap is reached.
+    pub fn remaining_wall_clock(&self) -> Option<Duration> {
+        self.scopes()
+            .into_iter()
+            .filter_map(|(_, limits, used)| {
+                limits.max_wall_clock.map(|max| {
+                    Duration::from_secs(max).saturating_sub(Duration::from_millis(used.duration_ms))
+                })
+            })
+            .min()
+    }
+
+    /// Returns each scope with its caps and what it has used.
+    ///
+    /// Wall-clock time of the run is measured from its start; for the
+    /// feature it adds the agent time recorded by earlier runs.
+    fn scopes(&self) -> [(String, BudgetLimits, Usage); 2] {
+        let elapsed = self.started.elapsed().as_millis() as u64;
+        let run = Usage {
+            duration_ms: elapsed,
+            ..self.spent
+        };
+        let feature = Usage {
+            duration_ms: self.before.duration_ms + elapsed,
+            ..(self.before + &self.spent)
+        };
+
+        [
+            (format!("{} run", self.workflow), self.run, run),
+            ("feature".to_string(), self.feature, feature),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(turns: u32, cost_usd: f64) -> Usage {
+        Usage {
+            turns,
+            cost_usd,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_unlimited_budget_never_exhausts() {
+        let mut budget = Budget::unlimited(WorkflowKind::Execute);
+        budget.record(&usage(1000, 1000.0));
+
+        assert!(budget.is_unlimited());
+        assert!(budget.check().is_ok());
+        assert_eq!(budget.remaining_turns(), None);
+        assert_eq!(budget.remaining_cost_usd(), None);
+        assert_eq!(budget.remaining_wall_clock(), None);
+    }
+
+    #[test]
+    fn test_run_caps() {
+        let mut config = BudgetConfig::default();
+        config.execute.max_cost_usd = Some(1.0);
+        config.plan.max_turns = Some(1);
+
+        let mut budget = Budget::new(&config, WorkflowKind::Execute, usage(50, 5.0));
+        budget.record(&usage(3, 0.4));
+        assert!(budget.check().is_ok());
+        assert_eq!(budget.remaining_turns(), None);
+        assert!((budget.remaining_cost_usd().unwrap() - 0.6).abs() < 1e-9);
+
+        budget.record(&usage(3, 0.6));
+        let reason = budget.exhausted().unwrap();
+        assert!(reason.starts_with("execute run reached max_cost_usd"));
+        assert!(matches!(
+            budget.check(),
+            Err(MPCAError::BudgetExceeded { .. })
+        ));
+        assert_eq!(budget.remaining_cost_usd(), Some(0.0));
+    }
+
+    #[test]
+    fn test_feature_caps_include_earlier_runs() {
+        let mut config = BudgetConfig::default();
+        config.feature.max_turns = Some(10);
+        config.execute.max_turns = Some(8);
+
+        let mut budget = Budget::new(&config, WorkflowKind::Execute, usage(6, 0.0));
+        assert_eq!(budget.remaining_turns(), Some(4));
+
+        budget.record(&usage(4, 0.0));
+        let reason = budget.exhausted().unwrap();
+        assert_eq!(reason, "feature reached max_turns = 10 (used 10)");
+    }
+
+    #[test]
+    fn test_wall_clock_cap() {
+        let mut config = BudgetConfig::default();
+        config.feature.max_wall_clock = Some(60);
+
+        let before = Usage {
+            duration_ms: 61_000,
+            ..Default::default()
+        };
+        let budget = Budget::new(&config, WorkflowKind::Execute, before);
+        assert_eq!(budget.remaining_wall_clock(), Some(Duration::ZERO));
+        assert!(budget.exhausted().unwrap().contains("max_wall_clock = 60s"));
+    }
+}
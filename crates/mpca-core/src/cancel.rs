@@ -0,0 +1,132 @@
+//! Cooperative cancellation for running workflows.
+//!
+//! A [`CancellationToken`] is shared between the code that wants to stop a
+//! workflow (e.g. the CLI's Ctrl-C handler) and the workflow itself.
+//! Workflows check it between plan steps, and
+//! [`AgentSession`](crate::agent::AgentSession) abandons a query in flight
+//! as soon as it is cancelled, so state can be checkpointed before exiting.
+
+use crate::error::{MPCAError, Result};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
+
+/// A token signalling that a workflow should stop.
+///
+/// Clones share the same state: cancelling one cancels all of them.
+///
+/// # Examples
+///
+/// ```
+/// use mpca_core::cancel::CancellationToken;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let token = CancellationToken::new();
+/// let waiter = token.clone();
+///
+/// let task = tokio::spawn(async move { waiter.cancelled().await });
+/// token.cancel();
+/// task.await.unwrap();
+/// assert!(token.is_cancelled());
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token and wakes everything waiting on it.
+    pub fn cancel(&self) {
+        if !self.inner.cancelled.swap(true, Ordering::SeqCst) {
+            self.inner.notify.notify_waiters();
+        }
+    }
+
+    /// Returns `true` once the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Waits until the token is cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            // Register before checking so a concurrent cancel is not missed
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Checks whether the token has been cancelled.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::Cancelled` if it has.
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(MPCAError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_cancel_is_shared_between_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(token.check().is_ok());
+
+        clone.cancel();
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert!(matches!(token.check(), Err(MPCAError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_returns_immediately_once_cancelled() {
+        let token = CancellationToken::new();
+        token.cancel();
+
+        tokio::time::timeout(Duration::from_secs(1), token.cancelled())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waits_for_cancel() {
+        let token = CancellationToken::new();
+        let waiter = token.clone();
+
+        let wait = tokio::time::timeout(Duration::from_millis(50), waiter.cancelled()).await;
+        assert!(wait.is_err());
+
+        let task = tokio::spawn(async move { waiter.cancelled().await });
+        tokio::task::yield_now().await;
+        token.cancel();
+        tokio::time::timeout(Duration::from_secs(1), task)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
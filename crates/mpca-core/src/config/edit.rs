@@ -0,0 +1,624 @@
+//! Editing `.mpca/config.toml` in place.
+//!
+//! [`ConfigFile`] changes single keys of the repository configuration file
+//! while keeping its comments and formatting. Every change is checked
+//! against the configuration schema and [`MpcaConfig::problems`] before it
+//! is accepted, so a file written through it always loads.
+
+use super::layers::{flatten, is_workflow_section, join, section_of};
+use super::{AgentMode, BudgetLimits, MpcaConfig, StageConfig, ToolSet};
+use crate::error::{MPCAError, Result};
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, InlineTable, Item, Table, TableLike, Value};
+
+/// The repository configuration file, opened for editing.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mpca_core::config::ConfigFile;
+/// use std::path::Path;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut file = ConfigFile::open(Path::new("/path/to/repo"))?;
+/// file.set("agent_modes.plan.model", "claude-opus-4-20250514")?;
+/// file.set("git.auto_commit", "false")?;
+/// file.save()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    repo_root: PathBuf,
+    path: PathBuf,
+    doc: DocumentMut,
+}
+
+impl ConfigFile {
+    /// Opens the configuration file of a repository.
+    ///
+    /// A missing file is treated as empty and created on [`ConfigFile::save`].
+    ///
+    /// # Arguments
+    ///
+    /// * `repo_root` - The repository root directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::ConfigParseError` if the file cannot be read or
+    /// is not valid TOML.
+    pub fn open(repo_root: &Path) -> Result<Self> {
+        let path = MpcaConfig::new(repo_root.to_path_buf()).config_file;
+
+        let content = if path.exists() {
+            std::fs::read_to_string(&path).map_err(|e| {
+                MPCAError::ConfigParseError(format!("failed to read {}: {}", path.display(), e))
+            })?
+        } else {
+            String::new()
+        };
+        let doc = content.parse::<DocumentMut>().map_err(|e| {
+            MPCAError::ConfigParseError(format!("failed to parse {}: {}", path.display(), e))
+        })?;
+
+        Ok(Self {
+            repo_root: repo_root.to_path_buf(),
+            path,
+            doc,
+        })
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Parses the file's current contents.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::ConfigParseError` if the contents are not valid
+    /// configuration.
+    pub fn to_config(&self) -> Result<MpcaConfig> {
+        let mut config = MpcaConfig::from_toml(&self.doc.to_string())?;
+        let defaults = MpcaConfig::new(self.repo_root.clone());
+        config.repo_root = defaults.repo_root;
+        config.trees_dir = defaults.trees_dir;
+        config.specs_dir = defaults.specs_dir;
+        config.claude_md = defaults.claude_md;
+        config.config_file = defaults.config_file;
+        Ok(config)
+    }
+
+    /// Sets a key.
+    ///
+    /// `value` is read as a TOML value (`true`, `0.5`, `["a", "b"]`, ...);
+    /// anything that does not parse as one is stored as a string. Missing
+    /// tables are created. An agent mode written as a preset name is turned
+    /// into an inline table so single keys of it can be set.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dotted key path (e.g., `"agent_modes.plan.model"`).
+    /// * `value` - New value.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::InvalidConfig` if the key is unknown, the value
+    /// has the wrong type, or the value is out of range. The file is left
+    /// unchanged on error.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let segments = split_key(key)?;
+        let known = known_key(&canonical_key(key));
+        if !known {
+            return Err(MPCAError::InvalidConfig(format!(
+                "unknown config key `{}`",
+                key
+            )));
+        }
+
+        let before = self.clone();
+        let problems_before = self.problem_messages();
+
+        let value = value
+            .parse::<Value>()
+            .unwrap_or_else(|_| Value::from(value));
+        if let Err(e) = set_value(self.doc.as_table_mut(), &segments, value) {
+            *self = before;
+            return Err(e);
+        }
+
+        let config = match self.to_config() {
+            Ok(config) => config,
+            Err(e) => {
+                *self = before;
+                return Err(MPCAError::InvalidConfig(format!(
+                    "invalid value for `{}`: {}",
+                    key, e
+                )));
+            }
+        };
+
+        // Reject only problems this change introduced
+        if let Some(problem) = config
+            .problems()
+            .into_iter()
+            .find(|problem| !problems_before.contains(&problem.to_string()))
+        {
+            *self = before;
+            return Err(problem);
+        }
+
+        Ok(())
+    }
+
+    /// Removes a key, so its value falls back to earlier layers.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dotted key path.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the key was set in the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::InvalidConfig` if the key is malformed.
+    pub fn unset(&mut self, key: &str) -> Result<bool> {
+        let segments = split_key(key)?;
+        let (last, parents) = segments.split_last().expect("split_key returns segments");
+
+        let mut table: &mut dyn TableLike = self.doc.as_table_mut();
+        let mut prefix = String::new();
+        for segment in parents {
+            let name = existing_key(table, &prefix, segment);
+            match table.get_mut(&name).and_then(Item::as_table_like_mut) {
+                Some(next) => table = next,
+                None => return Ok(false),
+            }
+            prefix = join(&prefix, &name);
+        }
+
+        let name = existing_key(table, &prefix, last);
+        Ok(table.remove(&name).is_some())
+    }
+
+    /// Writes the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::FileWriteError` if the file cannot be written.
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| MPCAError::FileWriteError(format!("{}: {}", parent.display(), e)))?;
+        }
+        std::fs::write(&self.path, self.doc.to_string())
+            .map_err(|e| MPCAError::FileWriteError(format!("{}: {}", self.path.display(), e)))
+    }
+
+    fn problem_messages(&self) -> Vec<String> {
+        self.to_config()
+            .map(|config| config.problems().iter().map(ToString::to_string).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl std::fmt::Display for ConfigFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.doc)
+    }
+}
+
+/// Normalizes a dotted key, writing the `run` alias as `execute`.
+///
+/// # Examples
+///
+/// ```
+/// use mpca_core::config::canonical_key;
+///
+/// assert_eq!(canonical_key("tool_sets.run"), "tool_sets.execute");
+/// assert_eq!(canonical_key("git.auto_commit"), "git.auto_commit");
+/// ```
+pub fn canonical_key(key: &str) -> String {
+    let mut segments: Vec<&str> = key.split('.').collect();
+    for i in 1..segments.len() {
+        if segments[i] == "run" && is_workflow_section(&segments[..i].join(".")) {
+            segments[i] = "execute";
+        }
+    }
+    segments.join(".")
+}
+
+fn split_key(key: &str) -> Result<Vec<&str>> {
+    let segments: Vec<&str> = key.split('.').collect();
+    if segments.iter().any(|segment| segment.trim().is_empty()) {
+        return Err(MPCAError::InvalidConfig(format!(
+            "invalid config key `{}`",
+            key
+        )));
+    }
+    Ok(segments)
+}
+
+/// Stage name standing in for every `[pipeline.stage.<name>]` in [`known_key`].
+const SAMPLE_STAGE: &str = "stage";
+
+/// Checks a canonical key against the configuration schema.
+///
+/// A key is known if it names a value or a table of values, directly or
+/// within a profile.
+fn known_key(key: &str) -> bool {
+    if key == "profiles" {
+        return true;
+    }
+    if let Some(rest) = key.strip_prefix("profiles.") {
+        return match rest.split_once('.') {
+            Some((_, key)) => !key.starts_with("profiles") && known_key(key),
+            None => true,
+        };
+    }
+
+    // Give optional values a value so they are serialized
+    let mut sample = MpcaConfig::default();
+    sample.api.base_url = Some(String::new());
+    sample.api.api_key_env = Some(String::new());
+    sample.api.api_key_cmd = Some(String::new());
+    sample.api.api_key_file = Some(PathBuf::new());
+    sample.tool_policy.allowed_tools = Some(Vec::new());
+    let modes = &mut sample.agent_modes;
+    for mode in [
+        &mut modes.init,
+        &mut modes.plan,
+        &mut modes.execute,
+        &mut modes.review,
+        &mut modes.verify,
+    ] {
+        mode.append_system_prompt = Some(String::new());
+        mode.fallback_model = Some(String::new());
+    }
+    let limits = BudgetLimits {
+        max_cost_usd: Some(0.0),
+        max_turns: Some(0),
+        max_wall_clock: Some(0),
+    };
+    // Stage names are free-form; every stage is checked against this one
+    sample.pipeline.stage.insert(
+        SAMPLE_STAGE.to_string(),
+        StageConfig {
+            template: Some(String::new()),
+            prompt: Some(String::new()),
+            agent_mode: AgentMode::preset(AgentMode::CODE).map(|mode| AgentMode {
+                append_system_prompt: Some(String::new()),
+                fallback_model: Some(String::new()),
+                ..mode
+            }),
+            tool_set: Some(ToolSet::Full),
+            gates: vec![String::new()],
+        },
+    );
+    let budget = &mut sample.budget;
+    for scope in [
+        &mut budget.feature,
+        &mut budget.init,
+        &mut budget.plan,
+        &mut budget.execute,
+        &mut budget.review,
+        &mut budget.verify,
+    ] {
+        *scope = limits;
+    }
+
+    let Ok(toml::Value::Table(table)) = toml::Value::try_from(&sample) else {
+        return false;
+    };
+    let mut entries = Vec::new();
+    flatten("", table, &mut entries);
+
+    // Header names are free-form
+    if let Some(name) = key.strip_prefix("api.headers.") {
+        return !name.contains('.');
+    }
+    if key == "api.headers" {
+        return true;
+    }
+
+    let key = match key
+        .strip_prefix("pipeline.stage.")
+        .map(|rest| rest.split_once('.'))
+    {
+        Some(Some((_, field))) => format!("pipeline.stage.{}.{}", SAMPLE_STAGE, field),
+        Some(None) => return true,
+        None => key.to_string(),
+    };
+
+    let nested = format!("{}.", key);
+    let is_preset = key.starts_with("agent_modes.")
+        && key.ends_with(".preset")
+        && key.matches('.').count() == 2
+        || key == format!("pipeline.stage.{}.agent_mode.preset", SAMPLE_STAGE);
+    is_preset
+        || entries
+            .iter()
+            .any(|(entry, _)| *entry == key || entry.starts_with(&nested))
+}
+
+/// Finds how a key is spelled in a table, accepting the `run` alias.
+fn existing_key(table: &dyn TableLike, prefix: &str, key: &str) -> String {
+    if is_workflow_section(prefix) && (key == "run" || key == "execute") {
+        for name in ["run", "execute"] {
+            if table.contains_key(name) {
+                return name.to_string();
+            }
+        }
+    }
+    key.to_string()
+}
+
+fn set_value(root: &mut Table, segments: &[&str], value: Value) -> Result<()> {
+    let (last, parents) = segments.split_last().expect("split_key returns segments");
+
+    let mut table: &mut dyn TableLike = root;
+    let mut inline = false;
+    let mut prefix = String::new();
+    for segment in parents {
+        let name = existing_key(table, &prefix, segment);
+
+        match table.get(&name) {
+            None => {
+                let item = if inline {
+                    Item::Value(Value::InlineTable(InlineTable::new()))
+                } else {
+                    let mut table = Table::new();
+                    table.set_implicit(true);
+                    Item::Table(table)
+                };
+                table.insert(&name, item);
+            }
+            // A preset name becomes a table starting from that preset
+            Some(Item::Value(Value::String(preset)))
+                if section_of(&prefix) == "agent_modes"
+                    || section_of(&prefix).starts_with("pipeline.stage.")
+                        && name == "agent_mode" =>
+            {
+                let mut mode = InlineTable::new();
+                mode.insert("preset", Value::from(preset.value().as_str()));
+                table.insert(&name, Item::Value(Value::InlineTable(mode)));
+            }
+            Some(_) => {}
+        }
+
+        let item = table.get_mut(&name).expect("inserted above");
+        inline = item.is_inline_table();
+        table = item.as_table_like_mut().ok_or_else(|| {
+            MPCAError::InvalidConfig(format!("`{}` is not a table", join(&prefix, &name)))
+        })?;
+        prefix = join(&prefix, &name);
+    }
+
+    let name = existing_key(table, &prefix, last);
+    let mut value = value;
+    match table.get_mut(&name) {
+        Some(item) => {
+            // Keep comments attached to the old value
+            if let Item::Value(old) = item {
+                *value.decor_mut() = old.decor().clone();
+            }
+            *item = Item::Value(value);
+        }
+        None => {
+            if inline && let Some((_, last)) = table.iter_mut().last() {
+                // The space before `}` belongs to the last value
+                if let Some(last) = last.as_value_mut() {
+                    last.decor_mut().set_suffix("");
+                }
+            }
+            table.insert(&name, Item::Value(value));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ToolSet;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn repo_with_config(content: &str) -> TempDir {
+        let repo = TempDir::new().unwrap();
+        fs::create_dir_all(repo.path().join(".mpca")).unwrap();
+        fs::write(repo.path().join(".mpca/config.toml"), content).unwrap();
+        repo
+    }
+
+    const CONFIG: &str = r#"# MPCA Configuration
+
+[git]
+# Automatically commit changes during workflows
+auto_commit = true # keep this
+branch_naming = "feature/{feature_slug}"
+
+[agent_modes]
+init = "standard"
+plan = { preset = "code", temperature = 0.3 }
+
+[tool_sets]
+run = "full"
+"#;
+
+    #[test]
+    fn test_set_preserves_comments() {
+        let repo = repo_with_config(CONFIG);
+        let mut file = ConfigFile::open(repo.path()).unwrap();
+
+        file.set("git.auto_commit", "false").unwrap();
+        file.set("agent_modes.plan.model", "claude-opus-4-20250514")
+            .unwrap();
+        file.save().unwrap();
+
+        let content = fs::read_to_string(file.path()).unwrap();
+        assert!(content.starts_with("# MPCA Configuration\n"));
+        assert!(content.contains(
+            "# Automatically commit changes during workflows\nauto_commit = false # keep this\n"
+        ));
+        assert!(content.contains(
+            "plan = { preset = \"code\", temperature = 0.3, model = \"claude-opus-4-20250514\" }"
+        ));
+
+        let config = MpcaConfig::load(repo.path().to_path_buf()).unwrap();
+        assert!(!config.git.auto_commit);
+        assert_eq!(config.agent_modes.plan.model, "claude-opus-4-20250514");
+    }
+
+    #[test]
+    fn test_set_expands_preset_and_creates_tables() {
+        let repo = repo_with_config(CONFIG);
+        let mut file = ConfigFile::open(repo.path()).unwrap();
+
+        file.set("agent_modes.init.max_tokens", "1024").unwrap();
+        file.set("tool_sets.execute", "minimal").unwrap();
+        file.set("budget.feature.max_cost_usd", "12.5").unwrap();
+
+        let content = file.to_string();
+        assert!(content.contains("init = { preset = \"standard\", max_tokens = 1024 }"));
+        assert!(content.contains("run = \"minimal\""));
+        assert!(!content.contains("execute"));
+        assert!(content.contains("[budget.feature]\nmax_cost_usd = 12.5"));
+
+        let config = file.to_config().unwrap();
+        assert_eq!(config.agent_modes.init.max_tokens, 1024);
+        assert_eq!(config.tool_sets.execute, ToolSet::Minimal);
+        assert_eq!(config.budget.feature.max_cost_usd, Some(12.5));
+    }
+
+    #[test]
+    fn test_set_rejects_invalid_changes() {
+        let repo = repo_with_config(CONFIG);
+        let mut file = ConfigFile::open(repo.path()).unwrap();
+        let original = file.to_string();
+
+        assert!(matches!(
+            file.set("git.autocommit", "false"),
+            Err(MPCAError::InvalidConfig(ref e)) if e.contains("unknown config key")
+        ));
+        assert!(matches!(
+            file.set("git.auto_commit", "maybe"),
+            Err(MPCAError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            file.set("agent_modes.plan.temperature", "1.5"),
+            Err(MPCAError::InvalidConfig(ref e)) if e.contains("between 0 and 1")
+        ));
+        assert!(matches!(
+            file.set("agent_modes.plan.model", "\"\""),
+            Err(MPCAError::MissingConfigField(ref e)) if e == "agent_modes.plan.model"
+        ));
+        assert!(matches!(
+            file.set("git.branch_naming", "feature"),
+            Err(MPCAError::InvalidConfig(ref e)) if e.contains("{feature_slug}")
+        ));
+        assert!(matches!(
+            file.set("prompt_dirs", "[\"missing\"]"),
+            Err(MPCAError::InvalidConfig(ref e)) if e.contains("missing")
+        ));
+        assert!(file.set("git..auto_commit", "true").is_err());
+
+        // Secrets stay out of the file
+        assert!(file.set("api.api_key", "sk-ant-123").is_err());
+        assert!(matches!(
+            file.set("api.headers.Authorization", "Bearer sk-ant-123"),
+            Err(MPCAError::InvalidConfig(ref e)) if e.contains("credential")
+        ));
+
+        assert_eq!(file.to_string(), original);
+    }
+
+    #[test]
+    fn test_unset() {
+        let repo = repo_with_config(CONFIG);
+        let mut file = ConfigFile::open(repo.path()).unwrap();
+
+        assert!(file.unset("git.auto_commit").unwrap());
+        assert!(file.unset("tool_sets.execute").unwrap());
+        assert!(!file.unset("git.auto_commit").unwrap());
+        assert!(!file.unset("budget.feature.max_turns").unwrap());
+
+        let content = file.to_string();
+        assert!(!content.contains("auto_commit"));
+        assert!(!content.contains("run ="));
+        assert!(content.contains("branch_naming"));
+    }
+
+    #[test]
+    fn test_open_missing_file() {
+        let repo = TempDir::new().unwrap();
+        let mut file = ConfigFile::open(repo.path()).unwrap();
+
+        file.set("git.auto_commit", "false").unwrap();
+        file.save().unwrap();
+
+        let config = MpcaConfig::load(repo.path().to_path_buf()).unwrap();
+        assert!(!config.git.auto_commit);
+    }
+
+    #[test]
+    fn test_known_keys() {
+        assert!(known_key("git.auto_commit"));
+        assert!(known_key("agent_modes.plan"));
+        assert!(known_key("agent_modes.plan.preset"));
+        assert!(known_key("api.base_url"));
+        assert!(known_key("api.api_key_cmd"));
+        assert!(known_key("api.headers.X-Team"));
+        assert!(!known_key("api.api_key"));
+        assert!(known_key("budget.execute.max_wall_clock"));
+        assert!(known_key("prompt_dirs"));
+        assert!(known_key("tool_policy.allowed_tools"));
+        assert!(known_key("agent_modes.execute.fallback_model"));
+        assert!(known_key("agent_modes.review.permission_mode"));
+        assert!(known_key("pipeline.stage.docs.agent_mode.add_dirs"));
+        assert!(known_key("tool_policy.shell_deny"));
+        assert!(!known_key("git.autocommit"));
+        assert!(!known_key("agent_modes.plan.preset.name"));
+        assert!(known_key("profiles.cheap"));
+        assert!(known_key("profiles.cheap.agent_modes.plan.model"));
+        assert!(!known_key("profiles.cheap.git.autocommit"));
+        assert!(!known_key("profiles.cheap.profiles.nested"));
+        assert!(known_key("pipeline.stages"));
+        assert!(known_key("pipeline.stage.security-scan"));
+        assert!(known_key("pipeline.stage.security-scan.gates"));
+        assert!(known_key("pipeline.stage.docs.agent_mode.preset"));
+        assert!(known_key("pipeline.stage.docs.agent_mode.model"));
+        assert!(!known_key("pipeline.stage.docs.command"));
+        assert!(known_key("profiles.cheap.pipeline.stage.docs.tool_set"));
+    }
+
+    #[test]
+    fn test_set_pipeline_stage() {
+        let repo = repo_with_config(CONFIG);
+        let mut file = ConfigFile::open(repo.path()).unwrap();
+
+        // A stage must be listed before it can be configured
+        assert!(file.set("pipeline.stage.docs.tool_set", "minimal").is_err());
+
+        file.set("pipeline.stages", r#"["execute", "docs"]"#)
+            .unwrap();
+        file.set("pipeline.stage.docs.agent_mode", "standard")
+            .unwrap();
+        file.set("pipeline.stage.docs.agent_mode.max_tokens", "2048")
+            .unwrap();
+        file.set("pipeline.stage.docs.gates", r#"["make docs"]"#)
+            .unwrap();
+
+        let content = file.to_string();
+        assert!(content.contains("agent_mode = { preset = \"standard\", max_tokens = 2048 }"));
+
+        let stages = file.to_config().unwrap().pipeline_stages();
+        assert_eq!(stages[1].name, "docs");
+        assert!(!stages[1].agent_mode.use_code_preset);
+        assert_eq!(stages[1].agent_mode.max_tokens, 2048);
+        assert_eq!(stages[1].gates, ["make docs"]);
+    }
+}
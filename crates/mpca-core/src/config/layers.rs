@@ -0,0 +1,953 @@
+//! Layered configuration sources.
+//!
+//! The effective [`MpcaConfig`] is resolved from several layers, each
+//! overriding the ones before it:
+//!
+//! 1. Built-in defaults
+//! 2. The user-global file, `~/.config/mpca/config.toml` (or
+//!    `$XDG_CONFIG_HOME/mpca/config.toml`)
+//! 3. The repository file, `.mpca/config.toml`
+//! 4. The selected profile, a `[profiles.<name>]` section of either file
+//!    chosen with `--profile` or `MPCA_PROFILE`
+//! 5. The feature file, `.mpca/specs/<slug>/config.toml`, when a feature is
+//!    loaded (see [`MpcaConfig::for_feature`])
+//! 6. `MPCA_*` environment variables
+//! 7. Command-line flags such as `--model` and `--base-url`
+//!
+//! Layers are merged key by key, so a layer only needs to set what it
+//! changes. An agent mode given as a preset name (or as a table with a
+//! `preset` key) replaces the mode of earlier layers instead of merging
+//! with it. [`ConfigLoader`] records which layer each value came from.
+
+use super::{MpcaConfig, WorkflowKind};
+use crate::error::{MPCAError, Result};
+use crate::tools::fs::FsAdapter;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// Environment variable setting the model of every workflow.
+pub const MODEL_ENV: &str = "MPCA_MODEL";
+
+/// Environment variable setting the Claude API base URL.
+pub const BASE_URL_ENV: &str = "MPCA_BASE_URL";
+
+/// Environment variable setting `git.auto_commit`.
+pub const AUTO_COMMIT_ENV: &str = "MPCA_AUTO_COMMIT";
+
+/// Environment variable adding prompt template directories, separated like `PATH`.
+pub const PROMPT_DIRS_ENV: &str = "MPCA_PROMPT_DIRS";
+
+/// Environment variable selecting a configuration profile.
+pub const PROFILE_ENV: &str = "MPCA_PROFILE";
+
+/// Sections whose `execute` entry may also be written as `run`.
+pub(crate) const WORKFLOW_SECTIONS: &[&str] = &["agent_modes", "tool_sets", "budget"];
+
+/// Workflows in the order they appear in configuration files.
+const WORKFLOWS: &[WorkflowKind] = &[
+    WorkflowKind::Init,
+    WorkflowKind::Plan,
+    WorkflowKind::Execute,
+    WorkflowKind::Review,
+    WorkflowKind::Verify,
+];
+
+/// The layer a configuration value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigOrigin {
+    /// Built-in default.
+    Default,
+
+    /// The user-global configuration file.
+    User(PathBuf),
+
+    /// The repository configuration file.
+    Repo(PathBuf),
+
+    /// A configuration profile.
+    Profile(String),
+
+    /// A feature's configuration file.
+    Feature(PathBuf),
+
+    /// An environment variable.
+    Env(String),
+
+    /// A command-line flag.
+    Cli(String),
+}
+
+impl std::fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Default => f.write_str("default"),
+            Self::User(path) => write!(f, "user ({})", path.display()),
+            Self::Repo(path) => write!(f, "repo ({})", path.display()),
+            Self::Profile(name) => write!(f, "profile ({})", name),
+            Self::Feature(path) => write!(f, "feature ({})", path.display()),
+            Self::Env(var) => write!(f, "env ({})", var),
+            Self::Cli(flag) => write!(f, "cli ({})", flag),
+        }
+    }
+}
+
+/// Configuration overrides given on the command line.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    /// Model for every workflow (`--model`).
+    pub model: Option<String>,
+
+    /// Claude API base URL (`--base-url`).
+    pub base_url: Option<String>,
+
+    /// Profile to apply (`--profile`), taking precedence over `MPCA_PROFILE`.
+    pub profile: Option<String>,
+}
+
+/// Resolves [`MpcaConfig`] from all configuration layers.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mpca_core::config::{ConfigLoader, ConfigOverrides};
+/// use std::path::PathBuf;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let loaded = ConfigLoader::new(PathBuf::from("/path/to/repo"))
+///     .with_overrides(ConfigOverrides {
+///         model: Some("claude-opus-4-20250514".to_string()),
+///         ..Default::default()
+///     })
+///     .load()?;
+///
+/// println!("plan model from {}", loaded.origin("agent_modes.plan.model"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    repo_root: PathBuf,
+    user_config: Option<PathBuf>,
+    env: BTreeMap<String, String>,
+    overrides: ConfigOverrides,
+    feature: Option<String>,
+}
+
+impl ConfigLoader {
+    /// Creates a loader reading the process environment.
+    ///
+    /// # Arguments
+    ///
+    /// * `repo_root` - The repository root directory.
+    pub fn new(repo_root: PathBuf) -> Self {
+        Self::with_env(repo_root, std::env::vars())
+    }
+
+    /// Creates a loader reading the given environment instead of the process's.
+    ///
+    /// The user-global file is located through `XDG_CONFIG_HOME` or `HOME`
+    /// in `vars`.
+    ///
+    /// # Arguments
+    ///
+    /// * `repo_root` - The repository root directory.
+    /// * `vars` - Environment variables.
+    pub fn with_env(repo_root: PathBuf, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        let env: BTreeMap<String, String> = vars.into_iter().collect();
+        let user_config = user_config_path(&env);
+
+        Self {
+            repo_root,
+            user_config,
+            env,
+            overrides: ConfigOverrides::default(),
+            feature: None,
+        }
+    }
+
+    /// Replaces the user-global configuration file (`None` skips the layer).
+    pub fn with_user_config(mut self, path: Option<PathBuf>) -> Self {
+        self.user_config = path;
+        self
+    }
+
+    /// Sets the command-line overrides.
+    pub fn with_overrides(mut self, overrides: ConfigOverrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Applies a feature's configuration overrides, if it has any.
+    pub fn with_feature(mut self, feature_slug: impl Into<String>) -> Self {
+        self.feature = Some(feature_slug.into());
+        self
+    }
+
+    /// Resolves the configuration.
+    ///
+    /// # Returns
+    ///
+    /// The effective configuration with the origin of its values.
+    ///
+    /// # Errors
+    ///
+    /// Returns:
+    /// - `MPCAError::ConfigParseError` if a configuration file cannot be
+    ///   read or parsed, or the merged configuration is invalid
+    /// - `MPCAError::InvalidConfig` if an environment variable has an
+    ///   invalid value or the selected profile is not defined
+    pub fn load(&self) -> Result<LoadedConfig> {
+        let mut merged = Layers::default();
+
+        if let Some(path) = &self.user_config {
+            merged.merge_file(path, ConfigOrigin::User(path.clone()))?;
+        }
+
+        let repo_config = MpcaConfig::new(self.repo_root.clone()).config_file;
+        merged.merge_file(&repo_config, ConfigOrigin::Repo(repo_config.clone()))?;
+
+        let profile = self
+            .overrides
+            .profile
+            .clone()
+            .or_else(|| self.env.get(PROFILE_ENV).cloned())
+            .filter(|name| !name.is_empty());
+        if let Some(name) = &profile {
+            let layer = merged.profile(name)?;
+            merged.merge(layer, ConfigOrigin::Profile(name.clone()));
+        }
+
+        let defaults = MpcaConfig::new(self.repo_root.clone());
+        if let Some(slug) = &self.feature {
+            let path = defaults.feature_config_file(slug);
+            if path.exists() {
+                let content = std::fs::read_to_string(&path).map_err(|e| {
+                    MPCAError::ConfigParseError(format!("failed to read {}: {}", path.display(), e))
+                })?;
+                merged.merge(feature_layer(&content, &path)?, ConfigOrigin::Feature(path));
+            }
+        }
+
+        if let Some(model) = self.env.get(MODEL_ENV) {
+            merged.merge_pinned(model_layer(model), ConfigOrigin::Env(MODEL_ENV.to_string()));
+        }
+        if let Some(base_url) = self.env.get(BASE_URL_ENV) {
+            merged.merge_pinned(
+                base_url_layer(base_url),
+                ConfigOrigin::Env(BASE_URL_ENV.to_string()),
+            );
+        }
+        if let Some(value) = self.env.get(AUTO_COMMIT_ENV) {
+            let auto_commit = parse_bool(value).ok_or_else(|| {
+                MPCAError::InvalidConfig(format!(
+                    "{} must be true or false, got {:?}",
+                    AUTO_COMMIT_ENV, value
+                ))
+            })?;
+            merged.merge_pinned(
+                section("git", "auto_commit", Value::Boolean(auto_commit)),
+                ConfigOrigin::Env(AUTO_COMMIT_ENV.to_string()),
+            );
+        }
+        if let Some(value) = self.env.get(PROMPT_DIRS_ENV) {
+            let dirs = std::env::split_paths(value)
+                .map(|dir| Value::String(dir.to_string_lossy().into_owned()))
+                .collect();
+            let mut layer = Table::new();
+            layer.insert("prompt_dirs".to_string(), Value::Array(dirs));
+            merged.merge_pinned(layer, ConfigOrigin::Env(PROMPT_DIRS_ENV.to_string()));
+        }
+
+        if let Some(model) = &self.overrides.model {
+            merged.merge_pinned(model_layer(model), ConfigOrigin::Cli("--model".to_string()));
+        }
+        if let Some(base_url) = &self.overrides.base_url {
+            merged.merge_pinned(
+                base_url_layer(base_url),
+                ConfigOrigin::Cli("--base-url".to_string()),
+            );
+        }
+
+        let content = toml::to_string(&merged.table).map_err(|e| {
+            MPCAError::ConfigParseError(format!("failed to merge configuration: {}", e))
+        })?;
+        let mut config = MpcaConfig::from_toml(&content)?;
+        config.repo_root = defaults.repo_root;
+        config.trees_dir = defaults.trees_dir;
+        config.specs_dir = defaults.specs_dir;
+        config.claude_md = defaults.claude_md;
+        config.config_file = defaults.config_file;
+        config.profile = profile;
+        config.feature = self.feature.clone();
+        config.pinned = merged.pinned;
+
+        Ok(LoadedConfig {
+            config,
+            origins: merged.origins,
+        })
+    }
+}
+
+impl MpcaConfig {
+    /// Applies a feature's configuration overrides.
+    ///
+    /// `.mpca/specs/<slug>/config.toml` may set any configuration key for
+    /// that feature only. Environment variables and command-line flags
+    /// still take precedence over it.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature_slug` - The feature slug identifier.
+    /// * `fs` - File system adapter used to read the feature file.
+    ///
+    /// # Returns
+    ///
+    /// The configuration for the feature; a copy of `self` if the feature
+    /// has no overrides or they are already applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::ConfigParseError` if the feature file cannot be
+    /// read or parsed, or the merged configuration is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mpca_core::MpcaConfig;
+    /// use mpca_core::tools::fs_impl::StdFsAdapter;
+    /// use std::path::PathBuf;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = MpcaConfig::load(PathBuf::from("/path/to/repo"))?;
+    /// let feature = config.for_feature("add-caching", &StdFsAdapter::new())?;
+    /// println!("branch: {}", feature.git.branch_naming);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn for_feature(&self, feature_slug: &str, fs: &dyn FsAdapter) -> Result<MpcaConfig> {
+        let path = self.feature_config_file(feature_slug);
+        if self.feature.as_deref() == Some(feature_slug) || !fs.exists(&path) {
+            let mut config = self.clone();
+            config.feature = Some(feature_slug.to_string());
+            return Ok(config);
+        }
+
+        let content = fs.read_to_string(&path).map_err(|e| {
+            MPCAError::ConfigParseError(format!("failed to read {}: {}", path.display(), e))
+        })?;
+        let layer = feature_layer(&content, &path)?;
+
+        let Value::Table(table) = Value::try_from(self).map_err(|e| {
+            MPCAError::ConfigParseError(format!("failed to serialize configuration: {}", e))
+        })?
+        else {
+            unreachable!("configuration serializes to a table");
+        };
+        let mut merged = Layers {
+            table,
+            ..Default::default()
+        };
+        merged.merge(layer, ConfigOrigin::Feature(path.clone()));
+        merged.merge(self.pinned.clone(), ConfigOrigin::Default);
+
+        let content = toml::to_string(&merged.table).map_err(|e| {
+            MPCAError::ConfigParseError(format!("failed to merge configuration: {}", e))
+        })?;
+        let mut config = MpcaConfig::from_toml(&content)
+            .map_err(|e| MPCAError::ConfigParseError(format!("{}: {}", path.display(), e)))?;
+        config.repo_root = self.repo_root.clone();
+        config.trees_dir = self.trees_dir.clone();
+        config.specs_dir = self.specs_dir.clone();
+        config.claude_md = self.claude_md.clone();
+        config.config_file = self.config_file.clone();
+        config.profile = self.profile.clone();
+        config.feature = Some(feature_slug.to_string());
+        config.pinned = self.pinned.clone();
+
+        Ok(config)
+    }
+}
+
+/// A resolved configuration together with where its values came from.
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    /// The effective configuration.
+    pub config: MpcaConfig,
+
+    /// Origin of each key set by a layer other than the defaults.
+    origins: BTreeMap<String, ConfigOrigin>,
+}
+
+impl LoadedConfig {
+    /// Returns the layer a key's value came from.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dotted key path (e.g., `"agent_modes.plan.model"`).
+    pub fn origin(&self, key: &str) -> &ConfigOrigin {
+        // A value set as a whole table or preset covers the keys below it
+        let mut path = key;
+        loop {
+            if let Some(origin) = self.origins.get(path) {
+                return origin;
+            }
+            match path.rfind('.') {
+                Some(dot) => path = &path[..dot],
+                None => return &ConfigOrigin::Default,
+            }
+        }
+    }
+
+    /// Returns every effective value with its dotted key and origin.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::ConfigParseError` if the configuration cannot be
+    /// serialized.
+    pub fn entries(&self) -> Result<Vec<(String, Value, &ConfigOrigin)>> {
+        let value = Value::try_from(&self.config).map_err(|e| {
+            MPCAError::ConfigParseError(format!("failed to serialize configuration: {}", e))
+        })?;
+
+        let mut entries = Vec::new();
+        if let Value::Table(table) = value {
+            flatten("", table, &mut entries);
+        }
+
+        Ok(entries
+            .into_iter()
+            .map(|(key, value)| {
+                let origin = self.origin(&key);
+                (key, value, origin)
+            })
+            .collect())
+    }
+}
+
+/// Merged configuration tables and the origin of their keys.
+#[derive(Default)]
+struct Layers {
+    table: Table,
+    origins: BTreeMap<String, ConfigOrigin>,
+
+    /// Layers that outrank feature overrides, merged on their own.
+    pinned: Table,
+}
+
+impl Layers {
+    /// Merges a configuration file, if it exists.
+    fn merge_file(&mut self, path: &Path, origin: ConfigOrigin) -> Result<()> {
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            MPCAError::ConfigParseError(format!("failed to read {}: {}", path.display(), e))
+        })?;
+        self.merge(parse_layer(&content, path)?, origin);
+        Ok(())
+    }
+
+    /// Merges a layer that also outranks feature overrides.
+    fn merge_pinned(&mut self, layer: Table, origin: ConfigOrigin) {
+        merge_table(
+            &mut self.pinned,
+            layer.clone(),
+            "",
+            &origin,
+            &mut BTreeMap::new(),
+        );
+        self.merge(layer, origin);
+    }
+
+    /// Returns a profile defined by the layers merged so far.
+    fn profile(&self, name: &str) -> Result<Table> {
+        let profiles = self.table.get("profiles").and_then(Value::as_table);
+        if let Some(Value::Table(profile)) = profiles.and_then(|profiles| profiles.get(name)) {
+            return Ok(profile.clone());
+        }
+
+        let defined: Vec<&str> = profiles
+            .map(|profiles| profiles.keys().map(String::as_str).collect())
+            .unwrap_or_default();
+        Err(MPCAError::InvalidConfig(if defined.is_empty() {
+            format!("unknown profile `{}`: no profiles are defined", name)
+        } else {
+            format!(
+                "unknown profile `{}`: expected one of {}",
+                name,
+                defined.join(", ")
+            )
+        }))
+    }
+
+    /// Merges one layer over the layers before it.
+    fn merge(&mut self, layer: Table, origin: ConfigOrigin) {
+        merge_table(&mut self.table, layer, "", &origin, &mut self.origins);
+    }
+}
+
+fn merge_table(
+    base: &mut Table,
+    layer: Table,
+    prefix: &str,
+    origin: &ConfigOrigin,
+    origins: &mut BTreeMap<String, ConfigOrigin>,
+) {
+    for (key, value) in layer {
+        let key = if key == "run" && is_workflow_section(prefix) {
+            "execute".to_string()
+        } else {
+            key
+        };
+        let path = join(prefix, &key);
+
+        let (value, replaces) = if section_of(prefix) == "agent_modes" {
+            match value {
+                // A preset name stands for a whole mode
+                Value::String(preset) => {
+                    let mut table = Table::new();
+                    table.insert("preset".to_string(), Value::String(preset));
+                    (Value::Table(table), true)
+                }
+                Value::Table(table) => {
+                    let replaces = table.contains_key("preset");
+                    (Value::Table(table), replaces)
+                }
+                value => (value, true),
+            }
+        } else {
+            (value, false)
+        };
+
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(layer)) if !replaces => {
+                merge_table(base, layer, &path, origin, origins);
+            }
+            // Record origins key by key, so unset keys keep their default origin
+            (None, Value::Table(layer)) if !replaces => {
+                let mut table = Table::new();
+                merge_table(&mut table, layer, &path, origin, origins);
+                base.insert(key, Value::Table(table));
+            }
+            (_, value) => {
+                let nested = format!("{}.", path);
+                origins.retain(|key, _| *key != path && !key.starts_with(&nested));
+                origins.insert(path, origin.clone());
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+pub(super) fn flatten(prefix: &str, table: Table, entries: &mut Vec<(String, Value)>) {
+    for (key, value) in table {
+        let path = join(prefix, &key);
+        match value {
+            Value::Table(table) => flatten(&path, table, entries),
+            value => entries.push((path, value)),
+        }
+    }
+}
+
+/// Returns the section a key prefix points into, looking through profiles.
+///
+/// `profiles.cheap.agent_modes` is the `agent_modes` section of a profile.
+pub(super) fn section_of(prefix: &str) -> &str {
+    match prefix.strip_prefix("profiles.") {
+        Some(rest) => rest.split_once('.').map_or("", |(_, section)| section),
+        None => prefix,
+    }
+}
+
+/// Checks whether a key prefix is a section with an entry per workflow.
+pub(super) fn is_workflow_section(prefix: &str) -> bool {
+    WORKFLOW_SECTIONS.contains(&section_of(prefix))
+}
+
+pub(super) fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// Parses a configuration file into a layer.
+fn parse_layer(content: &str, path: &Path) -> Result<Table> {
+    // Validate the file on its own so errors point at it
+    MpcaConfig::from_toml(content)
+        .map_err(|e| MPCAError::ConfigParseError(format!("{}: {}", path.display(), e)))?;
+    toml::from_str(content).map_err(|e| {
+        MPCAError::ConfigParseError(format!("failed to parse {}: {}", path.display(), e))
+    })
+}
+
+/// Parses a feature's configuration file into a layer.
+fn feature_layer(content: &str, path: &Path) -> Result<Table> {
+    let layer = parse_layer(content, path)?;
+    if layer.contains_key("profiles") {
+        return Err(MPCAError::ConfigParseError(format!(
+            "{}: feature configuration cannot define profiles",
+            path.display()
+        )));
+    }
+
+    Ok(layer)
+}
+
+/// Builds a layer setting one key of a section.
+fn section(name: &str, key: &str, value: Value) -> Table {
+    let mut section = Table::new();
+    section.insert(key.to_string(), value);
+    let mut layer = Table::new();
+    layer.insert(name.to_string(), Value::Table(section));
+    layer
+}
+
+/// Builds a layer setting the model of every workflow.
+fn model_layer(model: &str) -> Table {
+    let mut modes = Table::new();
+    for workflow in WORKFLOWS {
+        let mut mode = Table::new();
+        mode.insert("model".to_string(), Value::String(model.to_string()));
+        modes.insert(workflow.as_str().to_string(), Value::Table(mode));
+    }
+    let mut layer = Table::new();
+    layer.insert("agent_modes".to_string(), Value::Table(modes));
+    layer
+}
+
+fn base_url_layer(base_url: &str) -> Table {
+    section("api", "base_url", Value::String(base_url.to_string()))
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Locates the user-global configuration file.
+fn user_config_path(env: &BTreeMap<String, String>) -> Option<PathBuf> {
+    let config_home = env
+        .get("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env.get("HOME").map(|home| Path::new(home).join(".config")))?;
+
+    Some(config_home.join("mpca").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CONFIG_VERSION, ToolSet};
+    use crate::tools::fs_impl::StdFsAdapter;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// Loader for a temporary repo whose user config lives in `home`.
+    fn loader(repo: &TempDir, home: &TempDir, vars: &[(&str, &str)]) -> ConfigLoader {
+        let mut env = vec![("HOME".to_string(), home.path().display().to_string())];
+        env.extend(vars.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+        ConfigLoader::with_env(repo.path().to_path_buf(), env)
+    }
+
+    #[test]
+    fn test_defaults_without_layers() {
+        let repo = TempDir::new().unwrap();
+        let home = TempDir::new().unwrap();
+
+        let loaded = loader(&repo, &home, &[]).load().unwrap();
+        assert_eq!(loaded.config.version, CONFIG_VERSION);
+        assert_eq!(loaded.config.repo_root, repo.path());
+        assert_eq!(loaded.origin("git.auto_commit"), &ConfigOrigin::Default);
+    }
+
+    #[test]
+    fn test_later_layers_override_earlier_ones() {
+        let repo = TempDir::new().unwrap();
+        let home = TempDir::new().unwrap();
+        let user_file = home.path().join(".config/mpca/config.toml");
+        let repo_file = repo.path().join(".mpca/config.toml");
+
+        write(
+            &user_file,
+            "[git]\nauto_commit = false\nbranch_naming = \"me/{feature_slug}\"\n\n[agent_modes]\nplan = { model = \"user-model\" }\n",
+        );
+        write(
+            &repo_file,
+            "[git]\nbranch_naming = \"feat/{feature_slug}\"\n\n[tool_sets]\nrun = \"standard\"\n",
+        );
+
+        let loaded = loader(&repo, &home, &[(BASE_URL_ENV, "http://env")])
+            .with_overrides(ConfigOverrides {
+                base_url: Some("http://cli".to_string()),
+                ..Default::default()
+            })
+            .load()
+            .unwrap();
+        let config = &loaded.config;
+
+        assert!(!config.git.auto_commit);
+        assert_eq!(config.git.branch_naming, "feat/{feature_slug}");
+        assert_eq!(config.agent_modes.plan.model, "user-model");
+        assert!(config.agent_modes.plan.use_code_preset);
+        assert_eq!(config.tool_sets.execute, ToolSet::Standard);
+        assert_eq!(config.api.base_url.as_deref(), Some("http://cli"));
+
+        assert_eq!(
+            loaded.origin("git.auto_commit"),
+            &ConfigOrigin::User(user_file.clone())
+        );
+        assert_eq!(
+            loaded.origin("git.branch_naming"),
+            &ConfigOrigin::Repo(repo_file.clone())
+        );
+        assert_eq!(
+            loaded.origin("tool_sets.execute"),
+            &ConfigOrigin::Repo(repo_file)
+        );
+        assert_eq!(
+            loaded.origin("api.base_url"),
+            &ConfigOrigin::Cli("--base-url".to_string())
+        );
+        assert_eq!(
+            loaded.origin("agent_modes.plan.temperature"),
+            &ConfigOrigin::Default
+        );
+    }
+
+    #[test]
+    fn test_preset_replaces_earlier_mode() {
+        let repo = TempDir::new().unwrap();
+        let home = TempDir::new().unwrap();
+        let repo_file = repo.path().join(".mpca/config.toml");
+
+        write(
+            &home.path().join(".config/mpca/config.toml"),
+            "[agent_modes]\ninit = { model = \"user-model\", max_tokens = 100 }\n",
+        );
+        write(&repo_file, "[agent_modes]\ninit = \"code\"\n");
+
+        let loaded = loader(&repo, &home, &[(MODEL_ENV, "env-model")])
+            .load()
+            .unwrap();
+        let init = &loaded.config.agent_modes.init;
+
+        assert!(init.use_code_preset);
+        assert_eq!(init.max_tokens, 8192);
+        assert_eq!(init.model, "env-model");
+        assert_eq!(
+            loaded.origin("agent_modes.init.max_tokens"),
+            &ConfigOrigin::Repo(repo_file)
+        );
+        assert_eq!(
+            loaded.origin("agent_modes.init.model"),
+            &ConfigOrigin::Env(MODEL_ENV.to_string())
+        );
+        assert_eq!(loaded.config.agent_modes.verify.model, "env-model");
+    }
+
+    #[test]
+    fn test_profile_overlays_files() {
+        let repo = TempDir::new().unwrap();
+        let home = TempDir::new().unwrap();
+
+        write(
+            &home.path().join(".config/mpca/config.toml"),
+            "[profiles.proxy.api]\nbase_url = \"http://proxy\"\n\n[profiles.cheap.agent_modes]\nrun = \"standard\"\n",
+        );
+        write(
+            &repo.path().join(".mpca/config.toml"),
+            "[git]\nauto_commit = false\n\n[profiles.cheap.agent_modes]\nplan = { model = \"cheap-model\" }\n\n[profiles.cheap.git]\nauto_commit = true\n",
+        );
+
+        // Without a profile the overlays are ignored
+        let loaded = loader(&repo, &home, &[]).load().unwrap();
+        assert_eq!(loaded.config.profile, None);
+        assert!(!loaded.config.git.auto_commit);
+        assert_eq!(loaded.config.profiles.len(), 2);
+
+        let loaded = loader(
+            &repo,
+            &home,
+            &[(PROFILE_ENV, "cheap"), (MODEL_ENV, "env-model")],
+        )
+        .load()
+        .unwrap();
+        let config = &loaded.config;
+        assert_eq!(config.profile.as_deref(), Some("cheap"));
+        assert!(config.git.auto_commit);
+        assert!(!config.agent_modes.execute.use_code_preset);
+        assert!(config.agent_modes.plan.use_code_preset);
+        assert_eq!(config.agent_modes.plan.model, "env-model");
+        assert_eq!(config.api.base_url, None);
+        assert_eq!(
+            loaded.origin("git.auto_commit"),
+            &ConfigOrigin::Profile("cheap".to_string())
+        );
+        assert_eq!(
+            loaded.origin("agent_modes.plan.model"),
+            &ConfigOrigin::Env(MODEL_ENV.to_string())
+        );
+
+        // --profile wins over MPCA_PROFILE
+        let loaded = loader(&repo, &home, &[(PROFILE_ENV, "cheap")])
+            .with_overrides(ConfigOverrides {
+                profile: Some("proxy".to_string()),
+                ..Default::default()
+            })
+            .load()
+            .unwrap();
+        assert_eq!(loaded.config.profile.as_deref(), Some("proxy"));
+        assert_eq!(loaded.config.api.base_url.as_deref(), Some("http://proxy"));
+        assert!(!loaded.config.git.auto_commit);
+    }
+
+    #[test]
+    fn test_feature_overrides() {
+        let repo = TempDir::new().unwrap();
+        let home = TempDir::new().unwrap();
+        let feature_file = repo.path().join(".mpca/specs/add-cache/config.toml");
+
+        write(
+            &repo.path().join(".mpca/config.toml"),
+            "[git]\nauto_commit = false\n",
+        );
+        write(
+            &feature_file,
+            "[git]\nbranch_naming = \"spike/{feature_slug}\"\n\n[agent_modes]\nrun = { model = \"feature-model\" }\nplan = \"standard\"\n\n[tool_sets]\nrun = \"minimal\"\n",
+        );
+
+        let loader = loader(&repo, &home, &[(MODEL_ENV, "env-model")]);
+        let config = loader.load().unwrap().config;
+        assert_eq!(config.git.branch_naming, "feature/{feature_slug}");
+
+        // Applied to a loaded configuration by the workflows
+        let fs = StdFsAdapter::new();
+        let feature = config.for_feature("add-cache", &fs).unwrap();
+        assert_eq!(feature.feature.as_deref(), Some("add-cache"));
+        assert_eq!(feature.git.branch_naming, "spike/{feature_slug}");
+        assert!(!feature.git.auto_commit);
+        assert_eq!(feature.tool_sets.execute, ToolSet::Minimal);
+        assert!(!feature.agent_modes.plan.use_code_preset);
+        // The environment still wins over the feature file
+        assert_eq!(feature.agent_modes.execute.model, "env-model");
+        assert_eq!(feature.agent_modes.plan.model, "env-model");
+        assert_eq!(feature.specs_dir, config.specs_dir);
+
+        // Other features are unaffected
+        let other = config.for_feature("other", &fs).unwrap();
+        assert_eq!(other.git.branch_naming, "feature/{feature_slug}");
+
+        // The loader gives the same result, with origins
+        let loaded = loader.with_feature("add-cache").load().unwrap();
+        assert_eq!(
+            toml::to_string(&loaded.config).unwrap(),
+            toml::to_string(&feature).unwrap()
+        );
+        assert_eq!(
+            loaded.origin("git.branch_naming"),
+            &ConfigOrigin::Feature(feature_file.clone())
+        );
+
+        write(&feature_file, "[profiles.cheap.git]\nauto_commit = true\n");
+        let err = config.for_feature("add-cache", &fs).unwrap_err();
+        assert!(err.to_string().contains("cannot define profiles"));
+    }
+
+    #[test]
+    fn test_unknown_profile() {
+        let repo = TempDir::new().unwrap();
+        let home = TempDir::new().unwrap();
+
+        let result = loader(&repo, &home, &[(PROFILE_ENV, "cheap")]).load();
+        assert!(
+            matches!(result, Err(MPCAError::InvalidConfig(ref e)) if e.contains("no profiles are defined"))
+        );
+
+        write(
+            &repo.path().join(".mpca/config.toml"),
+            "[profiles.draft.git]\nauto_commit = false\n",
+        );
+        let result = loader(&repo, &home, &[(PROFILE_ENV, "cheap")]).load();
+        assert!(
+            matches!(result, Err(MPCAError::InvalidConfig(ref e)) if e.contains("expected one of draft"))
+        );
+    }
+
+    #[test]
+    fn test_invalid_env_value() {
+        let repo = TempDir::new().unwrap();
+        let home = TempDir::new().unwrap();
+
+        let result = loader(&repo, &home, &[(AUTO_COMMIT_ENV, "maybe")]).load();
+        assert!(
+            matches!(result, Err(MPCAError::InvalidConfig(ref e)) if e.contains(AUTO_COMMIT_ENV))
+        );
+    }
+
+    #[test]
+    fn test_invalid_file_names_the_file() {
+        let repo = TempDir::new().unwrap();
+        let home = TempDir::new().unwrap();
+        let user_file = home.path().join(".config/mpca/config.toml");
+        write(&user_file, "[tool_sets]\nplan = \"everything\"\n");
+
+        let err = loader(&repo, &home, &[]).load().unwrap_err();
+        assert!(err.to_string().contains(&user_file.display().to_string()));
+    }
+
+    #[test]
+    fn test_entries_report_origins() {
+        let repo = TempDir::new().unwrap();
+        let home = TempDir::new().unwrap();
+
+        let loaded = loader(&repo, &home, &[(AUTO_COMMIT_ENV, "false")])
+            .load()
+            .unwrap();
+        let entries = loaded.entries().unwrap();
+
+        let (_, value, origin) = entries
+            .iter()
+            .find(|(key, _, _)| key == "git.auto_commit")
+            .unwrap();
+        assert_eq!(value, &Value::Boolean(false));
+        assert_eq!(*origin, &ConfigOrigin::Env(AUTO_COMMIT_ENV.to_string()));
+        assert!(
+            entries
+                .iter()
+                .any(|(key, _, _)| key == "agent_modes.plan.model")
+        );
+        assert!(!entries.iter().any(|(key, _, _)| key == "repo_root"));
+    }
+
+    #[test]
+    fn test_user_config_path() {
+        let env = BTreeMap::from([
+            ("HOME".to_string(), "/home/me".to_string()),
+            ("XDG_CONFIG_HOME".to_string(), "/xdg".to_string()),
+        ]);
+        assert_eq!(
+            user_config_path(&env),
+            Some(PathBuf::from("/xdg/mpca/config.toml"))
+        );
+
+        let env = BTreeMap::from([("HOME".to_string(), "/home/me".to_string())]);
+        assert_eq!(
+            user_config_path(&env),
+            Some(PathBuf::from("/home/me/.config/mpca/config.toml"))
+        );
+        assert_eq!(user_config_path(&BTreeMap::new()), None);
+    }
+}
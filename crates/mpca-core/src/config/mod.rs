@@ -0,0 +1,1127 @@
+//! Configuration types for MPCA runtime.
+//!
+//! This module defines all configuration structures used throughout MPCA,
+//! including main configuration, git settings, review settings, agent modes,
+//! and tool sets. The [`layers`] submodule resolves the effective
+//! configuration from defaults, configuration files, environment variables
+//! and command-line flags; the [`edit`] submodule changes the repository
+//! configuration file in place, [`secret`] resolves API credentials
+//! that are kept out of it, [`pipeline`] describes the stages of
+//! `mpca run`, and [`policy`] the permissions enforced on agent tool calls.
+
+pub mod edit;
+pub mod layers;
+pub mod pipeline;
+pub mod policy;
+pub mod secret;
+
+pub use edit::{ConfigFile, canonical_key};
+pub use layers::{ConfigLoader, ConfigOrigin, ConfigOverrides, LoadedConfig};
+pub use pipeline::{EXECUTE_STAGE, PipelineConfig, Stage, StageConfig};
+pub use policy::ToolPolicy;
+pub use secret::{ApiCredentials, SecretString};
+
+use crate::error::{MPCAError, Result};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Version of the configuration schema written by `mpca init`.
+///
+/// Files without a `version` key are read as this version.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Main MPCA configuration.
+///
+/// Contains all paths, settings, and sub-configurations needed for MPCA runtime.
+/// This structure is typically loaded from `.mpca/config.toml` with defaults
+/// applied for missing values: every section and every key is optional.
+///
+/// Paths are derived from the repository root and are never read from or
+/// written to the file.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MpcaConfig {
+    /// Schema version of the configuration file.
+    pub version: u32,
+
+    /// Repository root directory (absolute path).
+    #[serde(skip)]
+    pub repo_root: PathBuf,
+
+    /// Directory for git worktrees (typically `.trees`).
+    #[serde(skip)]
+    pub trees_dir: PathBuf,
+
+    /// Directory for feature specs (typically `.mpca/specs`).
+    #[serde(skip)]
+    pub specs_dir: PathBuf,
+
+    /// Path to CLAUDE.md file in repository root.
+    #[serde(skip)]
+    pub claude_md: PathBuf,
+
+    /// Path to MPCA configuration file (`.mpca/config.toml`).
+    #[serde(skip)]
+    pub config_file: PathBuf,
+
+    /// Additional prompt template directories (for user overrides), highest
+    /// priority first. Each template is loaded from the first directory
+    /// that has it, falling back to `.mpca/prompts` and then the bundled
+    /// templates.
+    pub prompt_dirs: Vec<PathBuf>,
+
+    /// Git-related configuration.
+    pub git: GitConfig,
+
+    /// Code review configuration.
+    pub review: ReviewConfig,
+
+    /// Agent mode configuration per workflow.
+    pub agent_modes: WorkflowModes,
+
+    /// Tool set configuration per workflow.
+    pub tool_sets: WorkflowTools,
+
+    /// Permissions enforced on every tool call of the agent.
+    pub tool_policy: ToolPolicy,
+
+    /// API configuration for Claude SDK.
+    pub api: ApiConfig,
+
+    /// Spending caps for unattended runs.
+    pub budget: BudgetConfig,
+
+    /// Stages of `mpca run`.
+    pub pipeline: PipelineConfig,
+
+    /// Named overlays (`[profiles.<name>]`) selected with `--profile` or
+    /// `MPCA_PROFILE`; each holds any of the keys above.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, toml::Table>,
+
+    /// Name of the profile applied to this configuration, if any.
+    #[serde(skip)]
+    pub profile: Option<String>,
+
+    /// Feature whose overrides are applied to this configuration, if any
+    /// (see [`MpcaConfig::for_feature`]).
+    #[serde(skip)]
+    pub feature: Option<String>,
+
+    /// Environment and command-line layers, which take precedence over
+    /// feature overrides.
+    #[serde(skip)]
+    pub(crate) pinned: toml::Table,
+}
+
+impl MpcaConfig {
+    /// Creates a new configuration with sensible defaults.
+    ///
+    /// # Arguments
+    ///
+    /// * `repo_root` - The repository root directory (must be absolute path).
+    ///
+    /// # Returns
+    ///
+    /// A new `MpcaConfig` with all paths derived from `repo_root` and default
+    /// settings for git, review, agent modes, and tool sets.
+    pub fn new(repo_root: PathBuf) -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            trees_dir: repo_root.join(".trees"),
+            specs_dir: repo_root.join(".mpca").join("specs"),
+            claude_md: repo_root.join("CLAUDE.md"),
+            config_file: repo_root.join(".mpca").join("config.toml"),
+            prompt_dirs: Vec::new(),
+            repo_root,
+            git: GitConfig::default(),
+            review: ReviewConfig::default(),
+            agent_modes: WorkflowModes::default(),
+            tool_sets: WorkflowTools::default(),
+            tool_policy: ToolPolicy::default(),
+            api: ApiConfig::default(),
+            budget: BudgetConfig::default(),
+            pipeline: PipelineConfig::default(),
+            profiles: BTreeMap::new(),
+            profile: None,
+            feature: None,
+            pinned: toml::Table::new(),
+        }
+    }
+
+    /// Loads configuration from `.mpca/config.toml` if it exists, otherwise returns defaults.
+    ///
+    /// # Arguments
+    ///
+    /// * `repo_root` - The repository root directory.
+    ///
+    /// # Returns
+    ///
+    /// A loaded or default `MpcaConfig`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::ConfigParseError` if the config file exists but
+    /// cannot be parsed, or was written for a newer schema version.
+    pub fn load(repo_root: PathBuf) -> Result<Self> {
+        let config_file = repo_root.join(".mpca").join("config.toml");
+
+        if !config_file.exists() {
+            // Return defaults if no config file
+            return Ok(Self::new(repo_root));
+        }
+
+        // Read and parse TOML
+        let content = std::fs::read_to_string(&config_file)
+            .map_err(|e| MPCAError::ConfigParseError(format!("failed to read config: {}", e)))?;
+
+        let mut config = Self::from_toml(&content)?;
+
+        // Override paths with canonical values based on repo_root
+        config.repo_root = repo_root.clone();
+        config.trees_dir = repo_root.join(".trees");
+        config.specs_dir = repo_root.join(".mpca").join("specs");
+        config.claude_md = repo_root.join("CLAUDE.md");
+        config.config_file = config_file;
+
+        Ok(config)
+    }
+
+    /// Returns the location of a feature's configuration overrides.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature_slug` - The feature slug identifier.
+    ///
+    /// # Returns
+    ///
+    /// `<specs_dir>/<feature_slug>/config.toml`.
+    pub fn feature_config_file(&self, feature_slug: &str) -> PathBuf {
+        self.specs_dir.join(feature_slug).join("config.toml")
+    }
+
+    /// Parses configuration from the contents of a `config.toml` file.
+    ///
+    /// Paths are left empty; [`MpcaConfig::load`] derives them from the
+    /// repository root.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - TOML configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::ConfigParseError` if the content is not valid
+    /// configuration, stores a credential in `api.api_key` or a plaintext
+    /// credential header, or its `version` is newer than [`CONFIG_VERSION`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mpca_core::config::{MpcaConfig, ToolSet};
+    ///
+    /// let config = MpcaConfig::from_toml(r#"
+    /// [agent_modes]
+    /// plan = "code"
+    ///
+    /// [tool_sets]
+    /// run = "full"
+    /// "#)?;
+    /// assert!(config.agent_modes.plan.use_code_preset);
+    /// assert_eq!(config.tool_sets.execute, ToolSet::Full);
+    /// # Ok::<(), mpca_core::MPCAError>(())
+    /// ```
+    pub fn from_toml(content: &str) -> Result<Self> {
+        // A key written into the file would otherwise be silently ignored
+        let table: toml::Table = toml::from_str(content)
+            .map_err(|e| MPCAError::ConfigParseError(format!("failed to parse TOML: {}", e)))?;
+        if let Some(toml::Value::Table(api)) = table.get("api")
+            && api.contains_key("api_key")
+        {
+            return Err(MPCAError::ConfigParseError(
+                "api.api_key must not be stored in config.toml; \
+                 set api.api_key_env, api.api_key_cmd or api.api_key_file instead"
+                    .to_string(),
+            ));
+        }
+
+        let config: MpcaConfig = toml::from_str(content)
+            .map_err(|e| MPCAError::ConfigParseError(format!("failed to parse TOML: {}", e)))?;
+
+        // Like api.api_key, a plaintext credential must not load at all
+        if let Some(name) = config.api.plaintext_secret_headers().next() {
+            return Err(MPCAError::ConfigParseError(secret_header_message(name)));
+        }
+
+        if config.version > CONFIG_VERSION {
+            return Err(MPCAError::ConfigParseError(format!(
+                "config version {} is newer than the supported version {}",
+                config.version, CONFIG_VERSION
+            )));
+        }
+
+        // Profiles are checked on their own so errors name the profile
+        for (name, profile) in &config.profiles {
+            if profile.contains_key("profiles") {
+                return Err(MPCAError::ConfigParseError(format!(
+                    "profile `{}` cannot define profiles",
+                    name
+                )));
+            }
+            let content = toml::to_string(profile)
+                .map_err(|e| MPCAError::ConfigParseError(format!("profile `{}`: {}", name, e)))?;
+            Self::from_toml(&content)
+                .map_err(|e| MPCAError::ConfigParseError(format!("profile `{}`: {}", name, e)))?;
+        }
+
+        Ok(config)
+    }
+
+    /// Checks the configuration for invalid values.
+    ///
+    /// Relative `prompt_dirs` are resolved against the repository root.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first problem found (see [`MpcaConfig::problems`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mpca_core::{MPCAError, MpcaConfig};
+    /// use std::path::PathBuf;
+    ///
+    /// let mut config = MpcaConfig::new(PathBuf::from("/repo"));
+    /// assert!(config.validate().is_ok());
+    ///
+    /// config.git.branch_naming = "feature".to_string();
+    /// assert!(matches!(config.validate(), Err(MPCAError::InvalidConfig(_))));
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        match self.problems().into_iter().next() {
+            Some(problem) => Err(problem),
+            None => Ok(()),
+        }
+    }
+
+    /// Lists every invalid value in the configuration.
+    ///
+    /// # Returns
+    ///
+    /// `MPCAError::MissingConfigField` for required values that are empty
+    /// and `MPCAError::InvalidConfig` for values out of range; empty if the
+    /// configuration is valid.
+    pub fn problems(&self) -> Vec<MPCAError> {
+        let mut problems = Vec::new();
+
+        for workflow in [
+            WorkflowKind::Init,
+            WorkflowKind::Plan,
+            WorkflowKind::Execute,
+            WorkflowKind::Review,
+            WorkflowKind::Verify,
+        ] {
+            let key = format!("agent_modes.{}", workflow);
+            check_mode(&key, self.agent_modes.get(workflow), &mut problems);
+        }
+
+        if !self.git.branch_naming.contains("{feature_slug}") {
+            problems.push(MPCAError::InvalidConfig(format!(
+                "git.branch_naming must contain the {{feature_slug}} placeholder, got {:?}",
+                self.git.branch_naming
+            )));
+        }
+
+        let key_sources = [
+            self.api.api_key_env.is_some(),
+            self.api.api_key_cmd.is_some(),
+            self.api.api_key_file.is_some(),
+        ];
+        if key_sources.iter().filter(|set| **set).count() > 1 {
+            problems.push(MPCAError::InvalidConfig(
+                "only one of api.api_key_env, api.api_key_cmd and api.api_key_file may be set"
+                    .to_string(),
+            ));
+        }
+        for name in self.api.plaintext_secret_headers() {
+            problems.push(MPCAError::InvalidConfig(secret_header_message(name)));
+        }
+
+        problems.extend(self.tool_policy.problems());
+        problems.extend(self.pipeline.problems());
+
+        for dir in &self.prompt_dirs {
+            let resolved = self.repo_root.join(dir);
+            if !resolved.is_dir() {
+                problems.push(MPCAError::InvalidConfig(format!(
+                    "prompt_dirs entry {} is not a directory",
+                    resolved.display()
+                )));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Checks an agent mode configured under `key` for invalid values.
+fn check_mode(key: &str, mode: &AgentMode, problems: &mut Vec<MPCAError>) {
+    if mode.model.trim().is_empty() {
+        problems.push(MPCAError::MissingConfigField(format!("{}.model", key)));
+    }
+    if !(0.0..=1.0).contains(&mode.temperature) {
+        problems.push(MPCAError::InvalidConfig(format!(
+            "{}.temperature must be between 0 and 1, got {}",
+            key, mode.temperature
+        )));
+    }
+    if mode.max_tokens == 0 {
+        problems.push(MPCAError::InvalidConfig(format!(
+            "{}.max_tokens must be greater than 0",
+            key
+        )));
+    }
+    if mode.max_turns == 0 {
+        problems.push(MPCAError::InvalidConfig(format!(
+            "{}.max_turns must be greater than 0",
+            key
+        )));
+    }
+    match mode.fallback_model.as_deref().map(str::trim) {
+        Some("") => problems.push(MPCAError::InvalidConfig(format!(
+            "{}.fallback_model must not be empty",
+            key
+        ))),
+        Some(fallback) if fallback == mode.model => problems.push(MPCAError::InvalidConfig(
+            format!("{}.fallback_model must differ from the model", key),
+        )),
+        _ => {}
+    }
+    for (name, tools) in [
+        ("allowed_tools", &mode.allowed_tools),
+        ("disallowed_tools", &mode.disallowed_tools),
+    ] {
+        if tools.iter().any(|tool| tool.trim().is_empty()) {
+            problems.push(MPCAError::InvalidConfig(format!(
+                "{}.{} must not contain empty entries",
+                key, name
+            )));
+        }
+    }
+}
+
+impl Default for MpcaConfig {
+    fn default() -> Self {
+        Self::new(PathBuf::new())
+    }
+}
+
+impl std::fmt::Debug for MpcaConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MpcaConfig")
+            .field("version", &self.version)
+            .field("repo_root", &self.repo_root)
+            .field("trees_dir", &self.trees_dir)
+            .field("specs_dir", &self.specs_dir)
+            .field("claude_md", &self.claude_md)
+            .field("config_file", &self.config_file)
+            .field("prompt_dirs", &self.prompt_dirs)
+            .field("git", &self.git)
+            .field("review", &self.review)
+            .field("agent_modes", &"<configured>")
+            .field("tool_sets", &"<configured>")
+            .field("tool_policy", &self.tool_policy)
+            .field("api", &self.api)
+            .field("budget", &self.budget)
+            .field("pipeline", &self.pipeline.stages)
+            .field("profiles", &self.profiles.keys().collect::<Vec<_>>())
+            .field("profile", &self.profile)
+            .field("feature", &self.feature)
+            .finish()
+    }
+}
+
+/// Git-related configuration.
+///
+/// Controls git behavior for MPCA workflows, including automatic commits
+/// and branch naming conventions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitConfig {
+    /// Whether to automatically commit changes during workflows.
+    pub auto_commit: bool,
+
+    /// Branch naming pattern (can include placeholders like `{feature_slug}`).
+    pub branch_naming: String,
+
+    /// Whether to commit the worktree as work in progress when a run is
+    /// interrupted, so `mpca resume` starts from a clean tree.
+    pub wip_commit: bool,
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            auto_commit: true,
+            branch_naming: "feature/{feature_slug}".to_string(),
+            wip_commit: true,
+        }
+    }
+}
+
+/// Code review configuration.
+///
+/// Controls code review behavior, including whether reviews are enabled
+/// and the list of reviewers.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReviewConfig {
+    /// Whether code review is enabled for this repository.
+    pub enabled: bool,
+
+    /// List of reviewers (usernames or email addresses).
+    pub reviewers: Vec<String>,
+}
+
+/// API configuration for Claude SDK.
+///
+/// Allows customization of the Claude API endpoint, useful for:
+/// - Custom deployments or private cloud instances
+/// - Proxy servers or API gateways
+/// - Testing environments with mock servers
+///
+/// The API key itself is never stored: at most one of `api_key_env`,
+/// `api_key_cmd` and `api_key_file` names where to read it (see
+/// [`ApiCredentials`]). Without one, the Claude CLI uses its own login.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// Base URL for Claude API (e.g., "https://api.anthropic.com").
+    /// If None, uses the SDK default endpoint.
+    pub base_url: Option<String>,
+
+    /// Environment variable holding the API key (e.g., "ANTHROPIC_API_KEY").
+    pub api_key_env: Option<String>,
+
+    /// Command printing the API key (e.g., "pass show anthropic").
+    pub api_key_cmd: Option<String>,
+
+    /// File containing the API key. `~` expands to the home directory;
+    /// relative paths are resolved against the repository root.
+    pub api_key_file: Option<PathBuf>,
+
+    /// Extra HTTP headers sent with every API request (e.g., for a gateway).
+    /// Values may reference environment variables as `${NAME}`; headers
+    /// carrying credentials must.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl ApiConfig {
+    /// Returns the headers that look like credentials but hold a literal
+    /// value instead of an environment variable reference.
+    pub fn plaintext_secret_headers(&self) -> impl Iterator<Item = &str> {
+        self.headers
+            .iter()
+            .filter(|(name, value)| {
+                secret::is_sensitive_header(name) && !secret::references_env(value)
+            })
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+/// Describes a credential header stored in plain text.
+fn secret_header_message(name: &str) -> String {
+    format!(
+        "api.headers.{} looks like a credential; reference an environment \
+         variable instead (e.g., \"Bearer ${{GATEWAY_TOKEN}}\")",
+        name
+    )
+}
+
+impl std::fmt::Debug for ApiConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiConfig")
+            .field("base_url", &self.base_url)
+            .field("api_key_env", &self.api_key_env)
+            .field(
+                "api_key_cmd",
+                &self.api_key_cmd.as_ref().map(|_| "<redacted>"),
+            )
+            .field("api_key_file", &self.api_key_file)
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Agent mode configuration for a specific workflow.
+///
+/// Defines how the Claude agent should behave for a particular workflow,
+/// including model selection, system prompt, permissions and limits. Every
+/// session a workflow starts gets these settings as its agent options.
+///
+/// In `config.toml` a mode is either the name of a preset or a table. A
+/// table starts from the preset named by its `preset` key (the workflow's
+/// default mode if omitted) and overrides the keys it sets:
+///
+/// ```toml
+/// [agent_modes]
+/// init = "standard"
+/// plan = { preset = "code", model = "claude-opus-4-20250514" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AgentMode {
+    /// Whether to use Claude Code preset (enables advanced code understanding).
+    pub use_code_preset: bool,
+
+    /// Model identifier (e.g., "claude-3-5-sonnet-20241022").
+    pub model: String,
+
+    /// Temperature for generation (0.0 = deterministic, 1.0 = creative).
+    ///
+    /// The Claude CLI has no temperature setting, so this is only
+    /// validated for now.
+    pub temperature: f64,
+
+    /// Maximum tokens for response.
+    pub max_tokens: u32,
+
+    /// How the agent asks for permission to use tools.
+    pub permission_mode: PermissionMode,
+
+    /// Instructions appended to the system prompt, after the workflow's
+    /// rendered template.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub append_system_prompt: Option<String>,
+
+    /// Tools allowed in addition to the workflow's tool set (e.g., MCP tools).
+    pub allowed_tools: Vec<String>,
+
+    /// Tools removed from the workflow's tool set.
+    pub disallowed_tools: Vec<String>,
+
+    /// Maximum number of agent turns per query.
+    pub max_turns: u32,
+
+    /// Model used when the primary model is unavailable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_model: Option<String>,
+
+    /// Directories the agent may access besides its working directory;
+    /// relative paths are resolved against the repository root.
+    pub add_dirs: Vec<PathBuf>,
+}
+
+impl AgentMode {
+    /// Name of the preset without the Claude Code system prompt.
+    pub const STANDARD: &'static str = "standard";
+
+    /// Name of the preset using the Claude Code system prompt.
+    pub const CODE: &'static str = "code";
+
+    /// Default maximum number of agent turns per query.
+    pub const DEFAULT_MAX_TURNS: u32 = 20;
+
+    /// Default model of the presets.
+    const DEFAULT_MODEL: &'static str = "claude-3-5-sonnet-20241022";
+
+    /// Returns the agent mode a preset name stands for.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - `"standard"` or `"code"`.
+    ///
+    /// # Returns
+    ///
+    /// The preset's agent mode, or `None` for an unknown name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mpca_core::config::AgentMode;
+    ///
+    /// let code = AgentMode::preset("code").unwrap();
+    /// assert!(code.use_code_preset);
+    /// assert!(AgentMode::preset("turbo").is_none());
+    /// ```
+    pub fn preset(name: &str) -> Option<Self> {
+        let standard = Self {
+            use_code_preset: false,
+            model: Self::DEFAULT_MODEL.to_string(),
+            temperature: 0.0,
+            max_tokens: 4096,
+            permission_mode: PermissionMode::Default,
+            append_system_prompt: None,
+            allowed_tools: Vec::new(),
+            disallowed_tools: Vec::new(),
+            max_turns: Self::DEFAULT_MAX_TURNS,
+            fallback_model: None,
+            add_dirs: Vec::new(),
+        };
+
+        match name {
+            Self::STANDARD => Some(standard),
+            Self::CODE => Some(Self {
+                use_code_preset: true,
+                max_tokens: 8192,
+                ..standard
+            }),
+            _ => None,
+        }
+    }
+
+    /// Looks up a preset, failing with a deserialization error if unknown.
+    fn preset_or_error<E: serde::de::Error>(name: &str) -> std::result::Result<Self, E> {
+        Self::preset(name).ok_or_else(|| {
+            E::invalid_value(
+                serde::de::Unexpected::Str(name),
+                &"an agent mode preset (\"standard\" or \"code\")",
+            )
+        })
+    }
+}
+
+/// How the agent asks for permission to use tools.
+///
+/// Written in `config.toml` as `"default"`, `"accept_edits"`, `"plan"` or
+/// `"bypass_permissions"`; the Claude CLI spellings (`"acceptEdits"`,
+/// `"bypassPermissions"`) are accepted too.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionMode {
+    /// Tools outside the allowed tools need permission.
+    #[default]
+    Default,
+
+    /// File edits are accepted without asking.
+    #[serde(alias = "acceptEdits")]
+    AcceptEdits,
+
+    /// The agent plans but doesn't change anything.
+    Plan,
+
+    /// No permission is asked for; the tool policy still applies.
+    #[serde(alias = "bypassPermissions")]
+    BypassPermissions,
+}
+
+/// An [`AgentMode`] as written in `config.toml`: a preset name or a table.
+///
+/// Keys the table leaves out come from its `preset`, or from the workflow's
+/// default mode when it names none.
+#[derive(Debug, Default)]
+struct AgentModeSpec {
+    preset: Option<AgentMode>,
+    use_code_preset: Option<bool>,
+    model: Option<String>,
+    temperature: Option<f64>,
+    max_tokens: Option<u32>,
+    permission_mode: Option<PermissionMode>,
+    append_system_prompt: Option<String>,
+    allowed_tools: Option<Vec<String>>,
+    disallowed_tools: Option<Vec<String>>,
+    max_turns: Option<u32>,
+    fallback_model: Option<String>,
+    add_dirs: Option<Vec<PathBuf>>,
+}
+
+impl AgentModeSpec {
+    /// Resolves the spec against the workflow's default mode.
+    fn apply(self, default: AgentMode) -> AgentMode {
+        let mut mode = self.preset.unwrap_or(default);
+        if let Some(use_code_preset) = self.use_code_preset {
+            mode.use_code_preset = use_code_preset;
+        }
+        if let Some(model) = self.model {
+            mode.model = model;
+        }
+        if let Some(temperature) = self.temperature {
+            mode.temperature = temperature;
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            mode.max_tokens = max_tokens;
+        }
+        if let Some(permission_mode) = self.permission_mode {
+            mode.permission_mode = permission_mode;
+        }
+        if let Some(append_system_prompt) = self.append_system_prompt {
+            mode.append_system_prompt = Some(append_system_prompt);
+        }
+        if let Some(allowed_tools) = self.allowed_tools {
+            mode.allowed_tools = allowed_tools;
+        }
+        if let Some(disallowed_tools) = self.disallowed_tools {
+            mode.disallowed_tools = disallowed_tools;
+        }
+        if let Some(max_turns) = self.max_turns {
+            mode.max_turns = max_turns;
+        }
+        if let Some(fallback_model) = self.fallback_model {
+            mode.fallback_model = Some(fallback_model);
+        }
+        if let Some(add_dirs) = self.add_dirs {
+            mode.add_dirs = add_dirs;
+        }
+        mode
+    }
+}
+
+/// Table form of an [`AgentModeSpec`].
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AgentModeTable {
+    preset: Option<String>,
+    use_code_preset: Option<bool>,
+    model: Option<String>,
+    temperature: Option<f64>,
+    max_tokens: Option<u32>,
+    permission_mode: Option<PermissionMode>,
+    append_system_prompt: Option<String>,
+    allowed_tools: Option<Vec<String>>,
+    disallowed_tools: Option<Vec<String>>,
+    max_turns: Option<u32>,
+    fallback_model: Option<String>,
+    add_dirs: Option<Vec<PathBuf>>,
+}
+
+impl<'de> Deserialize<'de> for AgentModeSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct AgentModeVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for AgentModeVisitor {
+            type Value = AgentModeSpec;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("an agent mode preset name or table")
+            }
+
+            fn visit_str<E: serde::de::Error>(
+                self,
+                name: &str,
+            ) -> std::result::Result<AgentModeSpec, E> {
+                Ok(AgentModeSpec {
+                    preset: Some(AgentMode::preset_or_error(name)?),
+                    ..Default::default()
+                })
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> std::result::Result<AgentModeSpec, A::Error> {
+                let table =
+                    AgentModeTable::deserialize(serde::de::value::MapAccessDeserializer::new(map))?;
+
+                Ok(AgentModeSpec {
+                    preset: table
+                        .preset
+                        .as_deref()
+                        .map(AgentMode::preset_or_error)
+                        .transpose()?,
+                    use_code_preset: table.use_code_preset,
+                    model: table.model,
+                    temperature: table.temperature,
+                    max_tokens: table.max_tokens,
+                    permission_mode: table.permission_mode,
+                    append_system_prompt: table.append_system_prompt,
+                    allowed_tools: table.allowed_tools,
+                    disallowed_tools: table.disallowed_tools,
+                    max_turns: table.max_turns,
+                    fallback_model: table.fallback_model,
+                    add_dirs: table.add_dirs,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(AgentModeVisitor)
+    }
+}
+
+/// Workflow kinds that run the Claude agent.
+///
+/// Used to select the [`AgentMode`] and [`ToolSet`] for a workflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WorkflowKind {
+    /// Repository initialization.
+    Init,
+
+    /// Feature planning.
+    Plan,
+
+    /// Feature execution.
+    Execute,
+
+    /// Code review.
+    Review,
+
+    /// Verification.
+    Verify,
+}
+
+impl WorkflowKind {
+    /// Returns the string representation of the workflow kind.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Init => "init",
+            Self::Plan => "plan",
+            Self::Execute => "execute",
+            Self::Review => "review",
+            Self::Verify => "verify",
+        }
+    }
+}
+
+impl std::fmt::Display for WorkflowKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Agent mode configuration for all workflows.
+///
+/// Provides defaults for each workflow type with appropriate settings.
+/// Users can override these in `.mpca/config.toml`; the execute workflow
+/// may also be configured under its command name, `run`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "WorkflowModesSpec")]
+pub struct WorkflowModes {
+    /// Agent mode for init workflow.
+    pub init: AgentMode,
+
+    /// Agent mode for plan workflow.
+    pub plan: AgentMode,
+
+    /// Agent mode for execute workflow.
+    pub execute: AgentMode,
+
+    /// Agent mode for review workflow.
+    pub review: AgentMode,
+
+    /// Agent mode for verify workflow.
+    pub verify: AgentMode,
+}
+
+impl WorkflowModes {
+    /// Returns the agent mode for a workflow.
+    pub fn get(&self, workflow: WorkflowKind) -> &AgentMode {
+        match workflow {
+            WorkflowKind::Init => &self.init,
+            WorkflowKind::Plan => &self.plan,
+            WorkflowKind::Execute => &self.execute,
+            WorkflowKind::Review => &self.review,
+            WorkflowKind::Verify => &self.verify,
+        }
+    }
+}
+
+impl Default for WorkflowModes {
+    fn default() -> Self {
+        let standard = || AgentMode::preset(AgentMode::STANDARD).expect("built-in preset");
+        let code = || AgentMode::preset(AgentMode::CODE).expect("built-in preset");
+
+        Self {
+            init: standard(),
+            plan: AgentMode {
+                temperature: 0.3,
+                ..code()
+            },
+            execute: code(),
+            review: code(),
+            verify: standard(),
+        }
+    }
+}
+
+/// [`WorkflowModes`] as written in `config.toml`.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct WorkflowModesSpec {
+    init: AgentModeSpec,
+    plan: AgentModeSpec,
+    #[serde(alias = "run")]
+    execute: AgentModeSpec,
+    review: AgentModeSpec,
+    verify: AgentModeSpec,
+}
+
+impl From<WorkflowModesSpec> for WorkflowModes {
+    fn from(spec: WorkflowModesSpec) -> Self {
+        let defaults = WorkflowModes::default();
+        Self {
+            init: spec.init.apply(defaults.init),
+            plan: spec.plan.apply(defaults.plan),
+            execute: spec.execute.apply(defaults.execute),
+            review: spec.review.apply(defaults.review),
+            verify: spec.verify.apply(defaults.verify),
+        }
+    }
+}
+
+/// Tool set variants for different workflow needs.
+///
+/// Defines which SDK tools the agent may call during a workflow; calls to
+/// any other tool are denied. [`ToolPolicy`] narrows this further.
+/// Written in `config.toml` as `"minimal"`, `"standard"` or `"full"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolSet {
+    /// Read-only tools: `Read`, `Glob`, `Grep`, `LS`.
+    #[serde(alias = "Minimal")]
+    Minimal,
+
+    /// Minimal tools plus `Write`, `Edit`, `MultiEdit` and `Bash`.
+    #[serde(alias = "Standard")]
+    Standard,
+
+    /// Standard tools plus `NotebookEdit`, `TodoWrite`, `Task`, `WebFetch`
+    /// and `WebSearch`.
+    #[serde(alias = "Full")]
+    Full,
+}
+
+impl ToolSet {
+    /// Returns the Claude tools the agent may use with this tool set.
+    ///
+    /// Each tool set includes all tools of the smaller ones.
+    pub fn allowed_tools(&self) -> Vec<String> {
+        const MINIMAL: &[&str] = &["Read", "Glob", "Grep", "LS"];
+        const STANDARD: &[&str] = &["Write", "Edit", "MultiEdit", "Bash"];
+        const FULL: &[&str] = &["NotebookEdit", "TodoWrite", "Task", "WebFetch", "WebSearch"];
+
+        let sets: &[&[&str]] = match self {
+            Self::Minimal => &[MINIMAL],
+            Self::Standard => &[MINIMAL, STANDARD],
+            Self::Full => &[MINIMAL, STANDARD, FULL],
+        };
+
+        sets.iter()
+            .flat_map(|set| set.iter())
+            .map(|tool| tool.to_string())
+            .collect()
+    }
+}
+
+/// Tool set configuration for all workflows.
+///
+/// Defines which tools are available to each workflow type.
+/// Follows principle of least privilege by default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkflowTools {
+    /// Tool set for init workflow.
+    pub init: ToolSet,
+
+    /// Tool set for plan workflow.
+    pub plan: ToolSet,
+
+    /// Tool set for execute workflow.
+    #[serde(alias = "run")]
+    pub execute: ToolSet,
+
+    /// Tool set for review workflow.
+    pub review: ToolSet,
+
+    /// Tool set for verify workflow.
+    pub verify: ToolSet,
+}
+
+impl WorkflowTools {
+    /// Returns the tool set for a workflow.
+    pub fn get(&self, workflow: WorkflowKind) -> ToolSet {
+        match workflow {
+            WorkflowKind::Init => self.init,
+            WorkflowKind::Plan => self.plan,
+            WorkflowKind::Execute => self.execute,
+            WorkflowKind::Review => self.review,
+            WorkflowKind::Verify => self.verify,
+        }
+    }
+}
+
+impl Default for WorkflowTools {
+    fn default() -> Self {
+        Self {
+            init: ToolSet::Minimal,
+            plan: ToolSet::Standard,
+            execute: ToolSet::Full,
+            review: ToolSet::Standard,
+            verify: ToolSet::Standard,
+        }
+    }
+}
+
+/// Budget configuration.
+///
+/// Caps what the agent may spend, so an unattended `mpca run` stops before
+/// it gets expensive. `feature` limits apply to everything recorded for a
+/// feature across all runs; the per-workflow limits apply to a single
+/// invocation of that workflow. No limit is set by default.
+///
+/// ```toml
+/// [budget.feature]
+/// max_cost_usd = 20.0
+///
+/// [budget.execute]
+/// max_turns = 200
+/// max_wall_clock = 3600
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetConfig {
+    /// Limits for a whole feature.
+    pub feature: BudgetLimits,
+
+    /// Limits for one init run.
+    pub init: BudgetLimits,
+
+    /// Limits for one plan run.
+    pub plan: BudgetLimits,
+
+    /// Limits for one execute run.
+    #[serde(alias = "run")]
+    pub execute: BudgetLimits,
+
+    /// Limits for one review run.
+    pub review: BudgetLimits,
+
+    /// Limits for one verify run.
+    pub verify: BudgetLimits,
+}
+
+impl BudgetConfig {
+    /// Returns the per-run limits of a workflow.
+    pub fn get(&self, workflow: WorkflowKind) -> &BudgetLimits {
+        match workflow {
+            WorkflowKind::Init => &self.init,
+            WorkflowKind::Plan => &self.plan,
+            WorkflowKind::Execute => &self.execute,
+            WorkflowKind::Review => &self.review,
+            WorkflowKind::Verify => &self.verify,
+        }
+    }
+}
+
+/// A set of spending caps. Unset caps are unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetLimits {
+    /// Maximum cost in USD.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_cost_usd: Option<f64>,
+
+    /// Maximum number of agent turns.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<u32>,
+
+    /// Maximum wall-clock time in seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_wall_clock: Option<u64>,
+}
+
+impl BudgetLimits {
+    /// Returns `true` if no cap is set.
+    pub fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
+}
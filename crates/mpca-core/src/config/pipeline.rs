@@ -0,0 +1,365 @@
+//! Pipeline configuration for `mpca run`.
+//!
+//! `[pipeline]` lists the stages `mpca run` goes through, in order. The
+//! built-in `execute` stage implements `plan.md` one step at a time; every
+//! other stage is a single agent query in the feature's worktree. Each stage
+//! binds a prompt template, an [`AgentMode`] and a [`ToolSet`], and may run
+//! shell gate commands in the worktree once its agent work is done:
+//!
+//! ```toml
+//! [pipeline]
+//! stages = ["execute", "security-scan", "docs", "changelog"]
+//!
+//! [pipeline.stage.execute]
+//! gates = ["cargo test"]
+//!
+//! [pipeline.stage.security-scan]
+//! template = "security"
+//! prompt = "Review the changes on this branch for security issues and fix them."
+//! agent_mode = "standard"
+//! tool_set = "standard"
+//! gates = ["cargo audit"]
+//! ```
+//!
+//! Without a `[pipeline]` section only the `execute` stage runs.
+
+use super::{AgentMode, AgentModeSpec, MpcaConfig, ToolSet, check_mode};
+use crate::error::MPCAError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Name of the built-in stage that runs the plan steps.
+pub const EXECUTE_STAGE: &str = "execute";
+
+/// The stages of `mpca run`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipelineConfig {
+    /// Stage names, in the order they run.
+    pub stages: Vec<String>,
+
+    /// Settings of individual stages (`[pipeline.stage.<name>]`); stages
+    /// without an entry use the defaults described on [`StageConfig`].
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub stage: BTreeMap<String, StageConfig>,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            stages: vec![EXECUTE_STAGE.to_string()],
+            stage: BTreeMap::new(),
+        }
+    }
+}
+
+impl PipelineConfig {
+    /// Lists every invalid value in the pipeline.
+    pub(super) fn problems(&self) -> Vec<MPCAError> {
+        let mut problems = Vec::new();
+
+        if !self.stages.iter().any(|name| name == EXECUTE_STAGE) {
+            problems.push(MPCAError::InvalidConfig(format!(
+                "pipeline.stages must include the `{}` stage",
+                EXECUTE_STAGE
+            )));
+        }
+
+        let mut seen = BTreeSet::new();
+        for name in &self.stages {
+            if !is_stage_name(name) {
+                problems.push(MPCAError::InvalidConfig(format!(
+                    "invalid stage name {:?} in pipeline.stages \
+                     (must be lowercase alphanumeric with hyphens or underscores)",
+                    name
+                )));
+            }
+            if !seen.insert(name) {
+                problems.push(MPCAError::InvalidConfig(format!(
+                    "stage `{}` is listed more than once in pipeline.stages",
+                    name
+                )));
+            }
+        }
+
+        for (name, stage) in &self.stage {
+            let key = format!("pipeline.stage.{}", name);
+            if !self.stages.contains(name) {
+                problems.push(MPCAError::InvalidConfig(format!(
+                    "{} is not listed in pipeline.stages",
+                    key
+                )));
+            }
+            if name == EXECUTE_STAGE && stage.prompt.is_some() {
+                problems.push(MPCAError::InvalidConfig(format!(
+                    "{}.prompt is not supported; the execute stage prompts once per plan step",
+                    key
+                )));
+            }
+            if let Some(mode) = &stage.agent_mode {
+                check_mode(&format!("{}.agent_mode", key), mode, &mut problems);
+            }
+            if stage.gates.iter().any(|gate| gate.trim().is_empty()) {
+                problems.push(MPCAError::InvalidConfig(format!(
+                    "{}.gates must not contain empty commands",
+                    key
+                )));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Settings of a pipeline stage.
+///
+/// Every key is optional: the template defaults to the stage name, and the
+/// agent mode and tool set to those of the execute workflow. `agent_mode`
+/// is written like an entry of `[agent_modes]`; a table without a `preset`
+/// starts from the `code` preset.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "StageConfigSpec")]
+pub struct StageConfig {
+    /// Prompt template rendered as the stage's system prompt, with the
+    /// variables of the `execute` template.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+
+    /// Prompt sent to the agent; not supported for the execute stage.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+
+    /// Agent mode the stage runs with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_mode: Option<AgentMode>,
+
+    /// Tools the agent may use during the stage.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_set: Option<ToolSet>,
+
+    /// Shell commands run in the worktree after the stage's agent work;
+    /// the stage fails unless every command exits successfully.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub gates: Vec<String>,
+}
+
+/// [`StageConfig`] as written in `config.toml`.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct StageConfigSpec {
+    template: Option<String>,
+    prompt: Option<String>,
+    agent_mode: Option<AgentModeSpec>,
+    tool_set: Option<ToolSet>,
+    gates: Vec<String>,
+}
+
+impl From<StageConfigSpec> for StageConfig {
+    fn from(spec: StageConfigSpec) -> Self {
+        Self {
+            template: spec.template,
+            prompt: spec.prompt,
+            agent_mode: spec.agent_mode.map(|mode| {
+                mode.apply(AgentMode::preset(AgentMode::CODE).expect("built-in preset"))
+            }),
+            tool_set: spec.tool_set,
+            gates: spec.gates,
+        }
+    }
+}
+
+/// A pipeline stage with its defaults applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stage {
+    /// Stage name from `pipeline.stages`.
+    pub name: String,
+
+    /// Prompt template rendered as the stage's system prompt.
+    pub template: String,
+
+    /// Prompt sent to the agent, if configured.
+    pub prompt: Option<String>,
+
+    /// Agent mode the stage runs with.
+    pub agent_mode: AgentMode,
+
+    /// Tools the agent may use during the stage.
+    pub tool_set: ToolSet,
+
+    /// Shell commands that must succeed for the stage to pass.
+    pub gates: Vec<String>,
+}
+
+impl Stage {
+    /// Returns `true` for the built-in stage that runs the plan steps.
+    pub fn is_execute(&self) -> bool {
+        self.name == EXECUTE_STAGE
+    }
+}
+
+impl MpcaConfig {
+    /// Returns the stages of `mpca run`, in order, with their defaults applied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mpca_core::config::{MpcaConfig, ToolSet};
+    ///
+    /// let config = MpcaConfig::from_toml(r#"
+    /// [pipeline]
+    /// stages = ["execute", "docs"]
+    ///
+    /// [pipeline.stage.docs]
+    /// tool_set = "standard"
+    /// "#)?;
+    /// let stages = config.pipeline_stages();
+    /// assert!(stages[0].is_execute());
+    /// assert_eq!(stages[1].template, "docs");
+    /// assert_eq!(stages[1].tool_set, ToolSet::Standard);
+    /// # Ok::<(), mpca_core::MPCAError>(())
+    /// ```
+    pub fn pipeline_stages(&self) -> Vec<Stage> {
+        self.pipeline
+            .stages
+            .iter()
+            .map(|name| {
+                let stage = self.pipeline.stage.get(name).cloned().unwrap_or_default();
+                Stage {
+                    name: name.clone(),
+                    template: stage.template.unwrap_or_else(|| name.clone()),
+                    prompt: stage.prompt,
+                    agent_mode: stage
+                        .agent_mode
+                        .unwrap_or_else(|| self.agent_modes.execute.clone()),
+                    tool_set: stage.tool_set.unwrap_or(self.tool_sets.execute),
+                    gates: stage.gates,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Checks that a stage name can be used as a template name and config key.
+fn is_stage_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_default_pipeline_runs_execute_only() {
+        let config = MpcaConfig::new(PathBuf::from("/repo"));
+        let stages = config.pipeline_stages();
+
+        assert_eq!(stages.len(), 1);
+        assert!(stages[0].is_execute());
+        assert_eq!(stages[0].template, "execute");
+        assert_eq!(stages[0].agent_mode, config.agent_modes.execute);
+        assert_eq!(stages[0].tool_set, config.tool_sets.execute);
+        assert!(stages[0].gates.is_empty());
+    }
+
+    #[test]
+    fn test_stage_settings() {
+        let config = MpcaConfig::from_toml(
+            r#"
+[tool_sets]
+execute = "standard"
+
+[pipeline]
+stages = ["execute", "security-scan", "changelog"]
+
+[pipeline.stage.execute]
+gates = ["cargo test"]
+
+[pipeline.stage.security-scan]
+template = "security"
+prompt = "Look for vulnerabilities."
+agent_mode = { model = "claude-opus-4-20250514" }
+tool_set = "minimal"
+gates = ["cargo audit", "cargo deny check"]
+"#,
+        )
+        .unwrap();
+        let stages = config.pipeline_stages();
+
+        let names: Vec<&str> = stages.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["execute", "security-scan", "changelog"]);
+        assert_eq!(stages[0].gates, ["cargo test"]);
+
+        let scan = &stages[1];
+        assert_eq!(scan.template, "security");
+        assert_eq!(scan.prompt.as_deref(), Some("Look for vulnerabilities."));
+        assert_eq!(scan.agent_mode.model, "claude-opus-4-20250514");
+        assert!(scan.agent_mode.use_code_preset);
+        assert_eq!(scan.tool_set, ToolSet::Minimal);
+        assert_eq!(scan.gates.len(), 2);
+
+        let changelog = &stages[2];
+        assert_eq!(changelog.template, "changelog");
+        assert_eq!(changelog.tool_set, ToolSet::Standard);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_stage_rejects_unknown_keys() {
+        let result = MpcaConfig::from_toml("[pipeline.stage.docs]\ncommand = \"make docs\"\n");
+        assert!(matches!(result, Err(MPCAError::ConfigParseError(_))));
+    }
+
+    #[test]
+    fn test_pipeline_problems() {
+        let config = MpcaConfig::from_toml(
+            r#"
+[pipeline]
+stages = ["docs", "Docs", "docs"]
+
+[pipeline.stage.execute]
+prompt = "Do everything at once."
+
+[pipeline.stage.typo]
+gates = [""]
+"#,
+        )
+        .unwrap();
+        let problems: Vec<String> = config
+            .pipeline
+            .problems()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert!(
+            problems
+                .iter()
+                .any(|p| p.contains("must include the `execute` stage"))
+        );
+        assert!(
+            problems
+                .iter()
+                .any(|p| p.contains("invalid stage name \"Docs\""))
+        );
+        assert!(problems.iter().any(|p| p.contains("listed more than once")));
+        assert!(
+            problems
+                .iter()
+                .any(|p| p.contains("pipeline.stage.typo is not listed"))
+        );
+        assert!(
+            problems
+                .iter()
+                .any(|p| p.contains("pipeline.stage.execute.prompt"))
+        );
+        assert!(
+            problems
+                .iter()
+                .any(|p| p.contains("pipeline.stage.typo.gates"))
+        );
+    }
+}
@@ -0,0 +1,394 @@
+//! API credentials.
+//!
+//! The API key is never stored in configuration files. [`ApiConfig`] names
+//! where to find it (an environment variable, a command or a file) and
+//! [`ApiCredentials::resolve`] reads it when an agent session starts.
+//! Custom header values may reference environment variables as `${NAME}`
+//! so gateway tokens stay out of the file as well.
+//!
+//! Resolved values are wrapped in [`SecretString`], whose `Debug` output is
+//! redacted, so they cannot end up in logs by accident.
+
+use super::ApiConfig;
+use crate::error::{MPCAError, Result};
+use std::path::{Path, PathBuf};
+
+/// A secret value whose `Debug` output is redacted.
+///
+/// # Examples
+///
+/// ```
+/// use mpca_core::config::SecretString;
+///
+/// let key = SecretString::new("sk-ant-123");
+/// assert_eq!(key.expose(), "sk-ant-123");
+/// assert_eq!(format!("{:?}", key), "SecretString(<redacted>)");
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Wraps a secret value.
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// Returns the secret value.
+    ///
+    /// Only call this where the value is handed to the agent.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretString(<redacted>)")
+    }
+}
+
+/// Credentials resolved from an [`ApiConfig`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApiCredentials {
+    /// API key, if a key source is configured.
+    pub api_key: Option<SecretString>,
+
+    /// Custom headers with environment references expanded.
+    pub headers: Vec<(String, SecretString)>,
+}
+
+impl ApiCredentials {
+    /// Resolves credentials using the process environment.
+    ///
+    /// # Arguments
+    ///
+    /// * `api` - API configuration naming the key source and headers.
+    /// * `repo_root` - Directory commands run in and relative key files
+    ///   are resolved against.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::AuthenticationFailed` if the key source or a
+    /// referenced environment variable yields no value.
+    pub fn resolve(api: &ApiConfig, repo_root: &Path) -> Result<Self> {
+        Self::resolve_with(api, repo_root, &|name| std::env::var(name).ok())
+    }
+
+    /// Resolves credentials, looking up environment variables with `env`.
+    ///
+    /// # Arguments
+    ///
+    /// * `api` - API configuration naming the key source and headers.
+    /// * `repo_root` - Directory commands run in and relative key files
+    ///   are resolved against.
+    /// * `env` - Environment variable lookup.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::AuthenticationFailed` if the key source or a
+    /// referenced environment variable yields no value, or a credential
+    /// header holds a literal value.
+    pub fn resolve_with(
+        api: &ApiConfig,
+        repo_root: &Path,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let api_key = if let Some(name) = &api.api_key_env {
+            let key = env(name).ok_or_else(|| {
+                MPCAError::AuthenticationFailed(format!(
+                    "environment variable {} named by api.api_key_env is not set",
+                    name
+                ))
+            })?;
+            Some(non_empty(key, || format!("environment variable {}", name))?)
+        } else if let Some(cmd) = &api.api_key_cmd {
+            Some(non_empty(run_key_command(cmd, repo_root)?, || {
+                "output of api.api_key_cmd".to_string()
+            })?)
+        } else if let Some(file) = &api.api_key_file {
+            let path = expand_path(file, repo_root, env);
+            let key = std::fs::read_to_string(&path).map_err(|e| {
+                MPCAError::AuthenticationFailed(format!(
+                    "cannot read api.api_key_file {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            Some(non_empty(key, || path.display().to_string())?)
+        } else {
+            None
+        };
+
+        // Configurations built in code bypass the check in `from_toml`
+        if let Some(name) = api.plaintext_secret_headers().next() {
+            return Err(MPCAError::AuthenticationFailed(format!(
+                "api.headers.{} holds a plaintext credential; reference an environment variable instead",
+                name
+            )));
+        }
+
+        let headers = api
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let value = expand_env(value, env).map_err(|var| {
+                    MPCAError::AuthenticationFailed(format!(
+                        "environment variable {} referenced by api.headers.{} is not set",
+                        var, name
+                    ))
+                })?;
+                Ok((name.clone(), SecretString::new(value)))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { api_key, headers })
+    }
+}
+
+/// Checks whether a header is likely to carry a credential.
+///
+/// Such headers must take their value from the environment (`${NAME}`)
+/// rather than store it in the configuration file.
+pub fn is_sensitive_header(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name == "authorization"
+        || name == "cookie"
+        || ["key", "token", "secret", "auth"]
+            .iter()
+            .any(|word| name.contains(word))
+}
+
+/// Checks whether a value references an environment variable.
+pub(crate) fn references_env(value: &str) -> bool {
+    value
+        .find("${")
+        .is_some_and(|start| value[start..].contains('}'))
+}
+
+/// Trims a key, rejecting empty ones.
+fn non_empty(key: String, source: impl FnOnce() -> String) -> Result<SecretString> {
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(MPCAError::AuthenticationFailed(format!(
+            "API key from {} is empty",
+            source()
+        )));
+    }
+    Ok(SecretString::new(key))
+}
+
+/// Runs `api_key_cmd` and returns its output.
+///
+/// Only the exit status and stderr are reported on failure; stdout may
+/// hold part of the secret.
+fn run_key_command(cmd: &str, repo_root: &Path) -> Result<String> {
+    #[cfg(unix)]
+    let mut command = {
+        let mut command = std::process::Command::new("sh");
+        command.arg("-c").arg(cmd);
+        command
+    };
+    #[cfg(not(unix))]
+    let mut command = {
+        let mut command = std::process::Command::new("cmd");
+        command.arg("/C").arg(cmd);
+        command
+    };
+    if repo_root.is_dir() {
+        command.current_dir(repo_root);
+    }
+
+    let output = command.output().map_err(|e| {
+        MPCAError::AuthenticationFailed(format!("cannot run api.api_key_cmd: {}", e))
+    })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(MPCAError::AuthenticationFailed(format!(
+            "api.api_key_cmd failed with {}: {}",
+            output.status,
+            stderr.lines().next().unwrap_or("").trim()
+        )));
+    }
+
+    String::from_utf8(output.stdout).map_err(|_| {
+        MPCAError::AuthenticationFailed("api.api_key_cmd printed invalid UTF-8".to_string())
+    })
+}
+
+/// Expands `~/` to the home directory and resolves relative paths.
+fn expand_path(path: &Path, repo_root: &Path, env: &dyn Fn(&str) -> Option<String>) -> PathBuf {
+    if let Ok(rest) = path.strip_prefix("~")
+        && let Some(home) = env("HOME")
+    {
+        return Path::new(&home).join(rest);
+    }
+    repo_root.join(path)
+}
+
+/// Replaces `${NAME}` references with environment values.
+///
+/// Returns the name of the first unset variable as error.
+fn expand_env(
+    value: &str,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> std::result::Result<String, String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&env(name).ok_or_else(|| name.to_string())?);
+        rest = &rest[start + 3 + len..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> + use<> {
+        let vars: BTreeMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_no_key_source() {
+        let credentials =
+            ApiCredentials::resolve_with(&ApiConfig::default(), Path::new("/repo"), &env(&[]))
+                .unwrap();
+        assert_eq!(credentials, ApiCredentials::default());
+    }
+
+    #[test]
+    fn test_key_from_env() {
+        let api = ApiConfig {
+            api_key_env: Some("MY_KEY".to_string()),
+            ..Default::default()
+        };
+
+        let credentials =
+            ApiCredentials::resolve_with(&api, Path::new("/repo"), &env(&[("MY_KEY", "sk-1\n")]))
+                .unwrap();
+        assert_eq!(credentials.api_key.unwrap().expose(), "sk-1");
+
+        let err = ApiCredentials::resolve_with(&api, Path::new("/repo"), &env(&[])).unwrap_err();
+        assert!(matches!(err, MPCAError::AuthenticationFailed(ref e) if e.contains("MY_KEY")));
+
+        let err = ApiCredentials::resolve_with(&api, Path::new("/repo"), &env(&[("MY_KEY", " ")]))
+            .unwrap_err();
+        assert!(matches!(err, MPCAError::AuthenticationFailed(ref e) if e.contains("empty")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_key_from_command() {
+        let repo = TempDir::new().unwrap();
+        let api = ApiConfig {
+            api_key_cmd: Some("echo sk-from-cmd".to_string()),
+            ..Default::default()
+        };
+        let credentials = ApiCredentials::resolve_with(&api, repo.path(), &env(&[])).unwrap();
+        assert_eq!(credentials.api_key.unwrap().expose(), "sk-from-cmd");
+
+        let api = ApiConfig {
+            api_key_cmd: Some("echo sk-partial; echo locked >&2; exit 3".to_string()),
+            ..Default::default()
+        };
+        let err = ApiCredentials::resolve_with(&api, repo.path(), &env(&[])).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("locked"));
+        assert!(!message.contains("sk-partial"));
+    }
+
+    #[test]
+    fn test_key_from_file() {
+        let repo = TempDir::new().unwrap();
+        let home = TempDir::new().unwrap();
+        std::fs::write(repo.path().join("key.txt"), "sk-repo\n").unwrap();
+        std::fs::write(home.path().join(".anthropic"), "sk-home").unwrap();
+        let home_env = env(&[("HOME", &home.path().display().to_string())]);
+
+        let api = ApiConfig {
+            api_key_file: Some(PathBuf::from("key.txt")),
+            ..Default::default()
+        };
+        let credentials = ApiCredentials::resolve_with(&api, repo.path(), &home_env).unwrap();
+        assert_eq!(credentials.api_key.unwrap().expose(), "sk-repo");
+
+        let api = ApiConfig {
+            api_key_file: Some(PathBuf::from("~/.anthropic")),
+            ..Default::default()
+        };
+        let credentials = ApiCredentials::resolve_with(&api, repo.path(), &home_env).unwrap();
+        assert_eq!(credentials.api_key.unwrap().expose(), "sk-home");
+
+        let api = ApiConfig {
+            api_key_file: Some(PathBuf::from("missing.txt")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ApiCredentials::resolve_with(&api, repo.path(), &home_env),
+            Err(MPCAError::AuthenticationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_headers_expand_env() {
+        let api = ApiConfig {
+            headers: BTreeMap::from([
+                ("Authorization".to_string(), "Bearer ${GATEWAY}".to_string()),
+                ("X-Team".to_string(), "platform".to_string()),
+            ]),
+            ..Default::default()
+        };
+
+        let credentials =
+            ApiCredentials::resolve_with(&api, Path::new("/repo"), &env(&[("GATEWAY", "tok")]))
+                .unwrap();
+        assert_eq!(credentials.headers.len(), 2);
+        assert_eq!(credentials.headers[0].0, "Authorization");
+        assert_eq!(credentials.headers[0].1.expose(), "Bearer tok");
+        assert_eq!(credentials.headers[1].1.expose(), "platform");
+        assert!(!format!("{:?}", credentials).contains("tok"));
+
+        let err = ApiCredentials::resolve_with(&api, Path::new("/repo"), &env(&[])).unwrap_err();
+        assert!(
+            matches!(err, MPCAError::AuthenticationFailed(ref e) if e.contains("GATEWAY") && e.contains("api.headers.Authorization"))
+        );
+    }
+
+    #[test]
+    fn test_plaintext_credential_header_is_refused() {
+        let api = ApiConfig {
+            headers: BTreeMap::from([("X-Api-Key".to_string(), "sk-ant-123".to_string())]),
+            ..Default::default()
+        };
+
+        let err = ApiCredentials::resolve_with(&api, Path::new("/repo"), &env(&[])).unwrap_err();
+        assert!(
+            matches!(err, MPCAError::AuthenticationFailed(ref e) if e.contains("api.headers.X-Api-Key") && !e.contains("sk-ant"))
+        );
+    }
+
+    #[test]
+    fn test_sensitive_headers() {
+        assert!(is_sensitive_header("Authorization"));
+        assert!(is_sensitive_header("x-api-key"));
+        assert!(is_sensitive_header("X-Gateway-Token"));
+        assert!(!is_sensitive_header("X-Team"));
+
+        assert!(references_env("Bearer ${TOKEN}"));
+        assert!(!references_env("Bearer sk-123"));
+        assert!(!references_env("${unterminated"));
+    }
+}
@@ -4,6 +4,7 @@
 //! from initialization to verification. All errors use `thiserror` for ergonomic
 //! error handling with context.
 
+use crate::usage::Usage;
 use std::path::PathBuf;
 use thiserror::Error;
 
@@ -44,6 +45,19 @@ pub enum MPCAError {
     #[error("invalid feature slug: {0} (must be lowercase alphanumeric with hyphens)")]
     InvalidFeatureSlug(String),
 
+    /// Feature is locked by another running MPCA process.
+    #[error(
+        "feature {feature} is locked by pid {pid} on {host} (run `mpca unlock {feature}` if that process is gone)"
+    )]
+    FeatureLocked {
+        /// Feature slug that is locked.
+        feature: String,
+        /// Process ID of the lock holder.
+        pid: u32,
+        /// Host name of the lock holder.
+        host: String,
+    },
+
     // State errors
     /// State file is corrupted and cannot be parsed.
     #[error("corrupted state file: {0}")]
@@ -57,6 +71,23 @@ pub enum MPCAError {
     #[error("state file missing: {0}")]
     StateMissing(PathBuf),
 
+    /// State file was written by a newer MPCA with an unknown schema version.
+    #[error("unsupported state schema version: {0}")]
+    UnsupportedStateVersion(u32),
+
+    // Layout errors
+    /// `.mpca/version` names a layout newer than this MPCA understands.
+    #[error("unsupported .mpca layout version: {0} (upgrade mpca)")]
+    UnsupportedLayoutVersion(u32),
+
+    /// `.mpca/version` cannot be parsed.
+    #[error("invalid .mpca/version: {0}")]
+    InvalidLayoutVersion(String),
+
+    /// A layout migration could not be applied.
+    #[error("migration failed: {0}")]
+    MigrationFailed(String),
+
     // Git errors
     /// Git worktree already exists at the specified path.
     #[error("worktree already exists: {0}")]
@@ -125,14 +156,18 @@ pub enum MPCAError {
     #[error("invalid template context: {0}")]
     InvalidTemplateContext(String),
 
+    /// Templates failed `mpca prompts check`.
+    #[error("invalid templates: {0}")]
+    InvalidTemplates(String),
+
     // Agent/SDK errors
     /// Claude agent SDK error occurred.
     #[error("claude agent error: {0}")]
     AgentError(String),
 
-    /// API authentication failed.
-    #[error("API authentication failed")]
-    AuthenticationFailed,
+    /// API authentication failed, or the API key could not be read.
+    #[error("API authentication failed: {0}")]
+    AuthenticationFailed(String),
 
     /// API rate limit exceeded.
     #[error("API rate limit exceeded")]
@@ -142,6 +177,20 @@ pub enum MPCAError {
     #[error("agent timeout after {0}s")]
     AgentTimeout(u64),
 
+    // Budget errors
+    /// A configured budget cap was reached.
+    #[error("budget exhausted: {reason}")]
+    BudgetExceeded {
+        /// Which cap was reached and by how much.
+        reason: String,
+        /// Usage of the interrupted query, not yet recorded anywhere.
+        usage: Usage,
+    },
+
+    /// The operation was cancelled (e.g. by Ctrl-C) after checkpointing state.
+    #[error("operation cancelled")]
+    Cancelled,
+
     // Plan errors
     /// Invalid plan format detected.
     #[error("invalid plan format: {0}")]
@@ -159,6 +208,18 @@ pub enum MPCAError {
     #[error("plan not found for feature: {0}")]
     PlanNotFound(String),
 
+    // Pipeline errors
+    /// A gate command of a pipeline stage exited unsuccessfully.
+    #[error("gate `{command}` of stage {stage} failed with exit code {exit_code}")]
+    GateFailed {
+        /// Stage the gate belongs to.
+        stage: String,
+        /// The gate command.
+        command: String,
+        /// Exit code of the command.
+        exit_code: i32,
+    },
+
     // Verification errors
     /// Verification failed with the specified error.
     #[error("verification failed: {0}")]
@@ -205,3 +266,41 @@ pub enum MPCAError {
 ///
 /// All fallible MPCA operations return this type, using [`MPCAError`] for error variants.
 pub type Result<T> = std::result::Result<T, MPCAError>;
+
+impl From<mpca_pm::PromptError> for MPCAError {
+    fn from(err: mpca_pm::PromptError) -> Self {
+        match err {
+            mpca_pm::PromptError::TemplateNotFound(name) => Self::TemplateNotFound(name),
+            mpca_pm::PromptError::InvalidTemplateContext(msg) => Self::InvalidTemplateContext(msg),
+            other => Self::TemplateRenderError(other.to_string()),
+        }
+    }
+}
+
+impl From<claude_agent_sdk_rs::ClaudeError> for MPCAError {
+    fn from(err: claude_agent_sdk_rs::ClaudeError) -> Self {
+        Self::agent_failure(err.to_string())
+    }
+}
+
+impl MPCAError {
+    /// Classifies an error reported by the agent.
+    ///
+    /// Rejected credentials become `AuthenticationFailed`; anything else
+    /// is an `AgentError`.
+    pub(crate) fn agent_failure(message: String) -> Self {
+        let lower = message.to_ascii_lowercase();
+        let rejected = [
+            "authentication_error",
+            "invalid x-api-key",
+            "invalid api key",
+            "401 unauthorized",
+            "status 401",
+        ];
+        if rejected.iter().any(|pattern| lower.contains(pattern)) {
+            Self::AuthenticationFailed(message)
+        } else {
+            Self::AgentError(message)
+        }
+    }
+}
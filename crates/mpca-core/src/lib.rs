@@ -9,9 +9,17 @@
 //! The core crate is organized into several modules:
 //!
 //! - [`error`]: Error types and result type alias
+//! - [`agent`]: Claude agent sessions shared by all workflows
+//! - [`budget`]: Spending caps enforced while the agent runs
+//! - [`cancel`]: Cancellation of running workflows
 //! - [`config`]: Configuration structures for MPCA runtime
 //! - [`state`]: Runtime state and workflow phase tracking
+//! - [`lock`]: Advisory per-feature locks
+//! - [`migrate`]: Upgrades of the `.mpca/` directory layout
+//! - [`prompts`]: Prompt template layers and customization
+//! - [`steps`]: Implementation plan step parsing
 //! - [`tools`]: Tool registry and adapter traits
+//! - [`usage`]: Token and cost accounting for agent queries
 //! - [`runtime`]: Agent runtime for orchestrating workflows
 //! - [`workflows`]: Workflow implementations (init, plan, run, verify)
 //!
@@ -28,21 +36,34 @@
 //! let runtime = AgentRuntime::new(config)?;
 //!
 //! // Initialize repository
-//! runtime.init_project()?;
+//! runtime.init_project().await?;
 //! ```
 
+pub mod agent;
+pub mod budget;
+pub mod cancel;
 pub mod config;
 pub mod error;
+pub mod lock;
+pub mod migrate;
+pub mod prompts;
 pub mod runtime;
 pub mod state;
+pub mod steps;
 pub mod tools;
+pub mod usage;
 pub mod workflows;
 
 // Re-export core types for convenience
+pub use agent::{AgentBackend, AgentEvent, AgentResponse, AgentRunner, AgentSession};
+pub use budget::Budget;
+pub use cancel::CancellationToken;
 pub use config::{
-    AgentMode, GitConfig, MpcaConfig, ReviewConfig, ToolSet, WorkflowModes, WorkflowTools,
+    AgentMode, BudgetConfig, BudgetLimits, GitConfig, MpcaConfig, PermissionMode, ReviewConfig,
+    ToolSet, WorkflowKind, WorkflowModes, WorkflowTools,
 };
 pub use error::{MPCAError, Result};
 pub use runtime::{AgentRuntime, Runtime};
-pub use state::{Phase, RuntimeState};
+pub use state::{FeatureState, Phase, RuntimeState, StageState, StepState, StepStatus};
 pub use tools::ToolRegistry;
+pub use usage::Usage;
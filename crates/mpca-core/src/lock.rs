@@ -0,0 +1,688 @@
+//! Advisory per-feature lock files.
+//!
+//! Only one MPCA process may work on a feature at a time. Workflows that
+//! mutate a feature acquire a [`FeatureLock`], which creates
+//! `.mpca/specs/<feature-slug>/mpca.lock` recording the owning PID and host.
+//! The lock is released when the guard is dropped.
+//!
+//! A lock left behind by a crashed process is considered stale when it was
+//! taken on this host and its PID is no longer alive; stale locks are
+//! reclaimed automatically. A lock file that cannot be parsed is treated as
+//! held until it is older than [`UNREADABLE_LOCK_GRACE`]. Locks from other
+//! hosts can be removed with `mpca unlock <feature> --force`.
+//!
+//! Lock files are published atomically with [`FsAdapter::create_new`], and a
+//! stale lock is only removed while holding a reclaim guard and after
+//! re-reading it unchanged, so two processes can never both end up holding
+//! the same feature.
+
+use crate::config::MpcaConfig;
+use crate::error::{MPCAError, Result};
+use crate::tools::fs::FsAdapter;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// File name of the lock inside a feature directory.
+pub const LOCK_FILE_NAME: &str = "mpca.lock";
+
+/// How long an empty or unparseable lock file is treated as held.
+pub const UNREADABLE_LOCK_GRACE: Duration = Duration::from_secs(60);
+
+/// Contents of a lock file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockInfo {
+    /// Process ID of the lock holder.
+    pub pid: u32,
+
+    /// Host name of the lock holder.
+    pub host: String,
+
+    /// When the lock was acquired.
+    pub acquired_at: DateTime<Utc>,
+}
+
+impl LockInfo {
+    /// Creates lock information describing the current process.
+    ///
+    /// # Returns
+    ///
+    /// A `LockInfo` with this process's PID and host name.
+    pub fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            host: current_host(),
+            acquired_at: Utc::now(),
+        }
+    }
+
+    /// Checks whether the lock holder is known to be gone.
+    ///
+    /// Only locks taken on this host can be checked; locks from other hosts
+    /// are never considered stale.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the lock was taken on this host by a process that no longer exists.
+    pub fn is_stale(&self) -> bool {
+        self.host == current_host() && !process_alive(self.pid)
+    }
+
+    /// Checks whether the lock is held by the current process.
+    fn is_own(&self) -> bool {
+        self.pid == std::process::id() && self.host == current_host()
+    }
+}
+
+/// Guard for an acquired feature lock.
+///
+/// The lock file is removed when the guard is dropped or explicitly
+/// released with [`FeatureLock::release`].
+///
+/// # Examples
+///
+/// ```
+/// use mpca_core::MpcaConfig;
+/// use mpca_core::lock::FeatureLock;
+/// use mpca_core::tools::fs_mock::MockFsAdapter;
+/// use std::path::PathBuf;
+///
+/// let config = MpcaConfig::new(PathBuf::from("/repo"));
+/// let fs = MockFsAdapter::new();
+///
+/// let lock = FeatureLock::acquire(&config, "add-caching", &fs).unwrap();
+/// assert!(FeatureLock::acquire(&config, "add-caching", &fs).is_err());
+///
+/// drop(lock);
+/// assert!(FeatureLock::acquire(&config, "add-caching", &fs).is_ok());
+/// ```
+pub struct FeatureLock<'a> {
+    fs: &'a dyn FsAdapter,
+    path: PathBuf,
+    released: bool,
+}
+
+impl<'a> FeatureLock<'a> {
+    /// Returns the location of the lock file for a feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - MPCA configuration with repository paths.
+    /// * `feature_slug` - The feature slug identifier.
+    ///
+    /// # Returns
+    ///
+    /// `<specs_dir>/<feature_slug>/mpca.lock`.
+    pub fn path(config: &MpcaConfig, feature_slug: &str) -> PathBuf {
+        config.specs_dir.join(feature_slug).join(LOCK_FILE_NAME)
+    }
+
+    /// Acquires the lock for a feature.
+    ///
+    /// A stale lock left by a dead process on this host is removed and
+    /// the acquisition retried.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - MPCA configuration with repository paths.
+    /// * `feature_slug` - The feature slug identifier.
+    /// * `fs` - File system adapter used to create the lock file.
+    ///
+    /// # Returns
+    ///
+    /// A guard that releases the lock when dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::FeatureLocked` if another live process holds the lock,
+    /// or a file system error if the lock file cannot be created.
+    pub fn acquire(config: &MpcaConfig, feature_slug: &str, fs: &'a dyn FsAdapter) -> Result<Self> {
+        let path = Self::path(config, feature_slug);
+        let info = LockInfo::current();
+        let content = toml::to_string(&info)
+            .map_err(|e| MPCAError::Other(format!("failed to serialize lock: {}", e)))?;
+
+        // A few attempts: later ones follow a released or reclaimed lock
+        for _ in 0..3 {
+            if fs.create_new(&path, &content)? {
+                tracing::debug!(
+                    feature = feature_slug,
+                    pid = info.pid,
+                    "acquired feature lock"
+                );
+                return Ok(Self {
+                    fs,
+                    path,
+                    released: false,
+                });
+            }
+
+            let Some(observed) = observe_lock(fs, &path)? else {
+                // Released between our attempt and the read
+                continue;
+            };
+            if !observed.is_reclaimable() {
+                return Err(observed.locked_error(feature_slug));
+            }
+
+            tracing::warn!(
+                feature = feature_slug,
+                pid = observed.holder.as_ref().map(|h| h.pid),
+                "reclaiming stale feature lock"
+            );
+            remove_observed(fs, &path, &observed)?;
+        }
+
+        let observed = observe_lock(fs, &path)?;
+        Err(match observed {
+            Some(observed) => observed.locked_error(feature_slug),
+            None => MPCAError::FeatureLocked {
+                feature: feature_slug.to_string(),
+                pid: 0,
+                host: "unknown".to_string(),
+            },
+        })
+    }
+
+    /// Releases the lock.
+    ///
+    /// # Errors
+    ///
+    /// Returns a file system error if the lock file cannot be removed.
+    pub fn release(mut self) -> Result<()> {
+        self.released = true;
+        self.remove_own()
+    }
+
+    /// Removes the lock file if it still belongs to this process.
+    fn remove_own(&self) -> Result<()> {
+        match read_lock(self.fs, &self.path)? {
+            Some(holder) if holder.is_own() => remove_if_present(self.fs, &self.path),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for FeatureLock<'_> {
+    fn drop(&mut self) {
+        if !self.released
+            && let Err(e) = self.remove_own()
+        {
+            tracing::warn!(
+                path = %self.path.display(),
+                error = %e,
+                "failed to release feature lock"
+            );
+        }
+    }
+}
+
+impl std::fmt::Debug for FeatureLock<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FeatureLock")
+            .field("path", &self.path)
+            .field("released", &self.released)
+            .finish()
+    }
+}
+
+/// Reads the lock file at `path`.
+///
+/// # Arguments
+///
+/// * `fs` - File system adapter.
+/// * `path` - Path to the lock file.
+///
+/// # Returns
+///
+/// `Ok(None)` if there is no lock file or it cannot be parsed (e.g. a
+/// truncated write from a crashed process), otherwise the lock holder.
+///
+/// # Errors
+///
+/// Returns a file system error if the lock file exists but cannot be read.
+pub fn read_lock(fs: &dyn FsAdapter, path: &Path) -> Result<Option<LockInfo>> {
+    let content = match fs.read_to_string(path) {
+        Ok(content) => content,
+        Err(MPCAError::PathNotFound(_)) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    Ok(toml::from_str(&content).ok())
+}
+
+/// A lock file as seen at one point in time.
+struct ObservedLock {
+    /// Raw file content, used to detect a lock replaced after it was read.
+    content: String,
+    /// Parsed lock holder, `None` if the content is empty or unparseable.
+    holder: Option<LockInfo>,
+    /// Time since the lock file was last written.
+    age: Duration,
+}
+
+impl ObservedLock {
+    /// Checks whether the lock may be reclaimed by another process.
+    ///
+    /// Unparseable locks may belong to a holder that is still writing, or to
+    /// an older MPCA that wrote lock files non-atomically, so they are only
+    /// reclaimed once older than [`UNREADABLE_LOCK_GRACE`].
+    fn is_reclaimable(&self) -> bool {
+        match &self.holder {
+            Some(holder) => holder.is_stale(),
+            None => self.age >= UNREADABLE_LOCK_GRACE,
+        }
+    }
+
+    /// Builds the error reported when the lock cannot be taken.
+    fn locked_error(&self, feature_slug: &str) -> MPCAError {
+        MPCAError::FeatureLocked {
+            feature: feature_slug.to_string(),
+            pid: self.holder.as_ref().map_or(0, |h| h.pid),
+            host: self
+                .holder
+                .as_ref()
+                .map_or_else(|| "unknown".to_string(), |h| h.host.clone()),
+        }
+    }
+}
+
+/// Reads the lock file at `path` along with its age.
+///
+/// Returns `Ok(None)` if there is no lock file.
+fn observe_lock(fs: &dyn FsAdapter, path: &Path) -> Result<Option<ObservedLock>> {
+    let content = match fs.read_to_string(path) {
+        Ok(content) => content,
+        Err(MPCAError::PathNotFound(_)) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let modified = match fs.modified(path) {
+        Ok(modified) => modified,
+        Err(MPCAError::PathNotFound(_)) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    Ok(Some(ObservedLock {
+        holder: toml::from_str(&content).ok(),
+        age: SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or(Duration::ZERO),
+        content,
+    }))
+}
+
+/// Removes the lock at `path` only if it is still the observed lock.
+///
+/// Reclaimers serialize on a short-lived `mpca.lock.reclaim` guard, and the
+/// lock is re-read under the guard, so a lock published after `observed`
+/// was read is never removed. A guard left by a crashed reclaimer is
+/// cleared once older than [`UNREADABLE_LOCK_GRACE`].
+///
+/// # Returns
+///
+/// `true` if the observed lock was removed, `false` if another process is
+/// reclaiming it or it changed in the meantime.
+fn remove_observed(fs: &dyn FsAdapter, path: &Path, observed: &ObservedLock) -> Result<bool> {
+    let guard = path.with_file_name(format!("{}.reclaim", LOCK_FILE_NAME));
+    let guard_content = toml::to_string(&LockInfo::current())
+        .map_err(|e| MPCAError::Other(format!("failed to serialize lock: {}", e)))?;
+
+    if !fs.create_new(&guard, &guard_content)? {
+        if let Ok(modified) = fs.modified(&guard)
+            && SystemTime::now()
+                .duration_since(modified)
+                .is_ok_and(|age| age >= UNREADABLE_LOCK_GRACE)
+        {
+            tracing::warn!(path = %guard.display(), "removing abandoned lock reclaim guard");
+            remove_if_present(fs, &guard)?;
+        }
+        return Ok(false);
+    }
+
+    let result = match fs.read_to_string(path) {
+        Ok(content) if content == observed.content => remove_if_present(fs, path).map(|()| true),
+        Ok(_) | Err(MPCAError::PathNotFound(_)) => Ok(false),
+        Err(e) => Err(e),
+    };
+    remove_if_present(fs, &guard)?;
+    result
+}
+
+/// Removes a feature lock left behind by another process.
+///
+/// Without `force`, only stale locks and unparseable locks older than
+/// [`UNREADABLE_LOCK_GRACE`] are removed.
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration with repository paths.
+/// * `feature_slug` - The feature slug identifier.
+/// * `fs` - File system adapter.
+/// * `force` - Remove the lock even if its holder may still be running.
+///
+/// # Returns
+///
+/// The removed lock holder, or `None` if the feature was not locked.
+///
+/// # Errors
+///
+/// Returns `MPCAError::FeatureLocked` if the lock is held by a live process
+/// and `force` is not set, or a file system error if removal fails.
+pub fn unlock_feature(
+    config: &MpcaConfig,
+    feature_slug: &str,
+    fs: &dyn FsAdapter,
+    force: bool,
+) -> Result<Option<LockInfo>> {
+    let path = FeatureLock::path(config, feature_slug);
+    let Some(observed) = observe_lock(fs, &path)? else {
+        return Ok(None);
+    };
+
+    if force {
+        remove_if_present(fs, &path)?;
+    } else if !observed.is_reclaimable() || !remove_observed(fs, &path, &observed)? {
+        return Err(observed.locked_error(feature_slug));
+    }
+    tracing::info!(feature = feature_slug, force, "removed feature lock");
+
+    Ok(observed.holder)
+}
+
+/// Removes a file, treating a missing file as success.
+fn remove_if_present(fs: &dyn FsAdapter, path: &Path) -> Result<()> {
+    match fs.remove_file(path) {
+        Ok(()) | Err(MPCAError::PathNotFound(_)) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Returns the host name of this machine.
+fn current_host() -> String {
+    #[cfg(unix)]
+    {
+        let mut buf = [0u8; 256];
+        // SAFETY: `buf` is valid for `buf.len()` bytes and gethostname
+        // writes at most that many bytes.
+        let rc = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+        if rc == 0 {
+            let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+            if len > 0 {
+                return String::from_utf8_lossy(&buf[..len]).into_owned();
+            }
+        }
+    }
+
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Checks whether a process with the given PID is running on this host.
+fn process_alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        let Ok(pid) = libc::pid_t::try_from(pid) else {
+            return false;
+        };
+        if pid <= 0 {
+            return false;
+        }
+        // SAFETY: signal 0 performs error checking only and sends nothing.
+        let rc = unsafe { libc::kill(pid, 0) };
+        rc == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+
+    #[cfg(not(unix))]
+    {
+        // Without a portable liveness check, assume the holder is alive.
+        let _ = pid;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::fs_impl::StdFsAdapter;
+    use crate::tools::fs_mock::MockFsAdapter;
+    use std::sync::Barrier;
+
+    fn test_config() -> MpcaConfig {
+        MpcaConfig::new(PathBuf::from("/repo"))
+    }
+
+    fn write_lock(fs: &dyn FsAdapter, config: &MpcaConfig, info: &LockInfo) {
+        let path = FeatureLock::path(config, "test-feature");
+        fs.write(&path, &toml::to_string(info).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_should_acquire_and_release_lock() {
+        let config = test_config();
+        let fs = MockFsAdapter::new();
+        let path = FeatureLock::path(&config, "test-feature");
+
+        let lock = FeatureLock::acquire(&config, "test-feature", &fs).unwrap();
+        let holder = read_lock(&fs, &path).unwrap().unwrap();
+        assert_eq!(holder.pid, std::process::id());
+
+        lock.release().unwrap();
+        assert!(!fs.exists(&path));
+    }
+
+    #[test]
+    fn test_should_reject_second_holder() {
+        let config = test_config();
+        let fs = MockFsAdapter::new();
+
+        let _lock = FeatureLock::acquire(&config, "test-feature", &fs).unwrap();
+        let result = FeatureLock::acquire(&config, "test-feature", &fs);
+
+        match result {
+            Err(MPCAError::FeatureLocked { feature, pid, host }) => {
+                assert_eq!(feature, "test-feature");
+                assert_eq!(pid, std::process::id());
+                assert_eq!(host, current_host());
+            }
+            other => panic!("expected FeatureLocked, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_should_release_on_drop() {
+        let config = test_config();
+        let fs = MockFsAdapter::new();
+
+        {
+            let _lock = FeatureLock::acquire(&config, "test-feature", &fs).unwrap();
+        }
+
+        assert!(!fs.exists(&FeatureLock::path(&config, "test-feature")));
+    }
+
+    #[test]
+    fn test_should_reclaim_stale_lock() {
+        let config = test_config();
+        let fs = MockFsAdapter::new();
+        let stale = LockInfo {
+            pid: 999_999_999,
+            host: current_host(),
+            acquired_at: Utc::now(),
+        };
+        assert!(stale.is_stale());
+        write_lock(&fs, &config, &stale);
+
+        let lock = FeatureLock::acquire(&config, "test-feature", &fs);
+        assert!(lock.is_ok());
+    }
+
+    #[test]
+    fn test_should_treat_fresh_corrupted_lock_as_held() {
+        let config = test_config();
+        let fs = MockFsAdapter::new();
+        fs.write(&FeatureLock::path(&config, "test-feature"), "pid = ")
+            .unwrap();
+
+        let result = FeatureLock::acquire(&config, "test-feature", &fs);
+        assert!(matches!(
+            result,
+            Err(MPCAError::FeatureLocked { pid: 0, .. })
+        ));
+        assert!(unlock_feature(&config, "test-feature", &fs, false).is_err());
+    }
+
+    #[test]
+    fn test_should_treat_empty_lock_as_held() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let fs = StdFsAdapter::new();
+        let path = FeatureLock::path(&config, "test-feature");
+        fs.write(&path, "").unwrap();
+
+        let result = FeatureLock::acquire(&config, "test-feature", &fs);
+        assert!(matches!(result, Err(MPCAError::FeatureLocked { .. })));
+        assert_eq!(fs.read_to_string(&path).unwrap(), "");
+    }
+
+    #[test]
+    fn test_should_reclaim_old_corrupted_lock() {
+        let config = test_config();
+        let fs = MockFsAdapter::new();
+        let path = FeatureLock::path(&config, "test-feature");
+        fs.write(&path, "pid = ").unwrap();
+        fs.set_modified(
+            &path,
+            SystemTime::now() - UNREADABLE_LOCK_GRACE - Duration::from_secs(1),
+        );
+
+        assert!(FeatureLock::acquire(&config, "test-feature", &fs).is_ok());
+    }
+
+    #[test]
+    fn test_racing_acquires_yield_single_holder() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let fs = StdFsAdapter::new();
+
+        for _ in 0..50 {
+            let barrier = Barrier::new(2);
+            let results: Vec<bool> = std::thread::scope(|s| {
+                let handles: Vec<_> = (0..2)
+                    .map(|_| {
+                        s.spawn(|| {
+                            barrier.wait();
+                            match FeatureLock::acquire(&config, "test-feature", &fs) {
+                                Ok(lock) => {
+                                    std::mem::forget(lock);
+                                    true
+                                }
+                                Err(MPCAError::FeatureLocked { .. }) => false,
+                                Err(e) => panic!("unexpected error: {:?}", e),
+                            }
+                        })
+                    })
+                    .collect();
+                handles.into_iter().map(|h| h.join().unwrap()).collect()
+            });
+
+            assert_eq!(results.iter().filter(|&&won| won).count(), 1);
+            fs.remove_file(&FeatureLock::path(&config, "test-feature"))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_racing_stale_reclaims_yield_single_holder() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let fs = StdFsAdapter::new();
+        let stale = LockInfo {
+            pid: 999_999_999,
+            host: current_host(),
+            acquired_at: Utc::now(),
+        };
+
+        for _ in 0..50 {
+            write_lock(&fs, &config, &stale);
+            let barrier = Barrier::new(4);
+            let winners = std::thread::scope(|s| {
+                let handles: Vec<_> = (0..4)
+                    .map(|_| {
+                        s.spawn(|| {
+                            barrier.wait();
+                            FeatureLock::acquire(&config, "test-feature", &fs)
+                                .map(std::mem::forget)
+                                .is_ok()
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|h| h.join().unwrap())
+                    .filter(|&won| won)
+                    .count()
+            });
+
+            assert_eq!(winners, 1);
+            fs.remove_file(&FeatureLock::path(&config, "test-feature"))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_should_not_treat_foreign_host_lock_as_stale() {
+        let config = test_config();
+        let fs = MockFsAdapter::new();
+        let foreign = LockInfo {
+            pid: 999_999_999,
+            host: "some-other-host".to_string(),
+            acquired_at: Utc::now(),
+        };
+        write_lock(&fs, &config, &foreign);
+
+        let result = FeatureLock::acquire(&config, "test-feature", &fs);
+        assert!(matches!(
+            result,
+            Err(MPCAError::FeatureLocked { ref host, .. }) if host == "some-other-host"
+        ));
+
+        // Plain unlock refuses, forced unlock removes it
+        assert!(unlock_feature(&config, "test-feature", &fs, false).is_err());
+        let removed = unlock_feature(&config, "test-feature", &fs, true).unwrap();
+        assert_eq!(removed, Some(foreign));
+        assert!(FeatureLock::acquire(&config, "test-feature", &fs).is_ok());
+    }
+
+    #[test]
+    fn test_should_not_release_lock_taken_over_by_another_process() {
+        let config = test_config();
+        let fs = MockFsAdapter::new();
+        let lock = FeatureLock::acquire(&config, "test-feature", &fs).unwrap();
+
+        // Another process forcibly took over the lock
+        unlock_feature(&config, "test-feature", &fs, true).unwrap();
+        let other = LockInfo {
+            pid: 1,
+            host: "other-host".to_string(),
+            acquired_at: Utc::now(),
+        };
+        write_lock(&fs, &config, &other);
+
+        drop(lock);
+        let path = FeatureLock::path(&config, "test-feature");
+        assert_eq!(read_lock(&fs, &path).unwrap(), Some(other));
+    }
+
+    #[test]
+    fn test_unlock_without_lock_is_noop() {
+        let config = test_config();
+        let fs = MockFsAdapter::new();
+
+        assert_eq!(
+            unlock_feature(&config, "test-feature", &fs, false).unwrap(),
+            None
+        );
+    }
+}
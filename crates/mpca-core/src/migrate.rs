@@ -0,0 +1,988 @@
+//! Upgrades of the `.mpca/` directory layout.
+//!
+//! The layout version of a repository is recorded in `.mpca/version`;
+//! repositories set up before the marker existed are at version 1.
+//! [`pending_migrations`] lists what [`migrate`] would change without
+//! touching anything, and [`migrate`] copies `.mpca/` to
+//! `.mpca/backups/<timestamp>/` before applying each pending migration in
+//! order and advancing the marker.
+//!
+//! A migration only plans [`MigrationAction`]s; planning reads the files it
+//! upgrades and never writes, so the same code serves dry runs.
+
+use crate::config::MpcaConfig;
+use crate::config::layers::WORKFLOW_SECTIONS;
+use crate::error::{MPCAError, Result};
+use crate::lock::{FeatureLock, LOCK_FILE_NAME};
+use crate::state::FeatureState;
+use crate::tools::fs::FsAdapter;
+use chrono::Utc;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Entry, Item, Key, TableLike};
+
+/// Layout version written by `mpca init` and reached by [`migrate`].
+pub const LAYOUT_VERSION: u32 = 3;
+
+/// Name of the layout version marker inside `.mpca/`.
+pub const VERSION_FILE_NAME: &str = "version";
+
+/// Directory inside `.mpca/` holding the backups taken by [`migrate`].
+pub const BACKUPS_DIR_NAME: &str = "backups";
+
+/// A single upgrade from one layout version to the next.
+struct Migration {
+    /// Layout version the migration upgrades to.
+    version: u32,
+
+    /// What the migration changes.
+    description: &'static str,
+
+    /// Plans the changes against the current files.
+    plan: fn(&MpcaConfig, &dyn FsAdapter) -> Result<Vec<MigrationAction>>,
+}
+
+/// All migrations, oldest first.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 2,
+        description: "rename `run` workflow keys to `execute` in config files",
+        plan: plan_config_keys,
+    },
+    Migration {
+        version: 3,
+        description: "move feature files into the documented specs/ layout",
+        plan: plan_feature_layout,
+    },
+];
+
+/// A change to a single file planned by a migration.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MigrationAction {
+    /// Write a file, replacing its content.
+    Write {
+        /// File to write.
+        path: PathBuf,
+        /// New content.
+        content: String,
+    },
+
+    /// Move a file to a path that does not exist yet.
+    Move {
+        /// Current location.
+        from: PathBuf,
+        /// New location.
+        to: PathBuf,
+    },
+
+    /// Delete a file.
+    Remove {
+        /// File to delete.
+        path: PathBuf,
+    },
+}
+
+impl MigrationAction {
+    /// Describes the action with paths relative to `root`.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - Directory the paths are shown relative to (usually the repository root).
+    ///
+    /// # Returns
+    ///
+    /// A line such as `move .mpca/specs/x/state.toml -> .mpca/specs/x/specs/state.toml`.
+    pub fn describe(&self, root: &Path) -> String {
+        let show = |path: &Path| {
+            path.strip_prefix(root)
+                .unwrap_or(path)
+                .display()
+                .to_string()
+        };
+        match self {
+            MigrationAction::Write { path, .. } => format!("write {}", show(path)),
+            MigrationAction::Move { from, to } => format!("move {} -> {}", show(from), show(to)),
+            MigrationAction::Remove { path } => format!("remove {}", show(path)),
+        }
+    }
+
+    fn apply(&self, fs: &dyn FsAdapter) -> Result<()> {
+        match self {
+            MigrationAction::Write { path, content } => fs.write(path, content),
+            MigrationAction::Move { from, to } => {
+                if let Some(parent) = to.parent() {
+                    fs.create_dir_all(parent)?;
+                }
+                fs.rename(from, to)
+            }
+            MigrationAction::Remove { path } => fs.remove_file(path),
+        }
+    }
+}
+
+/// The changes of one pending migration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedMigration {
+    /// Layout version the migration upgrades to.
+    pub version: u32,
+
+    /// What the migration changes.
+    pub description: &'static str,
+
+    /// File changes, in the order they are applied.
+    pub actions: Vec<MigrationAction>,
+}
+
+/// Outcome of [`pending_migrations`] or [`migrate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationReport {
+    /// Layout version before migrating.
+    pub from: u32,
+
+    /// Layout version after migrating.
+    pub to: u32,
+
+    /// Migrations that are (or would be) applied, oldest first.
+    pub migrations: Vec<PlannedMigration>,
+
+    /// Backup of `.mpca/` taken before the first change, if any file changed.
+    pub backup: Option<PathBuf>,
+}
+
+impl MigrationReport {
+    /// Returns `true` if no migration is pending.
+    pub fn is_up_to_date(&self) -> bool {
+        self.from == self.to
+    }
+
+    /// Returns `true` if any migration changes a file (as opposed to only
+    /// advancing the version marker).
+    pub fn changes_files(&self) -> bool {
+        self.migrations.iter().any(|m| !m.actions.is_empty())
+    }
+}
+
+impl fmt::Display for MigrationAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.describe(Path::new("")))
+    }
+}
+
+/// Returns the path of the layout version marker (`.mpca/version`).
+pub fn version_file(config: &MpcaConfig) -> PathBuf {
+    mpca_dir(config).join(VERSION_FILE_NAME)
+}
+
+/// Reads the layout version of a repository.
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration with repository paths.
+/// * `fs` - File system adapter.
+///
+/// # Returns
+///
+/// The version recorded in `.mpca/version`, or 1 if there is no marker.
+///
+/// # Errors
+///
+/// Returns `MPCAError::InvalidLayoutVersion` if the marker is not a number.
+pub fn layout_version(config: &MpcaConfig, fs: &dyn FsAdapter) -> Result<u32> {
+    let path = version_file(config);
+    if !fs.exists(&path) {
+        return Ok(1);
+    }
+
+    let content = fs.read_to_string(&path)?;
+    content
+        .trim()
+        .parse()
+        .map_err(|_| MPCAError::InvalidLayoutVersion(format!("{:?}", content.trim())))
+}
+
+/// Records [`LAYOUT_VERSION`] in `.mpca/version`.
+///
+/// # Errors
+///
+/// Returns `MPCAError::FileWriteError` if the marker cannot be written.
+pub fn write_layout_version(config: &MpcaConfig, fs: &dyn FsAdapter) -> Result<()> {
+    write_version(config, fs, LAYOUT_VERSION)
+}
+
+/// Plans the pending migrations without changing anything.
+///
+/// Every migration is planned against the files as they are now.
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration with repository paths.
+/// * `fs` - File system adapter.
+///
+/// # Errors
+///
+/// Returns:
+/// - `MPCAError::UnsupportedLayoutVersion` if `.mpca/` is newer than [`LAYOUT_VERSION`]
+/// - `MPCAError::InvalidLayoutVersion` if `.mpca/version` cannot be parsed
+/// - `MPCAError::FileReadError` if a file to upgrade cannot be read
+pub fn pending_migrations(config: &MpcaConfig, fs: &dyn FsAdapter) -> Result<MigrationReport> {
+    let from = checked_layout_version(config, fs)?;
+
+    let migrations = MIGRATIONS
+        .iter()
+        .filter(|m| m.version > from)
+        .map(|m| plan_migration(m, config, fs))
+        .collect::<Result<_>>()?;
+
+    Ok(MigrationReport {
+        from,
+        to: LAYOUT_VERSION,
+        migrations,
+        backup: None,
+    })
+}
+
+/// Applies the pending migrations.
+///
+/// If any migration changes a file, the lock of every feature is taken so
+/// no other MPCA process works on a feature while its files are rewritten,
+/// and `.mpca/` (without lock files and earlier backups) is copied to
+/// `.mpca/backups/<timestamp>/`. Each
+/// migration is planned against the files left by the previous one and
+/// advances `.mpca/version` once applied, so an interrupted upgrade resumes
+/// where it stopped.
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration with repository paths.
+/// * `fs` - File system adapter.
+///
+/// # Returns
+///
+/// The applied migrations and the backup location.
+///
+/// # Errors
+///
+/// Returns the errors of [`pending_migrations`], plus
+/// `MPCAError::FeatureLocked` if another process holds a feature lock and
+/// `MPCAError::MigrationFailed` if a change cannot be applied.
+pub fn migrate(config: &MpcaConfig, fs: &dyn FsAdapter) -> Result<MigrationReport> {
+    let pending = pending_migrations(config, fs)?;
+    if pending.is_up_to_date() {
+        return Ok(pending);
+    }
+
+    // Held until the migrations are applied
+    let mut locks = Vec::new();
+    let backup = if pending.changes_files() {
+        for slug in feature_slugs(config, fs)? {
+            locks.push(FeatureLock::acquire(config, &slug, fs)?);
+        }
+        Some(backup_mpca_dir(config, fs)?)
+    } else {
+        None
+    };
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.version > pending.from) {
+        let planned = plan_migration(migration, config, fs)?;
+        for action in &planned.actions {
+            action.apply(fs).map_err(|e| {
+                let restore = backup
+                    .as_ref()
+                    .map(|path| format!("; the previous files are in {}", path.display()))
+                    .unwrap_or_default();
+                MPCAError::MigrationFailed(format!(
+                    "{} (layout version {}): {}{}",
+                    action, migration.version, e, restore
+                ))
+            })?;
+        }
+        write_version(config, fs, migration.version)?;
+
+        tracing::debug!(
+            version = migration.version,
+            changes = planned.actions.len(),
+            "applied layout migration"
+        );
+        applied.push(planned);
+    }
+    drop(locks);
+
+    Ok(MigrationReport {
+        from: pending.from,
+        to: LAYOUT_VERSION,
+        migrations: applied,
+        backup,
+    })
+}
+
+fn mpca_dir(config: &MpcaConfig) -> PathBuf {
+    config.repo_root.join(".mpca")
+}
+
+fn write_version(config: &MpcaConfig, fs: &dyn FsAdapter, version: u32) -> Result<()> {
+    fs.write(&version_file(config), &format!("{}\n", version))
+}
+
+fn checked_layout_version(config: &MpcaConfig, fs: &dyn FsAdapter) -> Result<u32> {
+    let version = layout_version(config, fs)?;
+    if version > LAYOUT_VERSION {
+        return Err(MPCAError::UnsupportedLayoutVersion(version));
+    }
+    Ok(version)
+}
+
+fn plan_migration(
+    migration: &Migration,
+    config: &MpcaConfig,
+    fs: &dyn FsAdapter,
+) -> Result<PlannedMigration> {
+    Ok(PlannedMigration {
+        version: migration.version,
+        description: migration.description,
+        actions: (migration.plan)(config, fs)?,
+    })
+}
+
+/// Copies `.mpca/` to a new directory under `.mpca/backups/`.
+fn backup_mpca_dir(config: &MpcaConfig, fs: &dyn FsAdapter) -> Result<PathBuf> {
+    let backups = mpca_dir(config).join(BACKUPS_DIR_NAME);
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut target = backups.join(&stamp);
+    let mut n = 1;
+    while fs.exists(&target) {
+        n += 1;
+        target = backups.join(format!("{}-{}", stamp, n));
+    }
+
+    copy_dir(fs, &mpca_dir(config), &target, &backups)?;
+    tracing::debug!(backup = %target.display(), "backed up .mpca before migrating");
+
+    Ok(target)
+}
+
+fn copy_dir(fs: &dyn FsAdapter, from: &Path, to: &Path, skip: &Path) -> Result<()> {
+    fs.create_dir_all(to)?;
+
+    let mut names = fs.list_dir(from)?;
+    names.sort();
+    for name in names {
+        let source = from.join(&name);
+        // Lock files and their reclaim guards belong to running processes
+        if source == skip || name.starts_with(LOCK_FILE_NAME) {
+            continue;
+        }
+
+        if fs.is_dir(&source) {
+            copy_dir(fs, &source, &to.join(&name), skip)?;
+        } else {
+            fs.copy(&source, &to.join(&name))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the feature directories under the specs directory.
+fn feature_slugs(config: &MpcaConfig, fs: &dyn FsAdapter) -> Result<Vec<String>> {
+    if !fs.is_dir(&config.specs_dir) {
+        return Ok(Vec::new());
+    }
+
+    let mut slugs: Vec<String> = fs
+        .list_dir(&config.specs_dir)?
+        .into_iter()
+        .filter(|name| fs.is_dir(&config.specs_dir.join(name)))
+        .collect();
+    slugs.sort();
+
+    Ok(slugs)
+}
+
+// Layout version 2: `run` -> `execute`
+
+fn plan_config_keys(config: &MpcaConfig, fs: &dyn FsAdapter) -> Result<Vec<MigrationAction>> {
+    let mut files = vec![config.config_file.clone()];
+    for slug in feature_slugs(config, fs)? {
+        files.push(config.feature_config_file(&slug));
+    }
+
+    let mut actions = Vec::new();
+    for path in files {
+        if !fs.exists(&path) {
+            continue;
+        }
+
+        // A file that does not parse is left for `mpca config validate`
+        let Ok(mut doc) = fs.read_to_string(&path)?.parse::<DocumentMut>() else {
+            tracing::warn!(path = %path.display(), "skipping unparsable config file");
+            continue;
+        };
+        if rename_run_keys(doc.as_table_mut()) {
+            actions.push(MigrationAction::Write {
+                path,
+                content: doc.to_string(),
+            });
+        }
+    }
+
+    Ok(actions)
+}
+
+/// Renames `run` to `execute` in the workflow sections of a file and of its profiles.
+fn rename_run_keys(root: &mut dyn TableLike) -> bool {
+    let mut changed = false;
+    for section in WORKFLOW_SECTIONS {
+        if let Some(table) = root.get_mut(section).and_then(Item::as_table_like_mut) {
+            changed |= rename_key(table, "run", "execute");
+        }
+    }
+
+    if let Some(profiles) = root.get_mut("profiles").and_then(Item::as_table_like_mut) {
+        for (_, profile) in profiles.iter_mut() {
+            if let Some(profile) = profile.as_table_like_mut() {
+                changed |= rename_run_keys(profile);
+            }
+        }
+    }
+
+    changed
+}
+
+/// Renames a key, keeping its position and comments.
+fn rename_key(table: &mut dyn TableLike, from: &str, to: &str) -> bool {
+    if !table.contains_key(from) || table.contains_key(to) {
+        return false;
+    }
+
+    // Entries keep their insertion order, so re-insert all of them
+    let names: Vec<String> = table.iter().map(|(name, _)| name.to_string()).collect();
+    for name in names {
+        let (Some(key), Some(item)) = (table.key(&name).cloned(), table.remove(&name)) else {
+            continue;
+        };
+        let key = if name == from {
+            Key::new(to)
+                .with_leaf_decor(key.leaf_decor().clone())
+                .with_dotted_decor(key.dotted_decor().clone())
+        } else {
+            key
+        };
+        if let Entry::Vacant(entry) = table.entry_format(&key) {
+            entry.insert(item);
+        }
+    }
+
+    true
+}
+
+// Layout version 3: feature files
+
+fn plan_feature_layout(config: &MpcaConfig, fs: &dyn FsAdapter) -> Result<Vec<MigrationAction>> {
+    let mut actions = Vec::new();
+    for slug in feature_slugs(config, fs)? {
+        plan_feature_state(config, &slug, fs, &mut actions)?;
+        plan_feature_specs(config, &slug, fs, &mut actions)?;
+    }
+    Ok(actions)
+}
+
+/// Moves `<slug>/state.toml`, written by older `mpca verify`, into
+/// `specs/state.toml` and rewrites state files in the current schema.
+fn plan_feature_state(
+    config: &MpcaConfig,
+    slug: &str,
+    fs: &dyn FsAdapter,
+    actions: &mut Vec<MigrationAction>,
+) -> Result<()> {
+    let legacy_path = config.specs_dir.join(slug).join("state.toml");
+    let state_path = FeatureState::path(config, slug);
+
+    let legacy = read_table(fs, &legacy_path)?;
+    let current = read_table(fs, &state_path)?;
+
+    let (mut table, moved) = match (current, &legacy) {
+        (Some(table), _) => (table, false),
+        (None, Some(legacy)) => {
+            actions.push(MigrationAction::Move {
+                from: legacy_path.clone(),
+                to: state_path.clone(),
+            });
+            (legacy.clone(), true)
+        }
+        (None, None) => return Ok(()),
+    };
+
+    let mut changed = upgrade_state_table(&mut table, slug);
+    if !moved && let Some(legacy) = &legacy {
+        // Keep the verification result only the old location recorded
+        if !table.contains_key("verification")
+            && let Some(verification) = legacy_verification(legacy)
+        {
+            table.insert("verification".to_string(), verification);
+            changed = true;
+        }
+    }
+
+    if changed {
+        let state: FeatureState = match toml::Value::Table(table).try_into() {
+            Ok(state) => state,
+            Err(e) => {
+                tracing::warn!(path = %state_path.display(), error = %e, "skipping unreadable state file");
+                return Ok(());
+            }
+        };
+        actions.push(MigrationAction::Write {
+            path: state_path,
+            content: state.to_toml()?,
+        });
+    }
+    if !moved && legacy.is_some() {
+        actions.push(MigrationAction::Remove { path: legacy_path });
+    }
+
+    Ok(())
+}
+
+/// Reads a TOML file as a table; a file that does not parse is skipped.
+fn read_table(fs: &dyn FsAdapter, path: &Path) -> Result<Option<toml::Table>> {
+    if !fs.exists(path) {
+        return Ok(None);
+    }
+
+    match fs.read_to_string(path)?.parse::<toml::Table>() {
+        Ok(table) => Ok(Some(table)),
+        Err(e) => {
+            tracing::warn!(path = %path.display(), error = %e, "skipping unparsable state file");
+            Ok(None)
+        }
+    }
+}
+
+/// Brings a state table written by an older MPCA to the current schema.
+///
+/// Returns `true` if the table was written by an older MPCA.
+fn upgrade_state_table(table: &mut toml::Table, slug: &str) -> bool {
+    let mut changed = !table.contains_key("schema_version");
+
+    if !table.contains_key("feature_slug") {
+        table.insert("feature_slug".to_string(), slug.into());
+        changed = true;
+    }
+
+    if table.contains_key("verification_status") {
+        if !table.contains_key("verification")
+            && let Some(verification) = legacy_verification(table)
+        {
+            table.insert("verification".to_string(), verification);
+        }
+        table.remove("verification_status");
+        changed = true;
+    }
+
+    changed
+}
+
+/// Converts an old `verification_status = "passed"` entry into a
+/// `[verification]` table. Old files did not record test counts.
+fn legacy_verification(table: &toml::Table) -> Option<toml::Value> {
+    let status = table.get("verification_status")?.clone();
+    let verified_at = table
+        .get("updated_at")
+        .cloned()
+        .unwrap_or_else(|| Utc::now().to_rfc3339().into());
+
+    let mut verification = toml::Table::new();
+    verification.insert("status".to_string(), status);
+    for count in ["tests_passed", "tests_failed", "tests_ignored"] {
+        verification.insert(count.to_string(), 0.into());
+    }
+    verification.insert("verified_at".to_string(), verified_at);
+
+    Some(verification.into())
+}
+
+/// Folds the `README.md` and `requirements.md` written by older
+/// `mpca plan` into `design.md`, and creates a missing `plan.md` from the
+/// design's implementation plan if it lists any steps.
+fn plan_feature_specs(
+    config: &MpcaConfig,
+    slug: &str,
+    fs: &dyn FsAdapter,
+    actions: &mut Vec<MigrationAction>,
+) -> Result<()> {
+    let specs_dir = config.specs_dir.join(slug).join("specs");
+    if !fs.is_dir(&specs_dir) {
+        return Ok(());
+    }
+
+    let read = |name: &str| -> Result<Option<String>> {
+        let path = specs_dir.join(name);
+        if fs.is_file(&path) {
+            fs.read_to_string(&path).map(Some)
+        } else {
+            Ok(None)
+        }
+    };
+    let design = read("design.md")?;
+
+    // Without real steps the feature is still being planned; a placeholder
+    // plan would let `mpca run` execute steps nobody wrote
+    if !fs.exists(&specs_dir.join("plan.md"))
+        && let Some(plan) = design.as_deref().and_then(|d| plan_from_design(slug, d))
+    {
+        actions.push(MigrationAction::Write {
+            path: specs_dir.join("plan.md"),
+            content: plan,
+        });
+    }
+
+    let mut folded = Vec::new();
+    for name in ["README.md", "requirements.md"] {
+        if let Some(content) = read(name)? {
+            folded.push((name, content));
+        }
+    }
+    if folded.is_empty() {
+        return Ok(());
+    }
+
+    let mut merged = design.unwrap_or_else(|| format!("# Design: {}\n", slug));
+    for (_, content) in &folded {
+        if !merged.ends_with('\n') {
+            merged.push('\n');
+        }
+        merged.push('\n');
+        merged.push_str(&demote_headings(content));
+    }
+    actions.push(MigrationAction::Write {
+        path: specs_dir.join("design.md"),
+        content: merged,
+    });
+    for (name, _) in folded {
+        actions.push(MigrationAction::Remove {
+            path: specs_dir.join(name),
+        });
+    }
+
+    Ok(())
+}
+
+/// Builds `plan.md` from the `## Implementation Plan` section of a design.
+///
+/// Returns `None` if the design has no such section or it lists no steps.
+fn plan_from_design(slug: &str, design: &str) -> Option<String> {
+    let steps: String = design
+        .lines()
+        .skip_while(|line| line.trim() != "## Implementation Plan")
+        .skip(1)
+        .take_while(|line| !line.starts_with("## "))
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| format!("{}\n", line))
+        .collect();
+
+    (!steps.is_empty()).then(|| format!("# Plan: {}\n\n## Steps\n{}", slug, steps))
+}
+
+/// Moves every Markdown heading one level down, leaving code blocks alone.
+fn demote_headings(markdown: &str) -> String {
+    let mut in_code = false;
+    let mut out = String::with_capacity(markdown.len());
+    for line in markdown.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        if !in_code && line.starts_with('#') {
+            out.push('#');
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{Phase, VerificationStatus};
+    use crate::tools::fs_impl::StdFsAdapter;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// A repository laid out the way `mpca init` and `mpca plan` used to
+    /// leave it, with `mpca verify` run on the feature.
+    fn legacy_repo() -> (TempDir, MpcaConfig) {
+        let temp_dir = TempDir::new().unwrap();
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let feature = config.specs_dir.join("add-caching");
+        fs::create_dir_all(feature.join("specs")).unwrap();
+        fs::create_dir_all(feature.join("docs")).unwrap();
+
+        fs::write(
+            &config.config_file,
+            "[agent_modes]\ninit = \"standard\"\n# Implementation\nrun = \"code\"\nverify = \"standard\"\n\n[tool_sets]\nrun = \"full\"\n",
+        )
+        .unwrap();
+        fs::write(
+            feature.join("specs/state.toml"),
+            "# MPCA workflow state for feature: add-caching\nfeature_slug = \"add-caching\"\nphase = \"Run\"\nstep = 0\nturns = 0\ncost_usd = 0.0\ncreated_at = \"2025-01-01T00:00:00+00:00\"\nupdated_at = \"2025-01-02T00:00:00+00:00\"\n",
+        )
+        .unwrap();
+        fs::write(
+            feature.join("state.toml"),
+            "phase = \"Verify\"\nverification_status = \"passed\"\nupdated_at = \"2025-01-03T00:00:00+00:00\"\n",
+        )
+        .unwrap();
+        fs::write(
+            feature.join("specs/README.md"),
+            "# Feature: add-caching\n\n## Overview\nCache responses.\n",
+        )
+        .unwrap();
+        fs::write(
+            feature.join("specs/requirements.md"),
+            "# Requirements: add-caching\n\n```sh\n# not a heading\n```\n",
+        )
+        .unwrap();
+        fs::write(
+            feature.join("specs/design.md"),
+            "# Design: add-caching\n\n## Implementation Plan\n1. Add cache\n2. Wire it\n\n## Testing Strategy\nUnit tests.\n",
+        )
+        .unwrap();
+
+        (temp_dir, config)
+    }
+
+    #[test]
+    fn test_missing_marker_is_version_one() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let fs = StdFsAdapter::new();
+
+        assert_eq!(layout_version(&config, &fs).unwrap(), 1);
+
+        write_layout_version(&config, &fs).unwrap();
+        assert_eq!(layout_version(&config, &fs).unwrap(), LAYOUT_VERSION);
+        assert!(pending_migrations(&config, &fs).unwrap().is_up_to_date());
+    }
+
+    #[test]
+    fn test_newer_layout_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let fs = StdFsAdapter::new();
+
+        fs.write(&version_file(&config), "99\n").unwrap();
+        assert!(matches!(
+            migrate(&config, &fs),
+            Err(MPCAError::UnsupportedLayoutVersion(99))
+        ));
+
+        fs.write(&version_file(&config), "two\n").unwrap();
+        assert!(matches!(
+            layout_version(&config, &fs),
+            Err(MPCAError::InvalidLayoutVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_dry_run_changes_nothing() {
+        let (temp_dir, config) = legacy_repo();
+        let fs = StdFsAdapter::new();
+        let root = temp_dir.path();
+
+        let report = pending_migrations(&config, &fs).unwrap();
+        assert_eq!((report.from, report.to), (1, LAYOUT_VERSION));
+        assert!(report.changes_files());
+
+        let actions: Vec<String> = report
+            .migrations
+            .iter()
+            .flat_map(|m| m.actions.iter().map(|a| a.describe(root)))
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                "write .mpca/config.toml",
+                "write .mpca/specs/add-caching/specs/state.toml",
+                "remove .mpca/specs/add-caching/state.toml",
+                "write .mpca/specs/add-caching/specs/plan.md",
+                "write .mpca/specs/add-caching/specs/design.md",
+                "remove .mpca/specs/add-caching/specs/README.md",
+                "remove .mpca/specs/add-caching/specs/requirements.md",
+            ]
+        );
+
+        assert!(!fs.exists(&version_file(&config)));
+        assert!(fs.exists(&config.specs_dir.join("add-caching/state.toml")));
+        assert!(!fs.exists(&root.join(".mpca").join(BACKUPS_DIR_NAME)));
+    }
+
+    #[test]
+    fn test_migrate_upgrades_legacy_repo() {
+        let (_temp_dir, config) = legacy_repo();
+        let fs = StdFsAdapter::new();
+        let feature = config.specs_dir.join("add-caching");
+
+        let report = migrate(&config, &fs).unwrap();
+        assert_eq!(report.migrations.len(), 2);
+        assert_eq!(layout_version(&config, &fs).unwrap(), LAYOUT_VERSION);
+
+        // Config keys are renamed in place, keeping comments
+        let content = fs::read_to_string(&config.config_file).unwrap();
+        assert!(content.contains("init = \"standard\"\n# Implementation\nexecute = \"code\"\n"));
+        assert!(content.contains("[tool_sets]\nexecute = \"full\"\n"));
+        assert!(!content.contains("run ="));
+        MpcaConfig::from_toml(&content).unwrap();
+
+        // The verification result moves into the authoritative state file
+        assert!(!fs.exists(&feature.join("state.toml")));
+        let state = FeatureState::load(&fs, &FeatureState::path(&config, "add-caching")).unwrap();
+        assert_eq!(state.phase, Phase::Run);
+        let verification = state.verification.unwrap();
+        assert_eq!(verification.status, VerificationStatus::Passed);
+        assert_eq!(
+            verification.verified_at.to_rfc3339(),
+            "2025-01-03T00:00:00+00:00"
+        );
+
+        // Specs follow the documented layout
+        assert!(!fs.exists(&feature.join("specs/README.md")));
+        assert!(!fs.exists(&feature.join("specs/requirements.md")));
+        let design = fs::read_to_string(feature.join("specs/design.md")).unwrap();
+        assert!(design.starts_with("# Design: add-caching\n"));
+        assert!(design.contains("\n## Feature: add-caching\n\n### Overview\nCache responses.\n"));
+        assert!(design.contains("```sh\n# not a heading\n```\n"));
+        assert_eq!(
+            fs::read_to_string(feature.join("specs/plan.md")).unwrap(),
+            "# Plan: add-caching\n\n## Steps\n1. Add cache\n2. Wire it\n"
+        );
+
+        // The original files are kept in the backup
+        let backup = report.backup.unwrap();
+        assert!(backup.starts_with(config.repo_root.join(".mpca/backups")));
+        assert_eq!(
+            fs::read_to_string(backup.join("specs/add-caching/specs/README.md")).unwrap(),
+            "# Feature: add-caching\n\n## Overview\nCache responses.\n"
+        );
+        assert!(backup.join("config.toml").exists());
+
+        // A second run has nothing left to do
+        let again = migrate(&config, &fs).unwrap();
+        assert!(again.is_up_to_date());
+        assert!(again.backup.is_none());
+    }
+
+    #[test]
+    fn test_design_without_plan_section_gets_no_plan() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let fs = StdFsAdapter::new();
+        let specs = config.specs_dir.join("add-caching/specs");
+        fs.write(
+            &specs.join("design.md"),
+            "# Design: add-caching\n\n## Overview\nStill being planned.\n",
+        )
+        .unwrap();
+        fs.write(&specs.join("README.md"), "# Feature: add-caching\n")
+            .unwrap();
+
+        migrate(&config, &fs).unwrap();
+
+        // Without a plan, `mpca run` keeps refusing to start the feature
+        assert!(fs.exists(&specs.join("design.md")));
+        assert!(!fs.exists(&specs.join("plan.md")));
+    }
+
+    #[test]
+    fn test_backup_copies_binary_files() {
+        let (_temp_dir, config) = legacy_repo();
+        let fs = StdFsAdapter::new();
+        let image = config.specs_dir.join("add-caching/docs/diagram.png");
+        let bytes = [0x89, b'P', b'N', b'G', 0xff, 0xfe, 0x00, 0x80];
+        fs::write(&image, bytes).unwrap();
+
+        let report = migrate(&config, &fs).unwrap();
+
+        let backup = report.backup.unwrap();
+        assert_eq!(
+            fs::read(backup.join("specs/add-caching/docs/diagram.png")).unwrap(),
+            bytes
+        );
+        assert_eq!(fs::read(&image).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_migrate_refuses_while_feature_is_locked() {
+        let (_temp_dir, config) = legacy_repo();
+        let fs = StdFsAdapter::new();
+        let lock_path = FeatureLock::path(&config, "add-caching");
+        let holder = crate::lock::LockInfo {
+            pid: 1,
+            host: "other-host".to_string(),
+            acquired_at: Utc::now(),
+        };
+        fs.write(&lock_path, &toml::to_string(&holder).unwrap())
+            .unwrap();
+
+        let result = migrate(&config, &fs);
+        assert!(matches!(result, Err(MPCAError::FeatureLocked { .. })));
+
+        // Nothing was changed or backed up
+        assert_eq!(layout_version(&config, &fs).unwrap(), 1);
+        assert!(!fs.exists(&config.repo_root.join(".mpca/backups")));
+
+        fs.remove_file(&lock_path).unwrap();
+        migrate(&config, &fs).unwrap();
+        assert!(!fs.exists(&lock_path));
+    }
+
+    #[test]
+    fn test_legacy_state_without_current_state_is_moved() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let fs = StdFsAdapter::new();
+        let feature = config.specs_dir.join("add-caching");
+        fs.write(
+            &feature.join("state.toml"),
+            "phase = \"Verify\"\nverification_status = \"failed\"\n",
+        )
+        .unwrap();
+
+        let report = migrate(&config, &fs).unwrap();
+        let actions: Vec<String> = report.migrations[1]
+            .actions
+            .iter()
+            .map(|a| a.describe(temp_dir.path()))
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                "move .mpca/specs/add-caching/state.toml -> .mpca/specs/add-caching/specs/state.toml",
+                "write .mpca/specs/add-caching/specs/state.toml",
+            ]
+        );
+
+        let state = FeatureState::load(&fs, &FeatureState::path(&config, "add-caching")).unwrap();
+        assert_eq!(state.feature_slug, "add-caching");
+        assert_eq!(state.phase, Phase::Verify);
+        assert_eq!(
+            state.verification.unwrap().status,
+            VerificationStatus::Failed
+        );
+        assert!(!fs.exists(&feature.join("state.toml")));
+    }
+
+    #[test]
+    fn test_profile_sections_are_renamed() {
+        let mut doc = "[profiles.draft.tool_sets]\nrun = \"standard\"\n\n[profiles.fast]\nbudget.run.max_turns = 5\n"
+            .parse::<DocumentMut>()
+            .unwrap();
+
+        assert!(rename_run_keys(doc.as_table_mut()));
+        assert_eq!(
+            doc.to_string(),
+            "[profiles.draft.tool_sets]\nexecute = \"standard\"\n\n[profiles.fast]\nbudget.execute.max_turns = 5\n"
+        );
+        assert!(!rename_run_keys(doc.as_table_mut()));
+    }
+}
@@ -0,0 +1,466 @@
+//! Prompt template layers and customization.
+//!
+//! Templates are resolved from the configured `prompt_dirs`, then from
+//! `.mpca/prompts/`, then from the templates bundled into the binary.
+//! [`eject`] copies bundled templates into `.mpca/prompts/` so they can be
+//! edited in place.
+//!
+//! The `*_context` functions build the typed context of each bundled
+//! template from the configuration and a feature's state and spec files.
+
+use crate::config::MpcaConfig;
+use crate::error::{MPCAError, Result};
+use crate::state::FeatureState;
+use crate::tools::fs::FsAdapter;
+use crate::tools::git::GitAdapter;
+use mpca_pm::bundled::{BUNDLED_PARTIALS, BUNDLED_TEMPLATES};
+use mpca_pm::{
+    ExecuteContext, InitContext, PlanContext, PromptManager, ReviewContext, VerifyContext,
+};
+use std::path::{Path, PathBuf};
+
+/// Returns the repository's template override directory (`.mpca/prompts`).
+pub fn prompts_dir(config: &MpcaConfig) -> PathBuf {
+    config.repo_root.join(".mpca").join("prompts")
+}
+
+/// Returns the directories templates are resolved from, highest priority
+/// first.
+///
+/// The layers are, in order:
+/// 1. User-specified directories in config.prompt_dirs (relative paths
+///    are resolved against the repository root)
+/// 2. `.mpca/prompts`, if it exists
+///
+/// Directories that don't exist are left out. The bundled templates come
+/// after all of them.
+pub fn template_dirs(config: &MpcaConfig) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    // User-specified directories first
+    for dir in &config.prompt_dirs {
+        let dir = config.repo_root.join(dir);
+        if dir.is_dir() {
+            dirs.push(dir);
+        } else {
+            tracing::warn!(dir = %dir.display(), "prompt_dirs entry is not a directory - skipping");
+        }
+    }
+
+    // Ejected or hand-written overrides of the repository
+    let local = prompts_dir(config);
+    if local.is_dir() && !dirs.contains(&local) {
+        dirs.push(local);
+    }
+
+    dirs
+}
+
+/// Creates the prompt manager for a repository.
+///
+/// # Errors
+///
+/// Returns `MPCAError::TemplateRenderError` if a template directory
+/// disappears while the manager is created.
+pub fn prompt_manager(config: &MpcaConfig) -> Result<PromptManager> {
+    Ok(PromptManager::with_bundled(template_dirs(config))?)
+}
+
+/// A bundled template copied by [`eject`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EjectedTemplate {
+    /// Template name without extension.
+    pub name: &'static str,
+
+    /// File the template was (or would have been) written to.
+    pub path: PathBuf,
+
+    /// `false` if the file already existed and was left alone.
+    pub written: bool,
+}
+
+/// Copies bundled templates into `.mpca/prompts/` for customization.
+///
+/// Copying everything includes the partials the templates share, under
+/// `.mpca/prompts/partials/`. Existing files are kept unless `force` is
+/// set, so edited templates are never lost.
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration with repository paths
+/// * `fs` - File system adapter
+/// * `name` - Template or partial to copy (e.g., "plan" or
+///   "partials/rules"), or `None` for all of them
+/// * `force` - Overwrite templates that were already ejected
+///
+/// # Returns
+///
+/// The templates in name order, then the partials, with whether each one
+/// was written.
+///
+/// # Errors
+///
+/// Returns:
+/// - `MPCAError::TemplateNotFound` if `name` is not a bundled template
+/// - `MPCAError::FileWriteError` if a template cannot be written
+pub fn eject(
+    config: &MpcaConfig,
+    fs: &dyn FsAdapter,
+    name: Option<&str>,
+    force: bool,
+) -> Result<Vec<EjectedTemplate>> {
+    let bundled = || BUNDLED_TEMPLATES.iter().chain(BUNDLED_PARTIALS);
+    let templates: Vec<(&'static str, &'static str)> = match name {
+        Some(name) => {
+            let template = bundled()
+                .find(|(bundled, _)| *bundled == name)
+                .ok_or_else(|| {
+                    let bundled: Vec<&str> = bundled().map(|(bundled, _)| *bundled).collect();
+                    MPCAError::TemplateNotFound(format!(
+                        "{} (bundled templates: {})",
+                        name,
+                        bundled.join(", ")
+                    ))
+                })?;
+            vec![*template]
+        }
+        None => bundled().copied().collect(),
+    };
+
+    let dir = prompts_dir(config);
+    templates
+        .into_iter()
+        .map(|(name, source)| {
+            let path = dir.join(format!("{name}.j2"));
+            let written = force || !fs.exists(&path);
+            if written {
+                if let Some(parent) = path.parent() {
+                    fs.create_dir_all(parent)?;
+                }
+                fs.write(&path, source)?;
+            }
+            Ok(EjectedTemplate {
+                name,
+                path,
+                written,
+            })
+        })
+        .collect()
+}
+
+/// Builds the context of the `init` template.
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration with repository paths
+pub fn init_context(config: &MpcaConfig) -> InitContext {
+    InitContext {
+        repo_root: config.repo_root.clone(),
+        config_file: config.config_file.clone(),
+        prompt_dirs: template_dirs(config),
+    }
+}
+
+/// Builds the context of the `plan` template.
+///
+/// Planning resumes when the feature already has a design or plan; their
+/// content is passed to the template.
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration with repository paths
+/// * `feature_slug` - Feature being planned
+/// * `fs` - File system adapter
+///
+/// # Errors
+///
+/// Returns `MPCAError::FileReadError` if an existing spec cannot be read.
+pub fn plan_context(
+    config: &MpcaConfig,
+    feature_slug: &str,
+    fs: &dyn FsAdapter,
+) -> Result<PlanContext> {
+    let specs_dir = feature_specs_dir(config, feature_slug);
+    let existing_design = read_spec(fs, &specs_dir.join("design.md"))?;
+    let existing_plan = read_spec(fs, &specs_dir.join("plan.md"))?;
+
+    Ok(PlanContext {
+        repo_root: config.repo_root.clone(),
+        feature_slug: feature_slug.to_string(),
+        worktree_dir: config.trees_dir.join(feature_slug),
+        branch: branch_name(config, feature_slug),
+        resume: existing_design.is_some() || existing_plan.is_some(),
+        user_goal: None,
+        existing_design,
+        existing_plan,
+        specs_dir,
+    })
+}
+
+/// Builds the context of the `execute` template for a feature.
+///
+/// The context describes the whole run; callers running a single plan
+/// step set `current_step`, and `resume` and `constraints` as they apply.
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration with repository paths
+/// * `state` - Current state of the feature
+/// * `fs` - File system adapter
+///
+/// # Errors
+///
+/// Returns `MPCAError::FileReadError` if `plan.md` cannot be read.
+pub fn execute_context(
+    config: &MpcaConfig,
+    state: &FeatureState,
+    fs: &dyn FsAdapter,
+) -> Result<ExecuteContext> {
+    let slug = &state.feature_slug;
+    let specs_dir = feature_specs_dir(config, slug);
+
+    Ok(ExecuteContext {
+        repo_root: config.repo_root.clone(),
+        feature_slug: slug.clone(),
+        worktree_dir: config.trees_dir.join(slug),
+        branch: branch_name(config, slug),
+        state_file: FeatureState::path(config, slug),
+        resume: false,
+        plan: read_spec(fs, &specs_dir.join("plan.md"))?.unwrap_or_default(),
+        constraints: Vec::new(),
+        phase: state.phase.to_string(),
+        current_step: None,
+        completed_steps: state.completed_steps().map(|s| s.title.clone()).collect(),
+        turns: state.turns,
+        cost_usd: state.cost_usd,
+        specs_dir,
+    })
+}
+
+/// Builds the context of the `review` template for a feature.
+///
+/// The diff summary lists the uncommitted changes in the feature's
+/// worktree, if it still exists.
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration with repository paths and reviewers
+/// * `state` - Current state of the feature
+/// * `fs` - File system adapter
+/// * `git` - Git adapter for the worktree status
+///
+/// # Errors
+///
+/// Returns `MPCAError::FileReadError` if a spec cannot be read, or
+/// `MPCAError::GitCommandFailed` if the worktree status cannot be read.
+pub fn review_context(
+    config: &MpcaConfig,
+    state: &FeatureState,
+    fs: &dyn FsAdapter,
+    git: &dyn GitAdapter,
+) -> Result<ReviewContext> {
+    let slug = &state.feature_slug;
+    let specs_dir = feature_specs_dir(config, slug);
+    let worktree_dir = config.trees_dir.join(slug);
+    let branch = branch_name(config, slug);
+
+    let changes = if fs.exists(&worktree_dir) {
+        git.status(&worktree_dir)?
+    } else {
+        Vec::new()
+    };
+    let diff_summary = if changes.is_empty() {
+        format!("no uncommitted changes; review the commits on {branch}")
+    } else {
+        changes.join(", ")
+    };
+
+    Ok(ReviewContext {
+        repo_root: config.repo_root.clone(),
+        feature_slug: slug.clone(),
+        diff_summary,
+        review_prefs: config.review.reviewers.clone(),
+        design_spec: read_spec(fs, &specs_dir.join("design.md"))?,
+        plan: read_spec(fs, &specs_dir.join("plan.md"))?,
+        verify_spec: read_spec(fs, &specs_dir.join("verify.md"))?,
+        impl_details: read_spec(
+            fs,
+            &config
+                .specs_dir
+                .join(slug)
+                .join("docs")
+                .join("impl_details.md"),
+        )?,
+        specs_dir,
+        branch,
+    })
+}
+
+/// Builds the context of the `verification` template for a feature.
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration with repository paths
+/// * `state` - Current state of the feature
+/// * `fs` - File system adapter
+///
+/// # Errors
+///
+/// Returns `MPCAError::FileReadError` if a spec cannot be read.
+pub fn verify_context(
+    config: &MpcaConfig,
+    state: &FeatureState,
+    fs: &dyn FsAdapter,
+) -> Result<VerifyContext> {
+    let slug = &state.feature_slug;
+    let specs_dir = feature_specs_dir(config, slug);
+
+    Ok(VerifyContext {
+        repo_root: config.repo_root.clone(),
+        feature_slug: slug.clone(),
+        worktree_dir: config.trees_dir.join(slug),
+        branch: branch_name(config, slug),
+        verify_spec: read_spec(fs, &specs_dir.join("verify.md"))?,
+        design_spec: read_spec(fs, &specs_dir.join("design.md"))?,
+        plan: read_spec(fs, &specs_dir.join("plan.md"))?,
+        state_file: FeatureState::path(config, slug),
+        specs_dir,
+    })
+}
+
+/// Returns a feature's `specs/` directory.
+fn feature_specs_dir(config: &MpcaConfig, feature_slug: &str) -> PathBuf {
+    config.specs_dir.join(feature_slug).join("specs")
+}
+
+/// Returns the branch a feature is implemented on.
+fn branch_name(config: &MpcaConfig, feature_slug: &str) -> String {
+    config
+        .git
+        .branch_naming
+        .replace("{feature_slug}", feature_slug)
+}
+
+/// Reads a spec file, or returns `None` if it doesn't exist.
+fn read_spec(fs: &dyn FsAdapter, path: &Path) -> Result<Option<String>> {
+    if !fs.exists(path) {
+        return Ok(None);
+    }
+    fs.read_to_string(path).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::fs_impl::StdFsAdapter;
+    use mpca_pm::bundled::bundled_template;
+    use mpca_pm::{PromptEngine, TemplateSource};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_eject_keeps_edited_templates() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let fs = StdFsAdapter::new();
+
+        let ejected = eject(&config, &fs, Some("plan"), false).unwrap();
+        let plan = prompts_dir(&config).join("plan.j2");
+        assert_eq!(
+            ejected,
+            [EjectedTemplate {
+                name: "plan",
+                path: plan.clone(),
+                written: true
+            }]
+        );
+        assert_eq!(
+            fs.read_to_string(&plan).unwrap(),
+            bundled_template("plan").unwrap()
+        );
+
+        // The ejected copy now shadows the bundled template
+        let pm = prompt_manager(&config).unwrap();
+        assert_eq!(
+            pm.candidates("plan"),
+            [
+                TemplateSource::File(plan.clone()),
+                TemplateSource::Bundled("plan")
+            ]
+        );
+
+        fs.write(&plan, "Edited plan").unwrap();
+        let ejected = eject(&config, &fs, None, false).unwrap();
+        assert_eq!(
+            ejected.len(),
+            BUNDLED_TEMPLATES.len() + BUNDLED_PARTIALS.len()
+        );
+        assert!(prompts_dir(&config).join("partials/rules.j2").is_file());
+        assert!(ejected.iter().all(|t| t.written == (t.name != "plan")));
+        assert_eq!(fs.read_to_string(&plan).unwrap(), "Edited plan");
+
+        eject(&config, &fs, Some("plan"), true).unwrap();
+        assert_eq!(
+            fs.read_to_string(&plan).unwrap(),
+            bundled_template("plan").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_contexts_are_built_from_feature_state() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let fs = StdFsAdapter::new();
+        let pm = prompt_manager(&config).unwrap();
+
+        // A feature without specs is planned from scratch
+        let plan = plan_context(&config, "add-caching", &fs).unwrap();
+        assert!(!plan.resume);
+        assert_eq!(plan.branch, "feature/add-caching");
+        assert_eq!(plan.worktree_dir, config.trees_dir.join("add-caching"));
+        pm.render("plan", &plan).unwrap();
+
+        let specs_dir = config.specs_dir.join("add-caching").join("specs");
+        fs.write(&specs_dir.join("plan.md"), "1. Add types\n2. Wire CLI\n")
+            .unwrap();
+        let plan = plan_context(&config, "add-caching", &fs).unwrap();
+        assert!(plan.resume);
+        assert_eq!(plan.existing_design, None);
+
+        let mut state = FeatureState::new("add-caching");
+        state.turns = 4;
+        state.sync_steps(&crate::steps::parse_plan_steps("1. Add types\n2. Wire CLI\n").unwrap());
+        state.complete_step(1, None).unwrap();
+
+        let execute = execute_context(&config, &state, &fs).unwrap();
+        assert_eq!(execute.plan, "1. Add types\n2. Wire CLI\n");
+        assert_eq!(execute.completed_steps, ["Add types"]);
+        assert_eq!(execute.turns, 4);
+        assert_eq!(execute.state_file, specs_dir.join("state.toml"));
+        pm.render("execute", &execute).unwrap();
+
+        let git = crate::tools::git_mock::MockGitAdapter::new();
+        let review = review_context(&config, &state, &fs, &git).unwrap();
+        assert_eq!(review.plan.as_deref(), Some("1. Add types\n2. Wire CLI\n"));
+        assert!(review.diff_summary.starts_with("no uncommitted changes"));
+        pm.render("review", &review).unwrap();
+
+        pm.render(
+            "verification",
+            &verify_context(&config, &state, &fs).unwrap(),
+        )
+        .unwrap();
+        pm.render("init", &init_context(&config)).unwrap();
+    }
+
+    #[test]
+    fn test_eject_unknown_template() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+
+        let result = eject(&config, &StdFsAdapter::new(), Some("security"), false);
+        assert!(
+            matches!(result, Err(MPCAError::TemplateNotFound(msg)) if msg.starts_with("security"))
+        );
+        assert!(!prompts_dir(&config).exists());
+    }
+}
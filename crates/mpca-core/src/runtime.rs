@@ -4,43 +4,49 @@
 //! workflows, manages state, and coordinates between the prompt manager, tools,
 //! and the Claude Agent SDK.
 
-use crate::config::MpcaConfig;
+use crate::agent::{self, AgentBackend, AgentRunner, AgentSession, SdkAgentBackend};
+use crate::cancel::CancellationToken;
+use crate::config::{MpcaConfig, WorkflowKind};
 use crate::error::Result;
+use crate::prompts;
 use crate::state::RuntimeState;
 use crate::tools::ToolRegistry;
 use crate::tools::fs_impl::StdFsAdapter;
 use crate::tools::git_impl::StdGitAdapter;
 use crate::tools::shell_impl::StdShellAdapter;
 use crate::workflows;
+use async_trait::async_trait;
+use std::sync::Arc;
 
 /// Runtime trait for MPCA workflow execution.
 ///
 /// Defines the core interface for executing MPCA workflows. This trait allows
 /// for alternative runtime implementations and facilitates testing.
-pub trait Runtime {
+#[async_trait]
+pub trait Runtime: Send + Sync {
     /// Initializes a repository for MPCA use.
     ///
     /// Creates `.mpca/` and `.trees/` directories, configuration files,
     /// and updates repository documentation.
-    fn init_project(&self) -> Result<()>;
+    async fn init_project(&self) -> Result<()>;
 
     /// Plans a new feature with the given slug.
     ///
     /// Interactively generates feature specifications through conversation
     /// with Claude.
-    fn plan_feature(&self, feature_slug: &str) -> Result<()>;
+    async fn plan_feature(&self, feature_slug: &str) -> Result<()>;
 
     /// Executes a planned feature.
     ///
     /// Implements the feature according to its specifications in an
     /// isolated git worktree.
-    fn run_feature(&self, feature_slug: &str) -> Result<()>;
+    async fn run_feature(&self, feature_slug: &str) -> Result<()>;
 
     /// Sends a chat message to the agent.
     ///
     /// Enables free-form conversation with Claude without committing to
     /// a specific feature workflow.
-    fn chat(&self, message: &str) -> Result<String>;
+    async fn chat(&self, message: &str) -> Result<String>;
 }
 
 /// Agent runtime for MPCA workflows.
@@ -55,12 +61,12 @@ pub trait Runtime {
 /// use mpca_core::{AgentRuntime, MpcaConfig};
 /// use std::path::PathBuf;
 ///
-/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
 /// let config = MpcaConfig::new(PathBuf::from("/path/to/repo"));
 /// let runtime = AgentRuntime::new(config)?;
 ///
 /// // Initialize repository
-/// runtime.init_project()?;
+/// runtime.init_project().await?;
 /// # Ok(())
 /// # }
 /// ```
@@ -70,13 +76,19 @@ pub struct AgentRuntime {
     pub config: MpcaConfig,
 
     /// Prompt manager for template rendering.
-    pub pm: Option<mpca_pm::PromptManager>,
+    pub pm: mpca_pm::PromptManager,
 
     /// Tool registry for file system, git, and shell operations.
     pub tools: ToolRegistry,
 
     /// Runtime state tracking workflow progress.
     pub state: RuntimeState,
+
+    /// Backend used to run the Claude agent.
+    pub agent: Arc<dyn AgentBackend>,
+
+    /// Token that stops running workflows at the next checkpoint.
+    pub cancel: CancellationToken,
 }
 
 impl AgentRuntime {
@@ -117,71 +129,91 @@ impl AgentRuntime {
         // Initialize runtime state
         let state = RuntimeState::default();
 
-        // Initialize prompt manager
-        let pm = Self::init_prompt_manager(&config)?;
+        // Initialize prompt manager over the repository's template layers
+        let pm = prompts::prompt_manager(&config)?;
 
         Ok(Self {
             config,
             pm,
             tools,
             state,
+            agent: Arc::new(SdkAgentBackend::new()),
+            cancel: CancellationToken::new(),
         })
     }
 
-    /// Initializes the prompt manager with template directory resolution.
-    ///
-    /// Searches for templates in the following order:
-    /// 1. User-specified directories in config.prompt_dirs
-    /// 2. Bundled templates in the crate's templates directory
-    /// 3. Installed location relative to executable
-    fn init_prompt_manager(config: &MpcaConfig) -> Result<Option<mpca_pm::PromptManager>> {
-        // Try user-specified directories first
-        for dir in &config.prompt_dirs {
-            if dir.exists() {
-                match mpca_pm::PromptManager::new(dir.clone()) {
-                    Ok(pm) => return Ok(Some(pm)),
-                    Err(_) => continue,
-                }
-            }
-        }
+    /// Replaces the agent backend.
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - Agent backend (e.g., a `ScriptedAgentBackend` for
+    ///   offline tests).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mpca_core::agent::ScriptedAgentBackend;
+    /// use mpca_core::{AgentRuntime, MpcaConfig};
+    /// use std::path::{Path, PathBuf};
+    /// use std::sync::Arc;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let backend = ScriptedAgentBackend::from_file(Path::new("fixtures/transcripts/offline.json"))?;
+    /// let runtime = AgentRuntime::new(MpcaConfig::new(PathBuf::from("/repo")))?
+    ///     .with_agent_backend(Arc::new(backend));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_agent_backend(mut self, backend: Arc<dyn AgentBackend>) -> Self {
+        self.agent = backend;
+        self
+    }
 
-        // Try to find bundled templates relative to crate root
-        // This works for development and when running from source
-        if let Ok(manifest_dir) = std::env::var("CARGO_MANIFEST_DIR") {
-            let template_dir = std::path::PathBuf::from(manifest_dir)
-                .parent()
-                .and_then(|p| p.parent())
-                .map(|p| p.join("crates/mpca-pm/templates"));
-
-            if let Some(dir) = template_dir
-                && dir.exists()
-                && let Ok(pm) = mpca_pm::PromptManager::new(dir)
-            {
-                return Ok(Some(pm));
-            }
-        }
+    /// Replaces the cancellation token.
+    ///
+    /// Cancelling the token makes a running workflow abandon the agent query
+    /// in flight, checkpoint its state and return `MPCAError::Cancelled`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cancel` - Token shared with the code that requests cancellation
+    ///   (e.g., a Ctrl-C handler).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mpca_core::{AgentRuntime, CancellationToken, MpcaConfig};
+    /// use std::path::PathBuf;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let cancel = CancellationToken::new();
+    /// let runtime = AgentRuntime::new(MpcaConfig::new(PathBuf::from("/repo")))?
+    ///     .with_cancellation(cancel.clone());
+    ///
+    /// tokio::spawn(async move {
+    ///     if tokio::signal::ctrl_c().await.is_ok() {
+    ///         cancel.cancel();
+    ///     }
+    /// });
+    /// runtime.run_feature("add-caching").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
 
-        // Try relative to executable (for installed version)
-        if let Ok(exe_path) = std::env::current_exe()
-            && let Some(exe_dir) = exe_path.parent()
-        {
-            let template_dir = exe_dir
-                .parent()
-                .map(|p| p.join("share/mpca/templates"))
-                .or_else(|| Some(exe_dir.join("templates")));
-
-            if let Some(dir) = template_dir
-                && dir.exists()
-                && let Ok(pm) = mpca_pm::PromptManager::new(dir)
-            {
-                return Ok(Some(pm));
-            }
-        }
+    /// Returns an agent runner for this runtime's configuration and backend.
+    pub fn agent_runner(&self) -> AgentRunner<'_> {
+        self.agent_runner_for(&self.config)
+    }
 
-        // Prompt manager is optional - workflows can still run without it
-        // but template-based prompts won't be available
-        tracing::warn!("Prompt manager not initialized - template directory not found");
-        Ok(None)
+    /// Returns an agent runner for `config` (e.g., a feature's configuration)
+    /// and this runtime's backend.
+    fn agent_runner_for<'a>(&'a self, config: &'a MpcaConfig) -> AgentRunner<'a> {
+        AgentRunner::new(config, Arc::clone(&self.agent), Some(&self.pm))
+            .with_cancellation(self.cancel.clone())
     }
 
     /// Initializes a repository for MPCA use.
@@ -211,21 +243,23 @@ impl AgentRuntime {
     /// use mpca_core::{AgentRuntime, MpcaConfig};
     /// use std::path::PathBuf;
     ///
-    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// let config = MpcaConfig::new(PathBuf::from("/path/to/repo"));
     /// let runtime = AgentRuntime::new(config)?;
     ///
-    /// runtime.init_project()?;
+    /// runtime.init_project().await?;
     /// println!("Repository initialized for MPCA!");
     /// # Ok(())
     /// # }
     /// ```
-    pub fn init_project(&self) -> Result<()> {
-        workflows::init_project(&self.config, &*self.tools.fs, &*self.tools.git)
+    pub async fn init_project(&self) -> Result<()> {
+        workflows::init_project(&self.config, &*self.tools.fs, &*self.tools.git).await
     }
 
     /// Plans a new feature with the given slug.
     ///
+    /// The implementation plan is written by the agent backend.
+    ///
     /// # Arguments
     ///
     /// * `feature_slug` - The feature identifier (e.g., "add-caching").
@@ -236,18 +270,23 @@ impl AgentRuntime {
     ///
     /// # Errors
     ///
-    /// Returns errors related to feature planning (see `workflows::plan_feature`).
-    pub fn plan_feature(&self, feature_slug: &str) -> Result<()> {
-        workflows::plan_feature(
-            &self.config,
+    /// Returns errors related to feature planning (see `workflows::plan_feature_with`).
+    pub async fn plan_feature(&self, feature_slug: &str) -> Result<()> {
+        let config = self.config.for_feature(feature_slug, &*self.tools.fs)?;
+        workflows::plan_feature_with(
+            &config,
             feature_slug,
             &*self.tools.fs,
             &*self.tools.git,
+            Some(&self.agent_runner_for(&config)),
         )
+        .await
     }
 
     /// Executes a feature plan with the given slug.
     ///
+    /// Every unfinished plan step is run by the agent backend.
+    ///
     /// # Arguments
     ///
     /// * `feature_slug` - The feature identifier (e.g., "add-caching").
@@ -258,75 +297,79 @@ impl AgentRuntime {
     ///
     /// # Errors
     ///
-    /// Returns errors related to feature execution (see `workflows::execute_feature`).
-    pub fn run_feature(&self, feature_slug: &str) -> Result<()> {
-        workflows::execute_feature(
-            &self.config,
+    /// Returns errors related to feature execution (see `workflows::execute_feature_with`),
+    /// or `MPCAError::Cancelled` once the runtime's token is cancelled; the
+    /// interrupted step is checkpointed so `mpca resume` can pick it up.
+    pub async fn run_feature(&self, feature_slug: &str) -> Result<()> {
+        let config = self.config.for_feature(feature_slug, &*self.tools.fs)?;
+        workflows::execute_feature_with(
+            &config,
             feature_slug,
             &*self.tools.fs,
             &*self.tools.git,
             &*self.tools.shell,
+            Some(&mut self.agent_runner_for(&config)),
+            &self.cancel,
         )
+        .await
     }
 
-    /// Sends a chat message to the agent (to be implemented in Stage 4).
+    /// Sends a chat message to the agent.
+    ///
+    /// Starts a one-shot agent session in the repository using the plan
+    /// workflow's agent mode and tool set, without a workflow system prompt.
     ///
     /// # Arguments
     ///
-    /// * `_message` - The message to send to the agent.
+    /// * `message` - The message to send to the agent.
     ///
     /// # Returns
     ///
-    /// Currently returns an empty string as a stub. Will return the agent's
-    /// response once implemented in Stage 4.
+    /// The agent's reply text.
     ///
     /// # Errors
     ///
-    /// Will return errors related to agent communication once implemented.
-    #[allow(unused_variables)]
-    pub fn chat(&self, _message: &str) -> Result<String> {
-        // TODO: Implement in Stage 4
-        // - Initialize Claude agent if not already done
-        // - Send message to agent
-        // - Return response
-        Ok(String::new())
+    /// Returns `MPCAError::AgentError` if the agent fails, or
+    /// `MPCAError::Cancelled` if the runtime's token is cancelled.
+    pub async fn chat(&self, message: &str) -> Result<String> {
+        let session = AgentSession::new(agent::build_options(
+            &self.config,
+            WorkflowKind::Plan,
+            None,
+        )?)
+        .with_backend(Arc::clone(&self.agent))
+        .with_cancellation(self.cancel.clone())
+        .with_tool_guard(agent::ToolGuard::for_feature(&self.config, None));
+        let response = session.run(message, &mut |_| {}).await?;
+        tracing::info!(usage = %response.usage, "chat reply received");
+        Ok(response.text)
     }
 }
 
+#[async_trait]
 impl Runtime for AgentRuntime {
-    fn init_project(&self) -> Result<()> {
-        workflows::init_project(&self.config, &*self.tools.fs, &*self.tools.git)
+    async fn init_project(&self) -> Result<()> {
+        AgentRuntime::init_project(self).await
     }
 
-    fn plan_feature(&self, feature_slug: &str) -> Result<()> {
-        workflows::plan_feature(
-            &self.config,
-            feature_slug,
-            &*self.tools.fs,
-            &*self.tools.git,
-        )
+    async fn plan_feature(&self, feature_slug: &str) -> Result<()> {
+        AgentRuntime::plan_feature(self, feature_slug).await
     }
 
-    fn run_feature(&self, feature_slug: &str) -> Result<()> {
-        workflows::execute_feature(
-            &self.config,
-            feature_slug,
-            &*self.tools.fs,
-            &*self.tools.git,
-            &*self.tools.shell,
-        )
+    async fn run_feature(&self, feature_slug: &str) -> Result<()> {
+        AgentRuntime::run_feature(self, feature_slug).await
     }
 
-    fn chat(&self, _message: &str) -> Result<String> {
-        // TODO: Implement in Stage 5
-        // For now, return empty string as stub
-        Ok(String::new())
+    async fn chat(&self, message: &str) -> Result<String> {
+        AgentRuntime::chat(self, message).await
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::MPCAError;
+    use crate::agent::{AgentEvent, AgentResult, ScriptedAgentBackend, ScriptedTurn};
     use std::fs;
     use std::process::Command;
     use tempfile::TempDir;
@@ -378,19 +421,51 @@ mod tests {
         assert!(runtime.is_ok());
 
         let runtime = runtime.unwrap();
-        assert!(runtime.pm.is_none());
+        assert_eq!(
+            runtime.pm.resolve("plan"),
+            Some(mpca_pm::TemplateSource::Bundled("plan"))
+        );
         assert_eq!(runtime.state.feature_slug, None);
     }
 
     #[test]
-    fn test_init_project_integration() {
+    fn test_prompt_dirs_override_single_templates() {
+        let temp_dir = TempDir::new().unwrap();
+        let prompts = temp_dir.path().join(".mpca/prompts");
+        fs::create_dir_all(&prompts).unwrap();
+        fs::write(prompts.join("plan.j2"), "Custom plan prompt").unwrap();
+
+        let mut config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        config.prompt_dirs = vec![
+            std::path::PathBuf::from(".mpca/prompts"),
+            std::path::PathBuf::from("missing"),
+        ];
+
+        let dirs = prompts::template_dirs(&config);
+        assert_eq!(dirs, std::slice::from_ref(&prompts));
+
+        let runtime = AgentRuntime::new(config).unwrap();
+        let pm = &runtime.pm;
+        assert_eq!(
+            pm.resolve("plan"),
+            Some(mpca_pm::TemplateSource::File(prompts.join("plan.j2")))
+        );
+        assert_eq!(pm.candidates("plan").len(), 2);
+        assert_eq!(
+            pm.resolve("execute"),
+            Some(mpca_pm::TemplateSource::Bundled("execute"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_init_project_integration() {
         let temp_dir = TempDir::new().unwrap();
         init_test_repo(temp_dir.path());
 
         let config = MpcaConfig::new(temp_dir.path().to_path_buf());
         let runtime = AgentRuntime::new(config).unwrap();
 
-        let result = runtime.init_project();
+        let result = runtime.init_project().await;
         assert!(result.is_ok());
 
         // Verify initialization artifacts
@@ -400,44 +475,143 @@ mod tests {
         assert!(runtime.tools.fs.exists(&runtime.config.claude_md));
     }
 
-    #[test]
-    fn test_plan_feature_stub() {
+    /// Scripted backend replaying the offline fixture transcript.
+    fn offline_backend() -> ScriptedAgentBackend {
+        let transcript = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../fixtures/transcripts/offline.json");
+        ScriptedAgentBackend::from_file(&transcript).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_plan_feature_with_agent() {
         let temp_dir = TempDir::new().unwrap();
         init_test_repo(temp_dir.path());
 
         let config = MpcaConfig::new(temp_dir.path().to_path_buf());
-        let runtime = AgentRuntime::new(config).unwrap();
+        let backend = offline_backend();
+        let runtime = AgentRuntime::new(config)
+            .unwrap()
+            .with_agent_backend(Arc::new(backend.clone()));
 
-        // Should succeed (stub implementation)
-        let result = runtime.plan_feature("test-feature");
+        let result = runtime.plan_feature("test-feature").await;
         if let Err(e) = &result {
             eprintln!("Error: {:#}", e);
         }
         assert!(result.is_ok());
+
+        // The agent's reply becomes the plan
+        let specs_dir = runtime.config.specs_dir.join("test-feature").join("specs");
+        let plan = fs::read_to_string(specs_dir.join("plan.md")).unwrap();
+        assert!(plan.contains("1. Add core types"));
+
+        let queries = backend.queries();
+        assert_eq!(queries.len(), 1);
+        assert!(queries[0].prompt.contains("test-feature"));
+        assert_eq!(
+            queries[0].model.as_deref(),
+            Some(runtime.config.agent_modes.plan.model.as_str())
+        );
+
+        let state =
+            crate::FeatureState::load(&*runtime.tools.fs, &specs_dir.join("state.toml")).unwrap();
+        assert_eq!(state.turns, 2);
     }
 
-    #[test]
-    fn test_run_feature_stub() {
+    #[tokio::test]
+    async fn test_plan_feature_rejects_plan_without_steps() {
         let temp_dir = TempDir::new().unwrap();
         init_test_repo(temp_dir.path());
 
+        let backend = ScriptedAgentBackend::new(vec![ScriptedTurn {
+            events: vec![
+                AgentEvent::Text {
+                    text: "I need more details first.".to_string(),
+                },
+                AgentEvent::Result(AgentResult::default()),
+            ],
+            ..Default::default()
+        }]);
         let config = MpcaConfig::new(temp_dir.path().to_path_buf());
-        let runtime = AgentRuntime::new(config).unwrap();
+        let runtime = AgentRuntime::new(config)
+            .unwrap()
+            .with_agent_backend(Arc::new(backend));
 
-        // Should succeed (stub implementation)
-        let result = runtime.run_feature("test-feature");
-        assert!(result.is_ok());
+        let result = runtime.plan_feature("test-feature").await;
+        assert!(matches!(result, Err(MPCAError::InvalidPlanFormat(_))));
+
+        // Nothing is left behind, so planning can be retried
+        assert!(!runtime.config.specs_dir.join("test-feature").exists());
     }
 
-    #[test]
-    fn test_chat_stub() {
+    #[tokio::test]
+    async fn test_run_feature_with_agent() {
         let temp_dir = TempDir::new().unwrap();
+        init_test_repo(temp_dir.path());
+
         let config = MpcaConfig::new(temp_dir.path().to_path_buf());
-        let runtime = AgentRuntime::new(config).unwrap();
+        let backend = offline_backend();
+        let runtime = AgentRuntime::new(config)
+            .unwrap()
+            .with_agent_backend(Arc::new(backend.clone()));
+
+        // Running requires a planned feature
+        runtime.init_project().await.unwrap();
+        runtime.plan_feature("test-feature").await.unwrap();
 
-        // Should succeed (stub implementation)
-        let result = runtime.chat("Hello");
+        let result = runtime.run_feature("test-feature").await;
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), "");
+
+        // One plan query, then one query per step inside the worktree
+        let queries = backend.queries();
+        assert_eq!(queries.len(), 4);
+        assert!(
+            queries[1]
+                .prompt
+                .starts_with("Implement step 1: Add core types")
+        );
+        assert_eq!(
+            queries[3].cwd,
+            Some(runtime.config.trees_dir.join("test-feature"))
+        );
+
+        let state_file = crate::FeatureState::path(&runtime.config, "test-feature");
+        let state = crate::FeatureState::load(&*runtime.tools.fs, &state_file).unwrap();
+        assert!(state.next_step().is_none());
+        assert_eq!(state.turns, 2 + 3 * 3);
+        assert_eq!(state.usage[&crate::Phase::Plan].input_tokens, 1500);
+        assert_eq!(state.usage[&crate::Phase::Run].output_tokens, 3 * 150);
+        assert_eq!(state.steps[2].usage.cache_creation_input_tokens, 300);
+    }
+
+    #[tokio::test]
+    async fn test_chat_with_agent() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let runtime = AgentRuntime::new(config)
+            .unwrap()
+            .with_agent_backend(Arc::new(offline_backend()));
+
+        let reply = runtime.chat("Hello").await.unwrap();
+        assert_eq!(reply, "Hello from the offline transcript.");
+    }
+
+    #[tokio::test]
+    async fn test_chat_reports_agent_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = ScriptedAgentBackend::new(vec![ScriptedTurn {
+            events: vec![AgentEvent::Result(AgentResult {
+                is_error: true,
+                error: Some("error_max_turns".to_string()),
+                ..Default::default()
+            })],
+            ..Default::default()
+        }]);
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let runtime = AgentRuntime::new(config)
+            .unwrap()
+            .with_agent_backend(Arc::new(backend));
+
+        let result = runtime.chat("Hello").await;
+        assert!(matches!(result, Err(MPCAError::AgentError(ref e)) if e == "error_max_turns"));
     }
 }
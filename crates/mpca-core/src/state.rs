@@ -1,17 +1,33 @@
 //! Runtime state management for MPCA workflows.
 //!
 //! This module defines the runtime state that tracks workflow progress,
-//! including the current phase, turn count, and cost tracking.
+//! including the current phase, turn count, and cost tracking, as well as
+//! [`FeatureState`], the typed representation of a feature's `state.toml`.
 
+use crate::config::MpcaConfig;
+use crate::error::{MPCAError, Result};
+use crate::steps::PlanStep;
+use crate::tools::fs::FsAdapter;
+use crate::usage::Usage;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+/// Current schema version written to `state.toml`.
+///
+/// Bump this whenever the on-disk layout of [`FeatureState`] changes in a
+/// way older binaries cannot read.
+pub const STATE_SCHEMA_VERSION: u32 = 1;
+
 /// Runtime state for MPCA workflows.
 ///
 /// Tracks the current execution state of a feature workflow, including
 /// which phase it's in, how many agent turns have occurred, and the
-/// cumulative cost. This state is persisted to `state.toml` to enable
-/// resumable workflows.
+/// cumulative cost. The persisted counterpart is [`FeatureState`], which
+/// is written to `state.toml` to enable resumable workflows.
 #[derive(Debug, Clone)]
 pub struct RuntimeState {
     /// Currently active feature slug (if any).
@@ -98,6 +114,16 @@ impl RuntimeState {
     pub fn add_cost(&mut self, cost: f64) {
         self.cost_usd += cost;
     }
+
+    /// Adds the turns and cost of an agent query.
+    ///
+    /// # Arguments
+    ///
+    /// * `usage` - Usage reported by the agent.
+    pub fn record_usage(&mut self, usage: &Usage) {
+        self.turns += usage.turns;
+        self.add_cost(usage.cost_usd);
+    }
 }
 
 impl Default for RuntimeState {
@@ -106,23 +132,766 @@ impl Default for RuntimeState {
     }
 }
 
+impl From<&FeatureState> for RuntimeState {
+    fn from(state: &FeatureState) -> Self {
+        Self {
+            feature_slug: Some(state.feature_slug.clone()),
+            phase: state.phase,
+            turns: state.turns,
+            cost_usd: state.cost_usd,
+        }
+    }
+}
+
+/// Persisted state of a single feature.
+///
+/// This is the authoritative content of
+/// `.mpca/specs/<feature-slug>/specs/state.toml`. All workflows read and
+/// write the file through [`FeatureState::load`], [`FeatureState::save`]
+/// and [`FeatureState::update`] so the file never drifts between phases.
+///
+/// # Examples
+///
+/// ```
+/// use mpca_core::state::{FeatureState, Phase};
+/// use mpca_core::tools::fs_mock::MockFsAdapter;
+/// use std::path::Path;
+///
+/// let fs = MockFsAdapter::new();
+/// let path = Path::new("/repo/.mpca/specs/add-caching/specs/state.toml");
+///
+/// let mut state = FeatureState::new("add-caching");
+/// state.save(&fs, path).unwrap();
+///
+/// let loaded = FeatureState::load(&fs, path).unwrap();
+/// assert_eq!(loaded.phase, Phase::Plan);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeatureState {
+    /// Schema version of the state file.
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
+
+    /// Feature slug this state belongs to.
+    pub feature_slug: String,
+
+    /// Current workflow phase.
+    pub phase: Phase,
+
+    /// Current implementation step (0 before execution starts).
+    #[serde(default)]
+    pub step: u32,
+
+    /// Number of agent turns executed so far.
+    #[serde(default)]
+    pub turns: u32,
+
+    /// Cumulative cost in USD for agent API calls.
+    #[serde(default)]
+    pub cost_usd: f64,
+
+    /// Agent usage accumulated per phase (`[usage.Plan]`, `[usage.Run]`, ...).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub usage: BTreeMap<Phase, Usage>,
+
+    /// Configuration profile the feature was last planned or run with;
+    /// `mpca resume` applies it again unless another profile is selected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+
+    /// When the feature was first planned.
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+
+    /// When the state was last saved.
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+
+    /// Result of the most recent verification run (if any).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification: Option<VerificationResult>,
+
+    /// Audit log of phase transitions, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transitions: Vec<PhaseTransition>,
+
+    /// Progress of each implementation step from `plan.md`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<StepState>,
+
+    /// Progress of each stage of the `mpca run` pipeline.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stages: Vec<StageState>,
+}
+
+fn default_schema_version() -> u32 {
+    STATE_SCHEMA_VERSION
+}
+
+impl FeatureState {
+    /// Creates a fresh state for a newly planned feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature_slug` - The feature slug identifier.
+    ///
+    /// # Returns
+    ///
+    /// A new `FeatureState` in the `Plan` phase with zero step, turns and cost.
+    pub fn new(feature_slug: impl Into<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            schema_version: STATE_SCHEMA_VERSION,
+            feature_slug: feature_slug.into(),
+            phase: Phase::Plan,
+            step: 0,
+            turns: 0,
+            cost_usd: 0.0,
+            usage: BTreeMap::new(),
+            profile: None,
+            created_at: now,
+            updated_at: now,
+            verification: None,
+            transitions: Vec::new(),
+            steps: Vec::new(),
+            stages: Vec::new(),
+        }
+    }
+
+    /// Returns the location of the state file for a feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - MPCA configuration with repository paths.
+    /// * `feature_slug` - The feature slug identifier.
+    ///
+    /// # Returns
+    ///
+    /// `<specs_dir>/<feature_slug>/specs/state.toml`.
+    pub fn path(config: &MpcaConfig, feature_slug: &str) -> PathBuf {
+        config
+            .specs_dir
+            .join(feature_slug)
+            .join("specs")
+            .join("state.toml")
+    }
+
+    /// Loads a feature state from disk.
+    ///
+    /// # Arguments
+    ///
+    /// * `fs` - File system adapter used to read the file.
+    /// * `path` - Path to `state.toml`.
+    ///
+    /// # Errors
+    ///
+    /// Returns:
+    /// - `MPCAError::StateMissing` if the file does not exist
+    /// - `MPCAError::CorruptedState` if the file cannot be parsed
+    /// - `MPCAError::UnsupportedStateVersion` if the file was written by a newer schema
+    pub fn load(fs: &dyn FsAdapter, path: &Path) -> Result<Self> {
+        if !fs.exists(path) {
+            return Err(MPCAError::StateMissing(path.to_path_buf()));
+        }
+
+        let content = fs.read_to_string(path)?;
+        let state: FeatureState = toml::from_str(&content).map_err(|e| {
+            tracing::error!(path = %path.display(), error = %e, "failed to parse state file");
+            MPCAError::CorruptedState(path.to_path_buf())
+        })?;
+
+        if state.schema_version > STATE_SCHEMA_VERSION {
+            return Err(MPCAError::UnsupportedStateVersion(state.schema_version));
+        }
+
+        Ok(state)
+    }
+
+    /// Saves the state to disk, refreshing `updated_at`.
+    ///
+    /// # Arguments
+    ///
+    /// * `fs` - File system adapter used to write the file.
+    /// * `path` - Path to `state.toml`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::FileWriteError` if the file cannot be written.
+    pub fn save(&mut self, fs: &dyn FsAdapter, path: &Path) -> Result<()> {
+        self.schema_version = STATE_SCHEMA_VERSION;
+        self.updated_at = Utc::now();
+
+        fs.write(path, &self.to_toml()?)
+    }
+
+    /// Serializes the state as the content of `state.toml`.
+    pub(crate) fn to_toml(&self) -> Result<String> {
+        let body = toml::to_string(self)
+            .map_err(|e| MPCAError::Other(format!("failed to serialize state: {}", e)))?;
+        Ok(format!(
+            "# MPCA workflow state for feature: {}\n{}",
+            self.feature_slug, body
+        ))
+    }
+
+    /// Loads the state, applies `f` to it and saves the result.
+    ///
+    /// # Arguments
+    ///
+    /// * `fs` - File system adapter.
+    /// * `path` - Path to `state.toml`.
+    /// * `f` - Mutation to apply; returning an error aborts without saving.
+    ///
+    /// # Returns
+    ///
+    /// The saved state.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from [`FeatureState::load`], `f`, or [`FeatureState::save`].
+    pub fn update<F>(fs: &dyn FsAdapter, path: &Path, f: F) -> Result<Self>
+    where
+        F: FnOnce(&mut FeatureState) -> Result<()>,
+    {
+        let mut state = Self::load(fs, path)?;
+        f(&mut state)?;
+        state.save(fs, path)?;
+        Ok(state)
+    }
+
+    /// Moves the feature to another phase, recording the transition.
+    ///
+    /// Only moves allowed by [`Phase::can_transition_to`] are accepted.
+    /// Staying in the current phase is a no-op and is not recorded.
+    /// Guards that depend on the feature's files are checked by the
+    /// workflows before calling this method.
+    ///
+    /// # Arguments
+    ///
+    /// * `to` - Target phase.
+    /// * `reason` - Optional human-readable reason stored in the audit log.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the phase changed, `false` if already in `to`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::InvalidStateTransition` if the move is not allowed.
+    pub fn transition_to(&mut self, to: Phase, reason: Option<String>) -> Result<bool> {
+        if self.phase == to {
+            return Ok(false);
+        }
+
+        if !self.phase.can_transition_to(to) {
+            return Err(MPCAError::InvalidStateTransition(
+                self.phase.to_string(),
+                to.to_string(),
+            ));
+        }
+
+        self.transitions.push(PhaseTransition {
+            from: self.phase,
+            to,
+            at: Utc::now(),
+            reason,
+        });
+        self.phase = to;
+
+        Ok(true)
+    }
+
+    /// Synchronises step progress with the steps parsed from `plan.md`.
+    ///
+    /// Steps whose number and title are unchanged keep their recorded
+    /// progress; new or edited steps start as pending.
+    ///
+    /// # Arguments
+    ///
+    /// * `plan` - Steps parsed from the current plan.
+    pub fn sync_steps(&mut self, plan: &[PlanStep]) {
+        let previous = std::mem::take(&mut self.steps);
+        self.steps = plan
+            .iter()
+            .map(|step| {
+                previous
+                    .iter()
+                    .find(|s| s.number == step.number && s.title == step.title)
+                    .cloned()
+                    .unwrap_or_else(|| StepState::pending(step))
+            })
+            .collect();
+    }
+
+    /// Returns the first step that is not done.
+    ///
+    /// # Returns
+    ///
+    /// The step to continue from, or `None` if every step is done.
+    pub fn next_step(&self) -> Option<&StepState> {
+        self.steps.iter().find(|s| s.status != StepStatus::Done)
+    }
+
+    /// Returns the steps that are already done.
+    pub fn completed_steps(&self) -> impl Iterator<Item = &StepState> {
+        self.steps.iter().filter(|s| s.status == StepStatus::Done)
+    }
+
+    /// Marks a step as in progress and makes it the current step.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - Step number.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::InvalidPlanFormat` if the step doesn't exist.
+    pub fn start_step(&mut self, number: u32) -> Result<()> {
+        let step = self.step_mut(number)?;
+        step.status = StepStatus::InProgress;
+        step.started_at = Some(Utc::now());
+        step.finished_at = None;
+        step.error = None;
+        self.step = number;
+        Ok(())
+    }
+
+    /// Marks a step as done.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - Step number.
+    /// * `commit` - Commit hash produced by the step (if any).
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::InvalidPlanFormat` if the step doesn't exist.
+    pub fn complete_step(&mut self, number: u32, commit: Option<String>) -> Result<()> {
+        let step = self.step_mut(number)?;
+        step.status = StepStatus::Done;
+        step.commit = commit;
+        step.finished_at = Some(Utc::now());
+        Ok(())
+    }
+
+    /// Marks a step as failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - Step number.
+    /// * `error` - Description of the failure.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::InvalidPlanFormat` if the step doesn't exist.
+    pub fn fail_step(&mut self, number: u32, error: impl Into<String>) -> Result<()> {
+        let step = self.step_mut(number)?;
+        step.status = StepStatus::Failed;
+        step.error = Some(error.into());
+        step.finished_at = Some(Utc::now());
+        Ok(())
+    }
+
+    fn step_mut(&mut self, number: u32) -> Result<&mut StepState> {
+        self.steps
+            .iter_mut()
+            .find(|s| s.number == number)
+            .ok_or_else(|| MPCAError::InvalidPlanFormat(format!("unknown step {}", number)))
+    }
+
+    /// Replaces the recorded pipeline stages with the configured ones.
+    ///
+    /// Stages that are still configured keep their recorded progress; new
+    /// stages start as pending.
+    ///
+    /// # Arguments
+    ///
+    /// * `names` - Stage names from `pipeline.stages`, in order.
+    pub fn sync_stages(&mut self, names: &[String]) {
+        let previous = std::mem::take(&mut self.stages);
+        self.stages = names
+            .iter()
+            .map(|name| {
+                previous
+                    .iter()
+                    .find(|s| &s.name == name)
+                    .cloned()
+                    .unwrap_or_else(|| StageState::pending(name))
+            })
+            .collect();
+    }
+
+    /// Returns the recorded progress of a pipeline stage.
+    pub fn stage(&self, name: &str) -> Option<&StageState> {
+        self.stages.iter().find(|s| s.name == name)
+    }
+
+    /// Marks a pipeline stage as in progress.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Stage name.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::InvalidConfig` if the stage isn't recorded.
+    pub fn start_stage(&mut self, name: &str) -> Result<()> {
+        let stage = self.stage_mut(name)?;
+        stage.status = StepStatus::InProgress;
+        stage.started_at = Some(Utc::now());
+        stage.finished_at = None;
+        stage.error = None;
+        Ok(())
+    }
+
+    /// Marks a pipeline stage as done.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Stage name.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::InvalidConfig` if the stage isn't recorded.
+    pub fn complete_stage(&mut self, name: &str) -> Result<()> {
+        let stage = self.stage_mut(name)?;
+        stage.status = StepStatus::Done;
+        stage.finished_at = Some(Utc::now());
+        Ok(())
+    }
+
+    /// Marks a pipeline stage as failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Stage name.
+    /// * `error` - Description of the failure.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::InvalidConfig` if the stage isn't recorded.
+    pub fn fail_stage(&mut self, name: &str, error: impl Into<String>) -> Result<()> {
+        let stage = self.stage_mut(name)?;
+        stage.status = StepStatus::Failed;
+        stage.error = Some(error.into());
+        stage.finished_at = Some(Utc::now());
+        Ok(())
+    }
+
+    fn stage_mut(&mut self, name: &str) -> Result<&mut StageState> {
+        self.stages
+            .iter_mut()
+            .find(|s| s.name == name)
+            .ok_or_else(|| MPCAError::InvalidConfig(format!("unknown pipeline stage {}", name)))
+    }
+
+    /// Records the usage of an agent query against the current phase.
+    ///
+    /// Also adds the query's turns and cost to the feature totals.
+    ///
+    /// # Arguments
+    ///
+    /// * `usage` - Usage reported by the agent.
+    pub fn record_usage(&mut self, usage: &Usage) {
+        self.turns += usage.turns;
+        self.cost_usd += usage.cost_usd;
+        *self.usage.entry(self.phase).or_default() += usage;
+    }
+
+    /// Records the usage of an agent query that worked on a plan step.
+    ///
+    /// The usage counts towards the step, the current phase and the
+    /// feature totals.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - Step number.
+    /// * `usage` - Usage reported by the agent.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::InvalidPlanFormat` if the step doesn't exist.
+    pub fn record_step_usage(&mut self, number: u32, usage: &Usage) -> Result<()> {
+        self.step_mut(number)?.usage += usage;
+        self.record_usage(usage);
+        Ok(())
+    }
+
+    /// Records the usage of an agent query that ran a pipeline stage.
+    ///
+    /// The usage counts towards the stage, the current phase and the
+    /// feature totals.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Stage name.
+    /// * `usage` - Usage reported by the agent.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::InvalidConfig` if the stage isn't recorded.
+    pub fn record_stage_usage(&mut self, name: &str, usage: &Usage) -> Result<()> {
+        self.stage_mut(name)?.usage += usage;
+        self.record_usage(usage);
+        Ok(())
+    }
+
+    /// Returns the usage accumulated across all phases.
+    ///
+    /// States written before per-phase accounting only know their turn and
+    /// cost totals, which are returned as-is.
+    pub fn total_usage(&self) -> Usage {
+        if self.usage.is_empty() {
+            return Usage {
+                turns: self.turns,
+                cost_usd: self.cost_usd,
+                ..Default::default()
+            };
+        }
+
+        self.usage.values().sum()
+    }
+
+    /// Loads the state of every feature under the specs directory.
+    ///
+    /// Directories without a `state.toml` are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - MPCA configuration with repository paths.
+    /// * `fs` - File system adapter used to read the files.
+    ///
+    /// # Returns
+    ///
+    /// The feature states, ordered by slug. Empty if `.mpca/specs` doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from [`FeatureState::load`].
+    pub fn load_all(config: &MpcaConfig, fs: &dyn FsAdapter) -> Result<Vec<Self>> {
+        if !fs.is_dir(&config.specs_dir) {
+            return Ok(Vec::new());
+        }
+
+        let mut slugs = fs.list_dir(&config.specs_dir)?;
+        slugs.sort();
+
+        slugs
+            .iter()
+            .map(|slug| Self::path(config, slug))
+            .filter(|path| fs.is_file(path))
+            .map(|path| Self::load(fs, &path))
+            .collect()
+    }
+
+    /// Records the outcome of a verification run.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - The verification result to store.
+    pub fn record_verification(&mut self, result: VerificationResult) {
+        self.verification = Some(result);
+    }
+}
+
+/// Progress of a single implementation step.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepState {
+    /// Step number from `plan.md` (1-based).
+    pub number: u32,
+
+    /// Step title from `plan.md`.
+    pub title: String,
+
+    /// Current status of the step.
+    pub status: StepStatus,
+
+    /// Commit hash produced by the step (if any).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+
+    /// When work on the step last started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+
+    /// When the step finished (done or failed).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+
+    /// Failure description for failed steps.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Agent usage spent on the step, across all attempts.
+    #[serde(default, skip_serializing_if = "Usage::is_empty")]
+    pub usage: Usage,
+}
+
+impl StepState {
+    /// Creates a pending step from a parsed plan step.
+    fn pending(step: &PlanStep) -> Self {
+        Self {
+            number: step.number,
+            title: step.title.clone(),
+            status: StepStatus::Pending,
+            commit: None,
+            started_at: None,
+            finished_at: None,
+            error: None,
+            usage: Usage::default(),
+        }
+    }
+}
+
+/// Progress of a single `mpca run` pipeline stage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageState {
+    /// Stage name from `pipeline.stages`.
+    pub name: String,
+
+    /// Current status of the stage.
+    pub status: StepStatus,
+
+    /// When work on the stage last started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+
+    /// When the stage finished (done or failed).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+
+    /// Failure description for failed stages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Agent usage spent by the stage's own query, across all attempts;
+    /// the execute stage records its usage on the plan steps instead.
+    #[serde(default, skip_serializing_if = "Usage::is_empty")]
+    pub usage: Usage,
+}
+
+impl StageState {
+    /// Creates a pending stage.
+    fn pending(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            status: StepStatus::Pending,
+            started_at: None,
+            finished_at: None,
+            error: None,
+            usage: Usage::default(),
+        }
+    }
+}
+
+/// Status of an implementation step or pipeline stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    /// Not started yet.
+    Pending,
+
+    /// Started but not finished (e.g. interrupted).
+    InProgress,
+
+    /// Finished successfully.
+    Done,
+
+    /// Finished with an error.
+    Failed,
+}
+
+impl StepStatus {
+    /// Returns the string representation of the status.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepStatus::Pending => "pending",
+            StepStatus::InProgress => "in_progress",
+            StepStatus::Done => "done",
+            StepStatus::Failed => "failed",
+        }
+    }
+}
+
+impl fmt::Display for StepStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A single entry in the phase transition audit log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTransition {
+    /// Phase before the transition.
+    pub from: Phase,
+
+    /// Phase after the transition.
+    pub to: Phase,
+
+    /// When the transition happened.
+    pub at: DateTime<Utc>,
+
+    /// Why the transition happened (if given).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Outcome of a verification run stored in [`FeatureState`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerificationResult {
+    /// Overall verification status.
+    pub status: VerificationStatus,
+
+    /// Number of tests that passed.
+    pub tests_passed: usize,
+
+    /// Number of tests that failed.
+    pub tests_failed: usize,
+
+    /// Number of tests that were ignored.
+    pub tests_ignored: usize,
+
+    /// When verification finished.
+    pub verified_at: DateTime<Utc>,
+}
+
+/// Overall verification status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerificationStatus {
+    /// All tests passed.
+    Passed,
+
+    /// One or more tests failed.
+    Failed,
+}
+
 /// Workflow phase enumeration.
 ///
 /// Represents the different phases of an MPCA feature workflow.
-/// Phases are sequential and non-reversible.
+/// Phases normally advance in order; a feature may be sent back from
+/// Verify to Run, or from Run or Verify to Plan (see
+/// [`Phase::allowed_transitions`]).
+///
+/// In `state.toml` phases are stored by variant name (e.g. `phase = "Run"`);
+/// the lowercase form is accepted when reading.
 #[non_exhaustive]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Phase {
     /// Initial setup phase (repository initialization).
+    #[serde(alias = "init")]
     Init,
 
     /// Planning phase (interactive specification and design).
+    #[serde(alias = "plan")]
     Plan,
 
     /// Execution phase (automated implementation).
+    #[serde(alias = "run")]
     Run,
 
     /// Verification phase (testing and validation).
+    #[serde(alias = "verify")]
     Verify,
 }
 
@@ -138,6 +907,38 @@ impl Phase {
             Phase::Verify => "verify",
         }
     }
+
+    /// Returns the phases that can be entered directly from this phase.
+    ///
+    /// The transition table is:
+    ///
+    /// | From   | To                |
+    /// |--------|-------------------|
+    /// | Init   | Plan              |
+    /// | Plan   | Run               |
+    /// | Run    | Verify, Plan      |
+    /// | Verify | Run, Plan         |
+    pub fn allowed_transitions(&self) -> &'static [Phase] {
+        match self {
+            Phase::Init => &[Phase::Plan],
+            Phase::Plan => &[Phase::Run],
+            Phase::Run => &[Phase::Verify, Phase::Plan],
+            Phase::Verify => &[Phase::Run, Phase::Plan],
+        }
+    }
+
+    /// Checks whether moving from this phase to `to` is allowed.
+    ///
+    /// # Arguments
+    ///
+    /// * `to` - Target phase.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the transition table permits the move.
+    pub fn can_transition_to(&self, to: Phase) -> bool {
+        self.allowed_transitions().contains(&to)
+    }
 }
 
 impl fmt::Display for Phase {
@@ -149,7 +950,7 @@ impl fmt::Display for Phase {
 impl FromStr for Phase {
     type Err = String;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         match s {
             "init" => Ok(Phase::Init),
             "plan" => Ok(Phase::Plan),
@@ -163,6 +964,11 @@ impl FromStr for Phase {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tools::fs_mock::MockFsAdapter;
+
+    fn state_path() -> PathBuf {
+        PathBuf::from("/repo/.mpca/specs/test-feature/specs/state.toml")
+    }
 
     #[test]
     fn test_should_create_default_state() {
@@ -248,4 +1054,325 @@ mod tests {
         assert_eq!(format!("{}", Phase::Run), "run");
         assert_eq!(format!("{}", Phase::Verify), "verify");
     }
+
+    #[test]
+    fn test_should_round_trip_feature_state() {
+        let fs = MockFsAdapter::new();
+        let path = state_path();
+
+        let mut state = FeatureState::new("test-feature");
+        state.turns = 3;
+        state.cost_usd = 0.25;
+        state.save(&fs, &path).unwrap();
+
+        let loaded = FeatureState::load(&fs, &path).unwrap();
+        assert_eq!(loaded, state);
+
+        let content = fs.read_to_string(&path).unwrap();
+        assert!(content.contains("phase = \"Plan\""));
+        assert!(content.contains("schema_version = 1"));
+    }
+
+    #[test]
+    fn test_should_accumulate_usage_per_phase_and_step() {
+        let fs = MockFsAdapter::new();
+        let path = state_path();
+        let usage = Usage {
+            turns: 2,
+            input_tokens: 100,
+            output_tokens: 30,
+            cost_usd: 0.5,
+            duration_ms: 1000,
+            ..Default::default()
+        };
+
+        let mut state = FeatureState::new("test-feature");
+        state.record_usage(&usage);
+        state.sync_steps(&[PlanStep {
+            number: 1,
+            title: "Add types".to_string(),
+        }]);
+        state.transition_to(Phase::Run, None).unwrap();
+        state.record_step_usage(1, &usage).unwrap();
+        state.record_step_usage(1, &usage).unwrap();
+        assert!(state.record_step_usage(2, &usage).is_err());
+        state.save(&fs, &path).unwrap();
+
+        let loaded = FeatureState::load(&fs, &path).unwrap();
+        assert_eq!(loaded, state);
+        assert_eq!(loaded.turns, 6);
+        assert_eq!(loaded.usage[&Phase::Plan].turns, 2);
+        assert_eq!(loaded.usage[&Phase::Run].input_tokens, 200);
+        assert_eq!(loaded.steps[0].usage.output_tokens, 60);
+        assert_eq!(loaded.total_usage().duration_ms, 3000);
+
+        let content = fs.read_to_string(&path).unwrap();
+        assert!(content.contains("[usage.Plan]"));
+        assert!(content.contains("[steps.usage]"));
+    }
+
+    #[test]
+    fn test_should_load_all_feature_states() {
+        let fs = MockFsAdapter::new();
+        let config = MpcaConfig::new(PathBuf::from("/repo"));
+        assert!(FeatureState::load_all(&config, &fs).unwrap().is_empty());
+
+        for slug in ["second-feature", "first-feature"] {
+            let path = FeatureState::path(&config, slug);
+            fs.create_dir_all(path.parent().unwrap()).unwrap();
+            FeatureState::new(slug).save(&fs, &path).unwrap();
+        }
+        fs.create_dir_all(&config.specs_dir.join("not-a-feature"))
+            .unwrap();
+
+        let states = FeatureState::load_all(&config, &fs).unwrap();
+        let slugs: Vec<_> = states.iter().map(|s| s.feature_slug.as_str()).collect();
+        assert_eq!(slugs, ["first-feature", "second-feature"]);
+    }
+
+    #[test]
+    fn test_should_report_missing_state() {
+        let fs = MockFsAdapter::new();
+        let result = FeatureState::load(&fs, &state_path());
+        assert!(matches!(result, Err(MPCAError::StateMissing(_))));
+    }
+
+    #[test]
+    fn test_should_report_corrupted_state() {
+        let fs = MockFsAdapter::new();
+        let path = state_path();
+        fs.write(&path, "phase = [not valid").unwrap();
+
+        let result = FeatureState::load(&fs, &path);
+        assert!(matches!(result, Err(MPCAError::CorruptedState(_))));
+    }
+
+    #[test]
+    fn test_should_reject_newer_schema_version() {
+        let fs = MockFsAdapter::new();
+        let path = state_path();
+        fs.write(
+            &path,
+            "schema_version = 99\nfeature_slug = \"test-feature\"\nphase = \"Plan\"\n",
+        )
+        .unwrap();
+
+        let result = FeatureState::load(&fs, &path);
+        assert!(matches!(
+            result,
+            Err(MPCAError::UnsupportedStateVersion(99))
+        ));
+    }
+
+    #[test]
+    fn test_should_load_legacy_state_without_version() {
+        let fs = MockFsAdapter::new();
+        let path = state_path();
+        fs.write(
+            &path,
+            r#"feature_slug = "test-feature"
+phase = "run"
+step = 2
+turns = 5
+cost_usd = 1.5
+created_at = "2024-01-01T00:00:00Z"
+updated_at = "2024-01-01T00:00:00Z"
+verification_status = "passed"
+"#,
+        )
+        .unwrap();
+
+        let state = FeatureState::load(&fs, &path).unwrap();
+        assert_eq!(state.schema_version, STATE_SCHEMA_VERSION);
+        assert_eq!(state.phase, Phase::Run);
+        assert_eq!(state.step, 2);
+        assert_eq!(state.turns, 5);
+        assert_eq!(state.total_usage().turns, 5);
+        assert_eq!(state.total_usage().cost_usd, 1.5);
+    }
+
+    #[test]
+    fn test_should_update_state_in_place() {
+        let fs = MockFsAdapter::new();
+        let path = state_path();
+        FeatureState::new("test-feature").save(&fs, &path).unwrap();
+
+        let updated = FeatureState::update(&fs, &path, |state| {
+            state.phase = Phase::Run;
+            state.record_verification(VerificationResult {
+                status: VerificationStatus::Passed,
+                tests_passed: 4,
+                tests_failed: 0,
+                tests_ignored: 1,
+                verified_at: Utc::now(),
+            });
+            Ok(())
+        })
+        .unwrap();
+
+        let loaded = FeatureState::load(&fs, &path).unwrap();
+        assert_eq!(loaded, updated);
+        assert_eq!(loaded.phase, Phase::Run);
+        assert_eq!(
+            loaded.verification.map(|v| v.status),
+            Some(VerificationStatus::Passed)
+        );
+    }
+
+    #[test]
+    fn test_should_follow_transition_table() {
+        assert!(Phase::Init.can_transition_to(Phase::Plan));
+        assert!(Phase::Plan.can_transition_to(Phase::Run));
+        assert!(Phase::Run.can_transition_to(Phase::Verify));
+        assert!(Phase::Run.can_transition_to(Phase::Plan));
+        assert!(Phase::Verify.can_transition_to(Phase::Run));
+        assert!(Phase::Verify.can_transition_to(Phase::Plan));
+
+        assert!(!Phase::Plan.can_transition_to(Phase::Verify));
+        assert!(!Phase::Init.can_transition_to(Phase::Run));
+        assert!(!Phase::Run.can_transition_to(Phase::Init));
+    }
+
+    #[test]
+    fn test_should_record_transitions() {
+        let fs = MockFsAdapter::new();
+        let path = state_path();
+        let mut state = FeatureState::new("test-feature");
+
+        assert!(state.transition_to(Phase::Run, None).unwrap());
+        assert!(
+            state
+                .transition_to(Phase::Plan, Some("review failed".to_string()))
+                .unwrap()
+        );
+        assert!(!state.transition_to(Phase::Plan, None).unwrap());
+        state.save(&fs, &path).unwrap();
+
+        let loaded = FeatureState::load(&fs, &path).unwrap();
+        assert_eq!(loaded.phase, Phase::Plan);
+        assert_eq!(loaded.transitions.len(), 2);
+        assert_eq!(loaded.transitions[0].from, Phase::Plan);
+        assert_eq!(loaded.transitions[0].to, Phase::Run);
+        assert_eq!(
+            loaded.transitions[1].reason.as_deref(),
+            Some("review failed")
+        );
+    }
+
+    #[test]
+    fn test_should_reject_illegal_transition() {
+        let mut state = FeatureState::new("test-feature");
+
+        let result = state.transition_to(Phase::Verify, None);
+        assert!(matches!(
+            result,
+            Err(MPCAError::InvalidStateTransition(ref from, ref to)) if from == "plan" && to == "verify"
+        ));
+        assert_eq!(state.phase, Phase::Plan);
+        assert!(state.transitions.is_empty());
+    }
+
+    fn plan(titles: &[&str]) -> Vec<PlanStep> {
+        titles
+            .iter()
+            .enumerate()
+            .map(|(i, t)| PlanStep {
+                number: i as u32 + 1,
+                title: t.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_should_track_step_progress() {
+        let fs = MockFsAdapter::new();
+        let path = state_path();
+        let mut state = FeatureState::new("test-feature");
+        state.sync_steps(&plan(&["Add types", "Wire CLI", "Write docs"]));
+
+        state.start_step(1).unwrap();
+        state.complete_step(1, Some("abc123".to_string())).unwrap();
+        state.start_step(2).unwrap();
+        state.fail_step(2, "tests failed").unwrap();
+        state.save(&fs, &path).unwrap();
+
+        let loaded = FeatureState::load(&fs, &path).unwrap();
+        assert_eq!(loaded.step, 2);
+        assert_eq!(loaded.steps[0].status, StepStatus::Done);
+        assert_eq!(loaded.steps[0].commit.as_deref(), Some("abc123"));
+        assert_eq!(loaded.steps[1].status, StepStatus::Failed);
+        assert_eq!(loaded.next_step().unwrap().number, 2);
+        assert_eq!(loaded.completed_steps().count(), 1);
+
+        let content = fs.read_to_string(&path).unwrap();
+        assert!(content.contains("[[steps]]"));
+        assert!(content.contains("status = \"failed\""));
+    }
+
+    #[test]
+    fn test_should_keep_progress_of_unchanged_steps_on_sync() {
+        let mut state = FeatureState::new("test-feature");
+        state.sync_steps(&plan(&["Add types", "Wire CLI"]));
+        state.start_step(1).unwrap();
+        state.complete_step(1, None).unwrap();
+        state.start_step(2).unwrap();
+        state.complete_step(2, None).unwrap();
+
+        // Step 2 was edited and a step 3 added
+        state.sync_steps(&plan(&["Add types", "Wire CLI and TUI", "Write docs"]));
+
+        assert_eq!(state.steps.len(), 3);
+        assert_eq!(state.steps[0].status, StepStatus::Done);
+        assert_eq!(state.steps[1].status, StepStatus::Pending);
+        assert_eq!(state.next_step().unwrap().number, 2);
+    }
+
+    #[test]
+    fn test_should_track_stage_progress_across_syncs() {
+        let fs = MockFsAdapter::new();
+        let path = state_path();
+        let stages = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        let mut state = FeatureState::new("test-feature");
+        state.sync_stages(&stages(&["execute", "docs"]));
+
+        state.start_stage("execute").unwrap();
+        state.complete_stage("execute").unwrap();
+        state.start_stage("docs").unwrap();
+        state
+            .record_stage_usage(
+                "docs",
+                &Usage {
+                    turns: 2,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        state.fail_stage("docs", "gate `make docs` failed").unwrap();
+        state.save(&fs, &path).unwrap();
+
+        let mut loaded = FeatureState::load(&fs, &path).unwrap();
+        assert_eq!(loaded.stage("execute").unwrap().status, StepStatus::Done);
+        assert_eq!(loaded.stage("docs").unwrap().status, StepStatus::Failed);
+        assert_eq!(loaded.stage("docs").unwrap().usage.turns, 2);
+        assert_eq!(loaded.turns, 2);
+
+        // A stage added to the pipeline starts pending; removed ones are dropped
+        loaded.sync_stages(&stages(&["execute", "changelog"]));
+        assert_eq!(loaded.stages.len(), 2);
+        assert_eq!(loaded.stages[0].status, StepStatus::Done);
+        assert_eq!(loaded.stages[1].status, StepStatus::Pending);
+        assert!(matches!(
+            loaded.start_stage("docs"),
+            Err(MPCAError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_should_reject_unknown_step() {
+        let mut state = FeatureState::new("test-feature");
+        assert!(matches!(
+            state.start_step(1),
+            Err(MPCAError::InvalidPlanFormat(_))
+        ));
+    }
 }
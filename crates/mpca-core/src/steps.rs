@@ -0,0 +1,230 @@
+//! Implementation plan step parsing.
+//!
+//! The execute workflow works through `specs/plan.md` one step at a time.
+//! This module extracts the numbered steps from the plan so their progress
+//! can be checkpointed in [`FeatureState`](crate::state::FeatureState).
+//!
+//! Two plan layouts are recognised:
+//!
+//! - Step headings such as `### Step 2: Add cache layer`
+//! - A top-level numbered list such as `2. Add cache layer`
+//!
+//! Headings take precedence when both are present, so nested lists inside
+//! a step's description are not mistaken for steps.
+
+use crate::error::{MPCAError, Result};
+use crate::tools::fs::FsAdapter;
+use std::path::Path;
+
+/// A single numbered step from a plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanStep {
+    /// Step number as written in the plan (1-based).
+    pub number: u32,
+
+    /// Short step title.
+    pub title: String,
+}
+
+/// Parses numbered steps from plan Markdown.
+///
+/// # Arguments
+///
+/// * `content` - Contents of `plan.md`.
+///
+/// # Returns
+///
+/// The steps in document order.
+///
+/// # Errors
+///
+/// Returns `MPCAError::InvalidPlanFormat` if no steps are found or step
+/// numbers are duplicated.
+///
+/// # Examples
+///
+/// ```
+/// use mpca_core::steps::parse_plan_steps;
+///
+/// let steps = parse_plan_steps("# Plan\n\n1. Add types\n2. Wire CLI\n").unwrap();
+/// assert_eq!(steps.len(), 2);
+/// assert_eq!(steps[1].title, "Wire CLI");
+/// ```
+pub fn parse_plan_steps(content: &str) -> Result<Vec<PlanStep>> {
+    let headings: Vec<PlanStep> = content.lines().filter_map(parse_step_heading).collect();
+    let steps = if headings.is_empty() {
+        content.lines().filter_map(parse_list_item).collect()
+    } else {
+        headings
+    };
+
+    if steps.is_empty() {
+        return Err(MPCAError::InvalidPlanFormat(
+            "no numbered steps found in plan.md".to_string(),
+        ));
+    }
+
+    for (i, step) in steps.iter().enumerate() {
+        if steps[..i].iter().any(|s| s.number == step.number) {
+            return Err(MPCAError::InvalidPlanFormat(format!(
+                "duplicate step number {} in plan.md",
+                step.number
+            )));
+        }
+    }
+
+    Ok(steps)
+}
+
+/// Loads and parses the steps of a feature's `plan.md`.
+///
+/// # Arguments
+///
+/// * `fs` - File system adapter.
+/// * `path` - Path to `plan.md`.
+/// * `feature_slug` - Feature the plan belongs to (used in errors).
+///
+/// # Returns
+///
+/// The parsed steps.
+///
+/// # Errors
+///
+/// Returns `MPCAError::PlanNotFound` if the plan doesn't exist, or
+/// `MPCAError::InvalidPlanFormat` if it contains no usable steps.
+pub fn load_plan_steps(
+    fs: &dyn FsAdapter,
+    path: &Path,
+    feature_slug: &str,
+) -> Result<Vec<PlanStep>> {
+    if !fs.exists(path) {
+        return Err(MPCAError::PlanNotFound(feature_slug.to_string()));
+    }
+
+    parse_plan_steps(&fs.read_to_string(path)?)
+}
+
+/// Parses a heading like `## Step 3: Title` or `### Step 3 - Title`.
+fn parse_step_heading(line: &str) -> Option<PlanStep> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start_matches('#');
+    let rest = rest.trim_start().strip_prefix("Step")?.trim_start();
+    let (number, rest) = split_number(rest)?;
+    let title = rest
+        .trim_start_matches([':', '.', ')', '-', ' '])
+        .trim()
+        .to_string();
+
+    Some(PlanStep {
+        number,
+        title: if title.is_empty() {
+            format!("Step {}", number)
+        } else {
+            title
+        },
+    })
+}
+
+/// Parses an unindented list item like `3. Title` or `3) Title`.
+fn parse_list_item(line: &str) -> Option<PlanStep> {
+    if line.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let (number, rest) = split_number(line)?;
+    let rest = rest.strip_prefix(['.', ')'])?;
+    if !rest.starts_with(' ') {
+        return None;
+    }
+
+    let title = rest.trim().trim_matches('*').trim().to_string();
+    if title.is_empty() {
+        return None;
+    }
+
+    Some(PlanStep { number, title })
+}
+
+/// Splits a leading decimal number from the rest of the string.
+fn split_number(s: &str) -> Option<(u32, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if end == 0 {
+        return None;
+    }
+    let number = s[..end].parse().ok()?;
+    Some((number, &s[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::fs_mock::MockFsAdapter;
+
+    #[test]
+    fn test_parse_numbered_list() {
+        let plan = r#"# Plan: add-caching
+
+## Steps
+1. Add cache trait
+2) **Implement LRU cache**
+   1. nested detail, not a step
+3. Wire into CLI
+"#;
+
+        let steps = parse_plan_steps(plan).unwrap();
+        assert_eq!(
+            steps,
+            vec![
+                PlanStep {
+                    number: 1,
+                    title: "Add cache trait".to_string()
+                },
+                PlanStep {
+                    number: 2,
+                    title: "Implement LRU cache".to_string()
+                },
+                PlanStep {
+                    number: 3,
+                    title: "Wire into CLI".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_step_headings_take_precedence() {
+        let plan = r#"# Plan
+
+### Step 1: Observe codebase
+1. Read src/
+2. Read tests/
+
+### Step 2 - Implement
+Details.
+"#;
+
+        let steps = parse_plan_steps(plan).unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].title, "Observe codebase");
+        assert_eq!(steps[1].number, 2);
+        assert_eq!(steps[1].title, "Implement");
+    }
+
+    #[test]
+    fn test_parse_plan_without_steps() {
+        let result = parse_plan_steps("# Plan\n\nTBD\n");
+        assert!(matches!(result, Err(MPCAError::InvalidPlanFormat(_))));
+    }
+
+    #[test]
+    fn test_parse_plan_duplicate_numbers() {
+        let result = parse_plan_steps("1. One\n1. Again\n");
+        assert!(matches!(result, Err(MPCAError::InvalidPlanFormat(_))));
+    }
+
+    #[test]
+    fn test_load_plan_missing() {
+        let fs = MockFsAdapter::new();
+        let result = load_plan_steps(&fs, Path::new("/repo/plan.md"), "feature");
+        assert!(matches!(result, Err(MPCAError::PlanNotFound(_))));
+    }
+}
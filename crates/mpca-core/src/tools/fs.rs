@@ -5,6 +5,7 @@
 
 use crate::error::Result;
 use std::path::Path;
+use std::time::SystemTime;
 
 /// File system adapter trait.
 ///
@@ -30,6 +31,9 @@ pub trait FsAdapter: Send + Sync {
 
     /// Writes a string to a file, creating it if it doesn't exist.
     ///
+    /// Implementations must make the write atomic: readers observe either the
+    /// previous content or the new content, never a truncated file.
+    ///
     /// # Arguments
     ///
     /// * `path` - Path to the file to write.
@@ -46,6 +50,101 @@ pub trait FsAdapter: Send + Sync {
     /// or `MPCAError::Io` for other IO errors.
     fn write(&self, path: &Path, content: &str) -> Result<()>;
 
+    /// Creates a new file with the given content, failing if it already exists.
+    ///
+    /// The existence check and creation happen as a single operation, and the
+    /// file becomes visible with its full content, so this can be used to
+    /// implement advisory lock files.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the file to create.
+    /// * `content` - Content to write to the file.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the file was created, `false` if it already existed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::FileWriteError` if creation fails,
+    /// `MPCAError::PermissionDenied` if lacking write permissions,
+    /// or `MPCAError::Io` for other IO errors.
+    fn create_new(&self, path: &Path, content: &str) -> Result<bool>;
+
+    /// Removes a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the file to remove.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` on success, or an error if the operation fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::PathNotFound` if the file doesn't exist,
+    /// `MPCAError::PermissionDenied` if lacking write permissions,
+    /// or `MPCAError::FileWriteError` for other failures.
+    fn remove_file(&self, path: &Path) -> Result<()>;
+
+    /// Copies a file byte for byte, creating missing parent directories of
+    /// the destination and replacing it if it exists.
+    ///
+    /// Unlike reading and writing the content, this works for files that
+    /// are not valid UTF-8.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - Path of the file to copy.
+    /// * `to` - Path of the copy.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` on success, or an error if the operation fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::PathNotFound` if `from` doesn't exist,
+    /// `MPCAError::PermissionDenied` if lacking write permissions,
+    /// or `MPCAError::FileWriteError` for other failures.
+    fn copy(&self, from: &Path, to: &Path) -> Result<()>;
+
+    /// Renames a file, replacing the destination if it exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - Path of the file to rename.
+    /// * `to` - New path of the file.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` on success, or an error if the operation fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::PathNotFound` if `from` doesn't exist,
+    /// `MPCAError::PermissionDenied` if lacking write permissions,
+    /// or `MPCAError::FileWriteError` for other failures.
+    fn rename(&self, from: &Path, to: &Path) -> Result<()>;
+
+    /// Returns the last modification time of a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the file.
+    ///
+    /// # Returns
+    ///
+    /// The time the file was last written.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::PathNotFound` if the file doesn't exist,
+    /// or `MPCAError::FileReadError` if its metadata cannot be read.
+    fn modified(&self, path: &Path) -> Result<SystemTime>;
+
     /// Lists all entries in a directory.
     ///
     /// # Arguments
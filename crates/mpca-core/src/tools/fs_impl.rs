@@ -5,7 +5,13 @@
 
 use crate::error::{MPCAError, Result};
 use crate::tools::fs::FsAdapter;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+/// Counter that keeps temporary and staging files unique within a process.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
 
 /// Standard file system adapter using `std::fs`.
 ///
@@ -45,15 +51,95 @@ impl FsAdapter for StdFsAdapter {
             self.create_dir_all(parent)?;
         }
 
-        std::fs::write(path, content).map_err(|e| {
-            if e.kind() == std::io::ErrorKind::PermissionDenied {
-                MPCAError::PermissionDenied(path.display().to_string())
+        // Write to a sibling temp file and rename it over the target so an
+        // interrupted write never leaves a truncated file behind.
+        let tmp_path = temp_path_for(path);
+        let result = write_synced(&tmp_path, content).and_then(|()| {
+            std::fs::rename(&tmp_path, path)?;
+            sync_parent_dir(path);
+            Ok(())
+        });
+
+        result.map_err(|e| {
+            let _ = std::fs::remove_file(&tmp_path);
+            map_write_error(path, e)
+        })
+    }
+
+    fn create_new(&self, path: &Path, content: &str) -> Result<bool> {
+        if let Some(parent) = path.parent()
+            && !parent.exists()
+        {
+            self.create_dir_all(parent)?;
+        }
+
+        // Stage the full content in a private file and publish it with a
+        // hard link, which fails if the target exists. Readers never see the
+        // file empty or half-written.
+        let staging = staging_path_for(path);
+        let result = write_synced(&staging, content).and_then(|()| {
+            match std::fs::hard_link(&staging, path) {
+                Ok(()) => {
+                    sync_parent_dir(path);
+                    Ok(true)
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+                Err(e) => Err(e),
+            }
+        });
+        let _ = std::fs::remove_file(&staging);
+
+        result.map_err(|e| map_write_error(path, e))
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        std::fs::remove_file(path).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                MPCAError::PathNotFound(path.to_path_buf())
             } else {
-                MPCAError::FileWriteError(format!("{}: {}", path.display(), e))
+                map_write_error(path, e)
+            }
+        })
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        if let Some(parent) = to.parent()
+            && !parent.exists()
+        {
+            self.create_dir_all(parent)?;
+        }
+
+        std::fs::copy(from, to).map(|_| ()).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound && !from.exists() {
+                MPCAError::PathNotFound(from.to_path_buf())
+            } else {
+                map_write_error(to, e)
+            }
+        })
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        std::fs::rename(from, to).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                MPCAError::PathNotFound(from.to_path_buf())
+            } else {
+                map_write_error(to, e)
             }
         })
     }
 
+    fn modified(&self, path: &Path) -> Result<SystemTime> {
+        std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .map_err(|e| {
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    MPCAError::PathNotFound(path.to_path_buf())
+                } else {
+                    MPCAError::FileReadError(format!("{}: {}", path.display(), e))
+                }
+            })
+    }
+
     fn list_dir(&self, path: &Path) -> Result<Vec<String>> {
         if !path.exists() {
             return Err(MPCAError::PathNotFound(path.to_path_buf()));
@@ -98,6 +184,54 @@ impl FsAdapter for StdFsAdapter {
     }
 }
 
+/// Returns the temporary sibling path used for atomic writes.
+fn temp_path_for(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let n = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{}.tmp-{}-{}", file_name, std::process::id(), n))
+}
+
+/// Returns a unique sibling path used to stage `create_new` content.
+fn staging_path_for(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let n = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{}.new-{}-{}", file_name, std::process::id(), n))
+}
+
+/// Writes content to a file and flushes it to disk.
+fn write_synced(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()
+}
+
+/// Flushes the parent directory so a rename survives a crash (best effort).
+fn sync_parent_dir(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent()
+        && let Ok(dir) = std::fs::File::open(parent)
+    {
+        let _ = dir.sync_all();
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+/// Maps an IO error from a write operation to an `MPCAError`.
+fn map_write_error(path: &Path, e: std::io::Error) -> MPCAError {
+    if e.kind() == std::io::ErrorKind::PermissionDenied {
+        MPCAError::PermissionDenied(path.display().to_string())
+    } else {
+        MPCAError::FileWriteError(format!("{}: {}", path.display(), e))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -192,4 +326,103 @@ mod tests {
         assert!(adapter.exists(&file_path));
         assert!(adapter.is_file(&file_path));
     }
+
+    #[test]
+    fn test_write_replaces_atomically() {
+        let temp_dir = TempDir::new().unwrap();
+        let adapter = StdFsAdapter::new();
+        let file_path = temp_dir.path().join("state.toml");
+
+        adapter.write(&file_path, "old").unwrap();
+        adapter.write(&file_path, "new").unwrap();
+
+        assert_eq!(adapter.read_to_string(&file_path).unwrap(), "new");
+
+        // No temp files are left behind
+        let entries = adapter.list_dir(temp_dir.path()).unwrap();
+        assert_eq!(entries, vec!["state.toml".to_string()]);
+    }
+
+    #[test]
+    fn test_create_new_fails_if_exists() {
+        let temp_dir = TempDir::new().unwrap();
+        let adapter = StdFsAdapter::new();
+        let file_path = temp_dir.path().join("lock");
+
+        assert!(adapter.create_new(&file_path, "first").unwrap());
+        assert!(!adapter.create_new(&file_path, "second").unwrap());
+        assert_eq!(adapter.read_to_string(&file_path).unwrap(), "first");
+    }
+
+    #[test]
+    fn test_concurrent_writes_to_same_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let adapter = StdFsAdapter::new();
+        let file_path = temp_dir.path().join("state.toml");
+        let contents: Vec<String> = (0..8).map(|i| format!("{}", i).repeat(4096)).collect();
+
+        std::thread::scope(|s| {
+            for content in &contents {
+                let (adapter, file_path) = (&adapter, &file_path);
+                s.spawn(move || {
+                    for _ in 0..20 {
+                        adapter.write(file_path, content).unwrap();
+                    }
+                });
+            }
+        });
+
+        // One complete write wins and no temp file is left behind
+        let content = adapter.read_to_string(&file_path).unwrap();
+        assert!(contents.contains(&content));
+        let entries = adapter.list_dir(temp_dir.path()).unwrap();
+        assert_eq!(entries, vec!["state.toml".to_string()]);
+    }
+
+    #[test]
+    fn test_create_new_leaves_no_staging_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let adapter = StdFsAdapter::new();
+        let file_path = temp_dir.path().join("lock");
+
+        assert!(adapter.create_new(&file_path, "first").unwrap());
+        assert!(!adapter.create_new(&file_path, "second").unwrap());
+
+        let entries = adapter.list_dir(temp_dir.path()).unwrap();
+        assert_eq!(entries, vec!["lock".to_string()]);
+    }
+
+    #[test]
+    fn test_rename() {
+        let temp_dir = TempDir::new().unwrap();
+        let adapter = StdFsAdapter::new();
+        let from = temp_dir.path().join("a");
+        let to = temp_dir.path().join("b");
+
+        adapter.write(&from, "content").unwrap();
+        adapter.rename(&from, &to).unwrap();
+
+        assert!(!adapter.exists(&from));
+        assert_eq!(adapter.read_to_string(&to).unwrap(), "content");
+        assert!(matches!(
+            adapter.rename(&from, &to),
+            Err(MPCAError::PathNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_remove_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let adapter = StdFsAdapter::new();
+        let file_path = temp_dir.path().join("file.txt");
+
+        adapter.write(&file_path, "content").unwrap();
+        adapter.remove_file(&file_path).unwrap();
+
+        assert!(!adapter.exists(&file_path));
+        assert!(matches!(
+            adapter.remove_file(&file_path),
+            Err(MPCAError::PathNotFound(_))
+        ));
+    }
 }
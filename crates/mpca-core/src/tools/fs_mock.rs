@@ -9,6 +9,7 @@ use crate::tools::fs::FsAdapter;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
 /// Mock file system adapter for testing.
 ///
@@ -32,6 +33,8 @@ pub struct MockFsAdapter {
     files: Arc<Mutex<HashMap<PathBuf, String>>>,
     /// In-memory directory storage
     dirs: Arc<Mutex<Vec<PathBuf>>>,
+    /// Modification times of files written through the adapter
+    mtimes: Arc<Mutex<HashMap<PathBuf, SystemTime>>>,
 }
 
 impl MockFsAdapter {
@@ -44,6 +47,7 @@ impl MockFsAdapter {
         Self {
             files: Arc::new(Mutex::new(HashMap::new())),
             dirs: Arc::new(Mutex::new(Vec::new())),
+            mtimes: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -69,6 +73,7 @@ impl MockFsAdapter {
         Self {
             files: Arc::new(Mutex::new(files)),
             dirs: Arc::new(Mutex::new(Vec::new())),
+            mtimes: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -90,10 +95,21 @@ impl MockFsAdapter {
         self.dirs.lock().unwrap().clone()
     }
 
+    /// Overrides the modification time reported for a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file.
+    /// * `time` - Modification time to report.
+    pub fn set_modified(&self, path: &Path, time: SystemTime) {
+        self.mtimes.lock().unwrap().insert(path.to_path_buf(), time);
+    }
+
     /// Clears all files and directories from the mock file system.
     pub fn clear(&self) {
         self.files.lock().unwrap().clear();
         self.dirs.lock().unwrap().clear();
+        self.mtimes.lock().unwrap().clear();
     }
 }
 
@@ -120,9 +136,77 @@ impl FsAdapter for MockFsAdapter {
             .lock()
             .unwrap()
             .insert(path.to_path_buf(), content.to_string());
+        self.mtimes
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), SystemTime::now());
         Ok(())
     }
 
+    fn create_new(&self, path: &Path, content: &str) -> Result<bool> {
+        // Hold the files lock across the check and insert so concurrent
+        // callers cannot both succeed
+        let mut files = self.files.lock().unwrap();
+        if files.contains_key(path) {
+            return Ok(false);
+        }
+
+        if let Some(parent) = path.parent() {
+            let mut dirs = self.dirs.lock().unwrap();
+            if !dirs.contains(&parent.to_path_buf()) {
+                dirs.push(parent.to_path_buf());
+            }
+        }
+        files.insert(path.to_path_buf(), content.to_string());
+        self.mtimes
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), SystemTime::now());
+        Ok(true)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| MPCAError::PathNotFound(path.to_path_buf()))
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        let content = self.read_to_string(from)?;
+        self.write(to, &content)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let mut files = self.files.lock().unwrap();
+        let content = files
+            .remove(from)
+            .ok_or_else(|| MPCAError::PathNotFound(from.to_path_buf()))?;
+        files.insert(to.to_path_buf(), content);
+
+        let mut mtimes = self.mtimes.lock().unwrap();
+        let mtime = mtimes.remove(from).unwrap_or_else(SystemTime::now);
+        mtimes.insert(to.to_path_buf(), mtime);
+        Ok(())
+    }
+
+    fn modified(&self, path: &Path) -> Result<SystemTime> {
+        if !self.files.lock().unwrap().contains_key(path) {
+            return Err(MPCAError::PathNotFound(path.to_path_buf()));
+        }
+
+        // Files pre-populated via `with_files` count as just written
+        Ok(self
+            .mtimes
+            .lock()
+            .unwrap()
+            .get(path)
+            .copied()
+            .unwrap_or_else(SystemTime::now))
+    }
+
     fn list_dir(&self, path: &Path) -> Result<Vec<String>> {
         let files = self.files.lock().unwrap();
         let dirs = self.dirs.lock().unwrap();
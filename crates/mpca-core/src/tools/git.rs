@@ -95,6 +95,22 @@ pub trait GitAdapter: Send + Sync {
     /// Returns `MPCAError::GitCommandFailed` if the git command fails.
     fn commit(&self, path: &Path, message: &str) -> Result<()>;
 
+    /// Gets the commit hash that `HEAD` points to.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the repository or worktree.
+    ///
+    /// # Returns
+    ///
+    /// The full commit hash, or an error if the operation fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MPCAError::GitCommandFailed` if the git command fails
+    /// (e.g. the repository has no commits yet).
+    fn head_commit(&self, path: &Path) -> Result<String>;
+
     /// Gets the current git status (list of modified files).
     ///
     /// # Arguments
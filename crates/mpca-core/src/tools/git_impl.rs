@@ -139,6 +139,10 @@ impl GitAdapter for StdGitAdapter {
         Ok(())
     }
 
+    fn head_commit(&self, path: &Path) -> Result<String> {
+        self.run_git(&["rev-parse", "HEAD"], Some(path))
+    }
+
     fn status(&self, path: &Path) -> Result<Vec<String>> {
         let output = self.run_git(&["status", "--porcelain"], Some(path))?;
 
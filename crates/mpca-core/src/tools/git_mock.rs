@@ -36,6 +36,8 @@ pub struct MockGitAdapter {
     branches: Arc<Mutex<HashSet<String>>>,
     /// Whether the repo is "clean" (no uncommitted changes)
     clean: Arc<Mutex<bool>>,
+    /// Messages of commits made through this mock, oldest first
+    commits: Arc<Mutex<Vec<String>>>,
 }
 
 impl MockGitAdapter {
@@ -50,6 +52,7 @@ impl MockGitAdapter {
             worktrees: Arc::new(Mutex::new(HashMap::new())),
             branches: Arc::new(Mutex::new(HashSet::new())),
             clean: Arc::new(Mutex::new(true)),
+            commits: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
@@ -111,11 +114,21 @@ impl MockGitAdapter {
         self.branches.lock().unwrap().clone()
     }
 
+    /// Returns the messages of all commits made through this mock.
+    ///
+    /// # Returns
+    ///
+    /// Commit messages, oldest first.
+    pub fn get_commits(&self) -> Vec<String> {
+        self.commits.lock().unwrap().clone()
+    }
+
     /// Clears all state from the mock.
     pub fn clear(&self) {
         self.repos.lock().unwrap().clear();
         self.worktrees.lock().unwrap().clear();
         self.branches.lock().unwrap().clear();
+        self.commits.lock().unwrap().clear();
         *self.clean.lock().unwrap() = true;
     }
 }
@@ -165,12 +178,21 @@ impl GitAdapter for MockGitAdapter {
         Ok(())
     }
 
-    fn commit(&self, _repo: &Path, _message: &str) -> Result<()> {
-        // In mock, just mark as clean after commit
-        *self.clean.lock().unwrap() = true;
+    fn commit(&self, _repo: &Path, message: &str) -> Result<()> {
+        // Like git, committing a clean tree is a no-op
+        let mut clean = self.clean.lock().unwrap();
+        if !*clean {
+            self.commits.lock().unwrap().push(message.to_string());
+            *clean = true;
+        }
         Ok(())
     }
 
+    fn head_commit(&self, _repo: &Path) -> Result<String> {
+        // Deterministic fake hash derived from the number of commits
+        Ok(format!("{:040x}", self.commits.lock().unwrap().len()))
+    }
+
     fn status(&self, _repo: &Path) -> Result<Vec<String>> {
         // Mock implementation returns empty list for clean repo
         if *self.clean.lock().unwrap() {
@@ -293,6 +315,22 @@ mod tests {
         assert!(!git.has_uncommitted_changes(&repo));
     }
 
+    #[test]
+    fn test_mock_git_commits_advance_head() {
+        let repo = PathBuf::from("/repo");
+        let git = MockGitAdapter::with_repo(repo.clone());
+        let initial = git.head_commit(&repo).unwrap();
+
+        // Nothing to commit: HEAD is unchanged
+        git.commit(&repo, "empty").unwrap();
+        assert_eq!(git.head_commit(&repo).unwrap(), initial);
+
+        git.set_clean(false);
+        git.commit(&repo, "step 1").unwrap();
+        assert_ne!(git.head_commit(&repo).unwrap(), initial);
+        assert_eq!(git.get_commits(), vec!["step 1".to_string()]);
+    }
+
     #[test]
     fn test_mock_git_diff() {
         let repo = PathBuf::from("/repo");
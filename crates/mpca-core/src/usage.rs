@@ -0,0 +1,194 @@
+//! Agent usage accounting.
+//!
+//! Every agent query ends with a result message reporting how many turns it
+//! took, the tokens it consumed, its cost and its duration. This module
+//! defines [`Usage`], which carries those numbers from the agent backend into
+//! [`FeatureState`](crate::state::FeatureState), where they are accumulated
+//! per phase and per plan step.
+
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, AddAssign};
+
+/// Resources consumed by one or more agent queries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    /// Number of agent turns.
+    pub turns: u32,
+
+    /// Input tokens billed at the normal rate.
+    pub input_tokens: u64,
+
+    /// Output tokens.
+    pub output_tokens: u64,
+
+    /// Input tokens written to the prompt cache.
+    pub cache_creation_input_tokens: u64,
+
+    /// Input tokens read from the prompt cache.
+    pub cache_read_input_tokens: u64,
+
+    /// Cost in USD.
+    pub cost_usd: f64,
+
+    /// Wall-clock duration in milliseconds.
+    pub duration_ms: u64,
+}
+
+impl Usage {
+    /// Extracts token counts from an SDK usage object.
+    ///
+    /// Missing or malformed fields count as zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The `usage` object of a result message.
+    ///
+    /// # Returns
+    ///
+    /// A `Usage` with only the token fields set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mpca_core::usage::Usage;
+    ///
+    /// let usage = Usage::from_token_json(&serde_json::json!({
+    ///     "input_tokens": 120,
+    ///     "output_tokens": 45,
+    ///     "cache_read_input_tokens": 3000,
+    /// }));
+    /// assert_eq!(usage.input_tokens, 120);
+    /// assert_eq!(usage.cache_creation_input_tokens, 0);
+    /// ```
+    pub fn from_token_json(value: &serde_json::Value) -> Self {
+        let tokens = |key: &str| value.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+
+        Self {
+            input_tokens: tokens("input_tokens"),
+            output_tokens: tokens("output_tokens"),
+            cache_creation_input_tokens: tokens("cache_creation_input_tokens"),
+            cache_read_input_tokens: tokens("cache_read_input_tokens"),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the total number of tokens, including cached input.
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens
+            + self.output_tokens
+            + self.cache_creation_input_tokens
+            + self.cache_read_input_tokens
+    }
+
+    /// Returns `true` if nothing has been recorded.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl AddAssign<&Usage> for Usage {
+    fn add_assign(&mut self, other: &Usage) {
+        self.turns += other.turns;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+        self.cost_usd += other.cost_usd;
+        self.duration_ms += other.duration_ms;
+    }
+}
+
+impl Add<&Usage> for Usage {
+    type Output = Usage;
+
+    fn add(mut self, other: &Usage) -> Usage {
+        self += other;
+        self
+    }
+}
+
+impl<'a> std::iter::Sum<&'a Usage> for Usage {
+    fn sum<I: Iterator<Item = &'a Usage>>(iter: I) -> Self {
+        iter.fold(Usage::default(), |total, usage| total + usage)
+    }
+}
+
+impl std::fmt::Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} turns, {} in / {} out tokens ({} cache read, {} cache write), ${:.4}, {:.1}s",
+            self.turns,
+            self.input_tokens,
+            self.output_tokens,
+            self.cache_read_input_tokens,
+            self.cache_creation_input_tokens,
+            self.cost_usd,
+            self.duration_ms as f64 / 1000.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_token_json_ignores_missing_fields() {
+        let usage = Usage::from_token_json(&serde_json::json!({
+            "input_tokens": 10,
+            "output_tokens": "not a number",
+            "cache_creation_input_tokens": 5,
+        }));
+
+        assert_eq!(usage.input_tokens, 10);
+        assert_eq!(usage.output_tokens, 0);
+        assert_eq!(usage.cache_creation_input_tokens, 5);
+        assert_eq!(usage.total_tokens(), 15);
+    }
+
+    #[test]
+    fn test_usage_accumulates() {
+        let a = Usage {
+            turns: 2,
+            input_tokens: 100,
+            cost_usd: 0.5,
+            duration_ms: 1000,
+            ..Default::default()
+        };
+        let b = Usage {
+            turns: 1,
+            output_tokens: 40,
+            cost_usd: 0.25,
+            duration_ms: 500,
+            ..Default::default()
+        };
+
+        let total: Usage = [a, b].iter().sum();
+        assert_eq!(total.turns, 3);
+        assert_eq!(total.input_tokens, 100);
+        assert_eq!(total.output_tokens, 40);
+        assert_eq!(total.cost_usd, 0.75);
+        assert_eq!(total.duration_ms, 1500);
+        assert!(!total.is_empty());
+        assert!(Usage::default().is_empty());
+    }
+
+    #[test]
+    fn test_usage_display() {
+        let usage = Usage {
+            turns: 3,
+            input_tokens: 1200,
+            output_tokens: 300,
+            cost_usd: 0.0123,
+            duration_ms: 4500,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            usage.to_string(),
+            "3 turns, 1200 in / 300 out tokens (0 cache read, 0 cache write), $0.0123, 4.5s"
+        );
+    }
+}
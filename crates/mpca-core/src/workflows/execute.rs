@@ -3,12 +3,22 @@
 //! This module implements the feature execution workflow, which loads
 //! specifications and executes the implementation plan with git worktree support.
 
-use crate::config::MpcaConfig;
+use crate::agent::AgentResponse;
+use crate::budget::Budget;
+use crate::cancel::CancellationToken;
+use crate::config::{MpcaConfig, Stage, WorkflowKind};
 use crate::error::{MPCAError, Result};
+use crate::lock::FeatureLock;
+use crate::prompts;
+use crate::state::{FeatureState, Phase, StepState, StepStatus};
+use crate::steps::{PlanStep, load_plan_steps};
 use crate::tools::fs::FsAdapter;
 use crate::tools::git::GitAdapter;
 use crate::tools::shell::ShellAdapter;
+use crate::workflows::phase::{check_transition, transition};
 use anyhow::Context;
+use async_trait::async_trait;
+use mpca_pm::ExecuteContext;
 use std::path::Path;
 
 /// Executes a feature implementation with the given slug.
@@ -25,6 +35,10 @@ use std::path::Path;
 /// 6. Updates state.toml after each step
 /// 7. Handles interruptions (saves state, allows resume)
 ///
+/// Progress is checkpointed per plan step: the steps in `specs/plan.md`
+/// are recorded in state.toml with their status and the commit each step
+/// produced, and a resumed run continues from the first unfinished step.
+///
 /// # Arguments
 ///
 /// * `config` - MPCA configuration with repository paths
@@ -41,6 +55,10 @@ use std::path::Path;
 ///
 /// Returns:
 /// - `MPCAError::FeatureNotFound` if feature specs don't exist
+/// - `MPCAError::ConfigParseError` if the feature's config.toml is invalid
+/// - `MPCAError::FeatureLocked` if another process is working on the feature
+/// - `MPCAError::InvalidStateTransition` if the feature cannot enter Run
+/// - `MPCAError::PlanNotFound` if the feature has no plan.md
 /// - `MPCAError::WorktreeExists` if worktree already exists
 /// - `MPCAError::GitCommandFailed` if git operations fail
 /// - `MPCAError::AgentError` if Claude agent fails
@@ -54,23 +72,129 @@ use std::path::Path;
 /// use mpca_core::tools::shell_impl::StdShellAdapter;
 /// use std::path::PathBuf;
 ///
-/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
 /// let config = MpcaConfig::new(PathBuf::from("/repo"));
 /// let fs = StdFsAdapter::new();
 /// let git = StdGitAdapter::new();
 /// let shell = StdShellAdapter::new();
 ///
-/// workflows::execute_feature(&config, "add-caching", &fs, &git, &shell)?;
+/// workflows::execute_feature(&config, "add-caching", &fs, &git, &shell).await?;
 /// # Ok(())
 /// # }
 /// ```
+pub async fn execute_feature(
+    config: &MpcaConfig,
+    feature_slug: &str,
+    fs: &dyn FsAdapter,
+    git: &dyn GitAdapter,
+    shell: &dyn ShellAdapter,
+) -> Result<()> {
+    execute_feature_with(
+        config,
+        feature_slug,
+        fs,
+        git,
+        shell,
+        None,
+        &CancellationToken::new(),
+    )
+    .await
+}
+
+/// Runs the agent work of the `mpca run` pipeline.
+///
+/// The execute workflow calls [`StepRunner::run_step`] once per unfinished
+/// plan step of the `execute` stage and [`StepRunner::run_stage`] once for
+/// every other stage, checkpointing state before and after each call.
+#[async_trait]
+pub trait StepRunner: Send {
+    /// Runs a single plan step.
+    ///
+    /// # Arguments
+    ///
+    /// * `stage` - The execute stage, with its template, agent mode and tools.
+    /// * `step` - The step to run.
+    /// * `context` - Prompt context for the step, including resume
+    ///   information, the steps already completed and the stage's gates.
+    /// * `budget` - What the run may still spend.
+    ///
+    /// # Returns
+    ///
+    /// The agent's response; its turns and cost are added to the feature state.
+    ///
+    /// # Errors
+    ///
+    /// `MPCAError::BudgetExceeded` and `MPCAError::Cancelled` leave the step
+    /// in progress so it can be resumed; any other error marks the step as
+    /// failed. All of them stop execution.
+    async fn run_step(
+        &mut self,
+        stage: &Stage,
+        step: &StepState,
+        context: &ExecuteContext,
+        budget: &Budget,
+    ) -> Result<AgentResponse>;
+
+    /// Runs a pipeline stage other than `execute`.
+    ///
+    /// # Arguments
+    ///
+    /// * `stage` - The stage to run.
+    /// * `context` - Prompt context for the stage, including resume
+    ///   information and the plan steps completed.
+    /// * `budget` - What the run may still spend.
+    ///
+    /// # Returns
+    ///
+    /// The agent's response; its turns and cost are added to the feature state.
+    ///
+    /// # Errors
+    ///
+    /// Handled like the errors of [`StepRunner::run_step`], for the stage.
+    async fn run_stage(
+        &mut self,
+        stage: &Stage,
+        context: &ExecuteContext,
+        budget: &Budget,
+    ) -> Result<AgentResponse>;
+}
+
+/// Executes a feature implementation, running the pipeline with `runner`.
+///
+/// Behaves like [`execute_feature`]; when a runner is given, the stages of
+/// `[pipeline]` run in order: the `execute` stage runs every unfinished
+/// plan step, other stages run a single agent query, and each stage then
+/// runs its gate commands in the worktree. Stages already done are skipped
+/// unless an earlier stage ran again. Without a runner the workflow only
+/// prepares the worktree and records the plan steps and stages.
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration with repository paths
+/// * `feature_slug` - Feature identifier (e.g., "add-caching")
+/// * `fs` - File system adapter for file operations
+/// * `git` - Git adapter for repository operations
+/// * `shell` - Shell adapter for executing commands
+/// * `runner` - Optional step runner
+/// * `cancel` - Stops execution between or during steps and stages; the
+///   current step and stage are checkpointed as in progress
+///
+/// # Errors
+///
+/// Returns the same errors as [`execute_feature`], plus:
+/// - `MPCAError::InvalidPlanFormat` if plan.md has no numbered steps
+/// - `MPCAError::Cancelled` if `cancel` was triggered
+/// - `MPCAError::GateFailed` if a stage's gate command fails
+/// - any error returned by the runner for a failed step or stage
 #[tracing::instrument(skip_all, fields(feature_slug = feature_slug))]
-pub fn execute_feature(
+pub async fn execute_feature_with(
     config: &MpcaConfig,
     feature_slug: &str,
     fs: &dyn FsAdapter,
     git: &dyn GitAdapter,
-    _shell: &dyn ShellAdapter,
+    shell: &dyn ShellAdapter,
+    runner: Option<&mut dyn StepRunner>,
+    cancel: &CancellationToken,
 ) -> Result<()> {
     // Verify feature exists
     let feature_dir = config.specs_dir.join(feature_slug);
@@ -87,9 +211,21 @@ pub fn execute_feature(
         )));
     }
 
-    // Check for existing state to support resume
+    // Apply the feature's configuration overrides
+    let config = &config.for_feature(feature_slug, fs)?;
+
+    // Only one process may work on a feature at a time
+    let _lock = FeatureLock::acquire(config, feature_slug, fs)?;
+
+    // Load persisted state; a feature that already has a worktree (Run, or
+    // sent back from Verify) is resumed
     let state_file = specs_dir.join("state.toml");
-    let resume = fs.exists(&state_file);
+    let state = FeatureState::load(fs, &state_file)?;
+    check_transition(config, &state, Phase::Run, fs)?;
+    let resume = matches!(state.phase, Phase::Run | Phase::Verify);
+
+    // Parse plan steps before touching the worktree so a bad plan fails early
+    let plan_steps = load_plan_steps(fs, &specs_dir.join("plan.md"), feature_slug)?;
 
     if resume {
         tracing::info!(
@@ -117,19 +253,326 @@ pub fn execute_feature(
         create_worktree(config, feature_slug, &branch_name, &worktree_dir, git)?;
     }
 
-    // Update state to execution phase
-    update_state_for_execution(&state_file, fs)?;
+    // Update state to execution phase and record plan steps and stages
+    let mut state = update_state_for_execution(config, &state_file, &plan_steps, fs)?;
 
     tracing::info!(
         feature = feature_slug,
         worktree = %worktree_dir.display(),
         branch = %branch_name,
+        next_step = state.next_step().map(|s| s.number),
         "feature execution initialized"
     );
 
+    if let Some(runner) = runner {
+        let resume = resume || state.completed_steps().next().is_some();
+        run_pipeline(
+            config,
+            &mut state,
+            &state_file,
+            &worktree_dir,
+            resume,
+            fs,
+            git,
+            shell,
+            runner,
+            cancel,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Runs the pipeline stages in order, checkpointing state around each one.
+///
+/// A stage already done is skipped unless an earlier stage ran in this
+/// invocation, since that stage's changes have not been through it yet.
+/// The execute stage also runs while plan steps are unfinished.
+#[allow(clippy::too_many_arguments)]
+async fn run_pipeline(
+    config: &MpcaConfig,
+    state: &mut FeatureState,
+    state_file: &Path,
+    worktree_dir: &Path,
+    resume: bool,
+    fs: &dyn FsAdapter,
+    git: &dyn GitAdapter,
+    shell: &dyn ShellAdapter,
+    runner: &mut dyn StepRunner,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    // One budget covers every stage of the run
+    let mut budget = Budget::new(&config.budget, WorkflowKind::Execute, state.total_usage());
+    let mut ran = false;
+
+    for stage in config.pipeline_stages() {
+        let recorded = state.stage(&stage.name).map(|s| s.status);
+        let unfinished_steps = stage.is_execute() && state.next_step().is_some();
+        if recorded == Some(StepStatus::Done) && !unfinished_steps && !ran {
+            tracing::debug!(stage = %stage.name, "pipeline stage already done");
+            continue;
+        }
+
+        if let Err(e) = cancel.check().and_then(|_| budget.check()) {
+            tracing::warn!(stage = %stage.name, error = %e, "stopping before pipeline stage");
+            return Err(e);
+        }
+
+        state.start_stage(&stage.name)?;
+        state.save(fs, state_file)?;
+        tracing::info!(stage = %stage.name, "starting pipeline stage");
+
+        let result = if stage.is_execute() {
+            run_steps(
+                config,
+                state,
+                state_file,
+                worktree_dir,
+                &stage,
+                resume,
+                fs,
+                git,
+                runner,
+                &mut budget,
+                cancel,
+            )
+            .await
+        } else {
+            let resume = recorded.is_some_and(|status| status != StepStatus::Pending);
+            run_agent_stage(
+                config,
+                state,
+                worktree_dir,
+                &stage,
+                resume,
+                fs,
+                git,
+                runner,
+                &mut budget,
+            )
+            .await
+        };
+
+        match result.and_then(|()| run_gates(&stage, worktree_dir, shell)) {
+            Ok(()) => {
+                state.complete_stage(&stage.name)?;
+                state.save(fs, state_file)?;
+                tracing::info!(stage = %stage.name, "pipeline stage done");
+            }
+            Err(e @ (MPCAError::BudgetExceeded { .. } | MPCAError::Cancelled)) => {
+                // Keep the stage in progress so `mpca resume` picks it up again
+                state.save(fs, state_file)?;
+                return Err(e);
+            }
+            Err(e) => {
+                tracing::warn!(stage = %stage.name, error = %e, "pipeline stage failed");
+                state.fail_stage(&stage.name, e.to_string())?;
+                state.save(fs, state_file)?;
+                return Err(e);
+            }
+        }
+
+        ran = true;
+    }
+
+    Ok(())
+}
+
+/// Runs every unfinished plan step, checkpointing state around each one.
+#[allow(clippy::too_many_arguments)]
+async fn run_steps(
+    config: &MpcaConfig,
+    state: &mut FeatureState,
+    state_file: &Path,
+    worktree_dir: &Path,
+    stage: &Stage,
+    mut resume: bool,
+    fs: &dyn FsAdapter,
+    git: &dyn GitAdapter,
+    runner: &mut dyn StepRunner,
+    budget: &mut Budget,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    while let Some(step) = state.next_step().cloned() {
+        if let Err(e) = cancel.check().and_then(|_| budget.check()) {
+            tracing::warn!(step = step.number, error = %e, "stopping before plan step");
+            return Err(e);
+        }
+
+        let context = step_prompt_context(config, state, stage, &step, resume, fs)?;
+        let head_before = git.head_commit(worktree_dir).ok();
+
+        state.start_step(step.number)?;
+        state.save(fs, state_file)?;
+        tracing::info!(step = step.number, title = %step.title, "starting plan step");
+
+        let response = match runner.run_step(stage, &step, &context, budget).await {
+            Ok(response) => response,
+            Err(MPCAError::BudgetExceeded { reason, usage }) => {
+                // Keep the step in progress so `mpca resume` picks it up again
+                tracing::warn!(step = step.number, %reason, "budget exhausted during plan step");
+                state.record_step_usage(step.number, &usage)?;
+                state.save(fs, state_file)?;
+                return Err(MPCAError::BudgetExceeded { reason, usage });
+            }
+            Err(MPCAError::Cancelled) => {
+                tracing::warn!(step = step.number, "cancelled during plan step");
+                if config.git.wip_commit {
+                    let message = format!(
+                        "{}: WIP step {} - {} (interrupted)",
+                        state.feature_slug, step.number, step.title
+                    );
+                    // The step stays resumable even if its changes can't be committed
+                    if let Err(e) = git.commit(worktree_dir, &message) {
+                        tracing::warn!(step = step.number, error = %e, "failed to commit work in progress");
+                    }
+                }
+                state.save(fs, state_file)?;
+                return Err(MPCAError::Cancelled);
+            }
+            Err(e) => {
+                tracing::warn!(step = step.number, error = %e, "plan step failed");
+                state.fail_step(step.number, e.to_string())?;
+                state.save(fs, state_file)?;
+                return Err(e);
+            }
+        };
+        budget.record(&response.usage);
+        state.record_step_usage(step.number, &response.usage)?;
+
+        if config.git.auto_commit {
+            let message = format!(
+                "{}: step {} - {}",
+                state.feature_slug, step.number, step.title
+            );
+            git.commit(worktree_dir, &message)?;
+        }
+
+        // Record the commit only if the step actually produced one
+        let commit = git
+            .head_commit(worktree_dir)
+            .ok()
+            .filter(|head| Some(head) != head_before.as_ref());
+
+        state.complete_step(step.number, commit)?;
+        state.save(fs, state_file)?;
+        tracing::info!(step = step.number, "plan step done");
+
+        resume = true;
+    }
+
     Ok(())
 }
 
+/// Runs a pipeline stage other than `execute` as a single agent query.
+#[allow(clippy::too_many_arguments)]
+async fn run_agent_stage(
+    config: &MpcaConfig,
+    state: &mut FeatureState,
+    worktree_dir: &Path,
+    stage: &Stage,
+    resume: bool,
+    fs: &dyn FsAdapter,
+    git: &dyn GitAdapter,
+    runner: &mut dyn StepRunner,
+    budget: &mut Budget,
+) -> Result<()> {
+    let context = stage_prompt_context(config, state, stage, resume, fs)?;
+
+    let response = match runner.run_stage(stage, &context, budget).await {
+        Ok(response) => response,
+        Err(MPCAError::BudgetExceeded { reason, usage }) => {
+            tracing::warn!(stage = %stage.name, %reason, "budget exhausted during pipeline stage");
+            state.record_stage_usage(&stage.name, &usage)?;
+            return Err(MPCAError::BudgetExceeded { reason, usage });
+        }
+        Err(MPCAError::Cancelled) => {
+            tracing::warn!(stage = %stage.name, "cancelled during pipeline stage");
+            if config.git.wip_commit {
+                let message = format!(
+                    "{}: WIP stage {} (interrupted)",
+                    state.feature_slug, stage.name
+                );
+                // The stage stays resumable even if its changes can't be committed
+                if let Err(e) = git.commit(worktree_dir, &message) {
+                    tracing::warn!(stage = %stage.name, error = %e, "failed to commit work in progress");
+                }
+            }
+            return Err(MPCAError::Cancelled);
+        }
+        Err(e) => return Err(e),
+    };
+    budget.record(&response.usage);
+    state.record_stage_usage(&stage.name, &response.usage)?;
+
+    if config.git.auto_commit {
+        let message = format!("{}: stage {}", state.feature_slug, stage.name);
+        git.commit(worktree_dir, &message)?;
+    }
+
+    Ok(())
+}
+
+/// Runs a stage's gate commands in the worktree, stopping at the first failure.
+fn run_gates(stage: &Stage, worktree_dir: &Path, shell: &dyn ShellAdapter) -> Result<()> {
+    for command in &stage.gates {
+        tracing::info!(stage = %stage.name, gate = %command, "running stage gate");
+        let output = shell.run(command, Some(worktree_dir))?;
+        if !output.success() {
+            tracing::warn!(
+                stage = %stage.name,
+                gate = %command,
+                exit_code = output.exit_code,
+                stderr = %output.stderr.trim(),
+                "stage gate failed"
+            );
+            return Err(MPCAError::GateFailed {
+                stage: stage.name.clone(),
+                command: command.clone(),
+                exit_code: output.exit_code,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds the prompt context for a pipeline stage.
+fn stage_prompt_context(
+    config: &MpcaConfig,
+    state: &FeatureState,
+    stage: &Stage,
+    resume: bool,
+    fs: &dyn FsAdapter,
+) -> Result<ExecuteContext> {
+    let constraints = stage
+        .gates
+        .iter()
+        .map(|gate| format!("`{}` must succeed after the {} stage", gate, stage.name))
+        .collect();
+    Ok(ExecuteContext {
+        resume,
+        constraints,
+        ..prompts::execute_context(config, state, fs)?
+    })
+}
+
+/// Builds the prompt context for a plan step.
+fn step_prompt_context(
+    config: &MpcaConfig,
+    state: &FeatureState,
+    stage: &Stage,
+    step: &StepState,
+    resume: bool,
+    fs: &dyn FsAdapter,
+) -> Result<ExecuteContext> {
+    Ok(ExecuteContext {
+        current_step: Some(step.number),
+        ..stage_prompt_context(config, state, stage, resume, fs)?
+    })
+}
+
 /// Creates a git worktree for feature development.
 fn create_worktree(
     config: &MpcaConfig,
@@ -156,56 +599,42 @@ fn create_worktree(
     Ok(())
 }
 
-/// Updates state.toml to reflect execution phase.
-fn update_state_for_execution(state_file: &Path, fs: &dyn FsAdapter) -> Result<()> {
-    // Read existing state if it exists
-    let mut state_content = if fs.exists(state_file) {
-        fs.read_to_string(state_file)
-            .context("failed to read state.toml")?
-    } else {
-        String::new()
-    };
-
-    // Update phase to "Run" if not already set
-    if !state_content.contains("phase = \"Run\"") {
-        if state_content.contains("phase = ") {
-            state_content = state_content.replace("phase = \"Plan\"", "phase = \"Run\"");
-        } else {
-            state_content.push_str("phase = \"Run\"\n");
-        }
-    }
-
-    // Update timestamp
-    let timestamp = chrono::Utc::now().to_rfc3339();
-    if state_content.contains("updated_at = ") {
-        // Replace existing timestamp
-        let lines: Vec<&str> = state_content.lines().collect();
-        let mut new_lines = Vec::new();
-        for line in lines {
-            if line.starts_with("updated_at = ") {
-                new_lines.push(format!("updated_at = \"{}\"", timestamp));
-            } else {
-                new_lines.push(line.to_string());
-            }
+/// Updates state.toml to reflect execution phase, the current plan steps,
+/// the configured pipeline stages and the profile in use.
+fn update_state_for_execution(
+    config: &MpcaConfig,
+    state_file: &Path,
+    plan_steps: &[PlanStep],
+    fs: &dyn FsAdapter,
+) -> Result<FeatureState> {
+    let state = FeatureState::update(fs, state_file, |state| {
+        // Work sent back from verification goes through every stage again
+        if state.phase == Phase::Verify {
+            state.stages.clear();
         }
-        state_content = new_lines.join("\n");
-        state_content.push('\n');
-    } else {
-        state_content.push_str(&format!("updated_at = \"{}\"\n", timestamp));
-    }
-
-    fs.write(state_file, &state_content)
-        .context("failed to update state.toml")?;
+        transition(config, state, Phase::Run, None, fs)?;
+        state.sync_steps(plan_steps);
+        state.sync_stages(&config.pipeline.stages);
+        state.profile = config.profile.clone();
+        Ok(())
+    })
+    .context("failed to update state.toml")?;
 
-    Ok(())
+    Ok(state)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::tools::fs_impl::StdFsAdapter;
+    use crate::tools::fs_mock::MockFsAdapter;
     use crate::tools::git_impl::StdGitAdapter;
+    use crate::tools::git_mock::MockGitAdapter;
+    use crate::tools::shell::CommandOutput;
     use crate::tools::shell_impl::StdShellAdapter;
+    use crate::tools::shell_mock::MockShellAdapter;
+    use crate::usage::Usage;
+    use std::path::PathBuf;
     use std::process::Command;
     use tempfile::TempDir;
 
@@ -265,22 +694,24 @@ updated_at = "2024-01-01T00:00:00Z"
         );
         fs.write(&specs_dir.join("state.toml"), &state_content)
             .unwrap();
+        fs.write(&specs_dir.join("plan.md"), "# Plan\n\n1. Step 1\n")
+            .unwrap();
     }
 
-    #[test]
-    fn test_execute_feature_not_found() {
+    #[tokio::test]
+    async fn test_execute_feature_not_found() {
         let temp_dir = TempDir::new().unwrap();
         let config = MpcaConfig::new(temp_dir.path().to_path_buf());
         let fs = StdFsAdapter::new();
         let git = StdGitAdapter::new();
         let shell = StdShellAdapter::new();
 
-        let result = execute_feature(&config, "nonexistent", &fs, &git, &shell);
+        let result = execute_feature(&config, "nonexistent", &fs, &git, &shell).await;
         assert!(matches!(result, Err(MPCAError::FeatureNotFound(_))));
     }
 
-    #[test]
-    fn test_execute_feature_creates_worktree() {
+    #[tokio::test]
+    async fn test_execute_feature_creates_worktree() {
         let temp_dir = TempDir::new().unwrap();
         init_test_repo(temp_dir.path());
 
@@ -292,7 +723,7 @@ updated_at = "2024-01-01T00:00:00Z"
         // Create feature specs
         create_test_feature(&config, "test-feature", &fs);
 
-        let result = execute_feature(&config, "test-feature", &fs, &git, &shell);
+        let result = execute_feature(&config, "test-feature", &fs, &git, &shell).await;
         assert!(result.is_ok());
 
         // Verify worktree was created
@@ -309,8 +740,37 @@ updated_at = "2024-01-01T00:00:00Z"
         assert!(state_content.contains("phase = \"Run\""));
     }
 
-    #[test]
-    fn test_execute_feature_resume() {
+    #[tokio::test]
+    async fn test_execute_feature_applies_feature_config() {
+        let temp_dir = TempDir::new().unwrap();
+        init_test_repo(temp_dir.path());
+
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let fs = StdFsAdapter::new();
+        let git = StdGitAdapter::new();
+        let shell = StdShellAdapter::new();
+
+        create_test_feature(&config, "test-feature", &fs);
+        fs.write(
+            &config.feature_config_file("test-feature"),
+            "[git]\nbranch_naming = \"spike/{feature_slug}\"\n",
+        )
+        .unwrap();
+
+        execute_feature(&config, "test-feature", &fs, &git, &shell)
+            .await
+            .unwrap();
+
+        let output = std::process::Command::new("git")
+            .args(["branch", "--list", "spike/test-feature"])
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap();
+        assert!(String::from_utf8_lossy(&output.stdout).contains("spike/test-feature"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_feature_resume() {
         let temp_dir = TempDir::new().unwrap();
         init_test_repo(temp_dir.path());
 
@@ -321,30 +781,587 @@ updated_at = "2024-01-01T00:00:00Z"
 
         // Create feature and execute once
         create_test_feature(&config, "test-feature", &fs);
-        execute_feature(&config, "test-feature", &fs, &git, &shell).unwrap();
+        execute_feature(&config, "test-feature", &fs, &git, &shell)
+            .await
+            .unwrap();
 
         // Execute again (should resume)
-        let result = execute_feature(&config, "test-feature", &fs, &git, &shell);
+        let result = execute_feature(&config, "test-feature", &fs, &git, &shell).await;
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_execute_feature_locked() {
+        let temp_dir = TempDir::new().unwrap();
+        init_test_repo(temp_dir.path());
+
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let fs = StdFsAdapter::new();
+        let git = StdGitAdapter::new();
+        let shell = StdShellAdapter::new();
+
+        create_test_feature(&config, "test-feature", &fs);
+
+        // Simulate another run holding the feature
+        let lock = FeatureLock::acquire(&config, "test-feature", &fs).unwrap();
+        let result = execute_feature(&config, "test-feature", &fs, &git, &shell).await;
+        assert!(matches!(result, Err(MPCAError::FeatureLocked { .. })));
+
+        drop(lock);
+        assert!(
+            execute_feature(&config, "test-feature", &fs, &git, &shell)
+                .await
+                .is_ok()
+        );
+    }
+
     #[test]
     fn test_update_state_for_execution() {
         let temp_dir = TempDir::new().unwrap();
-        let state_file = temp_dir.path().join("state.toml");
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
         let fs = StdFsAdapter::new();
+        create_test_feature(&config, "test", &fs);
+        let state_file = FeatureState::path(&config, "test");
 
-        let initial_state = r#"feature_slug = "test"
-phase = "Plan"
-step = 0
-"#;
-        fs.write(&state_file, initial_state).unwrap();
-
-        let result = update_state_for_execution(&state_file, &fs);
+        let plan_steps = vec![PlanStep {
+            number: 1,
+            title: "Step 1".to_string(),
+        }];
+        let result = update_state_for_execution(&config, &state_file, &plan_steps, &fs);
         assert!(result.is_ok());
 
         let updated = fs.read_to_string(&state_file).unwrap();
         assert!(updated.contains("phase = \"Run\""));
         assert!(updated.contains("updated_at = "));
     }
+
+    #[tokio::test]
+    async fn test_execute_feature_requires_plan() {
+        let temp_dir = TempDir::new().unwrap();
+        init_test_repo(temp_dir.path());
+
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let fs = StdFsAdapter::new();
+        let git = StdGitAdapter::new();
+        let shell = StdShellAdapter::new();
+
+        create_test_feature(&config, "test-feature", &fs);
+        let specs_dir = config.specs_dir.join("test-feature").join("specs");
+        std::fs::remove_file(specs_dir.join("plan.md")).unwrap();
+
+        let result = execute_feature(&config, "test-feature", &fs, &git, &shell).await;
+        assert!(matches!(result, Err(MPCAError::PlanNotFound(_))));
+        assert!(!fs.exists(&config.trees_dir.join("test-feature")));
+    }
+
+    #[tokio::test]
+    async fn test_execute_feature_after_verify_rollback() {
+        let temp_dir = TempDir::new().unwrap();
+        init_test_repo(temp_dir.path());
+
+        let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+        let fs = StdFsAdapter::new();
+        let git = StdGitAdapter::new();
+        let shell = StdShellAdapter::new();
+
+        create_test_feature(&config, "test-feature", &fs);
+        execute_feature(&config, "test-feature", &fs, &git, &shell)
+            .await
+            .unwrap();
+
+        // Feature was verified, then sent back for more work
+        let state_file = FeatureState::path(&config, "test-feature");
+        FeatureState::update(&fs, &state_file, |state| {
+            state.phase = Phase::Verify;
+            Ok(())
+        })
+        .unwrap();
+
+        execute_feature(&config, "test-feature", &fs, &git, &shell)
+            .await
+            .unwrap();
+
+        let state = FeatureState::load(&fs, &state_file).unwrap();
+        assert_eq!(state.phase, Phase::Run);
+        assert_eq!(state.transitions.last().unwrap().from, Phase::Verify);
+    }
+
+    /// Step runner that records calls and optionally fails one step.
+    #[derive(Default)]
+    struct RecordingRunner {
+        git: Option<MockGitAdapter>,
+        fail_at: Option<u32>,
+        exhaust_at: Option<u32>,
+        cancel_at: Option<(u32, CancellationToken)>,
+        calls: Vec<(u32, ExecuteContext)>,
+        stages: Vec<(String, ExecuteContext)>,
+    }
+
+    #[async_trait]
+    impl StepRunner for RecordingRunner {
+        async fn run_stage(
+            &mut self,
+            stage: &Stage,
+            context: &ExecuteContext,
+            _budget: &Budget,
+        ) -> Result<AgentResponse> {
+            self.stages.push((stage.name.clone(), context.clone()));
+            if let Some(git) = &self.git {
+                git.set_clean(false);
+            }
+            Ok(AgentResponse {
+                usage: Usage {
+                    turns: 1,
+                    cost_usd: 0.1,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+        }
+
+        async fn run_step(
+            &mut self,
+            _stage: &Stage,
+            step: &StepState,
+            context: &ExecuteContext,
+            _budget: &Budget,
+        ) -> Result<AgentResponse> {
+            self.calls.push((step.number, context.clone()));
+            if self.fail_at == Some(step.number) {
+                return Err(MPCAError::AgentError("step failed".to_string()));
+            }
+            // Simulate the agent editing files
+            if let Some(git) = &self.git {
+                git.set_clean(false);
+            }
+            if let Some((number, cancel)) = &self.cancel_at
+                && *number == step.number
+            {
+                // Ctrl-C arrives while the agent works on the step
+                cancel.cancel();
+                return Err(MPCAError::Cancelled);
+            }
+            if self.exhaust_at == Some(step.number) {
+                return Err(MPCAError::BudgetExceeded {
+                    reason: "execute run reached max_cost_usd = 1.00".to_string(),
+                    usage: Usage {
+                        turns: 1,
+                        cost_usd: 0.5,
+                        ..Default::default()
+                    },
+                });
+            }
+            Ok(AgentResponse {
+                usage: Usage {
+                    turns: 2,
+                    output_tokens: 50,
+                    cost_usd: 0.25,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+        }
+    }
+
+    fn mock_feature() -> (MpcaConfig, MockFsAdapter, MockGitAdapter) {
+        let config = MpcaConfig::new(PathBuf::from("/repo"));
+        let fs = MockFsAdapter::new();
+        let git = MockGitAdapter::with_repo(config.repo_root.clone());
+
+        let specs_dir = config.specs_dir.join("test-feature").join("specs");
+        fs.create_dir_all(&specs_dir).unwrap();
+        fs.write(
+            &specs_dir.join("plan.md"),
+            "# Plan\n\n1. Add types\n2. Wire CLI\n3. Write docs\n",
+        )
+        .unwrap();
+        FeatureState::new("test-feature")
+            .save(&fs, &specs_dir.join("state.toml"))
+            .unwrap();
+
+        (config, fs, git)
+    }
+
+    #[tokio::test]
+    async fn test_execute_feature_checkpoints_steps() {
+        let (config, fs, git) = mock_feature();
+        let shell = MockShellAdapter::with_success();
+        let mut runner = RecordingRunner {
+            git: Some(git.clone()),
+            ..Default::default()
+        };
+
+        execute_feature_with(
+            &config,
+            "test-feature",
+            &fs,
+            &git,
+            &shell,
+            Some(&mut runner),
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        let steps: Vec<u32> = runner.calls.iter().map(|(n, _)| *n).collect();
+        assert_eq!(steps, vec![1, 2, 3]);
+        assert!(!runner.calls[0].1.resume);
+        assert_eq!(
+            runner.calls[2].1.completed_steps,
+            vec!["Add types", "Wire CLI"]
+        );
+
+        let state = FeatureState::load(&fs, &FeatureState::path(&config, "test-feature")).unwrap();
+        assert_eq!(state.step, 3);
+        assert!(state.steps.iter().all(|s| s.status == StepStatus::Done));
+        assert!(state.steps.iter().all(|s| s.commit.is_some()));
+        assert_eq!(git.get_commits().len(), 3);
+        assert_eq!(state.turns, 6);
+        assert!((state.cost_usd - 0.75).abs() < 1e-9);
+        assert_eq!(state.usage[&Phase::Run].output_tokens, 150);
+        assert!(state.steps.iter().all(|s| s.usage.turns == 2));
+    }
+
+    #[tokio::test]
+    async fn test_execute_feature_resumes_from_first_unfinished_step() {
+        let (config, fs, git) = mock_feature();
+        let shell = MockShellAdapter::with_success();
+
+        let mut failing = RecordingRunner {
+            fail_at: Some(2),
+            ..Default::default()
+        };
+        let result = execute_feature_with(
+            &config,
+            "test-feature",
+            &fs,
+            &git,
+            &shell,
+            Some(&mut failing),
+            &CancellationToken::new(),
+        )
+        .await;
+        assert!(matches!(result, Err(MPCAError::AgentError(_))));
+
+        let state_file = FeatureState::path(&config, "test-feature");
+        let state = FeatureState::load(&fs, &state_file).unwrap();
+        assert_eq!(state.steps[0].status, StepStatus::Done);
+        assert_eq!(state.steps[0].commit, None);
+        assert_eq!(state.steps[1].status, StepStatus::Failed);
+        assert_eq!(state.steps[2].status, StepStatus::Pending);
+
+        let mut runner = RecordingRunner::default();
+        execute_feature_with(
+            &config,
+            "test-feature",
+            &fs,
+            &git,
+            &shell,
+            Some(&mut runner),
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        let (first, context) = &runner.calls[0];
+        assert_eq!(*first, 2);
+        assert!(context.resume);
+        assert_eq!(context.current_step, Some(2));
+        assert_eq!(context.completed_steps, vec!["Add types"]);
+        assert_eq!(runner.calls.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_execute_feature_stops_when_budget_is_exhausted() {
+        let (mut config, fs, git) = mock_feature();
+        config.budget.execute.max_turns = Some(3);
+        let shell = MockShellAdapter::with_success();
+        let mut runner = RecordingRunner {
+            git: Some(git.clone()),
+            ..Default::default()
+        };
+
+        let result = execute_feature_with(
+            &config,
+            "test-feature",
+            &fs,
+            &git,
+            &shell,
+            Some(&mut runner),
+            &CancellationToken::new(),
+        )
+        .await;
+        assert!(matches!(result, Err(MPCAError::BudgetExceeded { .. })));
+
+        // Each step takes 2 turns, so the cap is reached after the second
+        assert_eq!(runner.calls.len(), 2);
+        let state = FeatureState::load(&fs, &FeatureState::path(&config, "test-feature")).unwrap();
+        assert_eq!(state.steps[1].status, StepStatus::Done);
+        assert_eq!(state.steps[2].status, StepStatus::Pending);
+        assert_eq!(state.turns, 4);
+    }
+
+    #[tokio::test]
+    async fn test_execute_feature_checkpoints_step_interrupted_by_budget() {
+        let (config, fs, git) = mock_feature();
+        let shell = MockShellAdapter::with_success();
+        let mut runner = RecordingRunner {
+            exhaust_at: Some(2),
+            ..Default::default()
+        };
+
+        let result = execute_feature_with(
+            &config,
+            "test-feature",
+            &fs,
+            &git,
+            &shell,
+            Some(&mut runner),
+            &CancellationToken::new(),
+        )
+        .await;
+        assert!(matches!(result, Err(MPCAError::BudgetExceeded { .. })));
+
+        // The interrupted step stays resumable and keeps what it spent
+        let state = FeatureState::load(&fs, &FeatureState::path(&config, "test-feature")).unwrap();
+        assert_eq!(state.steps[0].status, StepStatus::Done);
+        assert_eq!(state.steps[1].status, StepStatus::InProgress);
+        assert_eq!(state.steps[1].usage.turns, 1);
+        assert_eq!(state.next_step().map(|s| s.number), Some(2));
+        assert!((state.cost_usd - 0.75).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_execute_feature_does_not_start_when_cancelled() {
+        let (config, fs, git) = mock_feature();
+        let shell = MockShellAdapter::with_success();
+        let mut runner = RecordingRunner::default();
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let result = execute_feature_with(
+            &config,
+            "test-feature",
+            &fs,
+            &git,
+            &shell,
+            Some(&mut runner),
+            &cancel,
+        )
+        .await;
+        assert!(matches!(result, Err(MPCAError::Cancelled)));
+        assert!(runner.calls.is_empty());
+
+        let state = FeatureState::load(&fs, &FeatureState::path(&config, "test-feature")).unwrap();
+        assert_eq!(state.next_step().map(|s| s.number), Some(1));
+        assert_eq!(state.steps[0].status, StepStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_execute_feature_checkpoints_step_interrupted_by_cancel() {
+        let (config, fs, git) = mock_feature();
+        let shell = MockShellAdapter::with_success();
+        let cancel = CancellationToken::new();
+        let mut runner = RecordingRunner {
+            git: Some(git.clone()),
+            cancel_at: Some((2, cancel.clone())),
+            ..Default::default()
+        };
+
+        let result = execute_feature_with(
+            &config,
+            "test-feature",
+            &fs,
+            &git,
+            &shell,
+            Some(&mut runner),
+            &cancel,
+        )
+        .await;
+        assert!(matches!(result, Err(MPCAError::Cancelled)));
+        assert_eq!(runner.calls.len(), 2);
+
+        // The interrupted step is left for `mpca resume`, not marked failed
+        let state = FeatureState::load(&fs, &FeatureState::path(&config, "test-feature")).unwrap();
+        assert_eq!(state.steps[0].status, StepStatus::Done);
+        assert_eq!(state.steps[1].status, StepStatus::InProgress);
+        assert_eq!(state.steps[1].error, None);
+        assert_eq!(state.next_step().map(|s| s.number), Some(2));
+        assert_eq!(state.steps[1].commit, None);
+
+        // What the agent did so far is committed as work in progress
+        assert_eq!(
+            git.get_commits(),
+            vec![
+                "test-feature: step 1 - Add types".to_string(),
+                "test-feature: WIP step 2 - Wire CLI (interrupted)".to_string(),
+            ]
+        );
+
+        // The feature lock is released, so it can be resumed right away
+        assert!(FeatureLock::acquire(&config, "test-feature", &fs).is_ok());
+    }
+
+    fn with_docs_stage(config: &mut MpcaConfig) {
+        config.pipeline = toml::from_str(
+            r#"
+stages = ["execute", "docs"]
+
+[stage.execute]
+gates = ["cargo test"]
+
+[stage.docs]
+prompt = "Update the docs."
+gates = ["make docs"]
+"#,
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_execute_feature_runs_pipeline_stages_and_gates() {
+        let (mut config, fs, git) = mock_feature();
+        with_docs_stage(&mut config);
+        let shell = MockShellAdapter::with_success();
+        let mut runner = RecordingRunner {
+            git: Some(git.clone()),
+            ..Default::default()
+        };
+
+        execute_feature_with(
+            &config,
+            "test-feature",
+            &fs,
+            &git,
+            &shell,
+            Some(&mut runner),
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(runner.calls.len(), 3);
+        assert_eq!(runner.stages.len(), 1);
+        let (name, context) = &runner.stages[0];
+        assert_eq!(name, "docs");
+        assert!(!context.resume);
+        assert_eq!(context.completed_steps.len(), 3);
+
+        // Gates run in the worktree after their stage
+        let worktree = config.trees_dir.join("test-feature");
+        let history = shell.get_history();
+        let gates: Vec<&str> = history.iter().map(|(cmd, _)| cmd.as_str()).collect();
+        assert_eq!(gates, ["cargo test", "make docs"]);
+        assert!(
+            history
+                .iter()
+                .all(|(_, cwd)| cwd.as_ref() == Some(&worktree))
+        );
+
+        let state = FeatureState::load(&fs, &FeatureState::path(&config, "test-feature")).unwrap();
+        assert!(state.stages.iter().all(|s| s.status == StepStatus::Done));
+        assert_eq!(state.stage("docs").unwrap().usage.turns, 1);
+        assert_eq!(state.turns, 7);
+        assert_eq!(
+            git.get_commits().last().map(String::as_str),
+            Some("test-feature: stage docs")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_feature_resumes_at_stage_with_failed_gate() {
+        let (mut config, fs, git) = mock_feature();
+        with_docs_stage(&mut config);
+        let shell = MockShellAdapter::with_success();
+        shell.set_output(
+            "make docs",
+            CommandOutput {
+                exit_code: 2,
+                stdout: String::new(),
+                stderr: "broken link".to_string(),
+            },
+        );
+
+        let mut runner = RecordingRunner::default();
+        let result = execute_feature_with(
+            &config,
+            "test-feature",
+            &fs,
+            &git,
+            &shell,
+            Some(&mut runner),
+            &CancellationToken::new(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(MPCAError::GateFailed { ref stage, exit_code: 2, .. }) if stage == "docs"
+        ));
+
+        let state_file = FeatureState::path(&config, "test-feature");
+        let state = FeatureState::load(&fs, &state_file).unwrap();
+        assert_eq!(state.stage("execute").unwrap().status, StepStatus::Done);
+        let docs = state.stage("docs").unwrap();
+        assert_eq!(docs.status, StepStatus::Failed);
+        assert!(docs.error.as_deref().unwrap().contains("make docs"));
+
+        // Only the failed stage runs again once its gate passes
+        shell.set_output(
+            "make docs",
+            CommandOutput {
+                exit_code: 0,
+                stdout: String::new(),
+                stderr: String::new(),
+            },
+        );
+        shell.clear_history();
+        let mut runner = RecordingRunner::default();
+        execute_feature_with(
+            &config,
+            "test-feature",
+            &fs,
+            &git,
+            &shell,
+            Some(&mut runner),
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        assert!(runner.calls.is_empty());
+        assert_eq!(runner.stages.len(), 1);
+        assert!(runner.stages[0].1.resume);
+        assert_eq!(shell.get_history().len(), 1);
+        let state = FeatureState::load(&fs, &state_file).unwrap();
+        assert_eq!(state.stage("docs").unwrap().status, StepStatus::Done);
+    }
+
+    #[tokio::test]
+    async fn test_execute_feature_failed_step_fails_execute_stage() {
+        let (mut config, fs, git) = mock_feature();
+        with_docs_stage(&mut config);
+        let shell = MockShellAdapter::with_success();
+        let mut runner = RecordingRunner {
+            fail_at: Some(1),
+            ..Default::default()
+        };
+
+        let result = execute_feature_with(
+            &config,
+            "test-feature",
+            &fs,
+            &git,
+            &shell,
+            Some(&mut runner),
+            &CancellationToken::new(),
+        )
+        .await;
+        assert!(matches!(result, Err(MPCAError::AgentError(_))));
+        assert!(runner.stages.is_empty());
+        assert!(shell.get_history().is_empty());
+
+        let state = FeatureState::load(&fs, &FeatureState::path(&config, "test-feature")).unwrap();
+        assert_eq!(state.stage("execute").unwrap().status, StepStatus::Failed);
+        assert_eq!(state.stage("docs").unwrap().status, StepStatus::Pending);
+    }
 }
@@ -5,6 +5,7 @@
 
 use crate::config::MpcaConfig;
 use crate::error::{MPCAError, Result};
+use crate::migrate;
 use crate::tools::fs::FsAdapter;
 use crate::tools::git::GitAdapter;
 
@@ -15,7 +16,7 @@ use crate::tools::git::GitAdapter;
 /// 2. Checks if already initialized (to prevent double-initialization)
 /// 3. Creates `.mpca/` directory structure
 /// 4. Creates `.trees/` directory for worktrees
-/// 5. Creates default `.mpca/config.toml`
+/// 5. Creates default `.mpca/config.toml` and the `.mpca/version` layout marker
 /// 6. Updates `.gitignore` to exclude `.trees/`
 /// 7. Creates or updates `CLAUDE.md` with links to MPCA directories
 ///
@@ -36,7 +37,11 @@ use crate::tools::git::GitAdapter;
 /// - `MPCAError::AlreadyInitialized` if MPCA is already initialized
 /// - `MPCAError::FileWriteError` if file creation fails
 /// - `MPCAError::PermissionDenied` if lacking write permissions
-pub fn init_project(config: &MpcaConfig, fs: &dyn FsAdapter, git: &dyn GitAdapter) -> Result<()> {
+pub async fn init_project(
+    config: &MpcaConfig,
+    fs: &dyn FsAdapter,
+    git: &dyn GitAdapter,
+) -> Result<()> {
     // Step 1: Verify it's a git repository
     if !git.is_git_repo(&config.repo_root) {
         return Err(MPCAError::NotGitRepository(config.repo_root.clone()));
@@ -55,9 +60,10 @@ pub fn init_project(config: &MpcaConfig, fs: &dyn FsAdapter, git: &dyn GitAdapte
     // Step 4: Create .trees/ directory
     fs.create_dir_all(&config.trees_dir)?;
 
-    // Step 5: Create default config.toml
+    // Step 5: Create default config.toml and record the layout version
     let default_config = generate_default_config();
     fs.write(&config.config_file, &default_config)?;
+    migrate::write_layout_version(config, fs)?;
 
     // Step 6: Update .gitignore to exclude .trees/
     update_gitignore(config, fs)?;
@@ -78,16 +84,36 @@ fn generate_default_config() -> String {
 # This file is automatically generated by `mpca init`.
 # You can customize these settings for your workflow.
 
+# Schema version of this file
+version = 1
+
+# Optional: Directories with prompt template overrides (<name>.j2), highest
+# priority first. Templates they don't define come from .mpca/prompts/
+# (`mpca prompts eject <name>` copies a bundled template there to edit) and
+# then from the bundled ones; `mpca prompts which <name>` shows which is used
+# and `mpca prompts check` lints them all. Override partials/rules.j2 to add
+# rules to every prompt.
+# prompt_dirs = ["../shared-prompts"]
+
 [api]
 # Optional: Override default Claude API endpoint
 # Useful for custom deployments, proxies, or testing environments
 # base_url = "https://api.anthropic.com"
+# Optional: Where to read the API key (one of these; never the key itself).
+# Without one, the Claude CLI uses its own login.
+# api_key_env = "ANTHROPIC_API_KEY"
+# api_key_cmd = "pass show anthropic"
+# api_key_file = "~/.config/anthropic/key"
+# Optional: Extra request headers; credentials must come from the environment
+# headers = { "X-Gateway-Token" = "${GATEWAY_TOKEN}" }
 
 [git]
 # Automatically commit changes during workflows
 auto_commit = true
 # Branch naming pattern (supports {feature_slug} placeholder)
 branch_naming = "feature/{feature_slug}"
+# Commit the worktree as work in progress when a run is interrupted
+wip_commit = true
 
 [review]
 # Enable code review workflow
@@ -97,10 +123,16 @@ reviewers = []
 
 [agent_modes]
 # Agent modes for different workflow phases
-# Options: "standard" or "code" (Claude Code preset)
+# Options: "standard" or "code" (Claude Code preset), or a table such as
+# plan = { preset = "code", model = "claude-3-5-sonnet-20241022" }
+# (`execute` is the workflow behind `mpca run`)
+# Tables may also set permission_mode ("default", "accept_edits", "plan",
+# "bypass_permissions"), append_system_prompt, allowed_tools and
+# disallowed_tools (on top of the tool set), max_turns (default 20),
+# fallback_model and add_dirs (extra directories the agent may access).
 init = "standard"
-plan = "code"
-run = "code"
+plan = { preset = "code", temperature = 0.3 }
+execute = "code"
 verify = "standard"
 review = "code"
 
@@ -109,9 +141,55 @@ review = "code"
 # Options: "minimal", "standard", "full"
 init = "minimal"
 plan = "standard"
-run = "full"
+execute = "full"
 verify = "standard"
 review = "standard"
+
+# Optional: Permissions checked on every tool call of the agent, on top of
+# the tool sets above. Write globs are relative to the agent's working
+# directory (the feature worktree during `mpca run`); by default writes stay
+# inside it and never touch .mpca/config.toml or .git. Denied calls are
+# recorded in the feature's docs/impl_details.md.
+# [tool_policy]
+# denied_tools = ["WebFetch", "WebSearch"]
+# write_deny = [".mpca/config.toml", ".git/**", "**/*.pem"]
+# shell_allow = ["cargo *", "git status*", "git diff*"]
+# shell_deny = ["git push*"]
+
+# Optional: Spending caps. A run that reaches a cap is checkpointed and
+# exits with code 3; `mpca resume` continues once the cap is raised.
+# [budget.feature]       # everything spent on a feature
+# max_cost_usd = 20.0
+# [budget.execute]       # a single `mpca run` (also: init, plan, review, verify)
+# max_turns = 200
+# max_wall_clock = 3600  # seconds
+
+# Optional: Stages of `mpca run`, in order. `execute` implements plan.md
+# step by step; other stages run the prompt template of the same name (or
+# `template`, given the variables of execute.j2) with the execute workflow's
+# agent mode and tools unless they set their own. Gate commands run in the worktree after a stage and must
+# succeed for the run to continue.
+# [pipeline]
+# stages = ["execute", "security-scan", "changelog"]
+# [pipeline.stage.execute]
+# gates = ["cargo test"]
+# [pipeline.stage.security-scan]
+# template = "security"
+# prompt = "Review the changes on this branch for security issues and fix them."
+# agent_mode = "standard"
+# tool_set = "standard"
+# gates = ["cargo audit"]
+
+# Optional: Named profiles overlaying the settings above, selected with
+# `--profile <name>` or MPCA_PROFILE. `mpca resume` reuses the profile a
+# feature was run with.
+# [profiles.draft.agent_modes]
+# plan = { preset = "code", model = "claude-3-5-haiku-20241022" }
+# [profiles.proxy.api]
+# base_url = "https://proxy.example.com"
+
+# Settings for a single feature go in .mpca/specs/<slug>/config.toml, which
+# may override any key above; `mpca status <slug>` shows the result.
 "#
     .to_string()
 }
@@ -268,8 +346,8 @@ mod tests {
             .unwrap();
     }
 
-    #[test]
-    fn test_init_project_success() {
+    #[tokio::test]
+    async fn test_init_project_success() {
         let temp_dir = TempDir::new().unwrap();
         init_test_repo(temp_dir.path());
 
@@ -277,7 +355,7 @@ mod tests {
         let fs = StdFsAdapter::new();
         let git = StdGitAdapter::new();
 
-        let result = init_project(&config, &fs, &git);
+        let result = init_project(&config, &fs, &git).await;
         assert!(result.is_ok());
 
         // Verify directories were created
@@ -297,15 +375,15 @@ mod tests {
         assert!(claude_md_content.contains("## MPCA"));
     }
 
-    #[test]
-    fn test_init_project_not_git_repo() {
+    #[tokio::test]
+    async fn test_init_project_not_git_repo() {
         let temp_dir = TempDir::new().unwrap();
 
         let config = MpcaConfig::new(temp_dir.path().to_path_buf());
         let fs = StdFsAdapter::new();
         let git = StdGitAdapter::new();
 
-        let result = init_project(&config, &fs, &git);
+        let result = init_project(&config, &fs, &git).await;
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -313,8 +391,8 @@ mod tests {
         ));
     }
 
-    #[test]
-    fn test_init_project_already_initialized() {
+    #[tokio::test]
+    async fn test_init_project_already_initialized() {
         let temp_dir = TempDir::new().unwrap();
         init_test_repo(temp_dir.path());
 
@@ -323,10 +401,10 @@ mod tests {
         let git = StdGitAdapter::new();
 
         // Initialize once
-        init_project(&config, &fs, &git).unwrap();
+        init_project(&config, &fs, &git).await.unwrap();
 
         // Try to initialize again
-        let result = init_project(&config, &fs, &git);
+        let result = init_project(&config, &fs, &git).await;
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), MPCAError::AlreadyInitialized));
     }
@@ -341,6 +419,22 @@ mod tests {
         assert!(config.contains("[tool_sets]"));
     }
 
+    #[test]
+    fn test_default_config_matches_defaults() {
+        let loaded = MpcaConfig::from_toml(&generate_default_config()).unwrap();
+        let defaults = MpcaConfig::default();
+
+        assert_eq!(loaded.version, crate::config::CONFIG_VERSION);
+        assert_eq!(loaded.git.branch_naming, defaults.git.branch_naming);
+        assert_eq!(loaded.agent_modes.init, defaults.agent_modes.init);
+        assert_eq!(loaded.agent_modes.execute, defaults.agent_modes.execute);
+        assert_eq!(loaded.agent_modes.verify, defaults.agent_modes.verify);
+        assert_eq!(loaded.agent_modes.plan, defaults.agent_modes.plan);
+        assert_eq!(loaded.tool_sets.init, defaults.tool_sets.init);
+        assert_eq!(loaded.tool_sets.execute, defaults.tool_sets.execute);
+        assert_eq!(loaded.tool_sets.review, defaults.tool_sets.review);
+    }
+
     #[test]
     fn test_update_gitignore_new() {
         let temp_dir = TempDir::new().unwrap();
@@ -4,15 +4,18 @@
 //! - `init`: Initialize a repository for MPCA use
 //! - `plan`: Plan a new feature
 //! - `execute`: Execute a feature plan
+//! - `phase`: Move a feature between phases
 //! - `verify`: Verify implementation against acceptance criteria
 
 pub mod execute;
 pub mod init;
+pub mod phase;
 pub mod plan;
 pub mod verify;
 
 // Re-export workflow functions
-pub use execute::execute_feature;
+pub use execute::{StepRunner, execute_feature, execute_feature_with};
 pub use init::init_project;
-pub use plan::plan_feature;
+pub use phase::set_phase;
+pub use plan::{plan_feature, plan_feature_with};
 pub use verify::verify_feature;
@@ -0,0 +1,231 @@
+//! Phase transition workflow implementation.
+//!
+//! This module applies the phase transition table from [`Phase`] together
+//! with guards that depend on the feature's files, and implements the
+//! manual `mpca phase <slug> --set <phase>` workflow used to send a
+//! feature back to an earlier phase.
+
+use crate::config::MpcaConfig;
+use crate::error::{MPCAError, Result};
+use crate::lock::FeatureLock;
+use crate::state::{FeatureState, Phase};
+use crate::tools::fs::FsAdapter;
+
+/// Moves a feature to the given phase.
+///
+/// This workflow:
+/// 1. Validates the feature exists
+/// 2. Acquires the feature lock
+/// 3. Checks the transition table and phase guards
+/// 4. Records the transition in state.toml
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration with repository paths
+/// * `feature_slug` - Feature identifier (e.g., "add-caching")
+/// * `to` - Target phase
+/// * `reason` - Optional reason stored in the transition audit log
+/// * `fs` - File system adapter
+///
+/// # Returns
+///
+/// The updated feature state.
+///
+/// # Errors
+///
+/// Returns:
+/// - `MPCAError::FeatureNotFound` if the feature doesn't exist
+/// - `MPCAError::FeatureLocked` if another process is working on the feature
+/// - `MPCAError::InvalidStateTransition` if the move is not allowed
+/// - `MPCAError::PlanNotFound` if entering Run without a plan.md
+/// - `MPCAError::VerificationSpecMissing` if entering Verify without a verify.md
+///
+/// # Examples
+///
+/// ```no_run
+/// use mpca_core::{MpcaConfig, Phase, workflows};
+/// use mpca_core::tools::fs_impl::StdFsAdapter;
+/// use std::path::PathBuf;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let config = MpcaConfig::new(PathBuf::from("/repo"));
+/// let fs = StdFsAdapter::new();
+///
+/// workflows::set_phase(&config, "add-caching", Phase::Run, Some("review failed"), &fs).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[tracing::instrument(skip_all, fields(feature_slug = feature_slug, to = %to))]
+pub async fn set_phase(
+    config: &MpcaConfig,
+    feature_slug: &str,
+    to: Phase,
+    reason: Option<&str>,
+    fs: &dyn FsAdapter,
+) -> Result<FeatureState> {
+    if !fs.exists(&config.specs_dir.join(feature_slug)) {
+        return Err(MPCAError::FeatureNotFound(feature_slug.to_string()));
+    }
+
+    let _lock = FeatureLock::acquire(config, feature_slug, fs)?;
+
+    let state_file = FeatureState::path(config, feature_slug);
+    let state = FeatureState::update(fs, &state_file, |state| {
+        transition(config, state, to, reason.map(str::to_string), fs)?;
+        Ok(())
+    })?;
+
+    tracing::info!(
+        feature = feature_slug,
+        phase = %state.phase,
+        "feature phase set"
+    );
+
+    Ok(state)
+}
+
+/// Checks whether a feature may enter `to` from its current phase.
+///
+/// Applies the transition table and the guards for the target phase:
+/// Run requires `specs/plan.md`, Verify requires `specs/verify.md`.
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration with repository paths
+/// * `state` - Current feature state
+/// * `to` - Target phase
+/// * `fs` - File system adapter
+///
+/// # Errors
+///
+/// Returns `MPCAError::InvalidStateTransition`, `MPCAError::PlanNotFound`
+/// or `MPCAError::VerificationSpecMissing` if the move is not allowed.
+pub(crate) fn check_transition(
+    config: &MpcaConfig,
+    state: &FeatureState,
+    to: Phase,
+    fs: &dyn FsAdapter,
+) -> Result<()> {
+    if state.phase == to {
+        return Ok(());
+    }
+
+    if !state.phase.can_transition_to(to) {
+        return Err(MPCAError::InvalidStateTransition(
+            state.phase.to_string(),
+            to.to_string(),
+        ));
+    }
+
+    let specs_dir = config.specs_dir.join(&state.feature_slug).join("specs");
+    match to {
+        Phase::Run if !fs.exists(&specs_dir.join("plan.md")) => {
+            Err(MPCAError::PlanNotFound(state.feature_slug.clone()))
+        }
+        Phase::Verify if !fs.exists(&specs_dir.join("verify.md")) => Err(
+            MPCAError::VerificationSpecMissing(state.feature_slug.clone()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Checks guards and moves the feature to `to`, recording the transition.
+///
+/// # Returns
+///
+/// `true` if the phase changed, `false` if already in `to`.
+pub(crate) fn transition(
+    config: &MpcaConfig,
+    state: &mut FeatureState,
+    to: Phase,
+    reason: Option<String>,
+    fs: &dyn FsAdapter,
+) -> Result<bool> {
+    check_transition(config, state, to, fs)?;
+    state.transition_to(to, reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::fs_mock::MockFsAdapter;
+    use std::path::PathBuf;
+
+    fn setup(phase: Phase) -> (MpcaConfig, MockFsAdapter) {
+        let config = MpcaConfig::new(PathBuf::from("/repo"));
+        let fs = MockFsAdapter::new();
+        let specs_dir = config.specs_dir.join("test-feature").join("specs");
+        fs.create_dir_all(&specs_dir).unwrap();
+        fs.write(&specs_dir.join("plan.md"), "# Plan").unwrap();
+        fs.write(&specs_dir.join("verify.md"), "# Verify").unwrap();
+
+        let mut state = FeatureState::new("test-feature");
+        state.phase = phase;
+        state
+            .save(&fs, &FeatureState::path(&config, "test-feature"))
+            .unwrap();
+
+        (config, fs)
+    }
+
+    #[tokio::test]
+    async fn test_set_phase_rolls_back_verify_to_run() {
+        let (config, fs) = setup(Phase::Verify);
+
+        let state = set_phase(
+            &config,
+            "test-feature",
+            Phase::Run,
+            Some("review failed"),
+            &fs,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(state.phase, Phase::Run);
+        let last = state.transitions.last().unwrap();
+        assert_eq!(last.from, Phase::Verify);
+        assert_eq!(last.reason.as_deref(), Some("review failed"));
+
+        let loaded = FeatureState::load(&fs, &FeatureState::path(&config, "test-feature")).unwrap();
+        assert_eq!(loaded, state);
+    }
+
+    #[tokio::test]
+    async fn test_set_phase_rejects_illegal_move() {
+        let (config, fs) = setup(Phase::Plan);
+
+        let result = set_phase(&config, "test-feature", Phase::Verify, None, &fs).await;
+        assert!(matches!(
+            result,
+            Err(MPCAError::InvalidStateTransition(_, _))
+        ));
+
+        let loaded = FeatureState::load(&fs, &FeatureState::path(&config, "test-feature")).unwrap();
+        assert_eq!(loaded.phase, Phase::Plan);
+        assert!(loaded.transitions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_requires_plan() {
+        let (config, fs) = setup(Phase::Plan);
+        let plan = config
+            .specs_dir
+            .join("test-feature")
+            .join("specs")
+            .join("plan.md");
+        fs.remove_file(&plan).unwrap();
+
+        let result = set_phase(&config, "test-feature", Phase::Run, None, &fs).await;
+        assert!(matches!(result, Err(MPCAError::PlanNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_set_phase_unknown_feature() {
+        let config = MpcaConfig::new(PathBuf::from("/repo"));
+        let fs = MockFsAdapter::new();
+
+        let result = set_phase(&config, "missing", Phase::Run, None, &fs).await;
+        assert!(matches!(result, Err(MPCAError::FeatureNotFound(_))));
+    }
+}
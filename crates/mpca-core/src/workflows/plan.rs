@@ -3,8 +3,13 @@
 //! This module implements the feature planning workflow, which guides the user
 //! through interactive planning to create comprehensive feature specifications.
 
-use crate::config::MpcaConfig;
+use crate::agent::{AgentResponse, AgentRunner};
+use crate::budget::Budget;
+use crate::config::{MpcaConfig, WorkflowKind};
 use crate::error::{MPCAError, Result};
+use crate::prompts;
+use crate::state::FeatureState;
+use crate::steps::parse_plan_steps;
 use crate::tools::fs::FsAdapter;
 use crate::tools::git::GitAdapter;
 use anyhow::Context;
@@ -18,9 +23,8 @@ use std::path::Path;
 /// 3. Initializes Claude agent with planning mode
 /// 4. Executes interactive planning conversation
 /// 5. Generates and saves specification files:
-///    - README.md (feature overview)
-///    - requirements.md (user requirements)
-///    - design.md (technical design)
+///    - design.md (overview, requirements and technical design)
+///    - plan.md (numbered implementation steps)
 /// 6. Creates state.toml to track progress
 /// 7. Returns summary of created specifications
 ///
@@ -40,6 +44,7 @@ use std::path::Path;
 /// Returns:
 /// - `MPCAError::InvalidFeatureSlug` if slug format is invalid
 /// - `MPCAError::FeatureAlreadyExists` if feature already planned
+/// - `MPCAError::ConfigParseError` if the feature's config.toml is invalid
 /// - `MPCAError::FileWriteError` if cannot create spec files
 /// - `MPCAError::AgentError` if Claude agent fails
 ///
@@ -51,34 +56,105 @@ use std::path::Path;
 /// use mpca_core::tools::git_impl::StdGitAdapter;
 /// use std::path::PathBuf;
 ///
-/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
 /// let config = MpcaConfig::new(PathBuf::from("/repo"));
 /// let fs = StdFsAdapter::new();
 /// let git = StdGitAdapter::new();
 ///
-/// workflows::plan_feature(&config, "add-caching", &fs, &git)?;
+/// workflows::plan_feature(&config, "add-caching", &fs, &git).await?;
 /// # Ok(())
 /// # }
 /// ```
-#[tracing::instrument(skip(fs, git), fields(feature_slug = %feature_slug))]
-pub fn plan_feature(
+pub async fn plan_feature(
     config: &MpcaConfig,
     feature_slug: &str,
     fs: &dyn FsAdapter,
     git: &dyn GitAdapter,
+) -> Result<()> {
+    plan_feature_with(config, feature_slug, fs, git, None).await
+}
+
+/// Plans a new feature, asking the agent for the implementation plan.
+///
+/// Behaves like [`plan_feature`]; when an agent runner is given, the plan
+/// agent is asked for the implementation plan before anything is written,
+/// and its reply becomes `plan.md`. The agent's turns and cost are recorded
+/// in state.toml. If the agent stops at a budget cap, only state.toml is
+/// written with what it spent, and planning the feature again continues
+/// from it. Without a runner a placeholder plan is written.
+///
+/// # Arguments
+///
+/// * `config` - MPCA configuration with repository paths
+/// * `feature_slug` - Feature identifier (e.g., "add-caching")
+/// * `fs` - File system adapter for creating files
+/// * `git` - Git adapter for repository operations
+/// * `agent` - Optional agent runner
+///
+/// # Errors
+///
+/// Returns the same errors as [`plan_feature`], plus
+/// `MPCAError::InvalidPlanFormat` if the agent's plan has no numbered steps.
+#[tracing::instrument(skip(fs, git, agent), fields(feature_slug = %feature_slug))]
+pub async fn plan_feature_with(
+    config: &MpcaConfig,
+    feature_slug: &str,
+    fs: &dyn FsAdapter,
+    git: &dyn GitAdapter,
+    agent: Option<&AgentRunner<'_>>,
 ) -> Result<()> {
     // Validate feature slug format
     validate_feature_slug(feature_slug)?;
 
+    // Apply the feature's own configuration, written before planning
+    let config = &config.for_feature(feature_slug, fs)?;
+
     // Create feature specs directory
     let feature_dir = config.specs_dir.join(feature_slug);
     let specs_dir = feature_dir.join("specs");
     let docs_dir = feature_dir.join("docs");
 
-    // Check if feature already exists
-    if fs.exists(&feature_dir) {
+    // Verify git repository before paying for an agent query
+    if !git.is_git_repo(&config.repo_root) {
+        return Err(MPCAError::NotGitRepository(config.repo_root.clone()));
+    }
+
+    // A plan stopped by its budget leaves only state.toml with what it
+    // spent; planning again picks it up. Anything else means the feature
+    // exists (the directory may hold just config.toml).
+    let state_file = specs_dir.join("state.toml");
+    let interrupted = fs.exists(&state_file) && !fs.exists(&specs_dir.join("plan.md"));
+    if fs.exists(&specs_dir) && !interrupted {
         return Err(MPCAError::FeatureAlreadyExists(feature_slug.to_string()));
     }
+    let mut state = if interrupted {
+        FeatureState::load(fs, &state_file).context("failed to read state.toml")?
+    } else {
+        let mut state = FeatureState::new(feature_slug);
+        state.profile = config.profile.clone();
+        state
+    };
+
+    // Ask the agent for the plan first so a failed query leaves no specs behind
+    let planned = match agent {
+        Some(agent) => match request_plan(config, feature_slug, &state, fs, agent).await {
+            Ok(response) => Some(response),
+            Err(MPCAError::BudgetExceeded { reason, usage }) => {
+                // Keep what the query spent so `mpca cost` accounts for it
+                if !usage.is_empty() {
+                    fs.create_dir_all(&specs_dir)
+                        .context("failed to create specs directory")?;
+                    state.record_usage(&usage);
+                    state
+                        .save(fs, &state_file)
+                        .context("failed to write state.toml")?;
+                }
+                return Err(MPCAError::BudgetExceeded { reason, usage });
+            }
+            Err(e) => return Err(e),
+        },
+        None => None,
+    };
 
     // Create directory structure
     fs.create_dir_all(&specs_dir)
@@ -87,32 +163,19 @@ pub fn plan_feature(
         .context("failed to create docs directory")?;
 
     // Initialize state.toml
-    let state_file = specs_dir.join("state.toml");
-    let initial_state = format!(
-        r#"# MPCA workflow state for feature: {}
-feature_slug = "{}"
-phase = "Plan"
-step = 0
-turns = 0
-cost_usd = 0.0
-created_at = "{}"
-updated_at = "{}"
-"#,
-        feature_slug,
-        feature_slug,
-        chrono::Utc::now().to_rfc3339(),
-        chrono::Utc::now().to_rfc3339()
-    );
-
-    fs.write(&state_file, &initial_state)
+    if let Some(response) = &planned {
+        state.record_usage(&response.usage);
+    }
+    state
+        .save(fs, &state_file)
         .context("failed to write state.toml")?;
 
     // Create placeholder spec files (will be filled by Claude agent)
     create_placeholder_specs(&specs_dir, feature_slug, fs)?;
 
-    // Verify git repository
-    if !git.is_git_repo(&config.repo_root) {
-        return Err(MPCAError::NotGitRepository(config.repo_root.clone()));
+    if let Some(response) = &planned {
+        fs.write(&specs_dir.join("plan.md"), &response.text)
+            .context("failed to write plan.md")?;
     }
 
     tracing::info!(
@@ -124,6 +187,34 @@ updated_at = "{}"
     Ok(())
 }
 
+/// Asks the plan agent for the implementation plan of a feature.
+///
+/// The reply must contain numbered steps so the execute workflow can
+/// checkpoint them.
+async fn request_plan(
+    config: &MpcaConfig,
+    feature_slug: &str,
+    state: &FeatureState,
+    fs: &dyn FsAdapter,
+    agent: &AgentRunner<'_>,
+) -> Result<AgentResponse> {
+    let context = prompts::plan_context(config, feature_slug, fs)?;
+    let prompt = format!(
+        "Write the implementation plan for feature `{feature_slug}`. \
+         Reply with Markdown only: a `# Plan: {feature_slug}` heading followed by \
+         numbered steps, one per line (`1. Step title`)."
+    );
+
+    // Only an interrupted earlier plan has spent anything on the feature
+    let budget = Budget::new(&config.budget, WorkflowKind::Plan, state.total_usage());
+    let response = agent
+        .query(WorkflowKind::Plan, &context, &prompt, &budget)
+        .await?;
+    parse_plan_steps(&response.text)?;
+
+    Ok(response)
+}
+
 /// Validates that a feature slug follows naming conventions.
 ///
 /// Valid slugs:
@@ -172,9 +263,9 @@ fn create_placeholder_specs(
     feature_slug: &str,
     fs: &dyn FsAdapter,
 ) -> Result<()> {
-    // README.md
-    let readme = format!(
-        r#"# Feature: {}
+    // design.md
+    let design = format!(
+        r#"# Design: {}
 
 ## Overview
 This document provides a high-level overview of the feature.
@@ -186,24 +277,13 @@ This document provides a high-level overview of the feature.
 ## Non-Goals
 - Non-goal 1
 
-## Success Criteria
-- [ ] Criterion 1
-- [ ] Criterion 2
-"#,
-        feature_slug
-    );
-    fs.write(&specs_dir.join("README.md"), &readme)
-        .context("failed to write README.md")?;
-
-    // requirements.md
-    let requirements = format!(
-        r#"# Requirements: {}
+## Requirements
 
-## Functional Requirements
+### Functional Requirements
 1. Requirement 1
 2. Requirement 2
 
-## Non-Functional Requirements
+### Non-Functional Requirements
 1. Performance: TBD
 2. Security: TBD
 3. Compatibility: TBD
@@ -211,15 +291,6 @@ This document provides a high-level overview of the feature.
 ## Constraints
 - Constraint 1
 - Constraint 2
-"#,
-        feature_slug
-    );
-    fs.write(&specs_dir.join("requirements.md"), &requirements)
-        .context("failed to write requirements.md")?;
-
-    // design.md
-    let design = format!(
-        r#"# Design: {}
 
 ## Architecture
 Describe the high-level architecture and component interactions.
@@ -243,6 +314,20 @@ Describe testing approach and coverage goals.
     fs.write(&specs_dir.join("design.md"), &design)
         .context("failed to write design.md")?;
 
+    // plan.md
+    let plan = format!(
+        r#"# Plan: {}
+
+## Steps
+1. Step 1
+2. Step 2
+3. Step 3
+"#,
+        feature_slug
+    );
+    fs.write(&specs_dir.join("plan.md"), &plan)
+        .context("failed to write plan.md")?;
+
     // verify.md
     let verify = format!(
         r#"# Verification: {}
@@ -322,8 +407,8 @@ mod tests {
         assert!(validate_feature_slug("add_caching").is_err());
     }
 
-    #[test]
-    fn test_plan_feature_creates_structure() {
+    #[tokio::test]
+    async fn test_plan_feature_creates_structure() {
         let temp_dir = TempDir::new().unwrap();
         init_test_repo(temp_dir.path());
 
@@ -334,7 +419,7 @@ mod tests {
         // Create .mpca/specs directory
         fs.create_dir_all(&config.specs_dir).unwrap();
 
-        let result = plan_feature(&config, "test-feature", &fs, &git);
+        let result = plan_feature(&config, "test-feature", &fs, &git).await;
         assert!(result.is_ok());
 
         // Verify directory structure
@@ -345,14 +430,14 @@ mod tests {
 
         // Verify spec files
         assert!(fs.exists(&feature_dir.join("specs").join("state.toml")));
-        assert!(fs.exists(&feature_dir.join("specs").join("README.md")));
-        assert!(fs.exists(&feature_dir.join("specs").join("requirements.md")));
         assert!(fs.exists(&feature_dir.join("specs").join("design.md")));
+        assert!(fs.exists(&feature_dir.join("specs").join("plan.md")));
         assert!(fs.exists(&feature_dir.join("specs").join("verify.md")));
+        assert!(!fs.exists(&feature_dir.join("specs").join("README.md")));
     }
 
-    #[test]
-    fn test_plan_feature_already_exists() {
+    #[tokio::test]
+    async fn test_plan_feature_already_exists() {
         let temp_dir = TempDir::new().unwrap();
         init_test_repo(temp_dir.path());
 
@@ -363,21 +448,23 @@ mod tests {
         fs.create_dir_all(&config.specs_dir).unwrap();
 
         // Create feature once
-        plan_feature(&config, "test-feature", &fs, &git).unwrap();
+        plan_feature(&config, "test-feature", &fs, &git)
+            .await
+            .unwrap();
 
         // Try to create again
-        let result = plan_feature(&config, "test-feature", &fs, &git);
+        let result = plan_feature(&config, "test-feature", &fs, &git).await;
         assert!(matches!(result, Err(MPCAError::FeatureAlreadyExists(_))));
     }
 
-    #[test]
-    fn test_plan_feature_invalid_slug() {
+    #[tokio::test]
+    async fn test_plan_feature_invalid_slug() {
         let temp_dir = TempDir::new().unwrap();
         let config = MpcaConfig::new(temp_dir.path().to_path_buf());
         let fs = StdFsAdapter::new();
         let git = StdGitAdapter::new();
 
-        let result = plan_feature(&config, "Invalid-Slug", &fs, &git);
+        let result = plan_feature(&config, "Invalid-Slug", &fs, &git).await;
         assert!(matches!(result, Err(MPCAError::InvalidFeatureSlug(_))));
     }
 }
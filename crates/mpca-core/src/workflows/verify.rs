@@ -5,8 +5,11 @@
 
 use crate::config::MpcaConfig;
 use crate::error::{MPCAError, Result};
+use crate::lock::FeatureLock;
+use crate::state::{FeatureState, Phase, VerificationResult, VerificationStatus};
 use crate::tools::fs::FsAdapter;
 use crate::tools::shell::ShellAdapter;
+use crate::workflows::phase::{check_transition, transition};
 use anyhow::Context;
 use std::path::Path;
 
@@ -37,6 +40,9 @@ use std::path::Path;
 /// Returns:
 /// - `MPCAError::FeatureNotFound` if feature specs don't exist
 /// - `MPCAError::VerificationSpecMissing` if verify.md doesn't exist
+/// - `MPCAError::ConfigParseError` if the feature's config.toml is invalid
+/// - `MPCAError::FeatureLocked` if another process is working on the feature
+/// - `MPCAError::InvalidStateTransition` if the feature has not been executed
 /// - `MPCAError::VerificationFailed` if tests fail or criteria not met
 /// - `MPCAError::VerificationTimeout` if tests take too long
 /// - `MPCAError::ShellCommandFailed` if test commands fail
@@ -49,17 +55,17 @@ use std::path::Path;
 /// use mpca_core::tools::shell_impl::StdShellAdapter;
 /// use std::path::PathBuf;
 ///
-/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
 /// let config = MpcaConfig::new(PathBuf::from("/repo"));
 /// let fs = StdFsAdapter::new();
 /// let shell = StdShellAdapter::new();
 ///
-/// workflows::verify_feature(&config, "add-caching", &fs, &shell)?;
+/// workflows::verify_feature(&config, "add-caching", &fs, &shell).await?;
 /// # Ok(())
 /// # }
 /// ```
 #[tracing::instrument(skip_all, fields(feature_slug = feature_slug))]
-pub fn verify_feature(
+pub async fn verify_feature(
     config: &MpcaConfig,
     feature_slug: &str,
     fs: &dyn FsAdapter,
@@ -69,7 +75,7 @@ pub fn verify_feature(
     let feature_dir = config.specs_dir.join(feature_slug);
     let specs_dir = feature_dir.join("specs");
     let verify_spec = specs_dir.join("verify.md");
-    let state_file = feature_dir.join("state.toml");
+    let state_file = FeatureState::path(config, feature_slug);
 
     if !fs.exists(&feature_dir) {
         return Err(MPCAError::FeatureNotFound(feature_slug.to_string()));
@@ -79,6 +85,16 @@ pub fn verify_feature(
         return Err(MPCAError::VerificationSpecMissing(feature_slug.to_string()));
     }
 
+    // Apply the feature's configuration overrides
+    let config = &config.for_feature(feature_slug, fs)?;
+
+    // Only one process may work on a feature at a time
+    let _lock = FeatureLock::acquire(config, feature_slug, fs)?;
+
+    // Verification is only allowed once the feature has been executed
+    let state = FeatureState::load(fs, &state_file)?;
+    check_transition(config, &state, Phase::Verify, fs)?;
+
     tracing::info!(
         feature = feature_slug,
         verify_spec = %verify_spec.display(),
@@ -118,7 +134,7 @@ pub fn verify_feature(
     );
 
     // Update state to reflect verification
-    update_state_for_verification(&state_file, &test_results, fs)?;
+    update_state_for_verification(config, &state_file, &test_results, fs)?;
 
     // Check if verification passed
     if test_results.failed > 0 {
@@ -408,90 +424,29 @@ Exit code: {}
 
 /// Updates state.toml to reflect verification results.
 fn update_state_for_verification(
+    config: &MpcaConfig,
     state_file: &Path,
     test_results: &TestResults,
     fs: &dyn FsAdapter,
 ) -> Result<()> {
-    // Read existing state if it exists
-    let mut state_content = if fs.exists(state_file) {
-        fs.read_to_string(state_file)
-            .context("failed to read state.toml")?
+    let status = if test_results.failed == 0 {
+        VerificationStatus::Passed
     } else {
-        String::new()
+        VerificationStatus::Failed
     };
 
-    // Update phase to "Verify"
-    if state_content.contains("phase = ") {
-        state_content = state_content
-            .lines()
-            .map(|line| {
-                if line.starts_with("phase = ") {
-                    "phase = \"Verify\""
-                } else {
-                    line
-                }
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-        state_content.push('\n');
-    } else {
-        state_content.push_str("phase = \"Verify\"\n");
-    }
-
-    // Add verification status
-    let verification_status = if test_results.failed == 0 {
-        "passed"
-    } else {
-        "failed"
-    };
-
-    if state_content.contains("verification_status = ") {
-        state_content = state_content
-            .lines()
-            .map(|line| {
-                if line.starts_with("verification_status = ") {
-                    format!("verification_status = \"{}\"", verification_status)
-                } else {
-                    line.to_string()
-                }
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-        state_content.push('\n');
-    } else {
-        state_content.push_str(&format!(
-            "verification_status = \"{}\"\n",
-            verification_status
-        ));
-    }
-
-    // Update timestamp
-    let timestamp = chrono::Utc::now().to_rfc3339();
-    if state_content.contains("updated_at = ") {
-        state_content = state_content
-            .lines()
-            .map(|line| {
-                if line.starts_with("updated_at = ") {
-                    format!("updated_at = \"{}\"", timestamp)
-                } else {
-                    line.to_string()
-                }
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-        state_content.push('\n');
-    } else {
-        state_content.push_str(&format!("updated_at = \"{}\"\n", timestamp));
-    }
-
-    // Add test counts
-    state_content.push_str(&format!(
-        "tests_passed = {}\ntests_failed = {}\ntests_ignored = {}\n",
-        test_results.passed, test_results.failed, test_results.ignored
-    ));
-
-    fs.write(state_file, &state_content)
-        .context("failed to update state.toml")?;
+    FeatureState::update(fs, state_file, |state| {
+        transition(config, state, Phase::Verify, None, fs)?;
+        state.record_verification(VerificationResult {
+            status,
+            tests_passed: test_results.passed,
+            tests_failed: test_results.failed,
+            tests_ignored: test_results.ignored,
+            verified_at: chrono::Utc::now(),
+        });
+        Ok(())
+    })
+    .context("failed to update state.toml")?;
 
     Ok(())
 }
@@ -499,6 +454,10 @@ fn update_state_for_verification(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tools::fs_mock::MockFsAdapter;
+    use crate::tools::shell::CommandOutput;
+    use crate::tools::shell_mock::MockShellAdapter;
+    use std::path::PathBuf;
 
     #[test]
     fn test_parse_test_output_success() {
@@ -550,4 +509,59 @@ test result: ok. 12 passed; 0 failed; 3 ignored; 0 measured; 0 filtered out
         assert_eq!(extract_count(line, "ignored"), Some(3));
         assert_eq!(extract_count(line, "measured"), Some(0));
     }
+
+    #[tokio::test]
+    async fn test_verify_feature_requires_run_phase() {
+        let config = MpcaConfig::new(PathBuf::from("/repo"));
+        let fs = MockFsAdapter::new();
+        let shell = MockShellAdapter::with_success();
+
+        let specs_dir = config.specs_dir.join("test-feature").join("specs");
+        fs.create_dir_all(&specs_dir).unwrap();
+        fs.write(&specs_dir.join("verify.md"), "# Verification")
+            .unwrap();
+        FeatureState::new("test-feature")
+            .save(&fs, &FeatureState::path(&config, "test-feature"))
+            .unwrap();
+
+        let result = verify_feature(&config, "test-feature", &fs, &shell).await;
+        assert!(matches!(
+            result,
+            Err(MPCAError::InvalidStateTransition(_, _))
+        ));
+        assert!(shell.get_history().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_verify_feature_records_result_in_feature_state() {
+        let config = MpcaConfig::new(PathBuf::from("/repo"));
+        let fs = MockFsAdapter::new();
+        let shell = MockShellAdapter::new();
+        shell.set_default_output(CommandOutput {
+            exit_code: 0,
+            stdout: "test result: ok. 7 passed; 0 failed; 1 ignored; 0 measured".to_string(),
+            stderr: String::new(),
+        });
+
+        let specs_dir = config.specs_dir.join("test-feature").join("specs");
+        fs.create_dir_all(&specs_dir).unwrap();
+        fs.write(&specs_dir.join("verify.md"), "# Verification")
+            .unwrap();
+        let state_file = FeatureState::path(&config, "test-feature");
+        let mut state = FeatureState::new("test-feature");
+        state.phase = Phase::Run;
+        state.save(&fs, &state_file).unwrap();
+
+        verify_feature(&config, "test-feature", &fs, &shell)
+            .await
+            .unwrap();
+
+        let state = FeatureState::load(&fs, &state_file).unwrap();
+        assert_eq!(state.phase, Phase::Verify);
+        let verification = state.verification.unwrap();
+        assert_eq!(verification.status, VerificationStatus::Passed);
+        assert_eq!(verification.tests_passed, 7);
+        assert_eq!(verification.tests_ignored, 1);
+        assert!(!fs.exists(&config.specs_dir.join("test-feature").join("state.toml")));
+    }
 }
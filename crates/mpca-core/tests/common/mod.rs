@@ -0,0 +1,28 @@
+//! Helpers shared by the workflow integration tests.
+
+// Each test binary compiles this module separately and uses a subset of it.
+#![allow(dead_code)]
+
+use mpca_core::agent::ScriptedAgentBackend;
+use mpca_core::{AgentRuntime, MpcaConfig};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Backend that replays the offline transcript fixture.
+pub fn offline_backend() -> ScriptedAgentBackend {
+    let transcript =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../../fixtures/transcripts/offline.json");
+    ScriptedAgentBackend::from_file(&transcript).unwrap()
+}
+
+/// Runtime driven by the given scripted backend instead of Claude.
+pub fn scripted_runtime(config: MpcaConfig, backend: ScriptedAgentBackend) -> AgentRuntime {
+    AgentRuntime::new(config)
+        .unwrap()
+        .with_agent_backend(Arc::new(backend))
+}
+
+/// Runtime that replays the offline transcript instead of running Claude.
+pub fn offline_runtime(config: MpcaConfig) -> AgentRuntime {
+    scripted_runtime(config, offline_backend())
+}
@@ -2,7 +2,8 @@
 //!
 //! Tests config file parsing, defaults, and loading behavior.
 
-use mpca_core::MpcaConfig;
+use mpca_core::config::{AgentMode, CONFIG_VERSION, PermissionMode, ToolSet};
+use mpca_core::{MPCAError, MpcaConfig};
 use std::fs;
 use tempfile::TempDir;
 
@@ -107,3 +108,182 @@ trees_dir = "/wrong/trees"
     assert_eq!(config.repo_root, temp_dir.path().to_path_buf());
     assert_eq!(config.trees_dir, temp_dir.path().join(".trees"));
 }
+
+#[test]
+fn test_config_shorthand_presets() {
+    let config = MpcaConfig::from_toml(
+        r#"
+[agent_modes]
+init = "code"
+run = "standard"
+plan = { preset = "code", model = "claude-opus-4-20250514" }
+verify = { max_tokens = 1024 }
+
+[tool_sets]
+init = "full"
+run = "minimal"
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(config.agent_modes.init, AgentMode::preset("code").unwrap());
+    assert_eq!(
+        config.agent_modes.execute,
+        AgentMode::preset("standard").unwrap()
+    );
+    assert!(config.agent_modes.plan.use_code_preset);
+    assert_eq!(config.agent_modes.plan.model, "claude-opus-4-20250514");
+    assert!(!config.agent_modes.verify.use_code_preset);
+    assert_eq!(config.agent_modes.verify.max_tokens, 1024);
+    assert_eq!(config.tool_sets.init, ToolSet::Full);
+    assert_eq!(config.tool_sets.execute, ToolSet::Minimal);
+
+    // Unset workflows keep their defaults
+    assert_eq!(config.tool_sets.plan, ToolSet::Standard);
+}
+
+#[test]
+fn test_config_rejects_unknown_preset() {
+    let err = MpcaConfig::from_toml("[agent_modes]\nplan = \"turbo\"\n").unwrap_err();
+    assert!(err.to_string().contains("turbo"));
+
+    let err = MpcaConfig::from_toml("[tool_sets]\nplan = \"everything\"\n").unwrap_err();
+    assert!(err.to_string().contains("everything"));
+}
+
+#[test]
+fn test_config_agent_mode_settings() {
+    let config = MpcaConfig::from_toml(
+        r#"
+[agent_modes]
+execute = { preset = "code", permission_mode = "accept_edits", max_turns = 40, fallback_model = "claude-3-5-haiku-20241022" }
+review = { permission_mode = "plan", append_system_prompt = "Be terse.", disallowed_tools = ["Bash"] }
+verify = { allowed_tools = ["mcp__ci__status"], add_dirs = ["../fixtures"] }
+"#,
+    )
+    .unwrap();
+
+    let execute = &config.agent_modes.execute;
+    assert_eq!(execute.permission_mode, PermissionMode::AcceptEdits);
+    assert_eq!(execute.max_turns, 40);
+    assert_eq!(
+        execute.fallback_model.as_deref(),
+        Some("claude-3-5-haiku-20241022")
+    );
+
+    let review = &config.agent_modes.review;
+    assert!(review.use_code_preset);
+    assert_eq!(review.permission_mode, PermissionMode::Plan);
+    assert_eq!(review.append_system_prompt.as_deref(), Some("Be terse."));
+    assert_eq!(review.disallowed_tools, ["Bash"]);
+    assert_eq!(review.max_turns, AgentMode::DEFAULT_MAX_TURNS);
+
+    let verify = &config.agent_modes.verify;
+    assert_eq!(verify.permission_mode, PermissionMode::Default);
+    assert_eq!(verify.allowed_tools, ["mcp__ci__status"]);
+    assert_eq!(verify.add_dirs, [std::path::PathBuf::from("../fixtures")]);
+    assert!(config.problems().is_empty());
+
+    let config = MpcaConfig::from_toml(
+        "[agent_modes]
+plan = { max_turns = 0, fallback_model = \"claude-3-5-sonnet-20241022\" }
+",
+    )
+    .unwrap();
+    let problems: Vec<String> = config.problems().iter().map(ToString::to_string).collect();
+    assert_eq!(problems.len(), 2);
+    assert!(problems[0].contains("agent_modes.plan.max_turns"));
+    assert!(problems[1].contains("agent_modes.plan.fallback_model must differ"));
+
+    let err = MpcaConfig::from_toml(
+        "[agent_modes]
+plan = { permission_mode = \"yolo\" }
+",
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("yolo"));
+}
+
+#[test]
+fn test_config_rejects_newer_version() {
+    let err = MpcaConfig::from_toml(&format!("version = {}\n", CONFIG_VERSION + 1)).unwrap_err();
+    assert!(err.to_string().contains("newer than the supported version"));
+}
+
+#[test]
+fn test_config_round_trip() {
+    let temp_dir = TempDir::new().unwrap();
+    let mpca_dir = temp_dir.path().join(".mpca");
+    fs::create_dir_all(&mpca_dir).unwrap();
+
+    let mut config = MpcaConfig::new(temp_dir.path().to_path_buf());
+    config.git.auto_commit = false;
+    config.agent_modes.plan.model = "claude-opus-4-20250514".to_string();
+    config.agent_modes.plan.fallback_model = Some("claude-3-5-haiku-20241022".to_string());
+    config.agent_modes.execute.permission_mode = PermissionMode::BypassPermissions;
+    config.tool_sets.review = ToolSet::Minimal;
+    config.api.base_url = Some("https://proxy.example.com".to_string());
+    config.budget.execute.max_turns = Some(50);
+
+    let written = toml::to_string(&config).unwrap();
+    assert!(!written.contains("repo_root"));
+    fs::write(mpca_dir.join("config.toml"), &written).unwrap();
+
+    let loaded = MpcaConfig::load(temp_dir.path().to_path_buf()).unwrap();
+    assert_eq!(toml::to_string(&loaded).unwrap(), written);
+    assert_eq!(loaded.config_file, mpca_dir.join("config.toml"));
+}
+
+#[test]
+fn test_config_rejects_invalid_profile() {
+    let err =
+        MpcaConfig::from_toml("[profiles.cheap.tool_sets]\nplan = \"everything\"\n").unwrap_err();
+    assert!(err.to_string().contains("profile `cheap`"));
+    assert!(err.to_string().contains("everything"));
+
+    let err = MpcaConfig::from_toml("[profiles.cheap.profiles.nested.git]\nauto_commit = false\n")
+        .unwrap_err();
+    assert!(err.to_string().contains("cannot define profiles"));
+}
+
+#[test]
+fn test_config_api_credentials() {
+    let config = MpcaConfig::from_toml(
+        r#"
+[api]
+api_key_cmd = "pass show anthropic"
+headers = { "X-Team" = "platform", "Authorization" = "Bearer ${GATEWAY_TOKEN}" }
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.api.api_key_cmd.as_deref(),
+        Some("pass show anthropic")
+    );
+    assert_eq!(config.api.headers.len(), 2);
+    assert!(config.problems().is_empty());
+
+    // The command is not logged
+    assert!(!format!("{:?}", config).contains("pass show"));
+
+    let err = MpcaConfig::from_toml("[api]\napi_key = \"sk-ant-123\"\n").unwrap_err();
+    assert!(err.to_string().contains("must not be stored"));
+
+    // A plaintext credential header is rejected like api.api_key
+    let err =
+        MpcaConfig::from_toml("[api]\nheaders = { \"X-Api-Key\" = \"sk-ant-123\" }\n").unwrap_err();
+    assert!(matches!(err, MPCAError::ConfigParseError(_)));
+    assert!(err.to_string().contains("api.headers.X-Api-Key"));
+
+    let mut config =
+        MpcaConfig::from_toml("[api]\napi_key_env = \"KEY\"\napi_key_file = \"key.txt\"\n")
+            .unwrap();
+    config
+        .api
+        .headers
+        .insert("X-Api-Key".to_string(), "sk-ant-123".to_string());
+    let problems: Vec<String> = config.problems().iter().map(ToString::to_string).collect();
+    assert_eq!(problems.len(), 2);
+    assert!(problems[0].contains("only one of"));
+    assert!(problems[1].contains("api.headers.X-Api-Key"));
+}
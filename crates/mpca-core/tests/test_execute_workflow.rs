@@ -3,7 +3,10 @@
 //! Tests feature execution including worktree creation, state updates,
 //! and resume capability.
 
-use mpca_core::{AgentRuntime, MpcaConfig};
+mod common;
+
+use common::{offline_backend, offline_runtime, scripted_runtime};
+use mpca_core::MpcaConfig;
 use std::fs;
 use std::process::Command;
 use tempfile::TempDir;
@@ -46,20 +49,20 @@ fn init_test_repo(dir: &std::path::Path) {
         .unwrap();
 }
 
-#[test]
-fn test_execute_workflow_creates_worktree() {
+#[tokio::test]
+async fn test_execute_workflow_creates_worktree() {
     let temp_dir = TempDir::new().unwrap();
     init_test_repo(temp_dir.path());
 
     let config = MpcaConfig::new(temp_dir.path().to_path_buf());
-    let runtime = AgentRuntime::new(config).unwrap();
+    let runtime = offline_runtime(config);
 
     // Initialize and plan
-    runtime.init_project().unwrap();
-    runtime.plan_feature("test-feature").unwrap();
+    runtime.init_project().await.unwrap();
+    runtime.plan_feature("test-feature").await.unwrap();
 
     // Execute
-    let result = runtime.run_feature("test-feature");
+    let result = runtime.run_feature("test-feature").await;
     assert!(result.is_ok(), "Execute failed: {:?}", result.err());
 
     // Verify worktree created
@@ -68,17 +71,17 @@ fn test_execute_workflow_creates_worktree() {
     assert!(worktree_dir.join(".git").exists());
 }
 
-#[test]
-fn test_execute_workflow_updates_state() {
+#[tokio::test]
+async fn test_execute_workflow_updates_state() {
     let temp_dir = TempDir::new().unwrap();
     init_test_repo(temp_dir.path());
 
     let config = MpcaConfig::new(temp_dir.path().to_path_buf());
-    let runtime = AgentRuntime::new(config).unwrap();
+    let runtime = offline_runtime(config);
 
-    runtime.init_project().unwrap();
-    runtime.plan_feature("test-feature").unwrap();
-    runtime.run_feature("test-feature").unwrap();
+    runtime.init_project().await.unwrap();
+    runtime.plan_feature("test-feature").await.unwrap();
+    runtime.run_feature("test-feature").await.unwrap();
 
     // Verify state updated to Run phase
     let state_file = temp_dir
@@ -89,50 +92,50 @@ fn test_execute_workflow_updates_state() {
     assert!(state_content.contains("phase = \"Run\""));
 }
 
-#[test]
-fn test_execute_nonexistent_feature_fails() {
+#[tokio::test]
+async fn test_execute_nonexistent_feature_fails() {
     let temp_dir = TempDir::new().unwrap();
     init_test_repo(temp_dir.path());
 
     let config = MpcaConfig::new(temp_dir.path().to_path_buf());
-    let runtime = AgentRuntime::new(config).unwrap();
+    let runtime = offline_runtime(config);
 
-    runtime.init_project().unwrap();
+    runtime.init_project().await.unwrap();
 
-    let result = runtime.run_feature("nonexistent");
+    let result = runtime.run_feature("nonexistent").await;
     assert!(result.is_err());
 }
 
-#[test]
-fn test_execute_workflow_resume() {
+#[tokio::test]
+async fn test_execute_workflow_resume() {
     let temp_dir = TempDir::new().unwrap();
     init_test_repo(temp_dir.path());
 
     let config = MpcaConfig::new(temp_dir.path().to_path_buf());
-    let runtime = AgentRuntime::new(config).unwrap();
+    let runtime = offline_runtime(config);
 
-    runtime.init_project().unwrap();
-    runtime.plan_feature("test-feature").unwrap();
+    runtime.init_project().await.unwrap();
+    runtime.plan_feature("test-feature").await.unwrap();
 
     // First execution
-    runtime.run_feature("test-feature").unwrap();
+    runtime.run_feature("test-feature").await.unwrap();
 
     // Second execution should resume without error
-    let result = runtime.run_feature("test-feature");
+    let result = runtime.run_feature("test-feature").await;
     assert!(result.is_ok());
 }
 
-#[test]
-fn test_execute_workflow_branch_naming() {
+#[tokio::test]
+async fn test_execute_workflow_branch_naming() {
     let temp_dir = TempDir::new().unwrap();
     init_test_repo(temp_dir.path());
 
     let config = MpcaConfig::new(temp_dir.path().to_path_buf());
-    let runtime = AgentRuntime::new(config).unwrap();
+    let runtime = offline_runtime(config);
 
-    runtime.init_project().unwrap();
-    runtime.plan_feature("my-feature").unwrap();
-    runtime.run_feature("my-feature").unwrap();
+    runtime.init_project().await.unwrap();
+    runtime.plan_feature("my-feature").await.unwrap();
+    runtime.run_feature("my-feature").await.unwrap();
 
     // Check that branch was created with correct name
     let output = Command::new("git")
@@ -145,3 +148,98 @@ fn test_execute_workflow_branch_naming() {
     let branches = String::from_utf8_lossy(&output.stdout);
     assert!(branches.contains("feature/my-feature"));
 }
+
+#[tokio::test]
+async fn test_execute_workflow_runs_configured_pipeline() {
+    let temp_dir = TempDir::new().unwrap();
+    init_test_repo(temp_dir.path());
+
+    let backend = offline_backend();
+    let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+    let runtime = scripted_runtime(config, backend.clone());
+
+    runtime.init_project().await.unwrap();
+    runtime.plan_feature("test-feature").await.unwrap();
+    fs::write(
+        temp_dir.path().join(".mpca/specs/test-feature/config.toml"),
+        r#"
+[pipeline]
+stages = ["execute", "docs"]
+
+[pipeline.stage.docs]
+prompt = "Update the docs for this feature."
+agent_mode = "standard"
+tool_set = "minimal"
+gates = ["test -f README.md"]
+"#,
+    )
+    .unwrap();
+    // Stage templates are rendered with the execute context
+    fs::create_dir_all(temp_dir.path().join(".mpca/prompts")).unwrap();
+    fs::write(
+        temp_dir.path().join(".mpca/prompts/docs.j2"),
+        "Document {{ feature_slug }} on {{ branch }} after {{ completed_steps | length }} steps",
+    )
+    .unwrap();
+    let runtime = scripted_runtime(runtime.config.clone(), backend.clone());
+
+    runtime.run_feature("test-feature").await.unwrap();
+
+    // The docs stage runs after the plan steps, in the worktree
+    let queries = backend.queries();
+    let docs = queries.last().unwrap();
+    assert_eq!(docs.prompt, "Update the docs for this feature.");
+    assert_eq!(
+        docs.system_prompt.as_deref(),
+        Some("Document test-feature on feature/test-feature after 3 steps")
+    );
+    assert_eq!(
+        docs.cwd.as_deref(),
+        Some(temp_dir.path().join(".trees/test-feature").as_path())
+    );
+    assert!(
+        queries[..queries.len() - 1]
+            .iter()
+            .any(|q| q.prompt.starts_with("Implement step"))
+    );
+
+    let state = fs::read_to_string(
+        temp_dir
+            .path()
+            .join(".mpca/specs/test-feature/specs/state.toml"),
+    )
+    .unwrap();
+    assert!(state.contains("[[stages]]\nname = \"docs\"\nstatus = \"done\""));
+}
+
+#[tokio::test]
+async fn test_execute_workflow_records_denied_tool_calls() {
+    let temp_dir = TempDir::new().unwrap();
+    init_test_repo(temp_dir.path());
+
+    let backend = offline_backend();
+    let config = MpcaConfig::new(temp_dir.path().to_path_buf());
+    let runtime = scripted_runtime(config, backend);
+
+    runtime.init_project().await.unwrap();
+    runtime.plan_feature("test-feature").await.unwrap();
+    fs::write(
+        temp_dir.path().join(".mpca/specs/test-feature/config.toml"),
+        "[tool_policy]\nwrite_deny = [\"src/**\"]\n",
+    )
+    .unwrap();
+
+    runtime.run_feature("test-feature").await.unwrap();
+
+    // The scripted step edits src/lib.rs, which the feature's policy denies
+    let details = fs::read_to_string(
+        temp_dir
+            .path()
+            .join(".mpca/specs/test-feature/docs/impl_details.md"),
+    )
+    .unwrap();
+    assert!(details.contains("## Tool Permission Denials"));
+    assert!(details.contains(
+        "`Edit`: writing src/lib.rs is denied by tool_policy.write_deny pattern `src/**`"
+    ));
+}
@@ -46,8 +46,8 @@ fn init_test_repo(dir: &std::path::Path) {
         .unwrap();
 }
 
-#[test]
-fn test_full_init_workflow() {
+#[tokio::test]
+async fn test_full_init_workflow() {
     let temp_dir = TempDir::new().unwrap();
     init_test_repo(temp_dir.path());
 
@@ -55,7 +55,7 @@ fn test_full_init_workflow() {
     let runtime = AgentRuntime::new(config).unwrap();
 
     // Execute init workflow
-    let result = runtime.init_project();
+    let result = runtime.init_project().await;
     assert!(result.is_ok(), "Init failed: {:?}", result.err());
 
     // Verify all created artifacts
@@ -70,15 +70,15 @@ fn test_full_init_workflow() {
     assert!(gitignore_content.contains(".trees"));
 }
 
-#[test]
-fn test_init_creates_valid_config() {
+#[tokio::test]
+async fn test_init_creates_valid_config() {
     let temp_dir = TempDir::new().unwrap();
     init_test_repo(temp_dir.path());
 
     let config = MpcaConfig::new(temp_dir.path().to_path_buf());
     let runtime = AgentRuntime::new(config).unwrap();
 
-    runtime.init_project().unwrap();
+    runtime.init_project().await.unwrap();
 
     // Load the created config
     let config_path = temp_dir.path().join(".mpca/config.toml");
@@ -90,10 +90,17 @@ fn test_init_creates_valid_config() {
     // Verify it can be loaded back
     let loaded_config = MpcaConfig::load(temp_dir.path().to_path_buf()).unwrap();
     assert_eq!(loaded_config.repo_root, temp_dir.path().to_path_buf());
+
+    // The generated settings are the defaults
+    let defaults = MpcaConfig::new(temp_dir.path().to_path_buf());
+    assert_eq!(
+        toml::to_string(&loaded_config).unwrap(),
+        toml::to_string(&defaults).unwrap()
+    );
 }
 
-#[test]
-fn test_init_idempotent() {
+#[tokio::test]
+async fn test_init_idempotent() {
     let temp_dir = TempDir::new().unwrap();
     init_test_repo(temp_dir.path());
 
@@ -101,21 +108,21 @@ fn test_init_idempotent() {
     let runtime = AgentRuntime::new(config).unwrap();
 
     // First init should succeed
-    runtime.init_project().unwrap();
+    runtime.init_project().await.unwrap();
 
     // Second init should fail (already initialized)
-    let result = runtime.init_project();
+    let result = runtime.init_project().await;
     assert!(result.is_err());
 }
 
-#[test]
-fn test_init_outside_git_repo_fails() {
+#[tokio::test]
+async fn test_init_outside_git_repo_fails() {
     let temp_dir = TempDir::new().unwrap();
     // Don't initialize git
 
     let config = MpcaConfig::new(temp_dir.path().to_path_buf());
     let runtime = AgentRuntime::new(config).unwrap();
 
-    let result = runtime.init_project();
+    let result = runtime.init_project().await;
     assert!(result.is_err());
 }
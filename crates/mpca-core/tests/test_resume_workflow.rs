@@ -2,7 +2,10 @@
 //!
 //! Tests interruption and resumption of workflows with state persistence.
 
-use mpca_core::{AgentRuntime, MpcaConfig};
+mod common;
+
+use common::offline_runtime;
+use mpca_core::MpcaConfig;
 use std::fs;
 use std::process::Command;
 use tempfile::TempDir;
@@ -44,17 +47,17 @@ fn init_test_repo(dir: &std::path::Path) {
         .unwrap();
 }
 
-#[test]
-fn test_resume_after_execution_start() {
+#[tokio::test]
+async fn test_resume_after_execution_start() {
     let temp_dir = TempDir::new().unwrap();
     init_test_repo(temp_dir.path());
 
     let config = MpcaConfig::new(temp_dir.path().to_path_buf());
-    let runtime = AgentRuntime::new(config).unwrap();
+    let runtime = offline_runtime(config);
 
-    runtime.init_project().unwrap();
-    runtime.plan_feature("resumable").unwrap();
-    runtime.run_feature("resumable").unwrap();
+    runtime.init_project().await.unwrap();
+    runtime.plan_feature("resumable").await.unwrap();
+    runtime.run_feature("resumable").await.unwrap();
 
     // Read state before resume
     let state_file = temp_dir
@@ -64,10 +67,10 @@ fn test_resume_after_execution_start() {
 
     // Create a new runtime instance (simulating restart)
     let new_config = MpcaConfig::new(temp_dir.path().to_path_buf());
-    let new_runtime = AgentRuntime::new(new_config).unwrap();
+    let new_runtime = offline_runtime(new_config);
 
     // Resume execution
-    let result = new_runtime.run_feature("resumable");
+    let result = new_runtime.run_feature("resumable").await;
     assert!(result.is_ok());
 
     // State should still be present
@@ -75,16 +78,16 @@ fn test_resume_after_execution_start() {
     assert!(state_after.contains("phase = \"Run\""));
 }
 
-#[test]
-fn test_state_persistence() {
+#[tokio::test]
+async fn test_state_persistence() {
     let temp_dir = TempDir::new().unwrap();
     init_test_repo(temp_dir.path());
 
     let config = MpcaConfig::new(temp_dir.path().to_path_buf());
-    let runtime = AgentRuntime::new(config).unwrap();
+    let runtime = offline_runtime(config);
 
-    runtime.init_project().unwrap();
-    runtime.plan_feature("persistent").unwrap();
+    runtime.init_project().await.unwrap();
+    runtime.plan_feature("persistent").await.unwrap();
 
     let state_file = temp_dir
         .path()
@@ -96,23 +99,23 @@ fn test_state_persistence() {
     assert!(state.contains("phase = \"Plan\""));
 
     // Execute and verify state updates
-    runtime.run_feature("persistent").unwrap();
+    runtime.run_feature("persistent").await.unwrap();
     let state_after = fs::read_to_string(&state_file).unwrap();
     assert!(state_after.contains("phase = \"Run\""));
     assert!(state_after.contains("updated_at"));
 }
 
-#[test]
-fn test_worktree_preserved_on_resume() {
+#[tokio::test]
+async fn test_worktree_preserved_on_resume() {
     let temp_dir = TempDir::new().unwrap();
     init_test_repo(temp_dir.path());
 
     let config = MpcaConfig::new(temp_dir.path().to_path_buf());
-    let runtime = AgentRuntime::new(config).unwrap();
+    let runtime = offline_runtime(config);
 
-    runtime.init_project().unwrap();
-    runtime.plan_feature("preserved").unwrap();
-    runtime.run_feature("preserved").unwrap();
+    runtime.init_project().await.unwrap();
+    runtime.plan_feature("preserved").await.unwrap();
+    runtime.run_feature("preserved").await.unwrap();
 
     let worktree_dir = temp_dir.path().join(".trees/preserved");
     assert!(worktree_dir.exists());
@@ -122,8 +125,8 @@ fn test_worktree_preserved_on_resume() {
 
     // Resume
     let new_config = MpcaConfig::new(temp_dir.path().to_path_buf());
-    let new_runtime = AgentRuntime::new(new_config).unwrap();
-    new_runtime.run_feature("preserved").unwrap();
+    let new_runtime = offline_runtime(new_config);
+    new_runtime.run_feature("preserved").await.unwrap();
 
     // Verify file still exists
     assert!(worktree_dir.join("test.txt").exists());
@@ -0,0 +1,93 @@
+//! Templates compiled into the crate.
+//!
+//! The templates in `templates/` are embedded at build time, so an
+//! installed binary has its prompts without any files on disk. They form
+//! the lowest-priority layer of a [`PromptManager`] created with
+//! [`PromptManager::with_bundled`]. The partials in `templates/partials/`
+//! hold the blocks the templates share and are bundled under their
+//! `partials/` name.
+//!
+//! [`PromptManager`]: crate::PromptManager
+//! [`PromptManager::with_bundled`]: crate::PromptManager::with_bundled
+
+/// Bundled templates as `(name, source)` pairs, sorted by name.
+pub const BUNDLED_TEMPLATES: &[(&str, &str)] = &[
+    ("execute", include_str!("../templates/execute.j2")),
+    ("init", include_str!("../templates/init.j2")),
+    ("plan", include_str!("../templates/plan.j2")),
+    ("review", include_str!("../templates/review.j2")),
+    ("verification", include_str!("../templates/verification.j2")),
+];
+
+/// Bundled partials as `(name, source)` pairs, sorted by name.
+///
+/// Partials are included or imported by the templates rather than rendered
+/// on their own, e.g. `{% include "partials/context.j2" %}`.
+pub const BUNDLED_PARTIALS: &[(&str, &str)] = &[
+    (
+        "partials/context",
+        include_str!("../templates/partials/context.j2"),
+    ),
+    (
+        "partials/macros",
+        include_str!("../templates/partials/macros.j2"),
+    ),
+    (
+        "partials/rules",
+        include_str!("../templates/partials/rules.j2"),
+    ),
+];
+
+/// Returns the source of a bundled template or partial.
+///
+/// # Arguments
+///
+/// * `name` - Template name without extension (e.g., "plan" or
+///   "partials/rules")
+///
+/// # Examples
+///
+/// ```
+/// use mpca_pm::bundled::bundled_template;
+///
+/// assert!(bundled_template("plan").is_some());
+/// assert!(bundled_template("partials/rules").is_some());
+/// assert!(bundled_template("security").is_none());
+/// ```
+pub fn bundled_template(name: &str) -> Option<&'static str> {
+    BUNDLED_TEMPLATES
+        .iter()
+        .chain(BUNDLED_PARTIALS)
+        .find(|(bundled, _)| *bundled == name)
+        .map(|(_, source)| *source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template_files(dir: &str) -> Vec<String> {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(dir);
+        let mut files: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "j2"))
+            .map(|path| path.file_stem().unwrap().to_string_lossy().into_owned())
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_every_template_file_is_bundled() {
+        let bundled: Vec<&str> = BUNDLED_TEMPLATES.iter().map(|(name, _)| *name).collect();
+        assert_eq!(template_files("templates"), bundled);
+
+        let partials: Vec<String> = template_files("templates/partials")
+            .into_iter()
+            .map(|name| format!("partials/{name}"))
+            .collect();
+        let bundled: Vec<&str> = BUNDLED_PARTIALS.iter().map(|(name, _)| *name).collect();
+        assert_eq!(partials, bundled);
+    }
+}
@@ -0,0 +1,310 @@
+//! Static checks of prompt templates.
+//!
+//! [`PromptManager::check`](crate::PromptManager::check) parses every
+//! template of every layer without rendering it and compares the variables
+//! it uses with the fields of its context: the typed context of a bundled
+//! template's name, or [`ExecuteContext`] for any other template, since
+//! those are pipeline stage templates.
+
+use crate::bundled::BUNDLED_TEMPLATES;
+use crate::context::{
+    ExecuteContext, InitContext, PlanContext, ReviewContext, TemplateContext, VerifyContext,
+};
+use crate::manager::TemplateSource;
+use minijinja::{Environment, ErrorKind, Template};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Name prefix of partials, the templates meant to be included or
+/// imported rather than rendered on their own.
+pub const PARTIALS: &str = "partials/";
+
+/// A problem found in a template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateProblem {
+    /// The template does not parse.
+    Syntax {
+        /// Line of the error, starting at 1.
+        line: usize,
+        /// Column of the error, starting at 1.
+        column: usize,
+        /// What is wrong.
+        message: String,
+    },
+
+    /// The template uses a variable its context does not provide.
+    UndeclaredVariable(String),
+
+    /// A field of the template's context is never used.
+    UnusedField(String),
+
+    /// The template includes or imports a template that does not exist.
+    MissingTemplate(String),
+}
+
+impl fmt::Display for TemplateProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax {
+                line,
+                column,
+                message,
+            } => write!(f, "{line}:{column}: syntax error: {message}"),
+            Self::UndeclaredVariable(name) => {
+                write!(f, "undeclared variable `{name}`")
+            }
+            Self::UnusedField(name) => write!(f, "unused context field `{name}`"),
+            Self::MissingTemplate(name) => write!(f, "template `{name}` not found"),
+        }
+    }
+}
+
+/// Outcome of checking one template file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateCheck {
+    /// Template name without extension.
+    pub name: String,
+
+    /// Where the checked template comes from.
+    pub source: TemplateSource,
+
+    /// Problems found, in the order they were found.
+    pub problems: Vec<TemplateProblem>,
+}
+
+impl TemplateCheck {
+    /// Returns `true` if no problem was found.
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Returns the variables a template is rendered with.
+///
+/// # Arguments
+///
+/// * `name` - Template name without extension (e.g., "plan")
+///
+/// # Examples
+///
+/// ```
+/// use mpca_pm::check::context_fields;
+///
+/// assert!(context_fields("init").contains("config_file"));
+/// // Other templates are pipeline stages, rendered like `execute`
+/// assert_eq!(context_fields("changelog"), context_fields("execute"));
+/// ```
+pub fn context_fields(name: &str) -> BTreeSet<String> {
+    match name {
+        "init" => fields_of(&InitContext::default()),
+        "plan" => fields_of(&PlanContext::default()),
+        "review" => fields_of(&ReviewContext::default()),
+        "verification" => fields_of(&VerifyContext::default()),
+        _ => fields_of(&ExecuteContext::default()),
+    }
+}
+
+/// Checks a template's syntax and the variables it uses.
+///
+/// Templates pulled in with `{% include %}` are rendered with the context
+/// of the including template, so their variables count as used by it;
+/// they are resolved through `env` like at render time. Partials are only
+/// checked for syntax on their own, since their context depends on who
+/// includes them.
+///
+/// Unused context fields are only reported for templates named after a
+/// bundled template, whose context is made for them; stage templates
+/// usually need a few of the execute variables.
+///
+/// # Arguments
+///
+/// * `env` - Environment the template is rendered in
+/// * `name` - Template name without extension (e.g., "plan")
+/// * `source` - Template source
+pub(crate) fn check_template(
+    env: &Environment<'_>,
+    name: &str,
+    source: &str,
+) -> Vec<TemplateProblem> {
+    let file = format!("{name}.j2");
+    let template = match env.template_from_named_str(&file, source) {
+        Ok(template) => template,
+        Err(e) => return vec![syntax_problem(&e, source)],
+    };
+    if name.starts_with(PARTIALS) {
+        return Vec::new();
+    }
+
+    let mut problems = Vec::new();
+    let mut used = BTreeSet::new();
+    let mut visited = BTreeSet::from([file.clone()]);
+    collect_variables(env, &template, &mut used, &mut visited, &mut problems);
+
+    let globals: BTreeSet<&str> = env.globals().map(|(global, _)| global).collect();
+    used.retain(|variable| !globals.contains(variable.as_str()));
+
+    let fields = context_fields(name);
+    problems.extend(
+        used.difference(&fields)
+            .cloned()
+            .map(TemplateProblem::UndeclaredVariable),
+    );
+    if BUNDLED_TEMPLATES
+        .iter()
+        .any(|(bundled, _)| *bundled == name)
+    {
+        problems.extend(
+            fields
+                .difference(&used)
+                .cloned()
+                .map(TemplateProblem::UnusedField),
+        );
+    }
+    problems
+}
+
+/// Adds the variables of a template and of the templates it includes to
+/// `used`, reporting references to templates that do not exist.
+fn collect_variables(
+    env: &Environment<'_>,
+    template: &Template<'_, '_>,
+    used: &mut BTreeSet<String>,
+    visited: &mut BTreeSet<String>,
+    problems: &mut Vec<TemplateProblem>,
+) {
+    used.extend(template.undeclared_variables(false));
+
+    for (keyword, name) in referenced_templates(template.source()) {
+        if !visited.insert(name.clone()) {
+            continue;
+        }
+        match env.get_template(&name) {
+            Ok(referenced) if keyword == "include" => {
+                collect_variables(env, &referenced, used, visited, problems);
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::TemplateNotFound => {
+                problems.push(TemplateProblem::MissingTemplate(name));
+            }
+            // Syntax errors are reported for the referenced template itself
+            Err(_) => {}
+        }
+    }
+}
+
+/// Finds the templates a source includes or imports by literal name, as
+/// `(keyword, name)` pairs.
+fn referenced_templates(source: &str) -> Vec<(&str, String)> {
+    let mut references = Vec::new();
+    let mut rest = source;
+
+    while let Some(start) = rest.find("{%") {
+        rest = &rest[start + 2..];
+        let block = rest.find("%}").map_or(rest, |end| &rest[..end]);
+        let block = block.trim_start_matches(['-', '+']).trim_start();
+        let Some((keyword, args)) = block.split_once(char::is_whitespace) else {
+            continue;
+        };
+        if !matches!(keyword, "include" | "import" | "from") {
+            continue;
+        }
+
+        let args = args.trim_start();
+        if let Some(quote) = args.chars().next().filter(|c| matches!(c, '"' | '\''))
+            && let Some((name, _)) = args[1..].split_once(quote)
+        {
+            references.push((keyword, name.to_string()));
+        }
+    }
+
+    references
+}
+
+/// Returns the top-level field names of a context.
+fn fields_of<C: TemplateContext>(context: &C) -> BTreeSet<String> {
+    let value = minijinja::Value::from_serialize(context);
+    value
+        .try_iter()
+        .map(|keys| keys.map(|key| key.to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// Describes a parse error with its position in `source`.
+fn syntax_problem(error: &minijinja::Error, source: &str) -> TemplateProblem {
+    let line = error.line().unwrap_or(1);
+    let column = match error.range() {
+        Some(range) => {
+            let before = &source[..range.start.min(source.len())];
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            before[line_start..].chars().count() + 1
+        }
+        None => 1,
+    };
+    TemplateProblem::Syntax {
+        line,
+        column,
+        message: error
+            .detail()
+            .map_or_else(|| error.kind().to_string(), str::to_string),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(name: &str, source: &str) -> Vec<TemplateProblem> {
+        let mut env = Environment::new();
+        env.set_loader(|name| {
+            Ok((name == "partials/paths.j2").then(|| "{{ specs_dir }} {{ worktree }}".to_string()))
+        });
+        check_template(&env, name, source)
+    }
+
+    #[test]
+    fn test_syntax_error_has_position() {
+        let problems = check("plan", "Plan\n{{ feature_slug }\n");
+        assert_eq!(problems.len(), 1);
+        let TemplateProblem::Syntax { line, column, .. } = &problems[0] else {
+            panic!("expected a syntax error, got {:?}", problems);
+        };
+        assert_eq!((*line, *column), (2, 17));
+        assert!(problems[0].to_string().starts_with("2:17: syntax error: "));
+    }
+
+    #[test]
+    fn test_undeclared_and_unused_variables() {
+        let problems = check(
+            "init",
+            "{% for dir in prompt_dirs %}{{ dir }}{% endfor %}{{ repo_root }}{{ config_path }}{{ range(3) }}",
+        );
+        assert_eq!(
+            problems,
+            [
+                TemplateProblem::UndeclaredVariable("config_path".to_string()),
+                TemplateProblem::UnusedField("config_file".to_string()),
+            ]
+        );
+
+        // Stage templates may use any subset of the execute variables
+        assert_eq!(check("docs", "Document {{ feature_slug }}"), []);
+    }
+
+    #[test]
+    fn test_included_variables_count() {
+        let problems = check(
+            "docs",
+            "{% include 'partials/paths.j2' %}{%- import \"partials/missing.j2\" as m %}",
+        );
+        assert_eq!(
+            problems,
+            [
+                TemplateProblem::MissingTemplate("partials/missing.j2".to_string()),
+                TemplateProblem::UndeclaredVariable("worktree".to_string()),
+            ]
+        );
+
+        // Partials are only checked for syntax
+        assert_eq!(check("partials/paths", "{{ anything }}"), []);
+    }
+}
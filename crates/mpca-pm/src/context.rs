@@ -5,8 +5,9 @@ use std::path::PathBuf;
 
 /// Context data provided to templates for rendering.
 ///
-/// This structure contains all the dynamic information that templates
-/// may need to generate prompts for different workflow phases.
+/// This structure contains general information for custom templates; the
+/// bundled templates are rendered with their typed contexts such as
+/// [`PlanContext`] and [`ExecuteContext`].
 ///
 /// # Examples
 ///
@@ -19,6 +20,8 @@ use std::path::PathBuf;
 ///     feature_slug: Some("add-caching".to_string()),
 ///     spec_paths: vec![PathBuf::from(".mpca/specs/add-caching")],
 ///     resume: false,
+///     current_step: None,
+///     completed_steps: Vec::new(),
 /// };
 /// ```
 #[derive(Debug, Clone, Serialize)]
@@ -34,6 +37,12 @@ pub struct PromptContext {
 
     /// Whether this is a resumed workflow (true) or a fresh start (false).
     pub resume: bool,
+
+    /// Plan step the agent should work on (if applicable).
+    pub current_step: Option<u32>,
+
+    /// Titles of plan steps that are already done, in order.
+    pub completed_steps: Vec<String>,
 }
 
 impl Default for PromptContext {
@@ -43,6 +52,8 @@ impl Default for PromptContext {
             feature_slug: None,
             spec_paths: Vec::new(),
             resume: false,
+            current_step: None,
+            completed_steps: Vec::new(),
         }
     }
 }
@@ -121,4 +132,254 @@ impl PromptContext {
         self.resume = resume;
         self
     }
+
+    /// Sets the current plan step for this context.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mpca_pm::PromptContext;
+    /// use std::path::PathBuf;
+    ///
+    /// let context = PromptContext::new(PathBuf::from("/repo"))
+    ///     .with_current_step(3);
+    /// assert_eq!(context.current_step, Some(3));
+    /// ```
+    #[must_use]
+    pub fn with_current_step(mut self, step: u32) -> Self {
+        self.current_step = Some(step);
+        self
+    }
+
+    /// Sets the titles of plan steps that are already done.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mpca_pm::PromptContext;
+    /// use std::path::PathBuf;
+    ///
+    /// let context = PromptContext::new(PathBuf::from("/repo"))
+    ///     .with_completed_steps(vec!["Add types".to_string()]);
+    /// assert_eq!(context.completed_steps.len(), 1);
+    /// ```
+    #[must_use]
+    pub fn with_completed_steps(mut self, steps: Vec<String>) -> Self {
+        self.completed_steps = steps;
+        self
+    }
+}
+
+/// Data a prompt template is rendered with.
+///
+/// Implemented by [`PromptContext`] and the typed contexts of the bundled
+/// templates, so code starting agent sessions can tell which feature a
+/// prompt is about without knowing the context type.
+pub trait TemplateContext: Serialize {
+    /// Feature the prompt is about, if any.
+    fn feature_slug(&self) -> Option<&str>;
+}
+
+impl TemplateContext for PromptContext {
+    fn feature_slug(&self) -> Option<&str> {
+        self.feature_slug.as_deref()
+    }
+}
+
+/// Context of the `init` template.
+///
+/// # Examples
+///
+/// ```
+/// use mpca_pm::InitContext;
+/// use std::path::PathBuf;
+///
+/// let context = InitContext {
+///     repo_root: PathBuf::from("/repo"),
+///     config_file: PathBuf::from("/repo/.mpca/config.toml"),
+///     prompt_dirs: Vec::new(),
+/// };
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct InitContext {
+    /// Absolute path to the repository root directory.
+    pub repo_root: PathBuf,
+
+    /// Path of `.mpca/config.toml`.
+    pub config_file: PathBuf,
+
+    /// Directories with prompt template overrides.
+    pub prompt_dirs: Vec<PathBuf>,
+}
+
+impl TemplateContext for InitContext {
+    fn feature_slug(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Context of the `plan` template.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PlanContext {
+    /// Absolute path to the repository root directory.
+    pub repo_root: PathBuf,
+
+    /// Feature being planned.
+    pub feature_slug: String,
+
+    /// The feature's `specs/` directory.
+    pub specs_dir: PathBuf,
+
+    /// Worktree the feature will be implemented in.
+    pub worktree_dir: PathBuf,
+
+    /// Branch the feature will be implemented on.
+    pub branch: String,
+
+    /// Whether planning continues from existing specs.
+    pub resume: bool,
+
+    /// What the user wants the feature to do, if stated up front.
+    pub user_goal: Option<String>,
+
+    /// Content of an existing `design.md`.
+    pub existing_design: Option<String>,
+
+    /// Content of an existing `plan.md`.
+    pub existing_plan: Option<String>,
+}
+
+impl TemplateContext for PlanContext {
+    fn feature_slug(&self) -> Option<&str> {
+        Some(&self.feature_slug)
+    }
+}
+
+/// Context of the `execute` template and of other pipeline stages.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ExecuteContext {
+    /// Absolute path to the repository root directory.
+    pub repo_root: PathBuf,
+
+    /// Feature being implemented.
+    pub feature_slug: String,
+
+    /// The feature's `specs/` directory.
+    pub specs_dir: PathBuf,
+
+    /// Worktree the feature is implemented in.
+    pub worktree_dir: PathBuf,
+
+    /// Branch the feature is implemented on.
+    pub branch: String,
+
+    /// The feature's `state.toml`.
+    pub state_file: PathBuf,
+
+    /// Whether this run continues an interrupted one.
+    pub resume: bool,
+
+    /// Content of `plan.md`.
+    pub plan: String,
+
+    /// Rules the run must respect, such as gate commands that must pass.
+    pub constraints: Vec<String>,
+
+    /// Workflow phase of the feature.
+    pub phase: String,
+
+    /// Plan step the agent should work on, if it runs a single step.
+    pub current_step: Option<u32>,
+
+    /// Titles of plan steps that are already done, in order.
+    pub completed_steps: Vec<String>,
+
+    /// Agent turns spent on the feature so far.
+    pub turns: u32,
+
+    /// Cost of the feature so far, in USD.
+    pub cost_usd: f64,
+}
+
+impl TemplateContext for ExecuteContext {
+    fn feature_slug(&self) -> Option<&str> {
+        Some(&self.feature_slug)
+    }
+}
+
+/// Context of the `review` template.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ReviewContext {
+    /// Absolute path to the repository root directory.
+    pub repo_root: PathBuf,
+
+    /// Feature under review.
+    pub feature_slug: String,
+
+    /// The feature's `specs/` directory.
+    pub specs_dir: PathBuf,
+
+    /// Branch the feature was implemented on.
+    pub branch: String,
+
+    /// Summary of the changes under review.
+    pub diff_summary: String,
+
+    /// Reviewers configured for the repository.
+    pub review_prefs: Vec<String>,
+
+    /// Content of `design.md`.
+    pub design_spec: Option<String>,
+
+    /// Content of `plan.md`.
+    pub plan: Option<String>,
+
+    /// Content of `verify.md`.
+    pub verify_spec: Option<String>,
+
+    /// Content of `docs/impl_details.md`.
+    pub impl_details: Option<String>,
+}
+
+impl TemplateContext for ReviewContext {
+    fn feature_slug(&self) -> Option<&str> {
+        Some(&self.feature_slug)
+    }
+}
+
+/// Context of the `verification` template.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct VerifyContext {
+    /// Absolute path to the repository root directory.
+    pub repo_root: PathBuf,
+
+    /// Feature being verified.
+    pub feature_slug: String,
+
+    /// The feature's `specs/` directory.
+    pub specs_dir: PathBuf,
+
+    /// Worktree the feature was implemented in.
+    pub worktree_dir: PathBuf,
+
+    /// Branch the feature was implemented on.
+    pub branch: String,
+
+    /// Content of `verify.md`.
+    pub verify_spec: Option<String>,
+
+    /// Content of `design.md`.
+    pub design_spec: Option<String>,
+
+    /// Content of `plan.md`.
+    pub plan: Option<String>,
+
+    /// The feature's `state.toml`.
+    pub state_file: PathBuf,
+}
+
+impl TemplateContext for VerifyContext {
+    fn feature_slug(&self) -> Option<&str> {
+        Some(&self.feature_slug)
+    }
 }
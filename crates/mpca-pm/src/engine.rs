@@ -11,11 +11,14 @@ use serde::Serialize;
 /// # Examples
 ///
 /// ```no_run
-/// use mpca_pm::{PromptEngine, PromptContext, PromptManager};
+/// use mpca_pm::{PromptEngine, InitContext, PromptManager};
 /// use std::path::PathBuf;
 ///
 /// fn render_example(engine: &PromptManager) -> Result<(), Box<dyn std::error::Error>> {
-///     let context = PromptContext::new(PathBuf::from("/repo"));
+///     let context = InitContext {
+///         repo_root: PathBuf::from("/repo"),
+///         ..Default::default()
+///     };
 ///     let rendered = engine.render("init", &context)?;
 ///     println!("Rendered prompt: {}", rendered);
 ///     Ok(())
@@ -35,15 +38,21 @@ pub trait PromptEngine {
     /// - The template does not exist
     /// - The context cannot be serialized
     /// - The template contains syntax errors
+    /// - The template uses a variable the context does not provide
+    ///   (`PromptError::InvalidTemplateContext`)
     /// - Template rendering fails
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// # use mpca_pm::{PromptEngine, PromptContext, PromptManager};
+    /// # use mpca_pm::{PromptEngine, PlanContext, PromptManager};
     /// # use std::path::PathBuf;
     /// # fn example(engine: &PromptManager) -> Result<(), Box<dyn std::error::Error>> {
-    /// let context = PromptContext::new(PathBuf::from("/repo"));
+    /// let context = PlanContext {
+    ///     repo_root: PathBuf::from("/repo"),
+    ///     feature_slug: "add-caching".to_string(),
+    ///     ..Default::default()
+    /// };
     /// let prompt = engine.render("plan", &context)?;
     /// # Ok(())
     /// # }
@@ -61,7 +70,10 @@ pub trait PromptEngine {
     ///
     /// # Errors
     ///
-    /// Returns an error if the template for the role does not exist or rendering fails.
+    /// Returns an error if the template for the role does not exist or rendering
+    /// fails, including when it uses variables beyond those of [`PromptContext`].
+    ///
+    /// [`PromptContext`]: crate::PromptContext
     ///
     /// # Examples
     ///
@@ -0,0 +1,196 @@
+//! Custom filters available in every template.
+//!
+//! Filters that work with paths resolve them against the `repo_root`
+//! variable of the context being rendered, so they behave the same for
+//! every workflow.
+//!
+//! | Filter | Example |
+//! |--------|---------|
+//! | [`truncate_tokens`] | `{{ plan \| truncate_tokens(2000) }}` |
+//! | [`read_file`] | `{{ "CONTRIBUTING.md" \| read_file }}` |
+//! | [`code_block`] | `{{ diff_summary \| code_block("diff") }}` |
+//! | [`relpath`] | `{{ state_file \| relpath }}` |
+
+use minijinja::{Environment, Error, ErrorKind, State};
+use std::path::PathBuf;
+
+/// Characters per token used to estimate prompt sizes.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Marker appended to truncated text.
+const TRUNCATED: &str = "\n… (truncated)";
+
+/// Registers the filters in an environment.
+pub(crate) fn register(env: &mut Environment<'_>) {
+    env.add_filter("truncate_tokens", truncate_tokens);
+    env.add_filter("read_file", read_file);
+    env.add_filter("code_block", code_block);
+    env.add_filter("relpath", relpath);
+}
+
+/// Shortens text to about `tokens` tokens.
+///
+/// Tokens are estimated at four characters each. Text is cut at the last
+/// whitespace before the limit when there is one, and marked as truncated.
+///
+/// # Examples
+///
+/// ```
+/// use mpca_pm::filters::truncate_tokens;
+///
+/// assert_eq!(truncate_tokens("short".to_string(), 10), "short");
+/// assert_eq!(
+///     truncate_tokens("one two three four".to_string(), 2),
+///     "one two\n… (truncated)"
+/// );
+/// ```
+pub fn truncate_tokens(value: String, tokens: usize) -> String {
+    let limit = tokens.saturating_mul(CHARS_PER_TOKEN);
+    let Some((cut, _)) = value.char_indices().nth(limit) else {
+        return value;
+    };
+
+    let kept = &value[..cut];
+    let kept = kept
+        .rfind(char::is_whitespace)
+        .map_or(kept, |space| &kept[..space]);
+    format!("{}{TRUNCATED}", kept.trim_end())
+}
+
+/// Reads a file of the repository.
+///
+/// Relative paths are resolved against `repo_root`; paths that lead
+/// outside the repository, including through symlinks, are rejected.
+///
+/// # Errors
+///
+/// Returns an error if the context has no `repo_root`, if the file is
+/// outside the repository, or if it cannot be read.
+pub fn read_file(state: &State, path: String) -> Result<String, Error> {
+    let root = repo_root(state).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidOperation,
+            "read_file needs a `repo_root` in the context",
+        )
+    })?;
+    let unreadable = |e: std::io::Error| {
+        Error::new(
+            ErrorKind::InvalidOperation,
+            format!("could not read {path}"),
+        )
+        .with_source(e)
+    };
+
+    let root = root.canonicalize().map_err(unreadable)?;
+    let file = root.join(&path).canonicalize().map_err(unreadable)?;
+    if !file.starts_with(&root) {
+        return Err(Error::new(
+            ErrorKind::InvalidOperation,
+            format!("{path} is outside the repository"),
+        ));
+    }
+    std::fs::read_to_string(&file).map_err(unreadable)
+}
+
+/// Wraps text in a fenced Markdown code block.
+///
+/// The fence is made longer than any run of backticks in the text, so
+/// the text cannot close it early.
+///
+/// # Examples
+///
+/// ```
+/// use mpca_pm::filters::code_block;
+///
+/// assert_eq!(
+///     code_block("fn main() {}\n".to_string(), Some("rust".to_string())),
+///     "```rust\nfn main() {}\n```"
+/// );
+/// ```
+pub fn code_block(value: String, lang: Option<String>) -> String {
+    let longest_run = value.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!(
+        "{fence}{}\n{}\n{fence}",
+        lang.unwrap_or_default(),
+        value.trim_end_matches('\n')
+    )
+}
+
+/// Makes a path relative to `repo_root`.
+///
+/// Paths outside the repository, and all paths when the context has no
+/// `repo_root`, are returned unchanged.
+pub fn relpath(state: &State, path: String) -> String {
+    repo_root(state)
+        .and_then(|root| {
+            std::path::Path::new(&path)
+                .strip_prefix(&root)
+                .ok()
+                .map(|relative| relative.display().to_string())
+        })
+        .unwrap_or(path)
+}
+
+/// Returns the `repo_root` variable of the context being rendered.
+fn repo_root(state: &State) -> Option<PathBuf> {
+    let root = state.lookup("repo_root")?;
+    let root = root.as_str()?;
+    (!root.is_empty()).then(|| PathBuf::from(root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minijinja::context;
+    use tempfile::TempDir;
+
+    fn render(source: &str, repo_root: &std::path::Path) -> Result<String, Error> {
+        let mut env = Environment::new();
+        register(&mut env);
+        env.render_str(source, context! { repo_root => repo_root })
+    }
+
+    #[test]
+    fn test_read_file_is_bounded_to_the_repo() {
+        let outside = TempDir::new().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+        let repo = TempDir::new().unwrap();
+        std::fs::create_dir(repo.path().join("docs")).unwrap();
+        std::fs::write(repo.path().join("docs/rules.md"), "Be kind").unwrap();
+
+        assert_eq!(
+            render(r#"{{ "docs/rules.md" | read_file }}"#, repo.path()).unwrap(),
+            "Be kind"
+        );
+
+        let name = outside.path().file_name().unwrap().to_string_lossy();
+        let relative = format!("../{name}/secret.txt");
+        let absolute = outside.path().join("secret.txt").display().to_string();
+        for path in [relative, absolute] {
+            let source = format!("{{{{ {path:?} | read_file }}}}");
+            let err = render(&source, repo.path()).unwrap_err();
+            assert!(err.to_string().contains("outside the repository"), "{err}");
+        }
+        assert!(render(r#"{{ "missing.md" | read_file }}"#, repo.path()).is_err());
+    }
+
+    #[test]
+    fn test_relpath_and_code_block() {
+        let repo = TempDir::new().unwrap();
+        let state_file = repo.path().join(".mpca/specs/x/specs/state.toml");
+        let source = format!(
+            "{{{{ {:?} | relpath }}}} {{{{ '/elsewhere' | relpath }}}}",
+            state_file.display().to_string()
+        );
+        assert_eq!(
+            render(&source, repo.path()).unwrap(),
+            ".mpca/specs/x/specs/state.toml /elsewhere"
+        );
+
+        assert_eq!(
+            code_block("a ``` b".to_string(), None),
+            "````\na ``` b\n````"
+        );
+    }
+}
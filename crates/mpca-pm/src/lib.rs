@@ -2,31 +2,53 @@
 //!
 //! This crate provides template loading and rendering capabilities using minijinja.
 //! It manages system prompts and custom templates for different workflow phases.
+//! The default templates are compiled in (see [`bundled`]), so directories
+//! only need to hold the templates they override. Each bundled template has
+//! a typed context ([`InitContext`], [`PlanContext`], [`ExecuteContext`],
+//! [`ReviewContext`], [`VerifyContext`]); referencing a variable the context
+//! does not provide is an error rather than an empty string, and
+//! [`PromptManager::check`] finds such variables without rendering.
+//!
+//! The templates share blocks through partials (`{% include
+//! "partials/context.j2" %}`) and a macro library (`{% import
+//! "partials/macros.j2" as macros %}`), which template directories can
+//! override like any template; [`filters`] adds `truncate_tokens`,
+//! `read_file`, `code_block` and `relpath`.
 //!
 //! # Examples
 //!
 //! ```no_run
-//! use mpca_pm::{PromptManager, PromptEngine, PromptContext};
+//! use mpca_pm::{PromptManager, PromptEngine, PlanContext};
 //! use std::path::PathBuf;
 //!
 //! let template_dir = PathBuf::from("./templates");
-//! let manager = PromptManager::new(template_dir)?;
+//! let manager = PromptManager::with_bundled(vec![template_dir])?;
 //!
-//! let context = PromptContext::new(PathBuf::from("/my/repo"))
-//!     .with_feature("add-caching");
+//! let context = PlanContext {
+//!     repo_root: PathBuf::from("/my/repo"),
+//!     feature_slug: "add-caching".to_string(),
+//!     ..Default::default()
+//! };
 //!
 //! let prompt = manager.render("plan", &context)?;
 //! println!("Generated prompt: {}", prompt);
 //! # Ok::<(), mpca_pm::PromptError>(())
 //! ```
 
+pub mod bundled;
+pub mod check;
 pub mod context;
 pub mod engine;
 pub mod error;
+pub mod filters;
 pub mod manager;
 
 // Re-export public types for convenience
-pub use context::PromptContext;
+pub use check::{TemplateCheck, TemplateProblem};
+pub use context::{
+    ExecuteContext, InitContext, PlanContext, PromptContext, ReviewContext, TemplateContext,
+    VerifyContext,
+};
 pub use engine::PromptEngine;
 pub use error::{PromptError, Result};
-pub use manager::PromptManager;
+pub use manager::{PromptManager, TemplateSource};
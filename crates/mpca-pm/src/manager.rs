@@ -1,35 +1,75 @@
 //! Prompt manager implementation using minijinja.
 
 use crate::{
+    bundled::{BUNDLED_PARTIALS, BUNDLED_TEMPLATES, bundled_template},
+    check::{PARTIALS, TemplateCheck, check_template},
     context::PromptContext,
     engine::PromptEngine,
     error::{PromptError, Result},
+    filters,
 };
 use serde::Serialize;
-use std::path::PathBuf;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+/// Where a template is loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateSource {
+    /// A `.j2` file in one of the template directories.
+    File(PathBuf),
+    /// A template compiled into the crate, by name.
+    Bundled(&'static str),
+}
+
+impl fmt::Display for TemplateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Bundled(name) => write!(f, "<bundled>/{name}.j2"),
+        }
+    }
+}
 
 /// Manager for loading and rendering prompt templates.
 ///
 /// `PromptManager` wraps the minijinja template engine and provides
 /// a convenient interface for rendering system prompts and custom templates.
 ///
+/// Templates are looked up in an ordered list of directories: each name
+/// resolves to the file in the first directory that has it, so a directory
+/// overriding a single template still falls back to the later ones for all
+/// others. Managers created with [`PromptManager::with_bundled`] fall back
+/// to the templates compiled into the crate last.
+///
+/// Partials in a `partials/` subdirectory are layered the same way, so
+/// `{% include "partials/rules.j2" %}` picks up a `partials/rules.j2`
+/// override from any template directory; the bundled templates share
+/// their context block, macros and rules this way. The filters of
+/// [`filters`](crate::filters) are available in every template.
+///
 /// # Examples
 ///
 /// ```no_run
-/// use mpca_pm::{PromptManager, PromptContext, PromptEngine};
+/// use mpca_pm::{PromptManager, InitContext, PromptEngine};
 /// use std::path::PathBuf;
 ///
 /// let template_dir = PathBuf::from("./templates");
 /// let manager = PromptManager::new(template_dir)?;
 ///
-/// let context = PromptContext::new(PathBuf::from("/repo"));
+/// let context = InitContext {
+///     repo_root: PathBuf::from("/repo"),
+///     ..Default::default()
+/// };
 /// let prompt = manager.render("init", &context)?;
 /// # Ok::<(), mpca_pm::PromptError>(())
 /// ```
 #[derive(Debug)]
 pub struct PromptManager {
-    /// Directory containing template files.
-    pub templates_dir: PathBuf,
+    /// Directories containing template files, highest priority first.
+    pub template_dirs: Vec<PathBuf>,
+    /// Whether the bundled templates form the lowest-priority layer.
+    pub bundled: bool,
     /// Minijinja environment for template rendering.
     env: minijinja::Environment<'static>,
 }
@@ -58,29 +98,219 @@ impl PromptManager {
     /// # Ok::<(), mpca_pm::PromptError>(())
     /// ```
     pub fn new(templates_dir: PathBuf) -> Result<Self> {
-        // Verify template directory exists
-        if !templates_dir.exists() {
-            return Err(PromptError::TemplateDirectoryNotFound(
-                templates_dir.clone(),
-            ));
-        }
+        Self::with_dirs(vec![templates_dir])
+    }
 
-        if !templates_dir.is_dir() {
-            return Err(PromptError::TemplateDirectoryNotFound(
-                templates_dir.clone(),
-            ));
+    /// Creates a `PromptManager` resolving templates across several directories.
+    ///
+    /// # Arguments
+    ///
+    /// * `template_dirs` - Directories containing `.j2` template files,
+    ///   highest priority first
+    ///
+    /// # Errors
+    ///
+    /// Returns `PromptError::TemplateDirectoryNotFound` for the first
+    /// directory that does not exist.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mpca_pm::PromptManager;
+    /// use std::path::PathBuf;
+    ///
+    /// // plan.j2 in `.mpca/prompts` overrides the one in `./templates`
+    /// let manager = PromptManager::with_dirs(vec![
+    ///     PathBuf::from(".mpca/prompts"),
+    ///     PathBuf::from("./templates"),
+    /// ])?;
+    /// # Ok::<(), mpca_pm::PromptError>(())
+    /// ```
+    pub fn with_dirs(template_dirs: Vec<PathBuf>) -> Result<Self> {
+        Self::build(template_dirs, false)
+    }
+
+    /// Creates a `PromptManager` resolving templates across several
+    /// directories, falling back to the bundled templates.
+    ///
+    /// # Arguments
+    ///
+    /// * `template_dirs` - Directories containing `.j2` template files,
+    ///   highest priority first; may be empty
+    ///
+    /// # Errors
+    ///
+    /// Returns `PromptError::TemplateDirectoryNotFound` for the first
+    /// directory that does not exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mpca_pm::{PromptManager, TemplateSource};
+    ///
+    /// let manager = PromptManager::with_bundled(Vec::new())?;
+    /// assert_eq!(manager.resolve("plan"), Some(TemplateSource::Bundled("plan")));
+    /// # Ok::<(), mpca_pm::PromptError>(())
+    /// ```
+    pub fn with_bundled(template_dirs: Vec<PathBuf>) -> Result<Self> {
+        Self::build(template_dirs, true)
+    }
+
+    /// Creates the manager and its minijinja environment.
+    fn build(template_dirs: Vec<PathBuf>, bundled: bool) -> Result<Self> {
+        // Verify template directories exist
+        if let Some(missing) = template_dirs.iter().find(|dir| !dir.is_dir()) {
+            return Err(PromptError::TemplateDirectoryNotFound(missing.clone()));
         }
 
-        // Create environment with path loader
+        // Create environment loading each template from the first layer that has it
         let mut env = minijinja::Environment::new();
-        env.set_loader(minijinja::path_loader(&templates_dir));
+        // A variable missing from the context is a bug, not an empty string
+        env.set_undefined_behavior(minijinja::UndefinedBehavior::Strict);
+        filters::register(&mut env);
+        let dirs = template_dirs.clone();
+        env.set_loader(move |name| {
+            let Some(path) = find_template(&dirs, name) else {
+                let source = name
+                    .strip_suffix(".j2")
+                    .filter(|_| bundled)
+                    .and_then(bundled_template);
+                return Ok(source.map(str::to_string));
+            };
+            std::fs::read_to_string(&path).map(Some).map_err(|e| {
+                minijinja::Error::new(
+                    minijinja::ErrorKind::InvalidOperation,
+                    format!("could not read template {}", path.display()),
+                )
+                .with_source(e)
+            })
+        });
+
+        Ok(Self {
+            template_dirs,
+            bundled,
+            env,
+        })
+    }
 
-        Ok(Self { templates_dir, env })
+    /// Returns where a template name resolves to.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Template name without extension (e.g., "plan")
+    ///
+    /// # Returns
+    ///
+    /// The template in the highest-priority layer that has it, or `None` if
+    /// no layer does.
+    pub fn resolve(&self, name: &str) -> Option<TemplateSource> {
+        self.candidates(name).into_iter().next()
+    }
+
+    /// Returns every template a name could resolve to.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Template name without extension (e.g., "plan")
+    ///
+    /// # Returns
+    ///
+    /// The matching templates, highest priority first; the first one is
+    /// used and shadows the others.
+    pub fn candidates(&self, name: &str) -> Vec<TemplateSource> {
+        let file = format!("{name}.j2");
+        let mut candidates: Vec<TemplateSource> = self
+            .template_dirs
+            .iter()
+            .filter_map(|dir| find_template(std::slice::from_ref(dir), &file))
+            .map(TemplateSource::File)
+            .collect();
+
+        if self.bundled
+            && let Some((bundled, _)) = BUNDLED_TEMPLATES
+                .iter()
+                .chain(BUNDLED_PARTIALS)
+                .find(|(n, _)| *n == name)
+        {
+            candidates.push(TemplateSource::Bundled(bundled));
+        }
+
+        candidates
+    }
+
+    /// Checks every template and partial of every layer, shadowed ones
+    /// included.
+    ///
+    /// Each template is parsed and the variables it uses, including those
+    /// of the partials it includes, are compared with its context (see
+    /// [`check`](crate::check)). Partials are only checked for syntax.
+    ///
+    /// # Returns
+    ///
+    /// One [`TemplateCheck`] per template, in layer order and by name
+    /// within a layer, partials last.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a template directory cannot be listed or a
+    /// template file cannot be read; problems in the templates themselves
+    /// are reported in the checks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mpca_pm::PromptManager;
+    ///
+    /// let manager = PromptManager::with_bundled(Vec::new())?;
+    /// assert!(manager.check()?.iter().all(|check| check.is_ok()));
+    /// # Ok::<(), mpca_pm::PromptError>(())
+    /// ```
+    pub fn check(&self) -> Result<Vec<TemplateCheck>> {
+        let mut checks = Vec::new();
+
+        for dir in &self.template_dirs {
+            let mut files = template_files(dir)?;
+            let partials = dir.join(PARTIALS);
+            if partials.is_dir() {
+                files.extend(
+                    template_files(&partials)?
+                        .into_iter()
+                        .map(|(name, path)| (format!("{PARTIALS}{name}"), path)),
+                );
+            }
+
+            for (name, path) in files {
+                let source = std::fs::read_to_string(&path).map_err(|source| {
+                    PromptError::TemplateLoadError {
+                        path: path.clone(),
+                        source,
+                    }
+                })?;
+                checks.push(TemplateCheck {
+                    problems: check_template(&self.env, &name, &source),
+                    name,
+                    source: TemplateSource::File(path),
+                });
+            }
+        }
+
+        if self.bundled {
+            checks.extend(BUNDLED_TEMPLATES.iter().chain(BUNDLED_PARTIALS).map(
+                |(name, source)| TemplateCheck {
+                    name: name.to_string(),
+                    source: TemplateSource::Bundled(name),
+                    problems: check_template(&self.env, name, source),
+                },
+            ));
+        }
+
+        Ok(checks)
     }
 
     /// Loads a template by name.
     ///
-    /// Templates are expected to have a `.j2` extension in the templates directory.
+    /// Templates are expected to have a `.j2` extension in one of the
+    /// template directories, or to be bundled.
     ///
     /// # Arguments
     ///
@@ -97,12 +327,61 @@ impl PromptManager {
     }
 }
 
+/// Finds a template file in the first directory that has it.
+///
+/// Names that would escape the directories (absolute paths or `..`
+/// segments) never match.
+fn find_template(dirs: &[PathBuf], name: &str) -> Option<PathBuf> {
+    let relative = Path::new(name);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    dirs.iter()
+        .map(|dir| dir.join(relative))
+        .find(|path| path.is_file())
+}
+
+/// Lists the `.j2` files of a template directory as `(name, path)` pairs,
+/// sorted by name.
+fn template_files(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let list_error = |source| PromptError::TemplateListError {
+        path: dir.to_path_buf(),
+        source,
+    };
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(list_error)? {
+        let path = entry.map_err(list_error)?.path();
+
+        // Only include .j2 files
+        if path.is_file()
+            && let Some(ext) = path.extension()
+            && ext == "j2"
+            && let Some(name) = path.file_stem()
+            && let Some(name_str) = name.to_str()
+        {
+            files.push((name_str.to_string(), path.clone()));
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
 impl PromptEngine for PromptManager {
     fn render<T: Serialize>(&self, template: &str, ctx: &T) -> Result<String> {
         // Load and render template directly with serializable context
         let tmpl = self.load_template(template)?;
-        tmpl.render(ctx)
-            .map_err(|e| PromptError::TemplateRenderError(format!("{template}: {e}")))
+        tmpl.render(ctx).map_err(|e| match e.kind() {
+            minijinja::ErrorKind::UndefinedError => {
+                PromptError::InvalidTemplateContext(format!("{template}: {e}"))
+            }
+            _ => PromptError::TemplateRenderError(format!("{template}: {e}")),
+        })
     }
 
     fn get_system_prompt(&self, role: &str) -> Result<String> {
@@ -112,36 +391,17 @@ impl PromptEngine for PromptManager {
     }
 
     fn list_templates(&self) -> Result<Vec<String>> {
-        let entries = std::fs::read_dir(&self.templates_dir).map_err(|source| {
-            PromptError::TemplateListError {
-                path: self.templates_dir.clone(),
-                source,
-            }
-        })?;
-
-        let mut templates = Vec::new();
-
-        for entry in entries {
-            let entry = entry.map_err(|source| PromptError::TemplateListError {
-                path: self.templates_dir.clone(),
-                source,
-            })?;
-
-            let path = entry.path();
-
-            // Only include .j2 files
-            if path.is_file()
-                && let Some(ext) = path.extension()
-                && ext == "j2"
-                && let Some(name) = path.file_stem()
-                && let Some(name_str) = name.to_str()
-            {
-                templates.push(name_str.to_string());
-            }
+        let mut templates = BTreeSet::new();
+
+        if self.bundled {
+            templates.extend(BUNDLED_TEMPLATES.iter().map(|(name, _)| name.to_string()));
+        }
+
+        for dir in &self.template_dirs {
+            templates.extend(template_files(dir)?.into_iter().map(|(name, _)| name));
         }
 
-        templates.sort();
-        Ok(templates)
+        Ok(templates.into_iter().collect())
     }
 }
 
@@ -174,7 +434,7 @@ mod tests {
         let (_temp, templates_path) = create_test_template_dir();
         let manager = PromptManager::new(templates_path.clone());
         assert!(manager.is_ok());
-        assert_eq!(manager.unwrap().templates_dir, templates_path);
+        assert_eq!(manager.unwrap().template_dirs, [templates_path]);
     }
 
     #[test]
@@ -240,10 +500,17 @@ mod tests {
         let (_temp, templates_path) = create_test_template_dir();
         let manager = PromptManager::new(templates_path).expect("failed to create manager");
 
-        let result = manager.get_system_prompt("test");
+        let result = manager.get_system_prompt("context");
         assert!(result.is_ok());
-        // Should use empty context, so name should be empty
-        assert_eq!(result.unwrap(), "Hello !");
+        // Should use empty context, so there is no feature
+        assert_eq!(result.unwrap(), "Repo: \nFeature: None");
+
+        // The empty context doesn't define `name`
+        let result = manager.get_system_prompt("test");
+        assert!(matches!(
+            result,
+            Err(PromptError::InvalidTemplateContext(msg)) if msg.starts_with("test: ")
+        ));
     }
 
     #[test]
@@ -273,6 +540,212 @@ mod tests {
         assert_eq!(result.unwrap().len(), 0);
     }
 
+    #[test]
+    fn test_layered_directories() {
+        let (_temp, base) = create_test_template_dir();
+        let overrides = TempDir::new().expect("failed to create temp dir");
+        fs::write(overrides.path().join("test.j2"), "Hi {{ name }}!")
+            .expect("failed to write override");
+        fs::write(
+            overrides.path().join("extra.j2"),
+            "{% include 'context.j2' %}",
+        )
+        .expect("failed to write extra template");
+
+        let manager = PromptManager::with_dirs(vec![overrides.path().to_path_buf(), base.clone()])
+            .expect("failed to create manager");
+
+        #[derive(Serialize)]
+        struct TestContext {
+            name: String,
+        }
+        let ctx = TestContext {
+            name: "World".to_string(),
+        };
+
+        // The override wins; templates it lacks come from the next layer
+        assert_eq!(manager.render("test", &ctx).unwrap(), "Hi World!");
+        let rendered = manager
+            .render("extra", &PromptContext::new(PathBuf::from("/my/repo")))
+            .unwrap();
+        assert!(rendered.contains("/my/repo"));
+
+        assert_eq!(
+            manager.resolve("test"),
+            Some(TemplateSource::File(overrides.path().join("test.j2")))
+        );
+        assert_eq!(
+            manager.resolve("context"),
+            Some(TemplateSource::File(base.join("context.j2")))
+        );
+        assert_eq!(manager.resolve("missing"), None);
+        assert_eq!(
+            manager.candidates("test"),
+            [
+                TemplateSource::File(overrides.path().join("test.j2")),
+                TemplateSource::File(base.join("test.j2"))
+            ]
+        );
+
+        assert_eq!(
+            manager.list_templates().unwrap(),
+            ["context", "extra", "test"]
+        );
+    }
+
+    #[test]
+    fn test_bundled_templates_are_the_lowest_layer() {
+        let (_temp, base) = create_test_template_dir();
+        fs::write(base.join("plan.j2"), "Custom plan").expect("failed to write override");
+
+        let manager =
+            PromptManager::with_bundled(vec![base.clone()]).expect("failed to create manager");
+        let ctx = PromptContext::new(PathBuf::from("/my/repo"));
+
+        assert_eq!(manager.render("plan", &ctx).unwrap(), "Custom plan");
+        assert_eq!(
+            manager.candidates("plan"),
+            [
+                TemplateSource::File(base.join("plan.j2")),
+                TemplateSource::Bundled("plan")
+            ]
+        );
+        assert_eq!(
+            manager.resolve("review"),
+            Some(TemplateSource::Bundled("review"))
+        );
+        let review = crate::context::ReviewContext::default();
+        assert_eq!(
+            manager.render("review", &review).unwrap(),
+            PromptManager::with_bundled(Vec::new())
+                .unwrap()
+                .render("review", &review)
+                .unwrap()
+        );
+        assert_eq!(
+            TemplateSource::Bundled("review").to_string(),
+            "<bundled>/review.j2"
+        );
+        assert_eq!(
+            manager.list_templates().unwrap(),
+            [
+                "context",
+                "execute",
+                "init",
+                "plan",
+                "review",
+                "test",
+                "verification"
+            ]
+        );
+
+        // Managers without the bundled layer don't see them
+        let manager = PromptManager::new(base).expect("failed to create manager");
+        assert_eq!(manager.resolve("review"), None);
+    }
+
+    #[test]
+    fn test_bundled_templates_render_with_typed_contexts() {
+        use crate::context::{
+            ExecuteContext, InitContext, PlanContext, ReviewContext, VerifyContext,
+        };
+
+        let manager = PromptManager::with_bundled(Vec::new()).expect("failed to create manager");
+        let execute = ExecuteContext {
+            feature_slug: "add-caching".to_string(),
+            resume: true,
+            current_step: Some(2),
+            completed_steps: vec!["Add types".to_string()],
+            constraints: vec!["`cargo test` must succeed".to_string()],
+            ..Default::default()
+        };
+
+        manager.render("init", &InitContext::default()).unwrap();
+        manager.render("plan", &PlanContext::default()).unwrap();
+        manager.render("review", &ReviewContext::default()).unwrap();
+        manager
+            .render("verification", &VerifyContext::default())
+            .unwrap();
+        let rendered = manager.render("execute", &execute).unwrap();
+        assert!(rendered.contains("Start at step 2"));
+        assert!(rendered.contains("- [x] Add types"));
+        assert!(rendered.contains("Constraints: `cargo test` must succeed"));
+
+        // A context lacking the template's variables is rejected
+        let result = manager.render("execute", &PlanContext::default());
+        assert!(matches!(
+            result,
+            Err(PromptError::InvalidTemplateContext(msg)) if msg.starts_with("execute: ")
+        ));
+    }
+
+    #[test]
+    fn test_partials_are_layered() {
+        let temp_dir = TempDir::new().expect("failed to create temp dir");
+        let partials = temp_dir.path().join("partials");
+        fs::create_dir(&partials).expect("failed to create partials dir");
+        let rules = partials.join("rules.j2");
+        fs::write(
+            &rules,
+            "{# Organization rules #}\n\n## Organization Rules\n- Never commit secrets\n",
+        )
+        .expect("failed to write rules");
+
+        let manager = PromptManager::with_bundled(vec![temp_dir.path().to_path_buf()])
+            .expect("failed to create manager");
+        assert_eq!(
+            manager.candidates("partials/rules"),
+            [
+                TemplateSource::File(rules.clone()),
+                TemplateSource::Bundled("partials/rules")
+            ]
+        );
+
+        let context = crate::context::PlanContext {
+            feature_slug: "add-caching".to_string(),
+            ..Default::default()
+        };
+        let rendered = manager.render("plan", &context).unwrap();
+        assert!(
+            rendered.contains(
+                "## Context Provided\n- Repository root: \n- Feature slug: add-caching\n"
+            )
+        );
+        assert!(rendered.contains("- `.mpca/specs/add-caching/specs/plan.md`\n"));
+        assert!(rendered.ends_with(
+            "without re-asking answered questions\n\n## Organization Rules\n- Never commit secrets"
+        ));
+
+        // Every layer passes the checks, the override included
+        let checks = manager.check().unwrap();
+        assert!(checks.iter().all(|check| check.is_ok()), "{checks:?}");
+        assert_eq!(checks[0].source, TemplateSource::File(rules));
+
+        fs::write(partials.join("rules.j2"), "{% if %}").expect("failed to write rules");
+        let checks = manager.check().unwrap();
+        assert!(!checks[0].is_ok());
+        assert!(checks[1..].iter().all(|check| check.is_ok()));
+    }
+
+    #[test]
+    fn test_with_dirs_rejects_missing_directory() {
+        let (_temp, base) = create_test_template_dir();
+        let result = PromptManager::with_dirs(vec![base, PathBuf::from("/nonexistent/path")]);
+        assert!(matches!(
+            result,
+            Err(PromptError::TemplateDirectoryNotFound(path)) if path == Path::new("/nonexistent/path")
+        ));
+    }
+
+    #[test]
+    fn test_resolve_stays_inside_directories() {
+        let (_temp, base) = create_test_template_dir();
+        let manager = PromptManager::new(base.join("..").join("templates"))
+            .expect("failed to create manager");
+        assert!(manager.resolve("test").is_some());
+        assert!(manager.resolve("../templates/test").is_none());
+    }
+
     #[test]
     fn test_context_builder_pattern() {
         let context = PromptContext::new(PathBuf::from("/repo"))